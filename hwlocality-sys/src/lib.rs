@@ -775,12 +775,9 @@ pub struct hwloc_obj {
 
     /// Application-given private data pointer, initialized to NULL, use it as
     /// you wish
-    //
-    // --- Implementation details ---
-    //
-    // TODO: Add once support is ready: "See
-    // [`hwloc_topology_set_userdata_export_callback()`] if you wish to export
-    // this field to XML."
+    ///
+    /// See [`hwloc_topology_set_userdata_export_callback()`] if you wish to
+    /// export this field to XML.
     pub userdata: *mut c_void,
 
     /// Global persistent index
@@ -2328,6 +2325,241 @@ mod memory_attributes {
 #[cfg(feature = "hwloc-2_3_0")]
 pub use memory_attributes::*;
 
+// === Topology differences: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__diff.html
+
+/// Type of one object attribute difference
+///
+/// We can't use Rust enums to model C enums in FFI because that results in
+/// undefined behavior if the C API gets new enum variants and sends them to us.
+#[doc(alias = "hwloc_topology_diff_obj_attr_type_t")]
+pub type hwloc_topology_diff_obj_attr_type_e = c_int;
+
+/// The object local memory is modified
+///
+/// The union is a [`hwloc_topology_diff_obj_attr_uint64_s`] (and the index
+/// field is ignored).
+pub const HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_SIZE: hwloc_topology_diff_obj_attr_type_e = 0;
+
+/// The object name is modified
+///
+/// The union is a [`hwloc_topology_diff_obj_attr_string_s`] (and the name
+/// field is ignored).
+pub const HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_NAME: hwloc_topology_diff_obj_attr_type_e = 1;
+
+/// The value of an info attribute is modified
+///
+/// The union is a [`hwloc_topology_diff_obj_attr_string_s`].
+pub const HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_INFO: hwloc_topology_diff_obj_attr_type_e = 2;
+
+/// One object attribute difference
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub union hwloc_topology_diff_obj_attr_u {
+    /// Common prefix of all object attribute differences
+    pub generic: hwloc_topology_diff_obj_attr_generic_s,
+
+    /// Integer attribute modification with an optional index
+    pub uint64: hwloc_topology_diff_obj_attr_uint64_s,
+
+    /// String attribute modification with an optional name
+    pub string: hwloc_topology_diff_obj_attr_string_s,
+}
+//
+impl Debug for hwloc_topology_diff_obj_attr_u {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("hwloc_topology_diff_obj_attr_u")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Common prefix of all object attribute differences
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_generic_s")]
+#[repr(C)]
+pub struct hwloc_topology_diff_obj_attr_generic_s {
+    /// Type of object attribute difference
+    #[doc(alias = "hwloc_topology_diff_obj_attr_generic_s::type")]
+    #[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_generic_s::type")]
+    pub ty: hwloc_topology_diff_obj_attr_type_e,
+}
+
+/// Integer attribute modification with an optional index
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_uint64_s")]
+#[repr(C)]
+pub struct hwloc_topology_diff_obj_attr_uint64_s {
+    /// Type of object attribute difference, must be
+    /// [`HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_SIZE`]
+    #[doc(alias = "hwloc_topology_diff_obj_attr_uint64_s::type")]
+    #[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_uint64_s::type")]
+    pub ty: hwloc_topology_diff_obj_attr_type_e,
+
+    /// Index of the modified attribute, currently unused
+    #[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_uint64_s::index")]
+    pub index: u64,
+
+    /// Value of the attribute in the old topology
+    #[doc(
+        alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_uint64_s::oldvalue"
+    )]
+    pub oldvalue: u64,
+
+    /// Value of the attribute in the new topology
+    #[doc(
+        alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_uint64_s::newvalue"
+    )]
+    pub newvalue: u64,
+}
+
+/// String attribute modification with an optional name
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_string_s")]
+#[repr(C)]
+pub struct hwloc_topology_diff_obj_attr_string_s {
+    /// Type of object attribute difference, must be
+    /// [`HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_NAME`] or
+    /// [`HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_INFO`]
+    #[doc(alias = "hwloc_topology_diff_obj_attr_string_s::type")]
+    #[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_string_s::type")]
+    pub ty: hwloc_topology_diff_obj_attr_type_e,
+
+    /// Name of the modified info attribute, ignored for name modifications
+    #[doc(alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_string_s::name")]
+    pub name: *mut c_char,
+
+    /// Value of the attribute in the old topology
+    #[doc(
+        alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_string_s::oldvalue"
+    )]
+    pub oldvalue: *mut c_char,
+
+    /// Value of the attribute in the new topology
+    #[doc(
+        alias = "hwloc_topology_diff_obj_attr_u::hwloc_topology_diff_obj_attr_string_s::newvalue"
+    )]
+    pub newvalue: *mut c_char,
+}
+
+/// Type of one element of a difference list
+///
+/// We can't use Rust enums to model C enums in FFI because that results in
+/// undefined behavior if the C API gets new enum variants and sends them to us.
+#[doc(alias = "hwloc_topology_diff_type_t")]
+pub type hwloc_topology_diff_type_e = c_int;
+
+/// An object attribute was changed
+///
+/// The union is a [`hwloc_topology_diff_obj_attr_s`].
+pub const HWLOC_TOPOLOGY_DIFF_OBJ_ATTR: hwloc_topology_diff_type_e = 0;
+
+/// The difference is too complex, it cannot be represented
+///
+/// The difference below this object has not been checked. The union is a
+/// [`hwloc_topology_diff_too_complex_s`].
+pub const HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX: hwloc_topology_diff_type_e = 1;
+
+/// One element of a difference list between two topologies
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub union hwloc_topology_diff_u {
+    /// Common prefix of all difference list elements
+    pub generic: hwloc_topology_diff_generic_s,
+
+    /// Object attribute difference
+    pub obj_attr: hwloc_topology_diff_obj_attr_s,
+
+    /// Too complex difference
+    pub too_complex: hwloc_topology_diff_too_complex_s,
+}
+//
+impl Debug for hwloc_topology_diff_u {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("hwloc_topology_diff_u")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Common prefix of all difference list elements
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_generic_s")]
+#[repr(C)]
+pub struct hwloc_topology_diff_generic_s {
+    /// Type of difference
+    #[doc(alias = "hwloc_topology_diff_generic_s::type")]
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_generic_s::type")]
+    pub ty: hwloc_topology_diff_type_e,
+
+    /// Next element of the difference list, or NULL if this is the last one
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_generic_s::next")]
+    pub next: *mut hwloc_topology_diff_u,
+}
+
+/// Object attribute difference
+#[derive(Copy, Clone, Debug)]
+#[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_obj_attr_s")]
+#[repr(C)]
+pub struct hwloc_topology_diff_obj_attr_s {
+    /// Type of difference, must be [`HWLOC_TOPOLOGY_DIFF_OBJ_ATTR`]
+    #[doc(alias = "hwloc_topology_diff_obj_attr_s::type")]
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_obj_attr_s::type")]
+    pub ty: hwloc_topology_diff_type_e,
+
+    /// Next element of the difference list, or NULL if this is the last one
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_obj_attr_s::next")]
+    pub next: *mut hwloc_topology_diff_u,
+
+    /// Depth of the object that was modified
+    ///
+    /// This is a [`hwloc_get_type_depth_e`] for special depths.
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_obj_attr_s::obj_depth")]
+    pub obj_depth: c_int,
+
+    /// Logical index of the object that was modified
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_obj_attr_s::obj_index")]
+    pub obj_index: c_uint,
+
+    /// Description of the object attribute difference
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_obj_attr_s::diff")]
+    pub diff: hwloc_topology_diff_obj_attr_u,
+}
+
+/// Too complex difference
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_too_complex_s")]
+#[repr(C)]
+pub struct hwloc_topology_diff_too_complex_s {
+    /// Type of difference, must be [`HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX`]
+    #[doc(alias = "hwloc_topology_diff_too_complex_s::type")]
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_too_complex_s::type")]
+    pub ty: hwloc_topology_diff_type_e,
+
+    /// Next element of the difference list, or NULL if this is the last one
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_too_complex_s::next")]
+    pub next: *mut hwloc_topology_diff_u,
+
+    /// Depth of the object where the difference was found
+    ///
+    /// This is a [`hwloc_get_type_depth_e`] for special depths.
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_too_complex_s::obj_depth")]
+    pub obj_depth: c_int,
+
+    /// Logical index of the object where the difference was found
+    #[doc(alias = "hwloc_topology_diff_u::hwloc_topology_diff_too_complex_s::obj_index")]
+    pub obj_index: c_uint,
+}
+
+/// A difference list between two topologies
+///
+/// This is a linked list of [`hwloc_topology_diff_u`], which must be
+/// destroyed with [`hwloc_topology_diff_destroy()`].
+pub type hwloc_topology_diff_t = *mut hwloc_topology_diff_u;
+
+/// Flags to be given to [`hwloc_topology_diff_apply()`]
+pub type hwloc_topology_diff_apply_flags_e = c_ulong;
+
+/// Apply topology diff in reverse direction
+pub const HWLOC_TOPOLOGY_DIFF_APPLY_REVERSE: hwloc_topology_diff_apply_flags_e = 1 << 0;
+
 // === Entry points
 
 /// Implement all the entry points with the right link name
@@ -2876,13 +3108,26 @@ macro_rules! extern_c_block {
             #[must_use]
             pub fn hwloc_bitmap_copy(dst: hwloc_bitmap_t, src: hwloc_const_bitmap_t) -> c_int;
 
+            #[must_use]
+            pub fn hwloc_bitmap_snprintf(
+                buf: *mut c_char,
+                len: usize,
+                bitmap: hwloc_const_bitmap_t,
+            ) -> c_int;
             #[must_use]
             pub fn hwloc_bitmap_list_snprintf(
                 buf: *mut c_char,
                 len: usize,
                 bitmap: hwloc_const_bitmap_t,
             ) -> c_int;
-            // NOTE: Not exposing other printfs and scanfs for now
+            #[must_use]
+            pub fn hwloc_bitmap_taskset_snprintf(
+                buf: *mut c_char,
+                len: usize,
+                bitmap: hwloc_const_bitmap_t,
+            ) -> c_int;
+            // NOTE: Not exposing asprintfs (snprintf is enough) and scanfs
+            //       (parsing is done on the Rust side for better errors)
 
             pub fn hwloc_bitmap_zero(bitmap: hwloc_bitmap_t);
             pub fn hwloc_bitmap_fill(bitmap: hwloc_bitmap_t);
@@ -2890,7 +3135,8 @@ macro_rules! extern_c_block {
             pub fn hwloc_bitmap_only(bitmap: hwloc_bitmap_t, id: c_uint) -> c_int;
             #[must_use]
             pub fn hwloc_bitmap_allbut(bitmap: hwloc_bitmap_t, id: c_uint) -> c_int;
-            // NOTE: Not exposing ulong-based APIs for now, so no from_ulong, from_ith_ulong, from_ulongs
+            // NOTE: Not exposing from_ulong, from_ith_ulong and from_ulongs,
+            //       set_ith_ulong is enough for the Rust-side conversions.
             //       If I decide to add them, gate from_ulongs with #[cfg(feature = "hwloc-2_1_0")]
             #[must_use]
             pub fn hwloc_bitmap_set(bitmap: hwloc_bitmap_t, id: c_uint) -> c_int;
@@ -2900,7 +3146,12 @@ macro_rules! extern_c_block {
                 begin: c_uint,
                 end: c_int,
             ) -> c_int;
-            // NOTE: Not exposing ulong-based APIs for now, so no set_ith_ulong
+            #[must_use]
+            pub fn hwloc_bitmap_set_ith_ulong(
+                bitmap: hwloc_bitmap_t,
+                i: c_uint,
+                mask: c_ulong,
+            ) -> c_int;
             #[must_use]
             pub fn hwloc_bitmap_clr(bitmap: hwloc_bitmap_t, id: c_uint) -> c_int;
             #[must_use]
@@ -2910,7 +3161,10 @@ macro_rules! extern_c_block {
                 end: c_int,
             ) -> c_int;
             pub fn hwloc_bitmap_singlify(bitmap: hwloc_bitmap_t) -> c_int;
-            // NOTE: Not exposing ulong-based APIs for now, so no to_ulong, to_ith_ulong, to_ulongs and nr_ulongs
+            #[must_use]
+            pub fn hwloc_bitmap_to_ith_ulong(bitmap: hwloc_const_bitmap_t, i: c_uint) -> c_ulong;
+            // NOTE: Not exposing to_ulong, to_ulongs and nr_ulongs,
+            //       to_ith_ulong is enough for the Rust-side conversions.
             //       If I decide to add them, gate nr_ulongs and to_ulongs with #[cfg(feature = "hwloc-2_1_0")]
 
             #[must_use]
@@ -3003,8 +3257,124 @@ macro_rules! extern_c_block {
                 flags: hwloc_topology_export_xml_flags_e,
             ) -> c_int;
             pub fn hwloc_free_xmlbuffer(topology: hwloc_const_topology_t, xmlbuffer: *mut c_char);
-            // NOTE: Not exposing userdata at the moment, so no need to bind
-            //       associated API functions yet.
+
+            /// Set the application-specific callback for exporting object
+            /// userdata
+            ///
+            /// The object userdata pointer is not exported to XML by default
+            /// because hwloc does not know what it contains. This function
+            /// lets applications set `export_cb` to a callback function that
+            /// converts this opaque userdata into an exportable string.
+            ///
+            /// `export_cb` is invoked during XML export for each object whose
+            /// `userdata` pointer is not NULL. The callback should use
+            /// [`hwloc_export_obj_userdata()`] or
+            /// [`hwloc_export_obj_userdata_base64()`] to actually export
+            /// something to XML (possibly multiple times per object).
+            ///
+            /// `export_cb` may be set to `None` if userdata should not be
+            /// exported to XML.
+            pub fn hwloc_topology_set_userdata_export_callback(
+                topology: hwloc_topology_t,
+                export_cb: Option<
+                    unsafe extern "C" fn(
+                        reserved: *mut c_void,
+                        topology: hwloc_topology_t,
+                        obj: hwloc_obj_t,
+                    ),
+                >,
+            );
+
+            /// Export some object userdata to XML
+            ///
+            /// This function may only be called from within the export
+            /// callback passed to
+            /// [`hwloc_topology_set_userdata_export_callback()`]. It may be
+            /// invoked one or multiple times to export some userdata to XML.
+            /// The `buffer` content of length `length` is stored with optional
+            /// name `name`.
+            ///
+            /// When importing this XML file, the import callback (if set) will
+            /// be called exactly as many times as this function was called
+            /// during export. It will receive the corresponding name, buffer
+            /// and length arguments.
+            ///
+            /// `reserved`, `topology` and `obj` must be the first three
+            /// parameters that were given to the export callback.
+            ///
+            /// Only printable characters may be exported to XML string
+            /// attributes. If a non-printable character is passed in `name` or
+            /// `buffer`, the function returns -1 with errno set to `EINVAL`.
+            ///
+            /// If exporting binary data, the application should first encode
+            /// into printable characters only (or use
+            /// [`hwloc_export_obj_userdata_base64()`]). It should also take
+            /// care of portability issues if the export may be reimported on a
+            /// different architecture.
+            #[must_use]
+            pub fn hwloc_export_obj_userdata(
+                reserved: *mut c_void,
+                topology: hwloc_topology_t,
+                obj: hwloc_obj_t,
+                name: *const c_char,
+                buffer: *const c_void,
+                length: usize,
+            ) -> c_int;
+
+            /// Encode and export some object userdata to XML
+            ///
+            /// This is similar to [`hwloc_export_obj_userdata()`] but it
+            /// encodes the input buffer into printable characters before
+            /// exporting. On import, decoding is automatically performed
+            /// before the data is given to the import callback if any.
+            ///
+            /// This function may only be called from within the export
+            /// callback passed to
+            /// [`hwloc_topology_set_userdata_export_callback()`].
+            ///
+            /// The name must be made of printable characters for export to XML
+            /// string attributes.
+            #[must_use]
+            pub fn hwloc_export_obj_userdata_base64(
+                reserved: *mut c_void,
+                topology: hwloc_topology_t,
+                obj: hwloc_obj_t,
+                name: *const c_char,
+                buffer: *const c_void,
+                length: usize,
+            ) -> c_int;
+
+            /// Set the application-specific callback for importing userdata
+            ///
+            /// On XML import, userdata is ignored by default because hwloc
+            /// does not know how to store it in memory.
+            ///
+            /// This function lets applications set `import_cb` to a callback
+            /// function that will get the XML-stored userdata and store it in
+            /// the object as expected by the application.
+            ///
+            /// `import_cb` is called during [`hwloc_topology_load()`] as many
+            /// times as [`hwloc_export_obj_userdata()`] was called during
+            /// export. The topology is not entirely setup yet. Object
+            /// attributes are ready to consult, but links between objects are
+            /// not.
+            ///
+            /// `import_cb` may be `None` if userdata should be ignored during
+            /// import.
+            ///
+            /// `buffer` contains `length` characters followed by a null byte.
+            pub fn hwloc_topology_set_userdata_import_callback(
+                topology: hwloc_topology_t,
+                import_cb: Option<
+                    unsafe extern "C" fn(
+                        topology: hwloc_topology_t,
+                        obj: hwloc_obj_t,
+                        name: *const c_char,
+                        buffer: *const c_void,
+                        length: usize,
+                    ),
+                >,
+            );
 
             // === Exporting Topologies to Synthetic: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__syntheticexport.html
 
@@ -3309,12 +3679,175 @@ macro_rules! extern_c_block {
                 flags: c_ulong,
             ) -> c_int;
 
+            // === Sharing topologies between processes: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__shmem.html
+
+            /// Get the required shared memory length for storing a topology
+            ///
+            /// This length (in bytes) must be used in
+            /// [`hwloc_shmem_topology_write()`] and
+            /// [`hwloc_shmem_topology_adopt()`] later. It is a multiple of the
+            /// page size. `flags` must be 0 for now.
+            #[must_use]
+            pub fn hwloc_shmem_topology_get_length(
+                topology: hwloc_topology_t,
+                lengthp: *mut usize,
+                flags: c_ulong,
+            ) -> c_int;
+
+            /// Duplicate a topology to a shared memory file
+            ///
+            /// Temporarily map a file in virtual memory at `mmap_address` and
+            /// duplicate `topology` into it. `fileoffset`, `mmap_address` and
+            /// `length` must be page-aligned. `flags` must be 0 for now.
+            ///
+            /// Returns -1 with errno set to `EBUSY` if the virtual memory
+            /// mapping defined by `mmap_address` and `length` isn't available
+            /// in the process, and with errno set to `EINVAL` if `fileoffset`,
+            /// `mmap_address` or `length` aren't page-aligned.
+            #[must_use]
+            pub fn hwloc_shmem_topology_write(
+                topology: hwloc_topology_t,
+                fd: c_int,
+                fileoffset: u64,
+                mmap_address: *mut c_void,
+                length: usize,
+                flags: c_ulong,
+            ) -> c_int;
+
+            /// Adopt a shared memory topology stored in a file
+            ///
+            /// Map a file in virtual memory at `mmap_address` and return the
+            /// topology that was previously stored there by
+            /// [`hwloc_shmem_topology_write()`] in `topologyp`. The returned
+            /// topology must be treated as read-only, and destroyed with
+            /// [`hwloc_topology_destroy()`] as usual. `flags` must be 0 for
+            /// now.
+            ///
+            /// Returns -1 with errno set to `EBUSY` if the virtual memory
+            /// mapping defined by `mmap_address` and `length` isn't available
+            /// in the process, and with errno set to `EINVAL` if `fileoffset`,
+            /// `mmap_address` or `length` aren't page-aligned or do not match
+            /// what was given to [`hwloc_shmem_topology_write()`] earlier, or
+            /// if the layout of the topology structure is different between
+            /// the writer process and the adopter one.
+            #[must_use]
+            pub fn hwloc_shmem_topology_adopt(
+                topologyp: *mut hwloc_topology_t,
+                fd: c_int,
+                fileoffset: u64,
+                mmap_address: *mut c_void,
+                length: usize,
+                flags: c_ulong,
+            ) -> c_int;
+
+            // === Topology differences: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__diff.html
+
+            /// Compute the difference between 2 topologies
+            ///
+            /// The difference is stored as a list of [`hwloc_topology_diff_u`]
+            /// entries starting at `diff`, which must be freed with
+            /// [`hwloc_topology_diff_destroy()`]. `flags` must be 0 for now.
+            ///
+            /// Returns 0 on success, 1 if the difference list contains some
+            /// [`HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX`] entries (in which case it
+            /// cannot be applied), and -1 on error, e.g. if the topologies
+            /// have different topology flags.
+            #[must_use]
+            pub fn hwloc_topology_diff_build(
+                topology: hwloc_topology_t,
+                newtopology: hwloc_topology_t,
+                flags: c_ulong,
+                diff: *mut hwloc_topology_diff_t,
+            ) -> c_int;
+
+            /// Apply a topology diff to an existing topology
+            ///
+            /// The topology is modified in place. [`hwloc_topology_dup()`]
+            /// may be used to duplicate it before patching.
+            ///
+            /// If the difference cannot be applied entirely, all previous
+            /// applied elements are unapplied before returning.
+            ///
+            /// Returns 0 on success, -N if applying the difference failed on
+            /// the N-th element of the list (-1 meaning the first one), with
+            /// errno set to `EINVAL` if the list contains some
+            /// [`HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX`] entries.
+            #[must_use]
+            pub fn hwloc_topology_diff_apply(
+                topology: hwloc_topology_t,
+                diff: hwloc_topology_diff_t,
+                flags: hwloc_topology_diff_apply_flags_e,
+            ) -> c_int;
+
+            /// Destroy a list of topology differences
+            #[must_use]
+            pub fn hwloc_topology_diff_destroy(diff: hwloc_topology_diff_t) -> c_int;
+
+            /// Load a list of topology differences from a XML file
+            ///
+            /// If not NULL, `refname` will be filled with the identifier
+            /// string of the reference topology for the difference file, if
+            /// any was specified in the XML file. This identifier is usually
+            /// the name of the other XML file that contains the reference
+            /// topology. It must be freed with `free()` by the caller.
+            #[must_use]
+            pub fn hwloc_topology_diff_load_xml(
+                xmlpath: *const c_char,
+                diff: *mut hwloc_topology_diff_t,
+                refname: *mut *mut c_char,
+            ) -> c_int;
+
+            /// Export a list of topology differences to a XML file
+            ///
+            /// If not NULL, `refname` defines an identifier string for the
+            /// reference topology which was used as a base when computing
+            /// this difference. This identifier is usually the name of the
+            /// other XML file that contains the reference topology.
+            ///
+            /// Lists containing [`HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX`] entries
+            /// cannot be exported.
+            #[must_use]
+            pub fn hwloc_topology_diff_export_xml(
+                diff: hwloc_topology_diff_t,
+                refname: *const c_char,
+                xmlpath: *const c_char,
+            ) -> c_int;
+
+            /// Load a list of topology differences from a XML buffer
+            ///
+            /// `buflen` is the length of `xmlbuffer`, including its trailing
+            /// NUL. `refname` works as in [`hwloc_topology_diff_load_xml()`].
+            #[must_use]
+            pub fn hwloc_topology_diff_load_xmlbuffer(
+                xmlbuffer: *const c_char,
+                buflen: c_int,
+                diff: *mut hwloc_topology_diff_t,
+                refname: *mut *mut c_char,
+            ) -> c_int;
+
+            /// Export a list of topology differences to a XML buffer
+            ///
+            /// `refname` works as in [`hwloc_topology_diff_export_xml()`].
+            /// The XML buffer should later be freed with
+            /// [`hwloc_free_xmlbuffer()`]. `buflen` is set to the length of
+            /// the buffer, including its trailing NUL.
+            ///
+            /// Lists containing [`HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX`] entries
+            /// cannot be exported.
+            #[must_use]
+            pub fn hwloc_topology_diff_export_xmlbuffer(
+                diff: hwloc_topology_diff_t,
+                refname: *const c_char,
+                xmlbuffer: *mut *mut c_char,
+                buflen: *mut c_int,
+            ) -> c_int;
+
             // NOTE: glibc interop is waiting for higher quality cpuset support
             //       in the libc crate: right now, it is not possible to safely
             //       crate a `cpu_set_t`, but functions that manipulate them
             //       expect `&mut cpu_set_t`...
 
-            // TODO: Cover more later: interop, differences, sharing, etc...
+            // TODO: Cover more later: interop, etc...
             //       Beware that primitives that modify the topology should be
             //       exposed in the TopologyEditor, not Topology, because per
             //       hwloc documentation hwloc_topology_refresh() must be called
@@ -3446,6 +3979,24 @@ mod tests {
         io::Write
     );
 
+    // Same goes for the unions that describe topology differences
+    assert_impl_all!(hwloc_topology_diff_obj_attr_u:
+        Copy, Debug, Sized, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(hwloc_topology_diff_obj_attr_u:
+        Binary, Default, Deref, Display, Drop, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialEq, Pointer, Read, Send, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+    assert_impl_all!(hwloc_topology_diff_u:
+        Copy, Debug, Sized, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(hwloc_topology_diff_u:
+        Binary, Default, Deref, Display, Drop, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialEq, Pointer, Read, Send, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+
     // Unions that contain only Sync types can additionally impl Sync, and this
     // also applies to types that contain such unions
     assert_impl_all!(hwloc_bridge_attr_s:
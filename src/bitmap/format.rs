@@ -0,0 +1,154 @@
+//! Selectable textual representations of bitmaps
+//!
+//! hwloc can display bitmaps in three different formats, which are useful when
+//! interacting with different third-party tools. [`Bitmap`] implements
+//! `Display` using the list format by default, and the adaptors from this
+//! module can be used to select another format.
+
+use super::Bitmap;
+use crate::ffi;
+#[cfg(doc)]
+use crate::{cpu::cpuset::CpuSet, memory::nodeset::NodeSet};
+use std::fmt::{self, Display, Formatter};
+
+/// Adaptor for displaying a [`Bitmap`], [`CpuSet`] or [`NodeSet`] in a
+/// specific textual format
+///
+/// This `struct` is created by the `display_list()`, `display_taskset()` and
+/// `display_hex()` methods of bitmap types. See their documentation for more
+/// information about the format of the output.
+///
+/// Infinite bitmaps are displayed the way hwloc does it, i.e. as an open
+/// range like `0-3,8-` in the list format, and using a `0xf...f` prefix to
+/// denote an infinite sequence of set bits in the hexadecimal formats.
+#[derive(Copy, Clone, Debug)]
+pub struct BitmapDisplay<'bitmap> {
+    /// Bitmap that is being displayed
+    bitmap: &'bitmap Bitmap,
+
+    /// Output format
+    format: BitmapFormat,
+}
+//
+impl<'bitmap> BitmapDisplay<'bitmap> {
+    /// Set up a bitmap display adaptor
+    pub(super) fn new(bitmap: &'bitmap Bitmap, format: BitmapFormat) -> Self {
+        Self { bitmap, format }
+    }
+}
+//
+impl Display for BitmapDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let snprintf = match self.format {
+            BitmapFormat::List => hwlocality_sys::hwloc_bitmap_list_snprintf,
+            BitmapFormat::Taskset => hwlocality_sys::hwloc_bitmap_taskset_snprintf,
+            BitmapFormat::Hex => hwlocality_sys::hwloc_bitmap_snprintf,
+        };
+        // SAFETY: - Bitmaps are trusted to contain a valid ptr (type invariant)
+        //         - hwloc ops are trusted not to modify *const parameters
+        //         - hwloc_bitmap_*snprintf are snprintf-like
+        unsafe { ffi::write_snprintf(f, |buf, len| snprintf(buf, len, self.bitmap.as_ptr())) }
+    }
+}
+
+/// Textual bitmap representation supported by hwloc
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub(super) enum BitmapFormat {
+    /// List of indices and index ranges (`0-3,8,10-`)
+    #[doc(alias = "hwloc_bitmap_list_snprintf")]
+    List,
+
+    /// Single hexadecimal mask (`0xf...f00000d0f`)
+    #[doc(alias = "hwloc_bitmap_taskset_snprintf")]
+    Taskset,
+
+    /// Comma-separated 32-bit hexadecimal chunks (`0xf...f,0x00000d0f`)
+    #[doc(alias = "hwloc_bitmap_snprintf")]
+    Hex,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(BitmapDisplay<'static>:
+        Copy, Debug, Display, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(BitmapDisplay<'static>:
+        Binary, Default, Deref, Drop, Error, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialEq, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+
+    #[test]
+    fn special_bitmaps() {
+        let empty = Bitmap::new();
+        assert_eq!(empty.display_list().to_string(), "");
+        assert_eq!(empty.display_taskset().to_string(), "0x0");
+        assert_eq!(empty.display_hex().to_string(), "0x0");
+        assert_eq!(format!("{empty:#}"), "0x0");
+
+        let full = Bitmap::full();
+        assert_eq!(full.display_list().to_string(), "0-");
+        assert_eq!(full.display_taskset().to_string(), "0xf...f");
+        assert_eq!(full.display_hex().to_string(), "0xf...f");
+        assert_eq!(format!("{full:#}"), "0xf...f");
+
+        let mut bitmap = Bitmap::from_range(0..=3);
+        bitmap.set(8);
+        bitmap.set_range(10..=11);
+        assert_eq!(bitmap.display_list().to_string(), "0-3,8,10-11");
+        assert_eq!(bitmap.display_taskset().to_string(), "0xd0f");
+        assert_eq!(bitmap.display_hex().to_string(), "0x00000d0f");
+        bitmap.set_range(40..);
+        assert_eq!(bitmap.display_list().to_string(), "0-3,8,10-11,40-");
+        assert_eq!(
+            bitmap.display_taskset().to_string(),
+            "0xf...fffffff0000000d0f"
+        );
+        assert_eq!(
+            bitmap.display_hex().to_string(),
+            "0xf...f,0xffffff00,0x00000d0f"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(bitmap: Bitmap) {
+            let list = bitmap.display_list().to_string();
+            prop_assert_eq!(&list, &bitmap.to_string());
+            prop_assert_eq!(&list.parse::<Bitmap>().unwrap(), &bitmap);
+
+            let taskset = bitmap.display_taskset().to_string();
+            prop_assert_eq!(&taskset.parse::<Bitmap>().unwrap(), &bitmap);
+
+            let hex = bitmap.display_hex().to_string();
+            prop_assert_eq!(&format!("{bitmap:#}"), &hex);
+            prop_assert_eq!(&hex.parse::<Bitmap>().unwrap(), &bitmap);
+        }
+
+        #[test]
+        fn padding(bitmap: Bitmap, width in 0usize..64) {
+            let list = bitmap.display_list().to_string();
+            prop_assert_eq!(
+                format!("{:>width$}", bitmap.display_list()),
+                format!("{list:>width$}")
+            );
+        }
+    }
+}
@@ -0,0 +1,967 @@
+//! Allocation-free fixed-capacity bitmaps
+//!
+//! Every [`Bitmap`] is backed by a heap-allocated hwloc object, so every
+//! operation that produces a new bitmap costs a memory allocation on top of an
+//! FFI call. This is fine for most uses, but it can become a bottleneck in
+//! inner loops that perform lots of set arithmetic, such as task schedulers.
+//!
+//! [`InlineBitmap`] is a pure-Rust alternative which lives on the stack and
+//! never allocates, at the expense of only supporting a finite range of
+//! indices that is decided at compile time. It is meant to be used for
+//! intermediary computations, with conversions from and to [`Bitmap`],
+//! [`CpuSet`] and [`NodeSet`] at the boundary with the rest of hwlocality.
+
+use super::{Bitmap, BitmapIndex, BitmapTruncationError, BAD_INDEX};
+#[cfg(doc)]
+use crate::{cpu::cpuset::CpuSet, memory::nodeset::NodeSet};
+#[cfg(any(test, feature = "proptest"))]
+use proptest::prelude::*;
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    ffi::c_ulong,
+    fmt::{self, Debug, Display, Formatter, Write},
+    iter::{FromIterator, FusedIterator},
+    mem,
+    ops::{
+        BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Bound, Not, RangeBounds,
+        Sub, SubAssign,
+    },
+};
+
+/// Fixed-capacity bitmap that is stored inline, without heap allocations
+///
+/// An `InlineBitmap<WORDS>` can hold any set of indices below
+/// [`CAPACITY`](Self::CAPACITY) `= 64 * WORDS`. Like [`Bitmap`], it can also
+/// be infinite, in which case all indices above the capacity are set. This
+/// makes it possible to implement all the set operations of [`Bitmap`],
+/// including negation, with identical semantics.
+///
+/// All operations are implemented in pure Rust, without memory allocations or
+/// FFI calls. Operations that would need to set or unset only part of the
+/// indices above the capacity are not representable, and will panic.
+///
+/// Conversions from [`Bitmap`], [`CpuSet`] and [`NodeSet`] are performed via
+/// `TryFrom`, and fail with a [`BitmapTruncationError`] if the source bitmap
+/// has set indices which do not fit. Conversions in the opposite direction are
+/// infallible and performed via `From`.
+///
+/// # Examples
+///
+/// ```
+/// use hwlocality::bitmap::{Bitmap, InlineBitmap};
+///
+/// let a = InlineBitmap::<2>::from_range(0..=63);
+/// let b = InlineBitmap::<2>::from_range(32..=95);
+/// let both = a & b;
+/// assert_eq!(both.weight(), Some(32));
+/// assert!(a.includes(both) && b.includes(both));
+/// assert_eq!(Bitmap::from(both), Bitmap::from_range(32..=63));
+///
+/// let outside = !(a | b);
+/// assert_eq!(format!("{outside}"), "96-");
+/// assert_eq!(InlineBitmap::<2>::try_from(&Bitmap::from(outside)), Ok(outside));
+/// ```
+#[derive(Copy, Clone, Eq, Hash, PartialEq)]
+pub struct InlineBitmap<const WORDS: usize> {
+    /// Indices `0..CAPACITY`, with the lowest index of each word in its least
+    /// significant bit
+    words: [u64; WORDS],
+
+    /// Truth that all indices from `CAPACITY` onwards are set
+    infinite: bool,
+}
+//
+impl<const WORDS: usize> InlineBitmap<WORDS> {
+    /// Number of indices that can be set or unset individually
+    ///
+    /// This must not be higher than the number of indices supported by
+    /// [`Bitmap`] (at least 2^15, usually 2^31).
+    pub const CAPACITY: usize = WORDS * WORD_BITS;
+
+    // === Constructors ===
+
+    /// Creates an empty `InlineBitmap`
+    pub const fn new() -> Self {
+        Self {
+            words: [0; WORDS],
+            infinite: false,
+        }
+    }
+
+    /// Creates a full `InlineBitmap`
+    pub const fn full() -> Self {
+        Self {
+            words: [u64::MAX; WORDS],
+            infinite: true,
+        }
+    }
+
+    /// Creates a new `InlineBitmap` with the given range of indices set
+    ///
+    /// Accepts both ranges of [`BitmapIndex`] and [`usize`].
+    ///
+    /// # Panics
+    ///
+    /// If `range` is bounded and goes beyond [`CAPACITY`](Self::CAPACITY), or
+    /// is unbounded and starts after [`CAPACITY`](Self::CAPACITY).
+    pub fn from_range<Idx>(range: impl RangeBounds<Idx>) -> Self
+    where
+        Idx: Copy + TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        let mut bitmap = Self::new();
+        bitmap.set_range(range);
+        bitmap
+    }
+
+    // === Getters and setters ===
+
+    /// Clear all indices
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Set all indices
+    pub fn fill(&mut self) {
+        *self = Self::full();
+    }
+
+    /// Clear all indices except for `idx`, which is set
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is not below [`CAPACITY`](Self::CAPACITY).
+    pub fn set_only<Idx>(&mut self, idx: Idx)
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        self.clear();
+        self.set(idx);
+    }
+
+    /// Set all indices except for `idx`, which is cleared
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is not below [`CAPACITY`](Self::CAPACITY).
+    pub fn set_all_but<Idx>(&mut self, idx: Idx)
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        self.fill();
+        self.unset(idx);
+    }
+
+    /// Set index `idx`
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is not below [`CAPACITY`](Self::CAPACITY) and the bitmap is
+    /// not infinite.
+    pub fn set<Idx>(&mut self, idx: Idx)
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        let idx = usize::from(idx.try_into().expect(BAD_INDEX));
+        self.assign_range(idx, Some(idx), true);
+    }
+
+    /// Set indices covered by `range`
+    ///
+    /// Accepts both ranges of [`BitmapIndex`] and [`usize`].
+    ///
+    /// # Panics
+    ///
+    /// If `range` sets indices at or above [`CAPACITY`](Self::CAPACITY),
+    /// unless it sets all of them or the bitmap is already infinite.
+    pub fn set_range<Idx>(&mut self, range: impl RangeBounds<Idx>)
+    where
+        Idx: Copy + TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        if let Some((start, end)) = Self::usize_range(range) {
+            self.assign_range(start, end, true);
+        }
+    }
+
+    /// Clear index `idx`
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is not below [`CAPACITY`](Self::CAPACITY) and the bitmap is
+    /// infinite.
+    pub fn unset<Idx>(&mut self, idx: Idx)
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        let idx = usize::from(idx.try_into().expect(BAD_INDEX));
+        self.assign_range(idx, Some(idx), false);
+    }
+
+    /// Clear indices covered by `range`
+    ///
+    /// Accepts both ranges of [`BitmapIndex`] and [`usize`].
+    ///
+    /// # Panics
+    ///
+    /// If `range` clears indices at or above [`CAPACITY`](Self::CAPACITY),
+    /// unless it clears all of them or the bitmap is already finite.
+    pub fn unset_range<Idx>(&mut self, range: impl RangeBounds<Idx>)
+    where
+        Idx: Copy + TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        if let Some((start, end)) = Self::usize_range(range) {
+            self.assign_range(start, end, false);
+        }
+    }
+
+    /// Check if index `idx` is set
+    ///
+    /// Unlike setters, this accepts indices above
+    /// [`CAPACITY`](Self::CAPACITY).
+    pub fn is_set<Idx>(&self, idx: Idx) -> bool
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        let idx = usize::from(idx.try_into().expect(BAD_INDEX));
+        self.words
+            .get(idx / WORD_BITS)
+            .map_or(self.infinite, |word| word & (1 << (idx % WORD_BITS)) != 0)
+    }
+
+    /// Check if all indices are unset
+    pub fn is_empty(&self) -> bool {
+        !self.infinite && self.words.iter().all(|&word| word == 0)
+    }
+
+    /// Check if all indices are set
+    pub fn is_full(&self) -> bool {
+        self.infinite && self.words.iter().all(|&word| word == u64::MAX)
+    }
+
+    /// Check the first set index, if any
+    pub fn first_set(&self) -> Option<BitmapIndex> {
+        Self::first_one(self.words, self.infinite)
+    }
+
+    /// Iterate over set indices
+    ///
+    /// If the bitmap is infinite, this iterates up to [`BitmapIndex::MAX`].
+    pub fn iter_set(&self) -> impl FusedIterator<Item = BitmapIndex> + Clone {
+        Self::iter_ones(self.words, self.infinite)
+    }
+
+    /// Check the last set index, if any
+    ///
+    /// Infinite bitmaps have no last set index.
+    pub fn last_set(&self) -> Option<BitmapIndex> {
+        Self::last_one(self.words, self.infinite)
+    }
+
+    /// The number of indices that are set in the bitmap
+    ///
+    /// None means that an infinite number of indices are set.
+    pub fn weight(&self) -> Option<usize> {
+        (!self.infinite).then(|| {
+            self.words
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum()
+        })
+    }
+
+    /// Check the first unset index, if any
+    pub fn first_unset(&self) -> Option<BitmapIndex> {
+        Self::first_one(self.words.map(|word| !word), !self.infinite)
+    }
+
+    /// Iterate over unset indices
+    ///
+    /// If the bitmap is finite, this iterates up to [`BitmapIndex::MAX`].
+    pub fn iter_unset(&self) -> impl FusedIterator<Item = BitmapIndex> + Clone {
+        Self::iter_ones(self.words.map(|word| !word), !self.infinite)
+    }
+
+    /// Check the last unset index, if any
+    ///
+    /// Finite bitmaps have no last unset index.
+    pub fn last_unset(&self) -> Option<BitmapIndex> {
+        Self::last_one(self.words.map(|word| !word), !self.infinite)
+    }
+
+    /// Inverts the current `InlineBitmap`
+    pub fn invert(&mut self) {
+        *self = !*self;
+    }
+
+    /// Truth that `self` and `rhs` have some set indices in common
+    pub fn intersects(&self, rhs: impl Borrow<Self>) -> bool {
+        !(*self & rhs).is_empty()
+    }
+
+    /// Truth that the indices set in `inner` are a subset of those set in
+    /// `self`
+    ///
+    /// The empty bitmap is considered included in any other bitmap.
+    pub fn includes(&self, inner: impl Borrow<Self>) -> bool {
+        (*inner.borrow() - self).is_empty()
+    }
+
+    // === Implementation details ===
+
+    /// Translate a range of indices into an inclusive range of `usize`
+    ///
+    /// The end of the range is `None` if it is unbounded, and the output is
+    /// `None` if the range is empty.
+    fn usize_range<Idx>(range: impl RangeBounds<Idx>) -> Option<(usize, Option<usize>)>
+    where
+        Idx: Copy + TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        let convert = |idx: Idx| usize::from(idx.try_into().expect(BAD_INDEX));
+        let start = match range.start_bound() {
+            Bound::Unbounded => 0,
+            Bound::Included(&start) => convert(start),
+            Bound::Excluded(&start) => convert(start).checked_add(1)?,
+        };
+        let end = match range.end_bound() {
+            Bound::Unbounded => None,
+            Bound::Included(&end) => Some(convert(end)),
+            Bound::Excluded(&end) => Some(convert(end).checked_sub(1)?),
+        };
+        (end.map_or(true, |end| start <= end)).then_some((start, end))
+    }
+
+    /// Set or clear the non-empty inclusive range of indices `start..=end`,
+    /// where `end == None` stands for an unbounded range
+    ///
+    /// # Panics
+    ///
+    /// If the range covers some but not all indices at or above `CAPACITY`,
+    /// and those indices are not already in the desired state.
+    fn assign_range(&mut self, start: usize, end: Option<usize>, value: bool) {
+        // Handle indices above the capacity
+        let covers_tail = start <= Self::CAPACITY && end.is_none();
+        let touches_tail = end.map_or(true, |end| end >= Self::CAPACITY);
+        if covers_tail {
+            self.infinite = value;
+        } else if touches_tail {
+            assert_eq!(
+                self.infinite,
+                value,
+                "Indices {start}..={end:?} do not fit in an InlineBitmap of capacity {}",
+                Self::CAPACITY
+            );
+        }
+
+        // Handle indices below the capacity
+        let Some(last_word) = Self::CAPACITY.checked_sub(1) else {
+            return;
+        };
+        let end = end.map_or(last_word, |end| end.min(last_word));
+        if start > end {
+            return;
+        }
+        for word_idx in start / WORD_BITS..=end / WORD_BITS {
+            let word_start = word_idx * WORD_BITS;
+            let low_bit = start.saturating_sub(word_start);
+            let high_bit = (end - word_start).min(WORD_BITS - 1);
+            let mask = (u64::MAX << low_bit) & (u64::MAX >> (WORD_BITS - 1 - high_bit));
+            if value {
+                self.words[word_idx] |= mask;
+            } else {
+                self.words[word_idx] &= !mask;
+            }
+        }
+    }
+
+    /// First set bit of a bitmap with the specified words and infinite flag
+    fn first_one(words: [u64; WORDS], infinite: bool) -> Option<BitmapIndex> {
+        words
+            .iter()
+            .enumerate()
+            .find(|(_, &word)| word != 0)
+            .map(|(word_idx, word)| word_idx * WORD_BITS + word.trailing_zeros() as usize)
+            .or_else(|| infinite.then_some(Self::CAPACITY))
+            .and_then(|idx| BitmapIndex::try_from(idx).ok())
+    }
+
+    /// Last set bit of a bitmap with the specified words and infinite flag
+    fn last_one(words: [u64; WORDS], infinite: bool) -> Option<BitmapIndex> {
+        if infinite {
+            return None;
+        }
+        words
+            .iter()
+            .enumerate()
+            .rfind(|(_, &word)| word != 0)
+            .map(|(word_idx, word)| {
+                let idx = word_idx * WORD_BITS + (WORD_BITS - 1 - word.leading_zeros() as usize);
+                BitmapIndex::try_from(idx).expect("CAPACITY should be a valid bitmap size")
+            })
+    }
+
+    /// Set bits of a bitmap with the specified words and infinite flag
+    fn iter_ones(
+        words: [u64; WORDS],
+        infinite: bool,
+    ) -> impl FusedIterator<Item = BitmapIndex> + Clone {
+        let finite = words
+            .into_iter()
+            .enumerate()
+            .flat_map(|(word_idx, word)| WordBits(word).map(move |bit| word_idx * WORD_BITS + bit));
+        let tail = infinite
+            .then_some(Self::CAPACITY..=usize::from(BitmapIndex::MAX))
+            .into_iter()
+            .flatten();
+        finite
+            .chain(tail)
+            .map(|idx| BitmapIndex::try_from(idx).expect("CAPACITY should be a valid bitmap size"))
+            .fuse()
+    }
+
+    /// Write the list representation of this bitmap
+    ///
+    /// This is the same format as that of [`Bitmap::display_list()`].
+    fn write_list(&self, f: &mut impl Write) -> fmt::Result {
+        let mut first = true;
+        let mut run_start = None;
+        for idx in 0..=Self::CAPACITY {
+            let is_set = self.is_set(idx);
+            match (run_start, is_set) {
+                (None, true) => run_start = Some(idx),
+                (Some(start), false) => {
+                    if !first {
+                        f.write_char(',')?;
+                    }
+                    first = false;
+                    if idx - 1 == start {
+                        write!(f, "{start}")?;
+                    } else {
+                        write!(f, "{start}-{}", idx - 1)?;
+                    }
+                    run_start = None;
+                }
+                (None, false) | (Some(_), true) => {}
+            }
+        }
+        if let Some(start) = run_start {
+            if !first {
+                f.write_char(',')?;
+            }
+            write!(f, "{start}-")?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of indices stored in each word of an [`InlineBitmap`]
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Iterator over the set bits of a word, from least to most significant
+#[derive(Copy, Clone, Debug)]
+struct WordBits(u64);
+//
+impl Iterator for WordBits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        (self.0 != 0).then(|| {
+            let bit = self.0.trailing_zeros() as usize;
+            self.0 &= self.0 - 1;
+            bit
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+//
+impl FusedIterator for WordBits {}
+
+#[cfg(any(test, feature = "proptest"))]
+impl<const WORDS: usize> Arbitrary for InlineBitmap<WORDS> {
+    type Parameters = ();
+    type Strategy = prop::strategy::Map<
+        (
+            prop::collection::VecStrategy<prop::num::u64::Any>,
+            prop::bool::Any,
+        ),
+        fn((Vec<u64>, bool)) -> Self,
+    >;
+
+    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
+        (
+            prop::collection::vec(prop::num::u64::ANY, WORDS),
+            prop::bool::ANY,
+        )
+            .prop_map(|(words, infinite)| Self {
+                words: words
+                    .try_into()
+                    .expect("Should have generated the right number of words"),
+                infinite,
+            })
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> BitAnd<B> for InlineBitmap<WORDS> {
+    type Output = Self;
+
+    fn bitand(mut self, rhs: B) -> Self {
+        self &= rhs;
+        self
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<InlineBitmap<WORDS>>> BitAnd<B> for &InlineBitmap<WORDS> {
+    type Output = InlineBitmap<WORDS>;
+
+    fn bitand(self, rhs: B) -> InlineBitmap<WORDS> {
+        *self & rhs
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> BitAndAssign<B> for InlineBitmap<WORDS> {
+    fn bitand_assign(&mut self, rhs: B) {
+        let rhs = rhs.borrow();
+        for (word, rhs_word) in self.words.iter_mut().zip(rhs.words) {
+            *word &= rhs_word;
+        }
+        self.infinite &= rhs.infinite;
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> BitOr<B> for InlineBitmap<WORDS> {
+    type Output = Self;
+
+    fn bitor(mut self, rhs: B) -> Self {
+        self |= rhs;
+        self
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<InlineBitmap<WORDS>>> BitOr<B> for &InlineBitmap<WORDS> {
+    type Output = InlineBitmap<WORDS>;
+
+    fn bitor(self, rhs: B) -> InlineBitmap<WORDS> {
+        *self | rhs
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> BitOrAssign<B> for InlineBitmap<WORDS> {
+    fn bitor_assign(&mut self, rhs: B) {
+        let rhs = rhs.borrow();
+        for (word, rhs_word) in self.words.iter_mut().zip(rhs.words) {
+            *word |= rhs_word;
+        }
+        self.infinite |= rhs.infinite;
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> BitXor<B> for InlineBitmap<WORDS> {
+    type Output = Self;
+
+    fn bitxor(mut self, rhs: B) -> Self {
+        self ^= rhs;
+        self
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<InlineBitmap<WORDS>>> BitXor<B> for &InlineBitmap<WORDS> {
+    type Output = InlineBitmap<WORDS>;
+
+    fn bitxor(self, rhs: B) -> InlineBitmap<WORDS> {
+        *self ^ rhs
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> BitXorAssign<B> for InlineBitmap<WORDS> {
+    fn bitxor_assign(&mut self, rhs: B) {
+        let rhs = rhs.borrow();
+        for (word, rhs_word) in self.words.iter_mut().zip(rhs.words) {
+            *word ^= rhs_word;
+        }
+        self.infinite ^= rhs.infinite;
+    }
+}
+
+impl<const WORDS: usize> Debug for InlineBitmap<WORDS> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl<const WORDS: usize> Default for InlineBitmap<WORDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const WORDS: usize> Display for InlineBitmap<WORDS> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut list = String::new();
+        self.write_list(&mut list)?;
+        f.pad(&list)
+    }
+}
+
+impl<const WORDS: usize, BI: Borrow<BitmapIndex>> Extend<BI> for InlineBitmap<WORDS> {
+    fn extend<T: IntoIterator<Item = BI>>(&mut self, iter: T) {
+        for idx in iter {
+            self.set(*idx.borrow());
+        }
+    }
+}
+
+impl<const WORDS: usize> From<InlineBitmap<WORDS>> for Bitmap {
+    fn from(inline: InlineBitmap<WORDS>) -> Self {
+        let mut result = Self::new();
+        for (word_idx, word) in inline.words.into_iter().enumerate() {
+            for (chunk_idx, chunk) in word.to_le_bytes().chunks(ULONG_BYTES).enumerate() {
+                let mask =
+                    c_ulong::from_le_bytes(chunk.try_into().expect("Chunks have the right size"));
+                if mask != 0 {
+                    result.set_ith_ulong(word_idx * ULONGS_PER_WORD + chunk_idx, mask);
+                }
+            }
+        }
+        if inline.infinite {
+            result.set_range(InlineBitmap::<WORDS>::CAPACITY..);
+        }
+        result
+    }
+}
+
+impl<const WORDS: usize, BI: Borrow<BitmapIndex>> FromIterator<BI> for InlineBitmap<WORDS> {
+    fn from_iter<I: IntoIterator<Item = BI>>(iter: I) -> Self {
+        let mut bitmap = Self::new();
+        bitmap.extend(iter);
+        bitmap
+    }
+}
+
+impl<const WORDS: usize> Not for InlineBitmap<WORDS> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self {
+            words: self.words.map(|word| !word),
+            infinite: !self.infinite,
+        }
+    }
+}
+
+impl<const WORDS: usize> Not for &InlineBitmap<WORDS> {
+    type Output = InlineBitmap<WORDS>;
+
+    fn not(self) -> InlineBitmap<WORDS> {
+        !*self
+    }
+}
+
+impl<const WORDS: usize> Ord for InlineBitmap<WORDS> {
+    /// Compare bitmaps like [`Bitmap`] does, i.e. as if they were integers
+    /// with index 0 as the least significant bit
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.infinite
+            .cmp(&other.infinite)
+            .then_with(|| self.words.iter().rev().cmp(other.words.iter().rev()))
+    }
+}
+
+impl<const WORDS: usize> PartialOrd for InlineBitmap<WORDS> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> Sub<B> for InlineBitmap<WORDS> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: B) -> Self {
+        self -= rhs;
+        self
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<InlineBitmap<WORDS>>> Sub<B> for &InlineBitmap<WORDS> {
+    type Output = InlineBitmap<WORDS>;
+
+    fn sub(self, rhs: B) -> InlineBitmap<WORDS> {
+        *self - rhs
+    }
+}
+
+impl<const WORDS: usize, B: Borrow<Self>> SubAssign<B> for InlineBitmap<WORDS> {
+    fn sub_assign(&mut self, rhs: B) {
+        *self &= !*rhs.borrow();
+    }
+}
+
+impl<const WORDS: usize> TryFrom<&Bitmap> for InlineBitmap<WORDS> {
+    type Error = BitmapTruncationError;
+
+    fn try_from(bitmap: &Bitmap) -> Result<Self, BitmapTruncationError> {
+        // Check that all indices above the capacity are in the same state
+        let capacity = Self::CAPACITY;
+        let infinite = bitmap.weight().is_none();
+        let last_finite_idx = if infinite {
+            bitmap.last_unset()
+        } else {
+            bitmap.last_set()
+        };
+        if last_finite_idx.is_some_and(|idx| usize::from(idx) >= capacity) {
+            let first_truncated = capacity
+                .checked_sub(1)
+                .map_or_else(
+                    || bitmap.first_set(),
+                    |last_idx| bitmap.next_set(BitmapIndex::try_from(last_idx).ok()),
+                )
+                .expect("There should be a set index above the capacity");
+            return Err(BitmapTruncationError {
+                capacity,
+                first_truncated: usize::from(first_truncated),
+            });
+        }
+
+        // Extract the indices below the capacity
+        let mut words = [0; WORDS];
+        for (word_idx, word) in words.iter_mut().enumerate() {
+            let mut bytes = [0; mem::size_of::<u64>()];
+            for (chunk_idx, chunk) in bytes.chunks_mut(ULONG_BYTES).enumerate() {
+                let mask = bitmap.ith_ulong(word_idx * ULONGS_PER_WORD + chunk_idx);
+                chunk.copy_from_slice(&mask.to_le_bytes());
+            }
+            *word = u64::from_le_bytes(bytes);
+        }
+        Ok(Self { words, infinite })
+    }
+}
+
+/// Size of a `c_ulong` in bytes
+const ULONG_BYTES: usize = mem::size_of::<c_ulong>();
+
+/// Number of `c_ulong` masks per [`InlineBitmap`] word
+const ULONGS_PER_WORD: usize = mem::size_of::<u64>() / ULONG_BYTES;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bitmap::tests::index_range, strategies::bitmap_index};
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{Binary, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex},
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    /// Inline bitmap type used in tests, which holds all indices produced by
+    /// the `Arbitrary` implementation of `Bitmap`
+    type TestBitmap = InlineBitmap<2>;
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(TestBitmap:
+        BitAnd<TestBitmap>, BitAnd<&'static TestBitmap>,
+        BitAndAssign<TestBitmap>, BitAndAssign<&'static TestBitmap>,
+        BitOr<TestBitmap>, BitOr<&'static TestBitmap>,
+        BitOrAssign<TestBitmap>, BitOrAssign<&'static TestBitmap>,
+        BitXor<TestBitmap>, BitXor<&'static TestBitmap>,
+        BitXorAssign<TestBitmap>, BitXorAssign<&'static TestBitmap>,
+        Copy, Debug, Default, Display, Extend<BitmapIndex>,
+        FromIterator<BitmapIndex>, Hash, Into<Bitmap>, Not, Ord, Send, Sized,
+        Sub<TestBitmap>, Sub<&'static TestBitmap>,
+        SubAssign<TestBitmap>, SubAssign<&'static TestBitmap>,
+        Sync, TryFrom<&'static Bitmap>, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(TestBitmap:
+        Binary, Deref, Drop, Error, IntoIterator, LowerExp, LowerHex, Octal,
+        Pointer, Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+
+    /// Number of indices that are compared when iterating over bitmaps
+    const MAX_CHECKED: usize = 2 * TestBitmap::CAPACITY;
+
+    /// Check that an inline bitmap has the same contents as a `Bitmap`
+    fn check_contents(inline: &TestBitmap, bitmap: &Bitmap) -> Result<(), TestCaseError> {
+        prop_assert_eq!(inline.is_empty(), bitmap.is_empty());
+        prop_assert_eq!(inline.is_full(), bitmap.is_full());
+        prop_assert_eq!(inline.first_set(), bitmap.first_set());
+        prop_assert_eq!(inline.last_set(), bitmap.last_set());
+        prop_assert_eq!(inline.weight(), bitmap.weight());
+        prop_assert_eq!(inline.first_unset(), bitmap.first_unset());
+        prop_assert_eq!(inline.last_unset(), bitmap.last_unset());
+        prop_assert!(inline
+            .iter_set()
+            .take(MAX_CHECKED)
+            .eq(bitmap.iter_set().take(MAX_CHECKED)));
+        prop_assert!(inline
+            .iter_unset()
+            .take(MAX_CHECKED)
+            .eq(bitmap.iter_unset().take(MAX_CHECKED)));
+        prop_assert_eq!(inline.to_string(), bitmap.to_string());
+        prop_assert_eq!(&Bitmap::from(*inline), bitmap);
+        Ok(())
+    }
+
+    #[test]
+    fn special_bitmaps() {
+        let empty = TestBitmap::new();
+        assert_eq!(empty, TestBitmap::default());
+        assert_eq!(empty.to_string(), "");
+        assert_eq!(Bitmap::from(empty), Bitmap::new());
+
+        let full = TestBitmap::full();
+        assert_eq!(full, !empty);
+        assert_eq!(full.to_string(), "0-");
+        assert_eq!(Bitmap::from(full), Bitmap::full());
+        assert!(full > empty);
+
+        let tail = TestBitmap::from_range(TestBitmap::CAPACITY..);
+        assert_eq!(
+            tail.first_set().map(usize::from),
+            Some(TestBitmap::CAPACITY)
+        );
+        assert_eq!(tail.weight(), None);
+        assert!(tail.is_set(usize::from(BitmapIndex::MAX)));
+
+        let zero_capacity = InlineBitmap::<0>::from_range(0..);
+        assert!(zero_capacity.is_full());
+        assert_eq!(Bitmap::from(zero_capacity), Bitmap::full());
+        assert_eq!(
+            InlineBitmap::<0>::try_from(&Bitmap::full()),
+            Ok(zero_capacity)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_beyond_capacity() {
+        TestBitmap::new().set(TestBitmap::CAPACITY);
+    }
+
+    #[test]
+    #[should_panic]
+    fn unset_beyond_capacity() {
+        TestBitmap::full().unset_range(TestBitmap::CAPACITY + 1..);
+    }
+
+    #[test]
+    fn truncation() {
+        let bitmap = Bitmap::from_range(60..=70);
+        assert_eq!(
+            InlineBitmap::<1>::try_from(&bitmap),
+            Err(BitmapTruncationError {
+                capacity: 64,
+                first_truncated: 64,
+            })
+        );
+        let inverted = !bitmap;
+        assert_eq!(
+            InlineBitmap::<1>::try_from(&inverted),
+            Err(BitmapTruncationError {
+                capacity: 64,
+                first_truncated: 71,
+            })
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn from_bitmap(bitmap: Bitmap) {
+            let inline = TestBitmap::try_from(&bitmap).unwrap();
+            check_contents(&inline, &bitmap)?;
+            let small_result = InlineBitmap::<1>::try_from(&bitmap);
+            if let Ok(small) = small_result {
+                prop_assert_eq!(Bitmap::from(small), bitmap);
+            }
+        }
+
+        #[test]
+        fn unary(inline: TestBitmap, idx in bitmap_index()) {
+            let bitmap = Bitmap::from(inline);
+            check_contents(&inline, &bitmap)?;
+            prop_assert_eq!(TestBitmap::try_from(&bitmap), Ok(inline));
+            prop_assert_eq!(inline.is_set(idx), bitmap.is_set(idx));
+            prop_assert_eq!(&Bitmap::from(!inline), &!&bitmap);
+            prop_assert_eq!(&Bitmap::from(!&inline), &!&bitmap);
+            let mut inverted = inline;
+            inverted.invert();
+            prop_assert_eq!(inverted, !inline);
+            prop_assert_eq!(
+                inline
+                    .iter_set()
+                    .take_while(|&idx| usize::from(idx) < TestBitmap::CAPACITY)
+                    .collect::<TestBitmap>(),
+                inline & TestBitmap::from_range(..TestBitmap::CAPACITY)
+            );
+
+            let mut buf = inline;
+            buf.set(idx);
+            prop_assert_eq!(&Bitmap::from(buf), &(bitmap.clone() | Bitmap::from(idx)));
+            buf.unset(idx);
+            prop_assert_eq!(&Bitmap::from(buf), &(bitmap - Bitmap::from(idx)));
+            buf.set_only(idx);
+            prop_assert_eq!(&Bitmap::from(buf), &Bitmap::from(idx));
+            buf.set_all_but(idx);
+            prop_assert_eq!(&Bitmap::from(buf), &!Bitmap::from(idx));
+        }
+
+        #[test]
+        fn ranges(inline: TestBitmap, range in index_range()) {
+            let bitmap = Bitmap::from(inline);
+            let range_bitmap = Bitmap::from_range(range.clone());
+            let mut buf = inline;
+            buf.set_range(range.clone());
+            prop_assert_eq!(&Bitmap::from(buf), &(bitmap.clone() | &range_bitmap));
+            buf = inline;
+            buf.unset_range(range.clone());
+            prop_assert_eq!(&Bitmap::from(buf), &(bitmap - &range_bitmap));
+            prop_assert_eq!(
+                Bitmap::from(TestBitmap::from_range(range)),
+                range_bitmap
+            );
+        }
+
+        #[test]
+        fn binary(inline1: TestBitmap, inline2: TestBitmap) {
+            let bitmap1 = Bitmap::from(inline1);
+            let bitmap2 = Bitmap::from(inline2);
+            prop_assert_eq!(inline1.intersects(inline2), bitmap1.intersects(&bitmap2));
+            prop_assert_eq!(inline1.includes(inline2), bitmap1.includes(&bitmap2));
+            prop_assert_eq!(inline1.cmp(&inline2), bitmap1.cmp(&bitmap2));
+            prop_assert_eq!(inline1 == inline2, bitmap1 == bitmap2);
+            prop_assert_eq!(&Bitmap::from(inline1 & inline2), &(&bitmap1 & &bitmap2));
+            prop_assert_eq!(&Bitmap::from(inline1 | inline2), &(&bitmap1 | &bitmap2));
+            prop_assert_eq!(&Bitmap::from(inline1 ^ inline2), &(&bitmap1 ^ &bitmap2));
+            prop_assert_eq!(&Bitmap::from(inline1 - inline2), &(&bitmap1 - &bitmap2));
+
+            let mut buf = inline1;
+            buf &= inline2;
+            prop_assert_eq!(buf, inline1 & inline2);
+            buf = inline1;
+            buf |= &inline2;
+            prop_assert_eq!(buf, inline1 | inline2);
+            buf = inline1;
+            buf ^= inline2;
+            prop_assert_eq!(buf, inline1 ^ inline2);
+            buf = inline1;
+            buf -= &inline2;
+            prop_assert_eq!(buf, inline1 - inline2);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Conversions between bitmaps and raw bit masks
+//!
+//! Operating system APIs like Linux's `sched_setaffinity` and `mbind` represent
+//! sets of CPUs and NUMA nodes as arrays of `unsigned long` masks, where bit
+//! `j` of mask `i` stands for index `i * c_ulong::BITS + j`. The `from_ulongs`,
+//! `to_ulongs` and `to_ulong_vec` methods of bitmap types convert to and from
+//! this representation.
+
+#[cfg(doc)]
+use super::{Bitmap, BitmapIndex};
+#[cfg(doc)]
+use crate::{cpu::cpuset::CpuSet, memory::nodeset::NodeSet};
+use std::ffi::c_ulong;
+use thiserror::Error;
+
+/// Number of bitmap indices that fit in a single `c_ulong` mask
+pub(super) const ULONG_BITS: usize = c_ulong::BITS as usize;
+
+/// Some set indices do not fit in the destination of a bitmap conversion
+///
+/// This happens when converting a [`Bitmap`], [`CpuSet`] or [`NodeSet`] into a
+/// fixed-size mask that is too small, in which case the indices that did fit
+/// have been written to the mask nonetheless. It also happens when converting
+/// masks with set bits above [`BitmapIndex::MAX`] into a bitmap.
+#[derive(Copy, Clone, Debug, Eq, Error, Hash, PartialEq)]
+#[error("index {first_truncated} does not fit in a destination of {capacity} indices")]
+pub struct BitmapTruncationError {
+    /// Number of indices that the destination can hold
+    pub capacity: usize,
+
+    /// First set index of the source that did not fit in the destination
+    pub first_truncated: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(BitmapTruncationError:
+        Copy, Debug, Display, Error, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(BitmapTruncationError:
+        Binary, Default, Deref, Drop, IntoIterator, LowerExp, LowerHex, Octal,
+        PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+}
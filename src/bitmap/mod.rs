@@ -49,18 +49,24 @@
 //
 // Main docs: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__bitmap.html
 
+mod format;
+mod inline;
+mod mask;
 mod newtypes;
+mod parse;
+mod rank;
 mod reference;
+#[cfg(feature = "serde")]
+mod serde;
 
 #[cfg(doc)]
 use crate::{
     cpu::cpuset::CpuSet, memory::nodeset::NodeSet, object::TopologyObject, topology::Topology,
 };
-use crate::{
-    errors,
-    ffi::{self, PositiveInt},
-};
+use crate::{errors, ffi::PositiveInt};
+use format::BitmapFormat;
 use hwlocality_sys::hwloc_bitmap_s;
+use mask::ULONG_BITS;
 #[cfg(any(test, feature = "proptest"))]
 use proptest::prelude::*;
 #[allow(unused)]
@@ -72,15 +78,16 @@ use std::{
     borrow::Borrow,
     cmp::Ordering,
     convert::TryFrom,
-    ffi::{c_int, c_uint},
+    ffi::{c_int, c_uint, c_ulong},
     fmt::{self, Debug, Display, Formatter, Pointer},
     hash::{self, Hash},
     iter::{FromIterator, FusedIterator},
     ops::{
         BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Bound, Deref, Not,
-        RangeBounds, Sub, SubAssign,
+        RangeBounds, RangeInclusive, Sub, SubAssign,
     },
     ptr::NonNull,
+    str::FromStr,
 };
 
 /// Valid bitmap index ranging from `0` to [`c_int::MAX`]
@@ -88,10 +95,26 @@ pub type BitmapIndex = PositiveInt;
 
 // Re-export BitmapRef so users don't need to know about the reference submodule
 pub use self::{
+    format::BitmapDisplay,
+    inline::InlineBitmap,
+    mask::BitmapTruncationError,
     newtypes::{BitmapKind, OwnedBitmap, OwnedSpecializedBitmap, SpecializedBitmap},
+    parse::{ParseBitmapError, ParseBitmapErrorKind},
+    rank::RankCache,
     reference::BitmapRef,
 };
 
+/// Parse a bitmap from its list representation, like `0-3,8`
+///
+/// This keeps tests that spell out many bitmaps readable.
+#[cfg(test)]
+pub(crate) fn parse_list<Set: FromStr>(list: &str) -> Set
+where
+    Set::Err: Debug,
+{
+    list.parse().expect("test bitmap lists should be valid")
+}
+
 /// A generic bitmap, understood by hwloc
 ///
 /// The `Bitmap` type represents a set of integers (positive or null). A bitmap
@@ -140,6 +163,24 @@ pub use self::{
 /// # Ok::<(), eyre::Report>(())
 /// ```
 ///
+/// # Textual representation
+///
+/// Bitmaps are displayed using hwloc's list format (e.g. `0-3,8,10-`), or
+/// hwloc's native `0x…,0x…` hexadecimal format when the `{:#}` alternate form
+/// is requested. Other formats can be selected using [`display_list()`],
+/// [`display_taskset()`] and [`display_hex()`].
+///
+/// Bitmaps can be parsed back from any of these formats via [`FromStr`]:
+///
+/// ```
+/// # use hwlocality::bitmap::Bitmap;
+/// let list = "0-3,8,10-11".parse::<Bitmap>()?;
+/// let hex = "0x00000d0f".parse::<Bitmap>()?;
+/// assert_eq!(list, hex);
+/// assert_eq!(list.to_string(), "0-3,8,10-11");
+/// # Ok::<(), eyre::Report>(())
+/// ```
+///
 /// # Panics
 ///
 /// Unlike most hwloc entry points in this crate, `Bitmap` functions always
@@ -150,6 +191,9 @@ pub use self::{
 /// way to handle this in Rust.
 ///
 /// [`CpuSet`]: crate::cpu::cpuset::CpuSet
+/// [`display_hex()`]: Bitmap::display_hex()
+/// [`display_list()`]: Bitmap::display_list()
+/// [`display_taskset()`]: Bitmap::display_taskset()
 /// [`NodeSet`]: crate::memory::nodeset::NodeSet
 //
 // --- Implementation details ---
@@ -832,6 +876,36 @@ impl Bitmap {
         Iter::new(self, Self::next_set)
     }
 
+    /// Iterate over ranges of consecutive set indices
+    ///
+    /// This is a faster alternative to [`Bitmap::iter_set()`] when you are
+    /// interested in contiguous runs of set indices rather than individual
+    /// indices. Ranges are yielded in increasing index order, and two
+    /// consecutive ranges are always separated by at least one unset index.
+    ///
+    /// If the bitmap is infinitely set, the last range is open-ended. It is
+    /// reported as ending at [`BitmapIndex::MAX`], which is the highest index
+    /// that a bitmap can hold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::{Bitmap, BitmapIndex};
+    ///
+    /// let mut bitmap = Bitmap::from_range(0..=3);
+    /// bitmap.set(8);
+    /// bitmap.set_range(10..);
+    /// let ranges = bitmap.iter_set_ranges().collect::<Vec<_>>();
+    /// assert_eq!(ranges.len(), 3);
+    /// assert_eq!(ranges[0], BitmapIndex::try_from(0)?..=BitmapIndex::try_from(3)?);
+    /// assert_eq!(ranges[1], BitmapIndex::try_from(8)?..=BitmapIndex::try_from(8)?);
+    /// assert_eq!(ranges[2], BitmapIndex::try_from(10)?..=BitmapIndex::MAX);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn iter_set_ranges(&self) -> Ranges<&Self> {
+        Ranges::new(self, Self::next_set, Self::next_unset)
+    }
+
     /// Check the last set index, if any
     ///
     /// # Examples
@@ -880,6 +954,75 @@ impl Bitmap {
         usize::try_from(result).ok()
     }
 
+    /// Find the `n`-th set index, counting from 0, if any
+    ///
+    /// This is equivalent to `self.iter_set().nth(n)`, but faster as it
+    /// operates on ranges of consecutive set indices rather than individual
+    /// indices. If you need to perform many of these queries on a bitmap that
+    /// does not change, consider building a [`RankCache`] instead.
+    ///
+    /// `None` is returned if less than `n + 1` indices are set, or if the
+    /// `n`-th set index would be above [`BitmapIndex::MAX`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(2..=3);
+    /// bitmap.set_range(8..);
+    /// let nth_set_usize = |n| bitmap.nth_set(n).map(usize::from);
+    /// assert_eq!(nth_set_usize(0), Some(2));
+    /// assert_eq!(nth_set_usize(1), Some(3));
+    /// assert_eq!(nth_set_usize(2), Some(8));
+    /// assert_eq!(nth_set_usize(42), Some(48));
+    /// assert_eq!(Bitmap::from_range(2..=3).nth_set(2), None);
+    /// ```
+    pub fn nth_set(&self, n: usize) -> Option<BitmapIndex> {
+        Self::nth_in_ranges(self.iter_set_ranges(), n)
+    }
+
+    /// Number of set indices that come before index `idx`
+    ///
+    /// This is the inverse of [`Bitmap::nth_set()`]: if `idx` is set, then
+    /// `self.nth_set(self.rank(idx))` is `Some(idx)`. If you need to perform
+    /// many of these queries on a bitmap that does not change, consider
+    /// building a [`RankCache`] instead.
+    ///
+    /// Accepts both [`BitmapIndex`] and [`usize`] operands. Use the former for
+    /// type-safety (it is guaranteed to be in range as a type invariant) or the
+    /// latter for convenience (it is more tightly integrated with Rust's
+    /// built-in integer support, for example it supports integer literals).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(2..=3);
+    /// bitmap.set_range(8..);
+    /// assert_eq!(bitmap.rank(0), 0);
+    /// assert_eq!(bitmap.rank(3), 1);
+    /// assert_eq!(bitmap.rank(8), 2);
+    /// assert_eq!(bitmap.rank(48), 42);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is above the implementation-defined maximum index (at least
+    /// 2^15-1, usually 2^31-1).
+    pub fn rank<Idx>(&self, idx: Idx) -> usize
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(self_: &Bitmap, idx: Option<BitmapIndex>) -> usize {
+            Bitmap::rank_in_ranges(self_.iter_set_ranges(), idx.expect(BAD_INDEX))
+        }
+        polymorphized(self, idx.try_into().ok())
+    }
+
     /// Check the first unset index, if any
     ///
     /// You can iterate over set indices with [`Bitmap::iter_unset()`].
@@ -920,6 +1063,35 @@ impl Bitmap {
         Iter::new(self, Self::next_unset)
     }
 
+    /// Iterate over ranges of consecutive unset indices
+    ///
+    /// This is a faster alternative to [`Bitmap::iter_unset()`] when you are
+    /// interested in contiguous runs of unset indices rather than individual
+    /// indices. Ranges are yielded in increasing index order, and two
+    /// consecutive ranges are always separated by at least one set index.
+    ///
+    /// If the bitmap is finite, the last range is open-ended. It is reported
+    /// as ending at [`BitmapIndex::MAX`], which is the highest index that a
+    /// bitmap can hold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::{Bitmap, BitmapIndex};
+    ///
+    /// let mut bitmap = Bitmap::from_range(2..=3);
+    /// bitmap.set(8);
+    /// let ranges = bitmap.iter_unset_ranges().collect::<Vec<_>>();
+    /// assert_eq!(ranges.len(), 3);
+    /// assert_eq!(ranges[0], BitmapIndex::MIN..=BitmapIndex::try_from(1)?);
+    /// assert_eq!(ranges[1], BitmapIndex::try_from(4)?..=BitmapIndex::try_from(7)?);
+    /// assert_eq!(ranges[2], BitmapIndex::try_from(9)?..=BitmapIndex::MAX);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn iter_unset_ranges(&self) -> Ranges<&Self> {
+        Ranges::new(self, Self::next_unset, Self::next_set)
+    }
+
     /// Check the last unset index, if any
     ///
     /// # Examples
@@ -942,6 +1114,73 @@ impl Bitmap {
         )
     }
 
+    /// Find the `n`-th unset index, counting from 0, if any
+    ///
+    /// This is equivalent to `self.iter_unset().nth(n)`, but faster as it
+    /// operates on ranges of consecutive unset indices rather than individual
+    /// indices. If you need to perform many of these queries on a bitmap that
+    /// does not change, consider building a [`RankCache`] instead.
+    ///
+    /// `None` is returned if less than `n + 1` indices are unset, or if the
+    /// `n`-th unset index would be above [`BitmapIndex::MAX`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(2..=3);
+    /// bitmap.set_range(8..);
+    /// let nth_unset_usize = |n| bitmap.nth_unset(n).map(usize::from);
+    /// assert_eq!(nth_unset_usize(0), Some(0));
+    /// assert_eq!(nth_unset_usize(2), Some(4));
+    /// assert_eq!(nth_unset_usize(5), Some(7));
+    /// assert_eq!(nth_unset_usize(6), None);
+    /// ```
+    pub fn nth_unset(&self, n: usize) -> Option<BitmapIndex> {
+        Self::nth_in_ranges(self.iter_unset_ranges(), n)
+    }
+
+    /// Number of unset indices that come before index `idx`
+    ///
+    /// This is the inverse of [`Bitmap::nth_unset()`]: if `idx` is unset,
+    /// then `self.nth_unset(self.rank_unset(idx))` is `Some(idx)`. If you need
+    /// to perform many of these queries on a bitmap that does not change,
+    /// consider building a [`RankCache`] instead.
+    ///
+    /// Accepts both [`BitmapIndex`] and [`usize`] operands. Use the former for
+    /// type-safety (it is guaranteed to be in range as a type invariant) or the
+    /// latter for convenience (it is more tightly integrated with Rust's
+    /// built-in integer support, for example it supports integer literals).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(2..=3);
+    /// bitmap.set_range(8..);
+    /// assert_eq!(bitmap.rank_unset(0), 0);
+    /// assert_eq!(bitmap.rank_unset(4), 2);
+    /// assert_eq!(bitmap.rank_unset(48), 6);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is above the implementation-defined maximum index (at least
+    /// 2^15-1, usually 2^31-1).
+    pub fn rank_unset<Idx>(&self, idx: Idx) -> usize
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(self_: &Bitmap, idx: Option<BitmapIndex>) -> usize {
+            Bitmap::rank_in_ranges(self_.iter_unset_ranges(), idx.expect(BAD_INDEX))
+        }
+        polymorphized(self, idx.try_into().ok())
+    }
+
     /// Optimized version of `*self = !self`
     ///
     /// # Examples
@@ -1024,6 +1263,240 @@ impl Bitmap {
         polymorphized(self, &inner)
     }
 
+    /// Build a bitmap from an array of `c_ulong` masks
+    ///
+    /// Bit `j` of `masks[i]` becomes index `i * c_ulong::BITS + j` of the
+    /// bitmap. This is the mask layout used by Linux system calls like
+    /// `sched_setaffinity` and `mbind`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    /// use std::ffi::c_ulong;
+    ///
+    /// let bitmap = Bitmap::from_ulongs(&[0b1101, 0b1])?;
+    /// let mut expected = Bitmap::from_range(2..=3);
+    /// expected.set(0);
+    /// expected.set(c_ulong::BITS as usize);
+    /// assert_eq!(bitmap, expected);
+    /// # Ok::<(), hwlocality::bitmap::BitmapTruncationError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - [`BitmapTruncationError`] if `masks` has set bits above the
+    ///   implementation-defined maximum index (at least 2^15-1, usually
+    ///   2^31-1), which cannot be represented by a bitmap.
+    #[doc(alias = "hwloc_bitmap_set_ith_ulong")]
+    pub fn from_ulongs(masks: &[c_ulong]) -> Result<Self, BitmapTruncationError> {
+        // Check that all set bits map into valid bitmap indices
+        let capacity = usize::from(BitmapIndex::MAX) + 1;
+        let first_truncated = masks.iter().enumerate().find_map(|(i, &mask)| {
+            let first_index = i * ULONG_BITS;
+            let num_valid_bits = capacity.saturating_sub(first_index);
+            let invalid_bits = if num_valid_bits >= ULONG_BITS {
+                0
+            } else {
+                mask >> num_valid_bits
+            };
+            (invalid_bits != 0)
+                .then(|| first_index + num_valid_bits + invalid_bits.trailing_zeros() as usize)
+        });
+        if let Some(first_truncated) = first_truncated {
+            return Err(BitmapTruncationError {
+                capacity,
+                first_truncated,
+            });
+        }
+
+        // Build the bitmap
+        let mut result = Self::new();
+        for (i, &mask) in masks.iter().enumerate().filter(|(_, &mask)| mask != 0) {
+            result.set_ith_ulong(i, mask);
+        }
+        Ok(result)
+    }
+
+    /// Write this bitmap into an array of `c_ulong` masks
+    ///
+    /// This is the inverse of [`Bitmap::from_ulongs()`]. All masks are
+    /// overwritten, so masks which lie beyond the last set index of the bitmap
+    /// are zeroed out.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitmapTruncationError`] if some set indices of the bitmap do not fit
+    ///   in `masks`, which is always the case for infinite bitmaps. The indices
+    ///   that did fit are written to `masks` nonetheless.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(2..=3);
+    /// let mut masks = [0; 2];
+    /// bitmap.to_ulongs(&mut masks)?;
+    /// assert_eq!(masks, [0b1100, 0]);
+    ///
+    /// bitmap.set(200);
+    /// let error = bitmap.to_ulongs(&mut masks).unwrap_err();
+    /// assert_eq!(error.first_truncated, 200);
+    /// assert_eq!(masks, [0b1100, 0]);
+    /// # Ok::<(), hwlocality::bitmap::BitmapTruncationError>(())
+    /// ```
+    #[doc(alias = "hwloc_bitmap_to_ith_ulong")]
+    pub fn to_ulongs(&self, masks: &mut [c_ulong]) -> Result<(), BitmapTruncationError> {
+        // Masks beyond the maximum bitmap index do not map into the bitmap
+        let max_masks = (usize::from(BitmapIndex::MAX) + 1) / ULONG_BITS;
+        let (valid_masks, invalid_masks) = masks.split_at_mut(masks.len().min(max_masks));
+        for (i, mask) in valid_masks.iter_mut().enumerate() {
+            *mask = self.ith_ulong(i);
+        }
+        invalid_masks.fill(0);
+
+        // Check for set indices beyond the end of the masks
+        let capacity = masks.len().saturating_mul(ULONG_BITS);
+        let first_truncated = capacity.checked_sub(1).map_or_else(
+            || self.first_set(),
+            |last_idx| {
+                BitmapIndex::try_from(last_idx)
+                    .ok()
+                    .filter(|&last_idx| last_idx != BitmapIndex::MAX)
+                    .and_then(|last_idx| self.next_set(Some(last_idx)))
+            },
+        );
+        first_truncated.map_or(Ok(()), |first_truncated| {
+            Err(BitmapTruncationError {
+                capacity,
+                first_truncated: usize::from(first_truncated),
+            })
+        })
+    }
+
+    /// Convert this bitmap into a vector of `c_ulong` masks
+    ///
+    /// This is a variant of [`Bitmap::to_ulongs()`] that allocates just enough
+    /// masks to hold all the set indices of a finite bitmap.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitmapTruncationError`] if the bitmap is infinite
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// assert!(Bitmap::new().to_ulong_vec()?.is_empty());
+    /// assert_eq!(Bitmap::from_range(2..=3).to_ulong_vec()?, [0b1100]);
+    /// assert!(Bitmap::full().to_ulong_vec().is_err());
+    /// # Ok::<(), hwlocality::bitmap::BitmapTruncationError>(())
+    /// ```
+    pub fn to_ulong_vec(&self) -> Result<Vec<c_ulong>, BitmapTruncationError> {
+        let last_finite_idx = if self.weight().is_some() {
+            self.last_set()
+        } else {
+            self.last_unset()
+        };
+        let num_masks = last_finite_idx.map_or(0, |idx| usize::from(idx) / ULONG_BITS + 1);
+        let mut masks = vec![0; num_masks];
+        self.to_ulongs(&mut masks)?;
+        Ok(masks)
+    }
+
+    /// Precompute a [`RankCache`] for fast repeated rank/select queries
+    ///
+    /// The cache is a snapshot of the current bitmap contents, it will not
+    /// reflect subsequent modifications of the bitmap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(2..=3);
+    /// bitmap.set_range(8..);
+    /// let cache = bitmap.rank_cache();
+    /// assert_eq!(cache.nth_set(2), bitmap.nth_set(2));
+    /// assert_eq!(cache.rank(48), bitmap.rank(48));
+    /// ```
+    pub fn rank_cache(&self) -> RankCache {
+        RankCache::new(self)
+    }
+
+    /// Display this bitmap in hwloc's list format
+    ///
+    /// This is the same format that the `Display` implementation of `Bitmap`
+    /// uses by default: a comma-separated list of indices and inclusive index
+    /// ranges. Infinite bitmaps end with an open range, and empty bitmaps are
+    /// displayed as an empty string. This is the format expected by e.g.
+    /// `numactl --physcpubind`, `docker run --cpuset-cpus` and cgroup cpusets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(0..=3);
+    /// bitmap.set(8);
+    /// bitmap.set_range(10..);
+    /// assert_eq!(bitmap.display_list().to_string(), "0-3,8,10-");
+    /// ```
+    #[doc(alias = "hwloc_bitmap_list_snprintf")]
+    pub fn display_list(&self) -> BitmapDisplay<'_> {
+        BitmapDisplay::new(self, BitmapFormat::List)
+    }
+
+    /// Display this bitmap in the `taskset` format
+    ///
+    /// This is a single hexadecimal mask with a `0x` prefix, as expected by
+    /// the `taskset` command-line tool. Infinite bitmaps start with a
+    /// `0xf...f` prefix that denotes an infinite sequence of set bits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(0..=3);
+    /// bitmap.set(8);
+    /// bitmap.set_range(10..=11);
+    /// assert_eq!(bitmap.display_taskset().to_string(), "0xd0f");
+    /// assert_eq!(Bitmap::full().display_taskset().to_string(), "0xf...f");
+    /// ```
+    #[doc(alias = "hwloc_bitmap_taskset_snprintf")]
+    pub fn display_taskset(&self) -> BitmapDisplay<'_> {
+        BitmapDisplay::new(self, BitmapFormat::Taskset)
+    }
+
+    /// Display this bitmap in hwloc's native hexadecimal format
+    ///
+    /// This is a comma-separated list of 32-bit hexadecimal chunks with a `0x`
+    /// prefix, ordered from most significant to least significant, where
+    /// all-zero chunks in the middle of the mask are left empty. Infinite
+    /// bitmaps start with a `0xf...f` chunk.
+    ///
+    /// This format can also be selected using the `{:#}` alternate form of
+    /// the `Display` implementation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hwlocality::bitmap::Bitmap;
+    ///
+    /// let mut bitmap = Bitmap::from_range(0..=3);
+    /// bitmap.set(8);
+    /// bitmap.set_range(10..=11);
+    /// assert_eq!(bitmap.display_hex().to_string(), "0x00000d0f");
+    /// assert_eq!(format!("{bitmap:#}"), "0x00000d0f");
+    /// ```
+    #[doc(alias = "hwloc_bitmap_snprintf")]
+    pub fn display_hex(&self) -> BitmapDisplay<'_> {
+        BitmapDisplay::new(self, BitmapFormat::Hex)
+    }
+
     // NOTE: When adding new methods, remember to add them to impl_newtype_ops too
 
     // === Implementation details ===
@@ -1106,6 +1579,33 @@ impl Bitmap {
         .unwrap_or((1, 0))
     }
 
+    /// Common logic for `nth_set()` and `nth_unset()`
+    fn nth_in_ranges(ranges: Ranges<&Self>, n: usize) -> Option<BitmapIndex> {
+        let mut remaining = n;
+        for range in ranges {
+            let start = usize::from(*range.start());
+            let len = usize::from(*range.end()) - start + 1;
+            if remaining < len {
+                return Some(
+                    BitmapIndex::try_from(start + remaining)
+                        .expect("Should be in range as it's below range.end()"),
+                );
+            }
+            remaining -= len;
+        }
+        None
+    }
+
+    /// Common logic for `rank()` and `rank_unset()`
+    fn rank_in_ranges(ranges: Ranges<&Self>, idx: BitmapIndex) -> usize {
+        let idx = usize::from(idx);
+        ranges
+            .map(|range| (usize::from(*range.start()), usize::from(*range.end())))
+            .take_while(|&(start, _end)| start < idx)
+            .map(|(start, end)| end.min(idx - 1) - start + 1)
+            .sum()
+    }
+
     /// Common logic for first/last/next set/unset index queries
     fn query_index(&self, api: &'static str, call: impl FnOnce() -> c_int) -> Option<BitmapIndex> {
         let result = errors::call_hwloc_int_raw(api, call, -1).expect(SHOULD_NOT_FAIL);
@@ -1138,6 +1638,43 @@ impl Bitmap {
         })
     }
 
+    /// Overwrite the `i`-th `c_ulong` mask of this bitmap
+    ///
+    /// # Panics
+    ///
+    /// If the highest index covered by `mask` is above the
+    /// implementation-defined maximum index.
+    fn set_ith_ulong(&mut self, i: usize, mask: c_ulong) {
+        // Check that the highest set bit of this mask is a valid index, which
+        // also guarantees that i fits in a c_uint
+        let highest_bit = (ULONG_BITS - 1).saturating_sub(mask.leading_zeros() as usize);
+        BitmapIndex::try_from(i * ULONG_BITS + highest_bit).expect(BAD_INDEX);
+        let i = c_uint::try_from(i).expect("Checked above");
+        errors::call_hwloc_int_normal("hwloc_bitmap_set_ith_ulong", || {
+            // SAFETY: - Bitmaps are trusted to contain a valid ptr (type invariant)
+            //         - hwloc ops are trusted to keep *mut parameters in a
+            //           valid state unless stated otherwise
+            //         - i has been checked to map into valid bitmap indices
+            unsafe { hwlocality_sys::hwloc_bitmap_set_ith_ulong(self.as_mut_ptr(), i, mask) }
+        })
+        .expect(MALLOC_FAIL_ONLY);
+    }
+
+    /// Read the `i`-th `c_ulong` mask of this bitmap
+    ///
+    /// # Panics
+    ///
+    /// If the indices covered by the `i`-th mask are above the
+    /// implementation-defined maximum index.
+    fn ith_ulong(&self, i: usize) -> c_ulong {
+        BitmapIndex::try_from(i * ULONG_BITS).expect(BAD_INDEX);
+        let i = c_uint::try_from(i).expect("Checked above");
+        // SAFETY: - Bitmaps are trusted to contain a valid ptr (type invariant)
+        //         - hwloc ops are trusted not to modify *const parameters
+        //         - i has been checked to map into valid bitmap indices
+        unsafe { hwlocality_sys::hwloc_bitmap_to_ith_ulong(self.as_ptr(), i) }
+    }
+
     /// Set index iterator building block
     fn next_set(&self, index: Option<BitmapIndex>) -> Option<BitmapIndex> {
         // SAFETY: This function is an hwloc bitmap iteration function
@@ -1375,7 +1912,7 @@ impl Clone for Bitmap {
 
 impl Debug for Bitmap {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        <Self as Display>::fmt(self, f)
+        Display::fmt(&self.display_list(), f)
     }
 }
 
@@ -1387,14 +1924,12 @@ impl Default for Bitmap {
 
 impl Display for Bitmap {
     #[doc(alias = "hwloc_bitmap_list_snprintf")]
+    #[doc(alias = "hwloc_bitmap_snprintf")]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        // SAFETY: - Bitmaps are trusted to contain a valid ptr (type invariant)
-        //         - hwloc ops are trusted not to modify *const parameters
-        //         - hwloc_bitmap_list_snprintf is snprintf-like
-        unsafe {
-            ffi::write_snprintf(f, |buf, len| {
-                hwlocality_sys::hwloc_bitmap_list_snprintf(buf, len, self.as_ptr())
-            })
+        if f.alternate() {
+            Display::fmt(&self.display_hex(), f)
+        } else {
+            Display::fmt(&self.display_list(), f)
         }
     }
 }
@@ -1436,6 +1971,14 @@ impl<BI: Borrow<BitmapIndex>> FromIterator<BI> for Bitmap {
     }
 }
 
+impl FromStr for Bitmap {
+    type Err = ParseBitmapError;
+
+    fn from_str(s: &str) -> Result<Self, ParseBitmapError> {
+        parse::parse(s)
+    }
+}
+
 impl Hash for Bitmap {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
         // Beware not to iterate infinitely over infinite bitmaps
@@ -1512,6 +2055,75 @@ impl IntoIterator for Bitmap {
     }
 }
 
+/// Iterator over ranges of consecutive set or unset [`Bitmap`] indices
+///
+/// This `struct` is created by the `iter_set_ranges()` and
+/// `iter_unset_ranges()` methods of bitmap types. See their documentation for
+/// more information.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Ranges<B> {
+    /// Bitmap over which we're iterating
+    bitmap: B,
+
+    /// End of the last emitted range
+    prev_end: Option<BitmapIndex>,
+
+    /// Truth that the last range, which may be open-ended, has been emitted
+    exhausted: bool,
+
+    /// Mapping from last index to first index of the next range
+    next_start: fn(&Bitmap, Option<BitmapIndex>) -> Option<BitmapIndex>,
+
+    /// Mapping from range start to first index past the end of the range
+    next_past_end: fn(&Bitmap, Option<BitmapIndex>) -> Option<BitmapIndex>,
+}
+//
+impl<B> Ranges<B> {
+    /// Set up a bitmap range iterator
+    fn new(
+        bitmap: B,
+        next_start: fn(&Bitmap, Option<BitmapIndex>) -> Option<BitmapIndex>,
+        next_past_end: fn(&Bitmap, Option<BitmapIndex>) -> Option<BitmapIndex>,
+    ) -> Self {
+        Self {
+            bitmap,
+            prev_end: None,
+            exhausted: false,
+            next_start,
+            next_past_end,
+        }
+    }
+}
+//
+impl<B: Borrow<Bitmap>> Iterator for Ranges<B> {
+    type Item = RangeInclusive<BitmapIndex>;
+
+    fn next(&mut self) -> Option<RangeInclusive<BitmapIndex>> {
+        if self.exhausted {
+            return None;
+        }
+        let bitmap = self.bitmap.borrow();
+        let Some(start) = (self.next_start)(bitmap, self.prev_end) else {
+            self.exhausted = true;
+            return None;
+        };
+        let end = if let Some(past_end) = (self.next_past_end)(bitmap, Some(start)) {
+            past_end
+                .checked_add_signed(-1)
+                .expect("Should not underflow as past_end > start >= 0")
+        } else {
+            // Open-ended range, we can't go beyond it (and shouldn't try to,
+            // as querying past BitmapIndex::MAX is not allowed)
+            self.exhausted = true;
+            BitmapIndex::MAX
+        };
+        self.prev_end = Some(end);
+        Some(start..=end)
+    }
+}
+//
+impl<B: Borrow<Bitmap>> FusedIterator for Ranges<B> {}
+
 impl Not for &Bitmap {
     type Output = Bitmap;
 
@@ -1687,7 +2299,7 @@ pub(crate) mod tests {
         Extend<BitmapIndex>, Extend<&'static BitmapIndex>,
         From<BitmapIndex>, From<&'static BitmapIndex>,
         FromIterator<BitmapIndex>, FromIterator<&'static BitmapIndex>,
-        Hash, IntoIterator<Item=BitmapIndex>, Not, Ord, OwnedBitmap,
+        FromStr, Hash, IntoIterator<Item=BitmapIndex>, Not, Ord, OwnedBitmap,
         PartialEq<&'static Bitmap>,
         PartialOrd<&'static Bitmap>,
         Pointer, Sized,
@@ -1724,6 +2336,22 @@ pub(crate) mod tests {
         Binary, Default, Deref, Display, LowerExp, LowerHex, Octal, Pointer,
         Read, UpperExp, UpperHex, fmt::Write, io::Write
     );
+    assert_impl_all!(Ranges<Bitmap>:
+        Clone, Debug, FusedIterator<Item=RangeInclusive<BitmapIndex>>, Hash,
+        Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(Ranges<Bitmap>:
+        Binary, Copy, Default, Deref, Display, LowerExp, LowerHex, Octal,
+        Pointer, Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(Ranges<&Bitmap>:
+        Copy, Debug, FusedIterator<Item=RangeInclusive<BitmapIndex>>, Hash,
+        Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(Ranges<&Bitmap>:
+        Binary, Default, Deref, Display, LowerExp, LowerHex, Octal, Pointer,
+        Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
 
     // We can't fully check the value of infinite iterators because that would
     // literally take forever, so we only check a small subrange of the final
@@ -1904,6 +2532,21 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ulongs_above_max_index() {
+        // Zeroed allocations are lazily backed by the OS, so this is cheap
+        let capacity = usize::from(BitmapIndex::MAX) + 1;
+        let mut masks = vec![0; capacity / ULONG_BITS + 1];
+        *masks.last_mut().unwrap() = 0b100;
+        assert_eq!(
+            Bitmap::from_ulongs(&masks),
+            Err(BitmapTruncationError {
+                capacity,
+                first_truncated: capacity + 2,
+            })
+        );
+    }
+
     #[allow(clippy::redundant_clone)]
     #[test]
     fn empty() -> Result<(), TestCaseError> {
@@ -1919,6 +2562,10 @@ pub(crate) mod tests {
             prop_assert!(!empty.is_full());
             prop_assert_eq!(empty.into_iter().count(), 0);
             prop_assert_eq!(empty.iter_set().count(), 0);
+            prop_assert_eq!(empty.iter_set_ranges().count(), 0);
+            prop_assert!(empty
+                .iter_unset_ranges()
+                .eq([BitmapIndex::MIN..=BitmapIndex::MAX]));
             prop_assert_eq!(empty.last_set(), None);
             prop_assert_eq!(empty.last_unset(), None);
             prop_assert_eq!(empty.weight(), Some(0));
@@ -2047,6 +2694,10 @@ pub(crate) mod tests {
             prop_assert!(!full.is_empty());
             prop_assert!(full.is_full());
             prop_assert_eq!(full.iter_unset().count(), 0);
+            prop_assert_eq!(full.iter_unset_ranges().count(), 0);
+            prop_assert!(full
+                .iter_set_ranges()
+                .eq([BitmapIndex::MIN..=BitmapIndex::MAX]));
             prop_assert_eq!(full.last_set(), None);
             prop_assert_eq!(full.last_unset(), None);
             prop_assert_eq!(full.weight(), None);
@@ -2549,6 +3200,7 @@ pub(crate) mod tests {
             prop_assert_eq!(&(!bitmap.clone()), &inverse);
             prop_assert_eq!(&format!("{bitmap:?}"), &display);
             prop_assert_eq!(&format!("{bitmap}"), &display);
+            prop_assert_eq!(&display.parse::<Bitmap>().unwrap(), &bitmap);
 
             // Test properties that should be true of all bitmaps
             test_basic_inplace(&bitmap, &inverse)?;
@@ -2605,6 +3257,91 @@ pub(crate) mod tests {
             prop_assert_eq!(!&clone, inverse);
         }
 
+        #[test]
+        fn arbitrary_ranges(bitmap: Bitmap) {
+            // Check that ranges are well-formed, then rebuild the bitmap from them
+            fn rebuild(
+                ranges: impl Iterator<Item = RangeInclusive<BitmapIndex>>,
+                expect_open_ended: bool,
+            ) -> Result<Bitmap, TestCaseError> {
+                let mut result = Bitmap::new();
+                let mut prev_end = None;
+                let mut open_ended = false;
+                for range in ranges {
+                    prop_assert!(!open_ended);
+                    prop_assert!(range.start() <= range.end());
+                    if let Some(prev_end) = prev_end {
+                        prop_assert!(usize::from(*range.start()) > usize::from(prev_end) + 1);
+                    }
+                    prev_end = Some(*range.end());
+                    if *range.end() == BitmapIndex::MAX {
+                        open_ended = true;
+                        result.set_range(*range.start()..);
+                    } else {
+                        result.set_range(range);
+                    }
+                }
+                prop_assert_eq!(open_ended, expect_open_ended);
+                Ok(result)
+            }
+            let infinite = bitmap.weight().is_none();
+            prop_assert_eq!(&rebuild(bitmap.iter_set_ranges(), infinite)?, &bitmap);
+            prop_assert_eq!(&rebuild(bitmap.iter_unset_ranges(), !infinite)?, &!&bitmap);
+
+            // Ranges are also reachable through bitmap references
+            let bitmap_ref = BitmapRef::from(&bitmap);
+            prop_assert!(bitmap_ref.iter_set_ranges().eq(bitmap.iter_set_ranges()));
+            prop_assert!(bitmap_ref.iter_unset_ranges().eq(bitmap.iter_unset_ranges()));
+        }
+
+        #[test]
+        fn arbitrary_rank_select(bitmap: Bitmap, n in 0usize..1024, idx in bitmap_index()) {
+            prop_assert_eq!(bitmap.nth_set(n), bitmap.iter_set().nth(n));
+            prop_assert_eq!(bitmap.nth_unset(n), bitmap.iter_unset().nth(n));
+
+            let below_idx = Bitmap::from_range(..idx);
+            prop_assert_eq!(Some(bitmap.rank(idx)), (&bitmap & &below_idx).weight());
+            prop_assert_eq!(Some(bitmap.rank_unset(idx)), (!&bitmap & below_idx).weight());
+
+            if bitmap.is_set(idx) {
+                prop_assert_eq!(bitmap.nth_set(bitmap.rank(idx)), Some(idx));
+            } else {
+                prop_assert_eq!(bitmap.nth_unset(bitmap.rank_unset(idx)), Some(idx));
+            }
+        }
+
+        #[test]
+        fn arbitrary_ulongs(bitmap: Bitmap, num_masks in 0usize..8) {
+            // Fixed-size conversion
+            let mut masks = vec![0; num_masks];
+            let result = bitmap.to_ulongs(&mut masks);
+            let capacity = num_masks * ULONG_BITS;
+            let fitting = &bitmap & Bitmap::from_range(..capacity);
+            prop_assert_eq!(&Bitmap::from_ulongs(&masks).unwrap(), &fitting);
+            if fitting == bitmap {
+                prop_assert_eq!(result, Ok(()));
+            } else {
+                prop_assert_eq!(
+                    result,
+                    Err(BitmapTruncationError {
+                        capacity,
+                        first_truncated: usize::from((&bitmap - &fitting).first_set().unwrap()),
+                    })
+                );
+            }
+
+            // Dynamically sized conversion
+            if bitmap.weight().is_some() {
+                let masks = bitmap.to_ulong_vec().unwrap();
+                if let Some(&last_mask) = masks.last() {
+                    prop_assert_ne!(last_mask, 0);
+                }
+                prop_assert_eq!(&Bitmap::from_ulongs(&masks).unwrap(), &bitmap);
+            } else {
+                prop_assert!(bitmap.to_ulong_vec().is_err());
+            }
+        }
+
         #[test]
         fn arbitrary_extend(
             bitmap: Bitmap,
@@ -2,7 +2,7 @@
 
 #[cfg(doc)]
 use super::BitmapRef;
-use super::{hwloc_bitmap_s, Bitmap};
+use super::{hwloc_bitmap_s, Bitmap, BitmapDisplay, Ranges};
 use crate::Sealed;
 #[cfg(doc)]
 use crate::{cpu::cpuset::CpuSet, memory::nodeset::NodeSet};
@@ -75,6 +75,41 @@ pub trait SpecializedBitmap: AsRef<Bitmap> + Borrow<Self::Owned> + Sealed {
     ///
     /// This is a generalization of [`Clone`] that also works for [`BitmapRef`].
     fn to_owned(&self) -> Self::Owned;
+
+    /// Iterate over ranges of consecutive set indices
+    ///
+    /// See [`Bitmap::iter_set_ranges()`].
+    fn iter_set_ranges(&self) -> Ranges<&Bitmap> {
+        self.as_ref().iter_set_ranges()
+    }
+
+    /// Iterate over ranges of consecutive unset indices
+    ///
+    /// See [`Bitmap::iter_unset_ranges()`].
+    fn iter_unset_ranges(&self) -> Ranges<&Bitmap> {
+        self.as_ref().iter_unset_ranges()
+    }
+
+    /// Display this bitmap in hwloc's list format
+    ///
+    /// See [`Bitmap::display_list()`].
+    fn display_list(&self) -> BitmapDisplay<'_> {
+        self.as_ref().display_list()
+    }
+
+    /// Display this bitmap in the `taskset` format
+    ///
+    /// See [`Bitmap::display_taskset()`].
+    fn display_taskset(&self) -> BitmapDisplay<'_> {
+        self.as_ref().display_taskset()
+    }
+
+    /// Display this bitmap in hwloc's native hexadecimal format
+    ///
+    /// See [`Bitmap::display_hex()`].
+    fn display_hex(&self) -> BitmapDisplay<'_> {
+        self.as_ref().display_hex()
+    }
 }
 
 /// An owned specialized bitmaps ([`CpuSet`], [`NodeSet`])
@@ -88,6 +123,7 @@ pub trait OwnedSpecializedBitmap: OwnedBitmap + SpecializedBitmap<Owned = Self>
 impl<B: OwnedBitmap + SpecializedBitmap<Owned = Self>> OwnedSpecializedBitmap for B {}
 
 /// Kind of specialized bitmap
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum BitmapKind {
     /// This bitmap is a [`CpuSet`]
@@ -97,30 +133,29 @@ pub enum BitmapKind {
     NodeSet,
 }
 
-/// Implement a specialized bitmap
+/// Implement a bitmap newtype
+///
+/// By default, the newtype is a specialized bitmap whose [`BitmapKind`] has the
+/// same name as the newtype. Newtypes which should not be accepted by the
+/// hwloc entry points that take a [`SpecializedBitmap`] must be declared using
+/// the `{ Newtype => module_name, unspecialized }` syntax.
 #[macro_export]
 #[doc(hidden)]
 macro_rules! impl_bitmap_newtype {
     (
+        @impl
         $(#[$attr:meta])*
-        $newtype:ident
-    ) => {
-        impl_bitmap_newtype!(
-            $(#[$attr])*
-            { $newtype => bitmap_newtype }
-        );
-    };
-    (
-        $(#[$attr:meta])*
-        { $newtype:ident => $mod_name:ident }
+        { $newtype:ident => $mod_name:ident $(, kind = $kind:ident)? }
     ) => {
         #[allow(unused_imports)]
         mod $mod_name {
             use super::*;
             use $crate::{
                 bitmap::{
-                    Bitmap, BitmapIndex, BitmapKind, BitmapRef, OwnedBitmap,
-                    Iter, SpecializedBitmap
+                    Bitmap, BitmapDisplay, BitmapIndex, BitmapKind, BitmapRef,
+                    BitmapTruncationError, InlineBitmap,
+                    OwnedBitmap, Iter, ParseBitmapError, RankCache, Ranges,
+                    SpecializedBitmap
                 },
             };
             use derive_more::{AsMut, AsRef, From, Into, IntoIterator, Not};
@@ -133,13 +168,15 @@ macro_rules! impl_bitmap_newtype {
             use std::{
                 borrow::{Borrow, BorrowMut},
                 cmp::Ordering,
+                ffi::c_ulong,
                 fmt::{self, Debug, Display, Formatter, Pointer},
                 hash::{Hash, Hasher},
                 ops::{
                     BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Deref,
                     BitXorAssign, Not, RangeBounds, Sub, SubAssign
                 },
-                ptr::NonNull
+                ptr::NonNull,
+                str::FromStr
             };
 
             $(#[$attr])*
@@ -158,15 +195,17 @@ macro_rules! impl_bitmap_newtype {
             #[repr(transparent)]
             pub struct $newtype(Bitmap);
 
-            impl SpecializedBitmap for $newtype {
-                const BITMAP_KIND: BitmapKind = BitmapKind::$newtype;
+            $(
+                impl SpecializedBitmap for $newtype {
+                    const BITMAP_KIND: BitmapKind = BitmapKind::$kind;
 
-                type Owned = Self;
+                    type Owned = Self;
 
-                fn to_owned(&self) -> Self {
-                    self.clone()
+                    fn to_owned(&self) -> Self {
+                        self.clone()
+                    }
                 }
-            }
+            )?
 
             /// # Re-export of the Bitmap API
             ///
@@ -408,6 +447,13 @@ macro_rules! impl_bitmap_newtype {
                     self.0.iter_set()
                 }
 
+                /// Iterate over ranges of consecutive set indices
+                ///
+                /// See [`Bitmap::iter_set_ranges`](crate::bitmap::Bitmap::iter_set_ranges).
+                pub fn iter_set_ranges(&self) -> Ranges<&Bitmap> {
+                    self.0.iter_set_ranges()
+                }
+
                 /// Check the last set index, if any
                 ///
                 /// See [`Bitmap::last_set`](crate::bitmap::Bitmap::last_set).
@@ -422,6 +468,24 @@ macro_rules! impl_bitmap_newtype {
                     self.0.weight()
                 }
 
+                /// Find the `n`-th set index, counting from 0, if any
+                ///
+                /// See [`Bitmap::nth_set`](crate::bitmap::Bitmap::nth_set).
+                pub fn nth_set(&self, n: usize) -> Option<BitmapIndex> {
+                    self.0.nth_set(n)
+                }
+
+                /// Number of set indices that come before index `idx`
+                ///
+                /// See [`Bitmap::rank`](crate::bitmap::Bitmap::rank).
+                pub fn rank<Idx>(&self, idx: Idx) -> usize
+                where
+                    Idx: TryInto<BitmapIndex>,
+                    <Idx as TryInto<BitmapIndex>>::Error: Debug,
+                {
+                    self.0.rank(idx)
+                }
+
                 /// Check the first unset index, if any
                 ///
                 /// See [`Bitmap::first_unset`](crate::bitmap::Bitmap::first_unset).
@@ -436,6 +500,13 @@ macro_rules! impl_bitmap_newtype {
                     self.0.iter_unset()
                 }
 
+                /// Iterate over ranges of consecutive unset indices
+                ///
+                /// See [`Bitmap::iter_unset_ranges`](crate::bitmap::Bitmap::iter_unset_ranges).
+                pub fn iter_unset_ranges(&self) -> Ranges<&Bitmap> {
+                    self.0.iter_unset_ranges()
+                }
+
                 /// Check the last unset index, if any
                 ///
                 /// See [`Bitmap::last_unset`](crate::bitmap::Bitmap::last_unset).
@@ -443,6 +514,24 @@ macro_rules! impl_bitmap_newtype {
                     self.0.last_unset()
                 }
 
+                /// Find the `n`-th unset index, counting from 0, if any
+                ///
+                /// See [`Bitmap::nth_unset`](crate::bitmap::Bitmap::nth_unset).
+                pub fn nth_unset(&self, n: usize) -> Option<BitmapIndex> {
+                    self.0.nth_unset(n)
+                }
+
+                /// Number of unset indices that come before index `idx`
+                ///
+                /// See [`Bitmap::rank_unset`](crate::bitmap::Bitmap::rank_unset).
+                pub fn rank_unset<Idx>(&self, idx: Idx) -> usize
+                where
+                    Idx: TryInto<BitmapIndex>,
+                    <Idx as TryInto<BitmapIndex>>::Error: Debug,
+                {
+                    self.0.rank_unset(idx)
+                }
+
                 /// Optimized version of `*self = !self`
                 ///
                 /// See [`Bitmap::invert`](crate::bitmap::Bitmap::invert).
@@ -464,6 +553,58 @@ macro_rules! impl_bitmap_newtype {
                 pub fn includes(&self, inner: impl Deref<Target = Self>) -> bool {
                     self.0.includes(&inner.0)
                 }
+
+                /// Build a bitmap from an array of `c_ulong` masks
+                ///
+                /// See [`Bitmap::from_ulongs`](crate::bitmap::Bitmap::from_ulongs).
+                #[allow(clippy::missing_errors_doc)]
+                pub fn from_ulongs(masks: &[c_ulong]) -> Result<Self, BitmapTruncationError> {
+                    Bitmap::from_ulongs(masks).map(Self::from)
+                }
+
+                /// Write this bitmap into an array of `c_ulong` masks
+                ///
+                /// See [`Bitmap::to_ulongs`](crate::bitmap::Bitmap::to_ulongs).
+                #[allow(clippy::missing_errors_doc)]
+                pub fn to_ulongs(&self, masks: &mut [c_ulong]) -> Result<(), BitmapTruncationError> {
+                    self.0.to_ulongs(masks)
+                }
+
+                /// Convert this bitmap into a vector of `c_ulong` masks
+                ///
+                /// See [`Bitmap::to_ulong_vec`](crate::bitmap::Bitmap::to_ulong_vec).
+                #[allow(clippy::missing_errors_doc)]
+                pub fn to_ulong_vec(&self) -> Result<Vec<c_ulong>, BitmapTruncationError> {
+                    self.0.to_ulong_vec()
+                }
+
+                /// Precompute a [`RankCache`] for fast repeated rank/select queries
+                ///
+                /// See [`Bitmap::rank_cache`](crate::bitmap::Bitmap::rank_cache).
+                pub fn rank_cache(&self) -> RankCache {
+                    self.0.rank_cache()
+                }
+
+                /// Display this bitmap in hwloc's list format
+                ///
+                /// See [`Bitmap::display_list`](crate::bitmap::Bitmap::display_list).
+                pub fn display_list(&self) -> BitmapDisplay<'_> {
+                    self.0.display_list()
+                }
+
+                /// Display this bitmap in the `taskset` format
+                ///
+                /// See [`Bitmap::display_taskset`](crate::bitmap::Bitmap::display_taskset).
+                pub fn display_taskset(&self) -> BitmapDisplay<'_> {
+                    self.0.display_taskset()
+                }
+
+                /// Display this bitmap in hwloc's native hexadecimal format
+                ///
+                /// See [`Bitmap::display_hex`](crate::bitmap::Bitmap::display_hex).
+                pub fn display_hex(&self) -> BitmapDisplay<'_> {
+                    self.0.display_hex()
+                }
             }
 
             #[cfg(any(test, feature = "proptest"))]
@@ -566,11 +707,24 @@ macro_rules! impl_bitmap_newtype {
 
             impl Display for $newtype {
                 fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-                    let text = format!("{}({})", stringify!($newtype), &self.0);
+                    let text = if f.alternate() {
+                        format!("{}({:#})", stringify!($newtype), &self.0)
+                    } else {
+                        format!("{}({})", stringify!($newtype), &self.0)
+                    };
                     f.pad(&text)
                 }
             }
 
+            #[cfg(feature = "serde")]
+            impl<'de> serde::Deserialize<'de> for $newtype {
+                fn deserialize<D: serde::Deserializer<'de>>(
+                    deserializer: D
+                ) -> Result<Self, D::Error> {
+                    <Bitmap as serde::Deserialize>::deserialize(deserializer).map(Self)
+                }
+            }
+
             impl<BI: Borrow<BitmapIndex>> Extend<BI> for $newtype {
                 fn extend<T: IntoIterator<Item = BI>>(&mut self, iter: T) {
                     self.0.extend(iter)
@@ -583,12 +737,47 @@ macro_rules! impl_bitmap_newtype {
                 }
             }
 
+            impl<const WORDS: usize> From<InlineBitmap<WORDS>> for $newtype {
+                fn from(inline: InlineBitmap<WORDS>) -> Self {
+                    Self(inline.into())
+                }
+            }
+
             impl<BI: Borrow<BitmapIndex>> FromIterator<BI> for $newtype {
                 fn from_iter<I: IntoIterator<Item = BI>>(iter: I) -> Self {
                     Self(Bitmap::from_iter(iter))
                 }
             }
 
+            impl<const WORDS: usize> TryFrom<&$newtype> for InlineBitmap<WORDS> {
+                type Error = BitmapTruncationError;
+
+                fn try_from(set: &$newtype) -> Result<Self, BitmapTruncationError> {
+                    Self::try_from(&set.0)
+                }
+            }
+
+            impl FromStr for $newtype {
+                type Err = ParseBitmapError;
+
+                fn from_str(s: &str) -> Result<Self, ParseBitmapError> {
+                    // Accept both the bare bitmap syntax and the Display form
+                    let prefix = concat!(stringify!($newtype), "(");
+                    let trimmed = s.trim_start();
+                    let Some(inner) = trimmed
+                        .trim_end()
+                        .strip_prefix(prefix)
+                        .and_then(|inner| inner.strip_suffix(')'))
+                    else {
+                        return s.parse().map(Self);
+                    };
+                    inner.parse().map(Self).map_err(|mut error: ParseBitmapError| {
+                        error.position += s.len() - trimmed.len() + prefix.len();
+                        error
+                    })
+                }
+            }
+
             impl Hash for $newtype {
                 fn hash<H: Hasher>(&self, state: &mut H) {
                     self.0.hash(state)
@@ -641,6 +830,16 @@ macro_rules! impl_bitmap_newtype {
 
             impl $crate::Sealed for $newtype {}
 
+            #[cfg(feature = "serde")]
+            impl serde::Serialize for $newtype {
+                fn serialize<S: serde::Serializer>(
+                    &self,
+                    serializer: S
+                ) -> Result<S::Ok, S::Error> {
+                    serde::Serialize::serialize(&self.0, serializer)
+                }
+            }
+
             impl<B: Borrow<$newtype>> Sub<B> for &$newtype {
                 type Output = $newtype;
 
@@ -663,7 +862,7 @@ macro_rules! impl_bitmap_newtype {
                 }
             }
 
-            $crate::impl_bitmap_newtype_ref!($newtype);
+            $crate::impl_bitmap_newtype_ref!($newtype $(, kind = $kind)?);
 
             #[allow(
                 clippy::cognitive_complexity,
@@ -676,10 +875,7 @@ macro_rules! impl_bitmap_newtype {
             mod tests {
                 use super::*;
                 use $crate::{
-                    bitmap::{
-                        tests::{index_range, index_vec, INFINITE_EXPLORE_ITERS},
-                        OwnedSpecializedBitmap,
-                    },
+                    bitmap::tests::{index_range, index_vec, INFINITE_EXPLORE_ITERS},
                     strategies::bitmap_index,
                 };
                 #[allow(unused)]
@@ -699,7 +895,7 @@ macro_rules! impl_bitmap_newtype {
                 };
                 use static_assertions::{assert_impl_all, assert_not_impl_any};
 
-                $crate::impl_bitmap_newtype_ref_tests!($newtype);
+                $crate::impl_bitmap_newtype_ref_tests!($newtype $(, kind = $kind)?);
 
                 // Check that newtypes keep implementing all expected traits,
                 // in the interest of detecting future semver-breaking changes
@@ -714,9 +910,10 @@ macro_rules! impl_bitmap_newtype {
                     BorrowMut<Bitmap>, Clone, Debug, Default, Display, Eq,
                     Extend<BitmapIndex>, Extend<&'static BitmapIndex>,
                     From<Bitmap>, From<BitmapIndex>, From<&'static BitmapIndex>,
+                    From<InlineBitmap<2>>,
                     FromIterator<BitmapIndex>, FromIterator<&'static BitmapIndex>,
-                    Hash, Into<Bitmap>, IntoIterator<Item=BitmapIndex>, Not, Ord,
-                    OwnedSpecializedBitmap,
+                    FromStr, Hash, Into<Bitmap>, IntoIterator<Item=BitmapIndex>, Not, Ord,
+                    OwnedBitmap,
                     PartialEq<&'static $newtype>,
                     PartialOrd<&'static $newtype>,
                     Pointer, Sized,
@@ -737,10 +934,33 @@ macro_rules! impl_bitmap_newtype {
                     Sub<$newtype>, Sub<&'static $newtype>,
                 );
 
-                #[test]
-                fn static_checks() {
-                    assert_eq!($newtype::BITMAP_KIND, BitmapKind::$newtype);
-                }
+                $(
+                    assert_impl_all!($newtype: $crate::bitmap::OwnedSpecializedBitmap);
+
+                    #[test]
+                    fn static_checks() {
+                        assert_eq!($newtype::BITMAP_KIND, BitmapKind::$kind);
+                    }
+
+                    proptest! {
+                        #[test]
+                        fn specialized(new: $newtype) {
+                            // Test SpecializedBitmap operations
+                            prop_assert!(
+                                SpecializedBitmap::iter_set_ranges(&new).eq(new.0.iter_set_ranges())
+                            );
+                            prop_assert!(
+                                SpecializedBitmap::iter_unset_ranges(&new)
+                                    .eq(new.0.iter_unset_ranges())
+                            );
+                            prop_assert_eq!(&SpecializedBitmap::to_owned(&new), &new);
+                            prop_assert_eq!(
+                                &SpecializedBitmap::to_owned(&BitmapRef::from(&new)),
+                                &new
+                            );
+                        }
+                    }
+                )?
 
                 #[test]
                 fn nullary() {
@@ -794,6 +1014,22 @@ macro_rules! impl_bitmap_newtype {
                         prop_assert_eq!(new.weight(), new.0.weight());
                         prop_assert_eq!(new.first_unset(), new.0.first_unset());
                         prop_assert_eq!(new.last_unset(), new.0.last_unset());
+                        for n in [0, 1, INFINITE_EXPLORE_ITERS] {
+                            prop_assert_eq!(new.nth_set(n), new.0.nth_set(n));
+                            prop_assert_eq!(new.nth_unset(n), new.0.nth_unset(n));
+                            prop_assert_eq!(new.rank(n), new.0.rank(n));
+                            prop_assert_eq!(new.rank_unset(n), new.0.rank_unset(n));
+                        }
+                        prop_assert_eq!(new.rank_cache(), new.0.rank_cache());
+                        prop_assert_eq!(new.to_ulong_vec(), new.0.to_ulong_vec());
+                        if let Ok(masks) = new.to_ulong_vec() {
+                            prop_assert_eq!(&$newtype::from_ulongs(&masks).unwrap(), &new);
+                        }
+                        let inline = InlineBitmap::<2>::try_from(&new);
+                        prop_assert_eq!(inline, InlineBitmap::<2>::try_from(&new.0));
+                        if let Ok(inline) = inline {
+                            prop_assert_eq!(&$newtype::from(inline), &new);
+                        }
                         prop_assert_eq!(
                             format!("{new:?}"),
                             format!("{}({:?})", stringify!($newtype), new.0)
@@ -803,6 +1039,22 @@ macro_rules! impl_bitmap_newtype {
                             new.to_string(),
                             format!("{}({})", stringify!($newtype), new.0)
                         );
+                        prop_assert_eq!(
+                            format!("{new:#}"),
+                            format!("{}({:#})", stringify!($newtype), new.0)
+                        );
+                        prop_assert_eq!(new.display_list().to_string(), new.0.to_string());
+                        prop_assert_eq!(
+                            new.display_taskset().to_string(),
+                            new.0.display_taskset().to_string()
+                        );
+                        prop_assert_eq!(
+                            new.display_hex().to_string(),
+                            new.0.display_hex().to_string()
+                        );
+                        prop_assert_eq!(&new.to_string().parse::<$newtype>().unwrap(), &new);
+                        prop_assert_eq!(&format!("{new:#}").parse::<$newtype>().unwrap(), &new);
+                        prop_assert_eq!(&new.0.to_string().parse::<$newtype>().unwrap(), &new);
                         let state = RandomState::new();
                         prop_assert_eq!(state.hash_one(&new), state.hash_one(&new.0));
                         // SAFETY: No mutation going on
@@ -825,6 +1077,8 @@ macro_rules! impl_bitmap_newtype {
                             .iter_unset()
                             .take(INFINITE_EXPLORE_ITERS)
                             .eq(new.0.iter_unset().take(INFINITE_EXPLORE_ITERS)));
+                        prop_assert!(new.iter_set_ranges().eq(new.0.iter_set_ranges()));
+                        prop_assert!(new.iter_unset_ranges().eq(new.0.iter_unset_ranges()));
                         //
                         let mut buf = new.clone();
                         buf.clear();
@@ -866,10 +1120,6 @@ macro_rules! impl_bitmap_newtype {
                             buf.0.as_ptr()
                         );
 
-                        // Test SpecializedBitmap operations
-                        prop_assert_eq!(&SpecializedBitmap::to_owned(&new), &new);
-                        prop_assert_eq!(&SpecializedBitmap::to_owned(&BitmapRef::from(&new)), &new);
-
                         // Test low-level functions and BitmapRef<$newtype>
                         test_newtype_ref_unary(&&new, BitmapRef::from(&new))?;
                         let new = ManuallyDrop::new(new);
@@ -1015,6 +1265,35 @@ macro_rules! impl_bitmap_newtype {
         #[doc(inline)]
         pub use $mod_name::$newtype;
     };
+    (
+        $(#[$attr:meta])*
+        $newtype:ident
+    ) => {
+        impl_bitmap_newtype!(
+            $(#[$attr])*
+            { $newtype => bitmap_newtype }
+        );
+    };
+    (
+        $(#[$attr:meta])*
+        { $newtype:ident => $mod_name:ident }
+    ) => {
+        impl_bitmap_newtype!(
+            @impl
+            $(#[$attr])*
+            { $newtype => $mod_name, kind = $newtype }
+        );
+    };
+    (
+        $(#[$attr:meta])*
+        { $newtype:ident => $mod_name:ident, unspecialized }
+    ) => {
+        impl_bitmap_newtype!(
+            @impl
+            $(#[$attr])*
+            { $newtype => $mod_name }
+        );
+    };
 }
 
 #[cfg(test)]
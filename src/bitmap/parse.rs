@@ -0,0 +1,380 @@
+//! Parsing bitmaps from their textual representations
+//!
+//! hwloc provides three textual representations of bitmaps, which this module
+//! knows how to parse back:
+//!
+//! - The list format, which is what [`Bitmap`]'s `Display` implementation
+//!   emits, is a comma-separated list of indices and inclusive index ranges
+//!   like `0-3,8,10-11`, where infinite bitmaps end with an open range like
+//!   `12-`. The empty bitmap is represented as an empty string.
+//! - The native hwloc format is a comma-separated list of 32-bit hexadecimal
+//!   chunks ordered from most significant to least significant, like
+//!   `0x000000ff,,0x0000000f`, where empty chunks are zero. Infinite bitmaps
+//!   start with a `0xf...f` chunk.
+//! - The taskset format is a single hexadecimal mask like `0xff0000000f`.
+//!   Infinite bitmaps start with a `0xf...f` prefix, as in `0xf...f00`.
+//
+// --- Implementation details ---
+//
+// This is reimplemented in Rust instead of going through the hwloc_*_sscanf
+// family of functions because those only tell that the input is invalid,
+// without telling which part of it is invalid.
+
+use super::{Bitmap, BitmapIndex};
+#[cfg(doc)]
+use crate::{cpu::cpuset::CpuSet, memory::nodeset::NodeSet};
+use std::ffi::c_int;
+use thiserror::Error;
+
+/// Marker that hwloc uses to denote an infinite sequence of set bits
+const INFINITE_MARKER: &str = "f...f";
+
+/// Parse a bitmap from any of the textual representations supported by hwloc
+///
+/// Strings starting with `0x` are interpreted as hwloc's native format if they
+/// contain commas and as taskset masks otherwise (a single native chunk has the
+/// same meaning in both formats). Other strings are interpreted as lists.
+/// Leading and trailing whitespace is ignored.
+pub(super) fn parse(s: &str) -> Result<Bitmap, ParseBitmapError> {
+    let trimmed = s.trim_start();
+    let offset = s.len() - trimmed.len();
+    let trimmed = trimmed.trim_end();
+    if strip_hex_prefix(trimmed).is_some() {
+        if trimmed.contains(',') {
+            parse_hex(trimmed, offset)
+        } else {
+            parse_taskset(trimmed, offset)
+        }
+    } else {
+        parse_list(trimmed, offset)
+    }
+}
+
+/// Parse the list format, e.g. `0-3,8,10-`
+fn parse_list(s: &str, offset: usize) -> Result<Bitmap, ParseBitmapError> {
+    let mut result = Bitmap::new();
+    if s.is_empty() {
+        return Ok(result);
+    }
+    for (position, token) in comma_separated(s, offset) {
+        let error = |kind| ParseBitmapError::new(token, position, kind);
+        let parse_index = |index: &str| {
+            index
+                .parse::<BitmapIndex>()
+                .map_err(|_| error(ParseBitmapErrorKind::BadIndex))
+        };
+        if token.is_empty() {
+            return Err(error(ParseBitmapErrorKind::EmptyToken));
+        }
+        match token.split_once('-') {
+            None => result.set(parse_index(token)?),
+            Some((start, "")) => result.set_range(parse_index(start)?..),
+            Some((start, end)) => {
+                let start = parse_index(start)?;
+                let end = parse_index(end)?;
+                if end < start {
+                    return Err(error(ParseBitmapErrorKind::ReversedRange));
+                }
+                result.set_range(start..=end);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Parse hwloc's native format, e.g. `0xf...f,0x000000ff,0x0000000f`
+fn parse_hex(s: &str, offset: usize) -> Result<Bitmap, ParseBitmapError> {
+    let mut chunks = comma_separated(s, offset).collect::<Vec<_>>();
+    let infinite_marker = chunks
+        .first()
+        .copied()
+        .filter(|(_, chunk)| is_infinite_marker(chunk));
+    if infinite_marker.is_some() {
+        chunks.remove(0);
+    }
+
+    let mut result = Bitmap::new();
+    let num_chunks = chunks.len();
+    for (chunk_idx, (position, chunk)) in chunks.into_iter().enumerate() {
+        let error = |kind| ParseBitmapError::new(chunk, position, kind);
+        if is_infinite_marker(chunk) {
+            return Err(error(ParseBitmapErrorKind::MisplacedInfiniteMarker));
+        }
+        if chunk.is_empty() {
+            // hwloc elides all-zero chunks in the middle of the mask
+            continue;
+        }
+        let digits = strip_hex_prefix(chunk).unwrap_or(chunk);
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(error(ParseBitmapErrorKind::BadHexChunk));
+        }
+        let value = u32::from_str_radix(digits, 16)
+            .map_err(|_| error(ParseBitmapErrorKind::BadHexChunk))?;
+        let first_bit = (num_chunks - 1 - chunk_idx) * CHUNK_BITS;
+        set_value_bits(&mut result, value, first_bit)
+            .map_err(|()| error(ParseBitmapErrorKind::TooManyBits))?;
+    }
+
+    if let Some((position, marker)) = infinite_marker {
+        let start = BitmapIndex::try_from(num_chunks * CHUNK_BITS).map_err(|_| {
+            ParseBitmapError::new(marker, position, ParseBitmapErrorKind::TooManyBits)
+        })?;
+        result.set_range(start..);
+    }
+    Ok(result)
+}
+
+/// Parse the taskset format, e.g. `0xf...f000000ff0000000f`
+fn parse_taskset(s: &str, offset: usize) -> Result<Bitmap, ParseBitmapError> {
+    let digits = strip_hex_prefix(s).expect("should only be called on 0x-prefixed strings");
+    let (infinite, digits) = match digits.get(..INFINITE_MARKER.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(INFINITE_MARKER) => {
+            (true, &digits[INFINITE_MARKER.len()..])
+        }
+        _ => (false, digits),
+    };
+    let digits_offset = offset + (s.len() - digits.len());
+    if digits.is_empty() && !infinite {
+        return Err(ParseBitmapError::new(
+            s,
+            offset,
+            ParseBitmapErrorKind::EmptyToken,
+        ));
+    }
+
+    let mut result = Bitmap::new();
+    for (digit_idx, (byte_idx, digit)) in digits.char_indices().rev().enumerate() {
+        let position = digits_offset + byte_idx;
+        let value = digit.to_digit(16).ok_or_else(|| {
+            ParseBitmapError::new(
+                &digits[byte_idx..byte_idx + digit.len_utf8()],
+                position,
+                ParseBitmapErrorKind::BadHexDigit,
+            )
+        })?;
+        set_value_bits(&mut result, value, digit_idx * DIGIT_BITS)
+            .map_err(|()| ParseBitmapError::new(s, offset, ParseBitmapErrorKind::TooManyBits))?;
+    }
+
+    if infinite {
+        let start = BitmapIndex::try_from(digits.len() * DIGIT_BITS)
+            .map_err(|_| ParseBitmapError::new(s, offset, ParseBitmapErrorKind::TooManyBits))?;
+        result.set_range(start..);
+    }
+    Ok(result)
+}
+
+/// Number of bits in a chunk of hwloc's native bitmap format
+const CHUNK_BITS: usize = 32;
+
+/// Number of bits in a hexadecimal digit
+const DIGIT_BITS: usize = 4;
+
+/// Set the bits of `bitmap` that correspond to the set bits of `value`,
+/// starting at index `first_bit`
+///
+/// Fails if some of the bits to be set are out of the [`BitmapIndex`] range.
+fn set_value_bits(bitmap: &mut Bitmap, value: u32, first_bit: usize) -> Result<(), ()> {
+    for bit in 0..u32::BITS {
+        if value & (1 << bit) != 0 {
+            let index = first_bit
+                .checked_add(bit as usize)
+                .and_then(|index| BitmapIndex::try_from(index).ok())
+                .ok_or(())?;
+            bitmap.set(index);
+        }
+    }
+    Ok(())
+}
+
+/// Split a string into comma-separated tokens, also yielding the byte position
+/// of each token within the original string (which starts at `offset`)
+fn comma_separated(s: &str, offset: usize) -> impl Iterator<Item = (usize, &str)> {
+    s.split(',').scan(offset, |next_position, token| {
+        let position = *next_position;
+        *next_position += token.len() + 1;
+        Some((position, token))
+    })
+}
+
+/// Strip the `0x` or `0X` prefix of a hexadecimal number, if any
+fn strip_hex_prefix(s: &str) -> Option<&str> {
+    s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))
+}
+
+/// Truth that a chunk of the native hwloc format is the `0xf...f` marker
+fn is_infinite_marker(chunk: &str) -> bool {
+    strip_hex_prefix(chunk).is_some_and(|marker| marker.eq_ignore_ascii_case(INFINITE_MARKER))
+}
+
+/// Error while parsing a [`Bitmap`], [`CpuSet`] or [`NodeSet`] from a string
+///
+/// See the [`FromStr`](std::str::FromStr) implementation of these types for
+/// more information about the accepted textual representations.
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+#[error("bad bitmap token {token:?} at byte {position}: {kind}")]
+pub struct ParseBitmapError {
+    /// Offending token, as it appears in the input string
+    pub token: Box<str>,
+
+    /// Byte offset of the offending token within the input string
+    pub position: usize,
+
+    /// What is wrong with this token
+    pub kind: ParseBitmapErrorKind,
+}
+//
+impl ParseBitmapError {
+    /// Set up a parsing error
+    fn new(token: &str, position: usize, kind: ParseBitmapErrorKind) -> Self {
+        Self {
+            token: token.into(),
+            position,
+            kind,
+        }
+    }
+}
+
+/// Reason why a token of a bitmap string was rejected
+#[derive(Copy, Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub enum ParseBitmapErrorKind {
+    /// Expected an index or index range, but got nothing (as in `0,,2`)
+    #[error("expected an index or an index range, got nothing")]
+    EmptyToken,
+
+    /// Expected a decimal bitmap index in range `0..=c_int::MAX`
+    #[error("expected a decimal index in range 0..={}", c_int::MAX)]
+    BadIndex,
+
+    /// Index range ends before it starts (as in `5-3`)
+    #[error("index range ends before it starts")]
+    ReversedRange,
+
+    /// Expected a 32-bit hexadecimal chunk of hwloc's native bitmap format
+    #[error("expected a 32-bit hexadecimal number")]
+    BadHexChunk,
+
+    /// Expected a hexadecimal digit in a taskset mask
+    #[error("expected a hexadecimal digit")]
+    BadHexDigit,
+
+    /// Hexadecimal mask has set bits above [`BitmapIndex::MAX`]
+    #[error("mask has set bits above index {}", c_int::MAX)]
+    TooManyBits,
+
+    /// The `0xf...f` infinite marker may only appear at the start of the string
+    #[error("the 0xf...f marker may only appear at the start of the mask")]
+    MisplacedInfiniteMarker,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(ParseBitmapError:
+        Clone, Debug, Display, Error, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(ParseBitmapError:
+        Binary, Copy, Default, Deref, Drop, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+    assert_impl_all!(ParseBitmapErrorKind:
+        Copy, Debug, Display, Error, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(ParseBitmapErrorKind:
+        Binary, Default, Deref, Drop, IntoIterator, LowerExp, LowerHex, Octal,
+        PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+
+    /// Check that a string parses into a certain list of index ranges
+    fn check_parse(s: &str, expected: &str) {
+        let parsed = s.parse::<Bitmap>().unwrap();
+        assert_eq!(parsed.to_string(), expected, "while parsing {s:?}");
+    }
+
+    /// Check that a string fails to parse in a certain way
+    fn check_error(s: &str, token: &str, position: usize, kind: ParseBitmapErrorKind) {
+        assert_eq!(
+            s.parse::<Bitmap>().unwrap_err(),
+            ParseBitmapError::new(token, position, kind),
+            "while parsing {s:?}"
+        );
+    }
+
+    #[test]
+    fn list() {
+        check_parse("", "");
+        check_parse("  ", "");
+        check_parse("5", "5");
+        check_parse("0-3,8,10-11", "0-3,8,10-11");
+        check_parse("10-11,0-3,8", "0-3,8,10-11");
+        check_parse(" 0-41,43- \n", "0-41,43-");
+        check_parse("0-", "0-");
+        check_parse("3-3", "3");
+        check_error("0,,2", "", 2, ParseBitmapErrorKind::EmptyToken);
+        check_error("0,", "", 2, ParseBitmapErrorKind::EmptyToken);
+        check_error("0,a", "a", 2, ParseBitmapErrorKind::BadIndex);
+        check_error(" 1,-3", "-3", 3, ParseBitmapErrorKind::BadIndex);
+        check_error("1-2-3", "1-2-3", 0, ParseBitmapErrorKind::BadIndex);
+        check_error(
+            "2147483648",
+            "2147483648",
+            0,
+            ParseBitmapErrorKind::BadIndex,
+        );
+        check_error("0,5-3", "5-3", 2, ParseBitmapErrorKind::ReversedRange);
+    }
+
+    #[test]
+    fn hex() {
+        check_parse("0x00000001,0x00000000", "32");
+        check_parse("0x1,0xf0000000", "28-32");
+        check_parse("0x00000001,,0x0", "64");
+        check_parse("0XF...F,0x00000000", "32-");
+        check_parse("0xf...f,0x0000ffff,0x00000000", "32-47,64-");
+        check_error("0x1,0xg", "0xg", 4, ParseBitmapErrorKind::BadHexChunk);
+        check_error("0x1,0x", "0x", 4, ParseBitmapErrorKind::BadHexChunk);
+        check_error(
+            "0x1,0x100000000",
+            "0x100000000",
+            4,
+            ParseBitmapErrorKind::BadHexChunk,
+        );
+        check_error(
+            "0x1,0xf...f",
+            "0xf...f",
+            4,
+            ParseBitmapErrorKind::MisplacedInfiniteMarker,
+        );
+    }
+
+    #[test]
+    fn taskset() {
+        check_parse("0x0", "");
+        check_parse("0x1", "0");
+        check_parse("0xf0", "4-7");
+        check_parse("0x100000000", "32");
+        check_parse("0xf...f", "0-");
+        check_parse("0xf...f0", "4-");
+        check_parse("0xF...F00000001", "0,28-");
+        check_error("0x", "0x", 0, ParseBitmapErrorKind::EmptyToken);
+        check_error(" 0x1g", "g", 4, ParseBitmapErrorKind::BadHexDigit);
+        check_error("0x1f...f", ".", 6, ParseBitmapErrorKind::BadHexDigit);
+    }
+}
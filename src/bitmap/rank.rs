@@ -0,0 +1,235 @@
+//! Cached rank/select queries on bitmaps
+//!
+//! [`Bitmap::nth_set()`], [`Bitmap::rank()`] and their unset counterparts
+//! need to walk over the ranges of the bitmap every time they are called. When
+//! many such queries are performed on a bitmap that does not change, for
+//! example to map worker number `k` to the `k`-th allowed PU, it is faster to
+//! precompute a [`RankCache`] once and query that instead.
+
+use super::{Bitmap, BitmapIndex, BAD_INDEX};
+#[cfg(doc)]
+use crate::{cpu::cpuset::CpuSet, memory::nodeset::NodeSet};
+use std::fmt::Debug;
+
+/// Precomputed rank/select index of a [`Bitmap`], [`CpuSet`] or [`NodeSet`]
+///
+/// This `struct` is created by the `rank_cache()` method of bitmap types. It
+/// is a snapshot of the bitmap contents at the time where it was created, and
+/// will not reflect subsequent modifications of the bitmap.
+///
+/// Queries take `O(log(r))` time, where `r` is the number of ranges of
+/// consecutive set indices in the bitmap, as opposed to `O(r)` for the
+/// equivalent [`Bitmap`] methods.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct RankCache {
+    /// Ranges of consecutive set indices, in increasing index order
+    ///
+    /// If the bitmap is infinitely set, the last range ends at
+    /// [`BitmapIndex::MAX`], like in [`Bitmap::iter_set_ranges()`].
+    runs: Vec<Run>,
+}
+//
+impl RankCache {
+    /// Precompute the rank/select index of a bitmap
+    ///
+    /// This is equivalent to [`Bitmap::rank_cache()`].
+    pub fn new(bitmap: &Bitmap) -> Self {
+        let mut rank = 0;
+        let runs = bitmap
+            .iter_set_ranges()
+            .map(|range| {
+                let run = Run {
+                    start: usize::from(*range.start()),
+                    end: usize::from(*range.end()),
+                    rank,
+                };
+                rank += run.len();
+                run
+            })
+            .collect();
+        Self { runs }
+    }
+
+    /// Find the `n`-th set index, counting from 0, if any
+    ///
+    /// See [`Bitmap::nth_set()`].
+    pub fn nth_set(&self, n: usize) -> Option<BitmapIndex> {
+        let num_runs_before = self.runs.partition_point(|run| run.rank <= n);
+        let run = self.runs.get(num_runs_before.checked_sub(1)?)?;
+        let idx = run.start.checked_add(n - run.rank)?;
+        (idx <= run.end).then(|| Self::to_index(idx))
+    }
+
+    /// Number of set indices that come before index `idx`
+    ///
+    /// See [`Bitmap::rank()`].
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is above the implementation-defined maximum index (at least
+    /// 2^15-1, usually 2^31-1).
+    pub fn rank<Idx>(&self, idx: Idx) -> usize
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(self_: &RankCache, idx: Option<BitmapIndex>) -> usize {
+            let idx = usize::from(idx.expect(BAD_INDEX));
+            let num_runs_before = self_.runs.partition_point(|run| run.start < idx);
+            num_runs_before.checked_sub(1).map_or(0, |run_idx| {
+                let run = &self_.runs[run_idx];
+                run.rank + run.end.min(idx - 1) - run.start + 1
+            })
+        }
+        polymorphized(self, idx.try_into().ok())
+    }
+
+    /// Find the `n`-th unset index, counting from 0, if any
+    ///
+    /// See [`Bitmap::nth_unset()`].
+    pub fn nth_unset(&self, n: usize) -> Option<BitmapIndex> {
+        // The number of unset indices before each run start grows
+        // monotonically, so we can binary search the last run of set indices
+        // that precedes the n-th unset index.
+        let num_runs_before = self.runs.partition_point(|run| run.start - run.rank <= n);
+        let Some(run_idx) = num_runs_before.checked_sub(1) else {
+            return BitmapIndex::try_from(n).ok();
+        };
+        let run = &self.runs[run_idx];
+        if run.end == usize::from(BitmapIndex::MAX) {
+            return None;
+        }
+        let idx = n.checked_add(run.rank + run.len())?;
+        BitmapIndex::try_from(idx).ok()
+    }
+
+    /// Number of unset indices that come before index `idx`
+    ///
+    /// See [`Bitmap::rank_unset()`].
+    ///
+    /// # Panics
+    ///
+    /// If `idx` is above the implementation-defined maximum index (at least
+    /// 2^15-1, usually 2^31-1).
+    pub fn rank_unset<Idx>(&self, idx: Idx) -> usize
+    where
+        Idx: TryInto<BitmapIndex>,
+        <Idx as TryInto<BitmapIndex>>::Error: Debug,
+    {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(self_: &RankCache, idx: Option<BitmapIndex>) -> usize {
+            let idx = idx.expect(BAD_INDEX);
+            usize::from(idx) - self_.rank(idx)
+        }
+        polymorphized(self, idx.try_into().ok())
+    }
+
+    /// The number of indices that are set in the bitmap
+    ///
+    /// See [`Bitmap::weight()`].
+    pub fn weight(&self) -> Option<usize> {
+        match self.runs.last() {
+            Some(run) if run.end == usize::from(BitmapIndex::MAX) => None,
+            Some(run) => Some(run.rank + run.len()),
+            None => Some(0),
+        }
+    }
+
+    /// Convert an index that is known to be in range to a [`BitmapIndex`]
+    fn to_index(idx: usize) -> BitmapIndex {
+        BitmapIndex::try_from(idx).expect("Should be in range by construction")
+    }
+}
+//
+impl From<&Bitmap> for RankCache {
+    fn from(bitmap: &Bitmap) -> Self {
+        Self::new(bitmap)
+    }
+}
+
+/// Range of consecutive set indices within a [`RankCache`]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+struct Run {
+    /// First index of the range
+    start: usize,
+
+    /// Last index of the range (inclusive)
+    end: usize,
+
+    /// Number of set indices before the start of the range
+    rank: usize,
+}
+//
+impl Run {
+    /// Number of indices within the range
+    fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::bitmap_index;
+    use proptest::prelude::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(RankCache:
+        Clone, Debug, Default, From<&'static Bitmap>, Hash, PartialEq, Sized,
+        Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(RankCache:
+        Binary, Copy, Deref, Display, Drop, Error, IntoIterator, LowerExp,
+        LowerHex, Octal, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+
+    #[test]
+    fn special_bitmaps() {
+        let empty = RankCache::new(&Bitmap::new());
+        assert_eq!(empty, RankCache::default());
+        assert_eq!(empty.weight(), Some(0));
+        assert_eq!(empty.nth_set(0), None);
+        assert_eq!(empty.rank(BitmapIndex::MAX), 0);
+        assert_eq!(empty.nth_unset(42).map(usize::from), Some(42));
+        assert_eq!(empty.rank_unset(42), 42);
+        assert_eq!(empty.nth_unset(usize::MAX), None);
+
+        let full = RankCache::new(&Bitmap::full());
+        assert_eq!(full.weight(), None);
+        assert_eq!(full.nth_set(42).map(usize::from), Some(42));
+        assert_eq!(full.nth_set(usize::from(BitmapIndex::MAX) + 1), None);
+        assert_eq!(full.nth_set(usize::MAX), None);
+        assert_eq!(full.rank(42), 42);
+        assert_eq!(full.nth_unset(0), None);
+        assert_eq!(full.rank_unset(BitmapIndex::MAX), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn queries(bitmap: Bitmap, n in 0usize..1024, idx in bitmap_index()) {
+            let cache = bitmap.rank_cache();
+            prop_assert_eq!(&RankCache::from(&bitmap), &cache);
+            prop_assert_eq!(cache.weight(), bitmap.weight());
+            prop_assert_eq!(cache.nth_set(n), bitmap.nth_set(n));
+            prop_assert_eq!(cache.nth_unset(n), bitmap.nth_unset(n));
+            prop_assert_eq!(cache.rank(idx), bitmap.rank(idx));
+            prop_assert_eq!(cache.rank_unset(idx), bitmap.rank_unset(idx));
+        }
+    }
+}
@@ -318,6 +318,13 @@ impl<Target: OwnedBitmap> Sealed for BitmapRef<'_, Target> {}
 // SAFETY: BitmapRef exposes no internal mutability
 unsafe impl<Target: OwnedBitmap + Sync> Send for BitmapRef<'_, Target> {}
 
+#[cfg(feature = "serde")]
+impl<Target: OwnedBitmap + serde::Serialize> serde::Serialize for BitmapRef<'_, Target> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
 impl<Target, Rhs> Sub<Rhs> for &BitmapRef<'_, Target>
 where
     Target: OwnedBitmap,
@@ -351,23 +358,25 @@ unsafe impl<Target: OwnedBitmap + Sync> Sync for BitmapRef<'_, Target> {}
 //       `Target` to implement `Borrow<BitmapRef<'target, Target>>`, which is
 //       wrong as outlined above.
 
-/// Implement BitmapRef for a specialized bitmap
+/// Implement BitmapRef for a bitmap newtype
 #[macro_export]
 #[doc(hidden)]
 macro_rules! impl_bitmap_newtype_ref {
     (
         $(#[$attr:meta])*
-        $newtype:ident
+        $newtype:ident $(, kind = $kind:ident)?
     ) => {
-        impl SpecializedBitmap for BitmapRef<'_, $newtype> {
-            const BITMAP_KIND: BitmapKind = BitmapKind::$newtype;
+        $(
+            impl SpecializedBitmap for BitmapRef<'_, $newtype> {
+                const BITMAP_KIND: BitmapKind = BitmapKind::$kind;
 
-            type Owned = $newtype;
+                type Owned = $newtype;
 
-            fn to_owned(&self) -> $newtype {
-                self.clone_target()
+                fn to_owned(&self) -> $newtype {
+                    self.clone_target()
+                }
             }
-        }
+        )?
 
         impl<'target> AsRef<Bitmap> for BitmapRef<'_, $newtype> {
             fn as_ref(&self) -> &Bitmap {
@@ -405,13 +414,13 @@ macro_rules! impl_bitmap_newtype_ref {
     };
 }
 
-/// `BitmapRef` related tests for a specialized bitmap type
+/// `BitmapRef` related tests for a bitmap newtype
 #[macro_export]
 #[doc(hidden)]
 macro_rules! impl_bitmap_newtype_ref_tests {
     (
         $(#[$attr:meta])*
-        $newtype:ident
+        $newtype:ident $(, kind = $kind:ident)?
     ) => {
         // Check that newtypes keep implementing all expected traits,
         // in the interest of detecting future semver-breaking changes
@@ -468,7 +477,7 @@ macro_rules! impl_bitmap_newtype_ref_tests {
             PartialOrd<&'static $newtype>,
             PartialOrd<BitmapRef<'static, $newtype>>,
             PartialOrd<&'static BitmapRef<'static, $newtype>>,
-            Pointer, Sized, SpecializedBitmap<Owned=$newtype>,
+            Pointer, Sized,
             Sub<$newtype>, Sub<&'static $newtype>,
             Sub<BitmapRef<'static, $newtype>>,
             Sub<&'static BitmapRef<'static, $newtype>>,
@@ -495,13 +504,19 @@ macro_rules! impl_bitmap_newtype_ref_tests {
             Sub<&'static BitmapRef<'static, $newtype>>,
         );
 
-        #[test]
-        fn static_checks_newtype() {
-            assert_eq!(
-                BitmapRef::<'static, $newtype>::BITMAP_KIND,
-                BitmapKind::$newtype
+        $(
+            assert_impl_all!(BitmapRef<'static, $newtype>:
+                SpecializedBitmap<Owned=$newtype>
             );
-        }
+
+            #[test]
+            fn static_checks_newtype() {
+                assert_eq!(
+                    BitmapRef::<'static, $newtype>::BITMAP_KIND,
+                    BitmapKind::$kind
+                );
+            }
+        )?
 
         /// Test properties of [`BitmapRef`]s of bitmap newtypes
         fn test_newtype_ref_unary(
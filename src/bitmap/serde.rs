@@ -0,0 +1,196 @@
+//! Serialization of bitmaps
+//!
+//! Bitmaps are serialized using hwloc's list format (e.g. `0-3,8,10-`) when the
+//! serialization format is human-readable, and deserialized from any of the
+//! textual formats supported by the [`FromStr`](std::str::FromStr)
+//! implementation of [`Bitmap`].
+//!
+//! When the serialization format is not human-readable, bitmaps are serialized
+//! as a compact byte string. The first byte is 1 if the bitmap is infinite and
+//! 0 otherwise. Each subsequent byte contains the next 8 bits of the bitmap,
+//! starting from index 0 and with the lowest index in the least significant
+//! bit. In infinite bitmaps, all bits after the end of the byte string are set.
+//! Like hwloc's own in-memory bitmap representation, this form is dense, so its
+//! size grows with the highest finite index rather than with the set size.
+
+use super::{Bitmap, BitmapIndex};
+use serde::{
+    de::{self, SeqAccess, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt::{self, Formatter};
+
+impl<'de> Deserialize<'de> for Bitmap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BitmapVisitor)
+        } else {
+            deserializer.deserialize_bytes(BitmapVisitor)
+        }
+    }
+}
+
+impl Serialize for Bitmap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&self.display_list())
+        } else {
+            serializer.serialize_bytes(&to_compact_bytes(self))
+        }
+    }
+}
+
+/// Number of bits in a byte of the compact binary form
+const BYTE_BITS: usize = 8;
+
+/// Encode a bitmap into the compact binary form
+fn to_compact_bytes(bitmap: &Bitmap) -> Vec<u8> {
+    let infinite = bitmap.weight().is_none();
+    let last_finite_idx = if infinite {
+        bitmap.last_unset()
+    } else {
+        bitmap.last_set()
+    };
+    let finite_len = last_finite_idx.map_or(0, |idx| usize::from(idx) + 1);
+    let num_mask_bytes = (finite_len + BYTE_BITS - 1) / BYTE_BITS;
+    let mut bytes = vec![0; 1 + num_mask_bytes];
+    bytes[0] = u8::from(infinite);
+    for idx in bitmap
+        .iter_set()
+        .map(usize::from)
+        .take_while(|&idx| idx < num_mask_bytes * BYTE_BITS)
+    {
+        bytes[1 + idx / BYTE_BITS] |= 1 << (idx % BYTE_BITS);
+    }
+    bytes
+}
+
+/// Decode a bitmap from the compact binary form
+fn from_compact_bytes<E: de::Error>(bytes: &[u8]) -> Result<Bitmap, E> {
+    let Some((&infinite, mask)) = bytes.split_first() else {
+        return Err(E::invalid_length(0, &BitmapVisitor));
+    };
+    let infinite = match infinite {
+        0 => false,
+        1 => true,
+        _ => {
+            return Err(E::invalid_value(
+                Unexpected::Unsigned(infinite.into()),
+                &"an infinite bitmap flag equal to 0 or 1",
+            ))
+        }
+    };
+    let too_long = || E::invalid_length(bytes.len(), &BitmapVisitor);
+
+    let mut result = Bitmap::new();
+    for (byte_idx, &byte) in mask.iter().enumerate() {
+        for bit in 0..BYTE_BITS {
+            if byte & (1 << bit) != 0 {
+                let idx =
+                    BitmapIndex::try_from(byte_idx * BYTE_BITS + bit).map_err(|_| too_long())?;
+                result.set(idx);
+            }
+        }
+    }
+    if infinite {
+        let start = BitmapIndex::try_from(mask.len() * BYTE_BITS).map_err(|_| too_long())?;
+        result.set_range(start..);
+    }
+    Ok(result)
+}
+
+/// Deserialization visitor for bitmaps
+struct BitmapVisitor;
+//
+impl<'de> Visitor<'de> for BitmapVisitor {
+    type Value = Bitmap;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a bitmap string like \"0-3,8,10-\" or a compact bitmap byte string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Bitmap, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Bitmap, E> {
+        from_compact_bytes(v)
+    }
+
+    // Needed for serialization formats that encode byte strings as sequences
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bitmap, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        from_compact_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Configure, Token};
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn special_bitmaps() {
+        let empty = Bitmap::new();
+        assert_tokens(&empty.clone().readable(), &[Token::Str("")]);
+        assert_tokens(&empty.compact(), &[Token::Bytes(&[0])]);
+
+        let full = Bitmap::full();
+        assert_tokens(&full.clone().readable(), &[Token::Str("0-")]);
+        assert_tokens(&full.compact(), &[Token::Bytes(&[1])]);
+
+        let mut bitmap = Bitmap::from_range(0..=3);
+        bitmap.set(8);
+        bitmap.set_range(10..=11);
+        assert_tokens(&bitmap.clone().readable(), &[Token::Str("0-3,8,10-11")]);
+        assert_tokens(&bitmap.clone().compact(), &[Token::Bytes(&[0, 0x0f, 0x0d])]);
+        assert_de_tokens(&bitmap.clone().readable(), &[Token::Str("0x00000d0f")]);
+        assert_de_tokens(
+            &bitmap.clone().compact(),
+            &[
+                Token::Seq { len: Some(3) },
+                Token::U8(0),
+                Token::U8(0x0f),
+                Token::U8(0x0d),
+                Token::SeqEnd,
+            ],
+        );
+
+        bitmap.set_range(17..);
+        assert_tokens(&bitmap.clone().readable(), &[Token::Str("0-3,8,10-11,17-")]);
+        assert_tokens(&bitmap.compact(), &[Token::Bytes(&[1, 0x0f, 0x0d, 0xfe])]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_de_tokens_error::<serde_test::Readable<Bitmap>>(
+            &[Token::Str("0,,1")],
+            "bad bitmap token \"\" at byte 2: expected an index or an index range, got nothing",
+        );
+        assert_de_tokens_error::<serde_test::Compact<Bitmap>>(
+            &[Token::Bytes(&[])],
+            "invalid length 0, expected a bitmap string like \"0-3,8,10-\" or a compact bitmap byte string",
+        );
+        assert_de_tokens_error::<serde_test::Compact<Bitmap>>(
+            &[Token::Bytes(&[2])],
+            "invalid value: integer `2`, expected an infinite bitmap flag equal to 0 or 1",
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn compact_round_trip(bitmap: Bitmap) {
+            let bytes = to_compact_bytes(&bitmap);
+            prop_assert_eq!(
+                from_compact_bytes::<de::value::Error>(&bytes).unwrap(),
+                bitmap
+            );
+        }
+    }
+}
@@ -22,12 +22,15 @@ use hwlocality_sys::{
     HWLOC_CPUBIND_NOMEMBIND, HWLOC_CPUBIND_PROCESS, HWLOC_CPUBIND_STRICT, HWLOC_CPUBIND_THREAD,
 };
 use libc::{ENOSYS, EXDEV};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 #[allow(unused)]
 #[cfg(test)]
 use similar_asserts::assert_eq;
 use std::{
     ffi::{c_int, c_uint},
     fmt::Display,
+    marker::PhantomData,
     ops::Deref,
 };
 use thiserror::Error;
@@ -478,6 +481,116 @@ impl Topology {
         }
     }
 
+    /// Temporarily bind the current process or thread on given CPUs
+    ///
+    /// This queries the current CPU binding of the process or thread, then
+    /// binds it like [`Topology::bind_cpu()`] would. The returned guard
+    /// restores the previous binding when it is dropped, including when the
+    /// code that holds it returns early or panics.
+    ///
+    /// Depending on the flags and operating system, the binding may target the
+    /// current thread and is then restored on the thread where the guard is
+    /// dropped. This is why the guard cannot be sent to another thread.
+    ///
+    /// `set` can be a `&'_ CpuSet` or a `BitmapRef<'_, CpuSet>`. `flags`
+    /// are used both to bind and to restore the previous binding, with the
+    /// exception of [`STRICT`] and [`NO_MEMORY_BINDING`] which are not used
+    /// when querying the previous binding.
+    ///
+    /// Requires the support flags of both [`Topology::cpu_binding()`] and
+    /// [`Topology::bind_cpu()`].
+    ///
+    /// This functionality is specific to the Rust bindings.
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by [`Topology::cpu_binding()`] or
+    /// [`Topology::bind_cpu()`]. The binding is left unchanged in that case.
+    ///
+    /// [`NO_MEMORY_BINDING`]: CpuBindingFlags::NO_MEMORY_BINDING
+    /// [`STRICT`]: CpuBindingFlags::STRICT
+    pub fn bind_cpu_scoped(
+        &self,
+        set: impl Deref<Target = CpuSet>,
+        flags: CpuBindingFlags,
+    ) -> Result<LocalCpuBindingGuard<'_>, HybridError<CpuBindingError>> {
+        Ok(LocalCpuBindingGuard(
+            CpuBindingGuard::new(self, CpuBoundObject::ThisProgram, &set, flags)?,
+            PhantomData,
+        ))
+    }
+
+    /// Temporarily bind a process (identified by its `pid`) on given CPUs
+    ///
+    /// This is the scoped variant of [`Topology::bind_process_cpu()`]. See
+    /// [`Topology::bind_cpu_scoped()`] for more information about scoped
+    /// binding.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by [`Topology::process_cpu_binding()`] or
+    /// [`Topology::bind_process_cpu()`]. The binding is left unchanged in
+    /// that case.
+    pub fn bind_process_cpu_scoped(
+        &self,
+        pid: ProcessId,
+        set: impl Deref<Target = CpuSet>,
+        flags: CpuBindingFlags,
+    ) -> Result<CpuBindingGuard<'_>, HybridError<CpuBindingError>> {
+        CpuBindingGuard::new(self, CpuBoundObject::ProcessOrThread(pid), &set, flags)
+    }
+
+    /// Temporarily bind a thread (identified by its `tid`) on given CPUs
+    ///
+    /// This is the scoped variant of [`Topology::bind_thread_cpu()`]. See
+    /// [`Topology::bind_cpu_scoped()`] for more information about scoped
+    /// binding.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by [`Topology::thread_cpu_binding()`] or
+    /// [`Topology::bind_thread_cpu()`]. The binding is left unchanged in that
+    /// case.
+    pub fn bind_thread_cpu_scoped(
+        &self,
+        tid: ThreadId,
+        set: impl Deref<Target = CpuSet>,
+        flags: CpuBindingFlags,
+    ) -> Result<CpuBindingGuard<'_>, HybridError<CpuBindingError>> {
+        CpuBindingGuard::new(self, CpuBoundObject::Thread(tid), &set, flags)
+    }
+
+    /// Query the CPU binding of an arbitrary target
+    fn target_cpu_binding(
+        &self,
+        target: CpuBoundObject,
+        flags: CpuBindingFlags,
+    ) -> Result<CpuSet, HybridError<CpuBindingError>> {
+        match target {
+            CpuBoundObject::ProcessOrThread(pid) => self.process_cpu_binding(pid, flags),
+            CpuBoundObject::Thread(tid) => self.thread_cpu_binding(tid, flags),
+            CpuBoundObject::ThisProgram => self.cpu_binding(flags),
+        }
+    }
+
+    /// Set the CPU binding of an arbitrary target
+    fn bind_target_cpu(
+        &self,
+        target: CpuBoundObject,
+        set: &CpuSet,
+        flags: CpuBindingFlags,
+    ) -> Result<(), HybridError<CpuBindingError>> {
+        match target {
+            CpuBoundObject::ProcessOrThread(pid) => self.bind_process_cpu(pid, set, flags),
+            CpuBoundObject::Thread(tid) => self.bind_thread_cpu(tid, set, flags),
+            CpuBoundObject::ThisProgram => Ok(self.bind_cpu(set, flags)?),
+        }
+    }
+
     /// Binding for `hwloc_set_cpubind`-like functions
     ///
     /// # Safety
@@ -613,6 +726,7 @@ bitflags! {
     /// Please check the documentation of the [cpu binding
     /// method](../../topology/struct.Topology.html#cpu-binding) that you are
     /// calling for more information.
+    #[cfg_attr(feature = "serde", derive(Deserialize, Serialize), serde(transparent))]
     #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
     #[doc(alias = "hwloc_cpubind_flags_t")]
     pub struct CpuBindingFlags: hwloc_cpubind_flags_t {
@@ -746,6 +860,12 @@ impl CpuBindingFlags {
 crate::impl_arbitrary_for_bitflags!(CpuBindingFlags, hwloc_cpubind_flags_t);
 
 /// Object that is being bound to particular CPUs
+//
+// --- Implementation notes ---
+//
+// Not implementing serde traits because ThreadId is a process-local handle
+// (pthread_t or HANDLE), so a deserialized value could designate an arbitrary
+// thread, or none at all, and would be unsound to pass to hwloc.
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum CpuBoundObject {
     /// A process, identified by its PID, or possibly a thread on Linux
@@ -775,6 +895,140 @@ impl Display for CpuBoundObject {
     }
 }
 
+/// Guard that restores the previous CPU binding of a process or thread when
+/// dropped
+///
+/// This `struct` is created by the [`bind_process_cpu_scoped()`] and
+/// [`bind_thread_cpu_scoped()`] methods of [`Topology`]. See their
+/// documentation for more information.
+///
+/// Since the target is designated by its PID or TID, the binding can be
+/// restored from any thread, so this guard can be sent to another thread.
+///
+/// Errors that occur while restoring the previous binding on drop are
+/// ignored. Use [`CpuBindingGuard::restore()`] if you need to handle them.
+///
+/// [`bind_process_cpu_scoped()`]: Topology::bind_process_cpu_scoped()
+/// [`bind_thread_cpu_scoped()`]: Topology::bind_thread_cpu_scoped()
+#[derive(Debug)]
+#[must_use = "The previous CPU binding is restored as soon as the guard is dropped"]
+pub struct CpuBindingGuard<'topology> {
+    /// Topology that was used to bind the target
+    topology: &'topology Topology,
+
+    /// Object whose CPU binding was changed
+    target: CpuBoundObject,
+
+    /// CPU binding to be restored, or None if it has already been restored
+    previous: Option<CpuSet>,
+
+    /// Flags that were used to bind the target
+    flags: CpuBindingFlags,
+}
+//
+impl<'topology> CpuBindingGuard<'topology> {
+    /// Query the current binding of `target`, then bind it to `set`
+    fn new(
+        topology: &'topology Topology,
+        target: CpuBoundObject,
+        set: &CpuSet,
+        flags: CpuBindingFlags,
+    ) -> Result<Self, HybridError<CpuBindingError>> {
+        let query_flags = flags - (CpuBindingFlags::STRICT | CpuBindingFlags::NO_MEMORY_BINDING);
+        let previous = topology.target_cpu_binding(target, query_flags)?;
+        topology.bind_target_cpu(target, set, flags)?;
+        Ok(Self {
+            topology,
+            target,
+            previous: Some(previous),
+            flags,
+        })
+    }
+
+    /// Object whose CPU binding will be restored
+    pub fn target(&self) -> CpuBoundObject {
+        self.target
+    }
+
+    /// CPU binding that will be restored
+    pub fn previous_binding(&self) -> &CpuSet {
+        self.previous
+            .as_ref()
+            .expect("Previous binding is only taken out on restore or drop")
+    }
+
+    /// Restore the previous CPU binding now, reporting errors
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by the underlying CPU binding method.
+    pub fn restore(mut self) -> Result<(), HybridError<CpuBindingError>> {
+        self.restore_impl()
+    }
+
+    /// Keep the current CPU binding, and get the previous one back
+    pub fn keep_current(mut self) -> CpuSet {
+        self.previous
+            .take()
+            .expect("Previous binding is only taken out on restore or drop")
+    }
+
+    /// Restore the previous CPU binding, if it has not been done already
+    fn restore_impl(&mut self) -> Result<(), HybridError<CpuBindingError>> {
+        self.previous.take().map_or(Ok(()), |previous| {
+            self.topology
+                .bind_target_cpu(self.target, &previous, self.flags)
+        })
+    }
+}
+//
+impl Drop for CpuBindingGuard<'_> {
+    fn drop(&mut self) {
+        // Drop cannot report errors and should not panic, as it may run during
+        // unwinding. Users who care about errors should call restore().
+        #[allow(let_underscore_drop)]
+        let _ = self.restore_impl();
+    }
+}
+
+/// Guard that restores the previous CPU binding of the current process or
+/// thread when dropped
+///
+/// This `struct` is created by the [`Topology::bind_cpu_scoped()`] method.
+/// See its documentation for more information.
+///
+/// Unlike [`CpuBindingGuard`], this guard cannot be sent to another thread,
+/// because the binding may target the current thread, in which case it must
+/// be restored on the thread where it was changed.
+///
+/// Errors that occur while restoring the previous binding on drop are
+/// ignored. Use [`LocalCpuBindingGuard::restore()`] if you need to handle
+/// them.
+#[derive(Debug)]
+#[must_use = "The previous CPU binding is restored as soon as the guard is dropped"]
+pub struct LocalCpuBindingGuard<'topology>(CpuBindingGuard<'topology>, PhantomData<*const ()>);
+//
+impl LocalCpuBindingGuard<'_> {
+    /// CPU binding that will be restored
+    pub fn previous_binding(&self) -> &CpuSet {
+        self.0.previous_binding()
+    }
+
+    /// Restore the previous CPU binding now, reporting errors
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by [`Topology::bind_cpu()`].
+    pub fn restore(self) -> Result<(), HybridError<CpuBindingError>> {
+        self.0.restore()
+    }
+
+    /// Keep the current CPU binding, and get the previous one back
+    pub fn keep_current(self) -> CpuSet {
+        self.0.keep_current()
+    }
+}
+
 /// Operation on that object's CPU binding
 #[derive(Copy, Clone, Debug, Display, Eq, Hash, PartialEq)]
 pub(crate) enum CpuBindingOperation {
@@ -877,3 +1131,134 @@ pub(crate) fn call_hwloc(
     }
     translate_result(object, cpuset, errors::call_hwloc_int_normal(api, ffi))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topology::support::{CpuBindingSupport, FeatureSupport};
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::Drop,
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(CpuBindingGuard<'static>:
+        Debug, Drop, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(CpuBindingGuard<'static>:
+        Binary, Clone, Default, Deref, Display, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(LocalCpuBindingGuard<'static>:
+        Debug, Sized, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(LocalCpuBindingGuard<'static>:
+        Binary, Clone, Default, Deref, Display, Drop, Error, Hash,
+        IntoIterator, LowerExp, LowerHex, Octal, PartialEq, Pointer, Read,
+        Send, Sync, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+
+    /// Check if the CPU binding of the current thread can be queried and set
+    fn current_thread_binding_supported(topology: &Topology) -> bool {
+        topology.supports(
+            FeatureSupport::cpu_binding,
+            CpuBindingSupport::get_current_thread,
+        ) && topology.supports(
+            FeatureSupport::cpu_binding,
+            CpuBindingSupport::set_current_thread,
+        )
+    }
+
+    /// Pick a single CPU from a binding, to make sure that binding changes
+    fn single_cpu(binding: &CpuSet) -> CpuSet {
+        let mut result = binding.clone();
+        result.singlify();
+        result
+    }
+
+    #[test]
+    fn scoped_binding() {
+        let topology = Topology::test_instance();
+        if !current_thread_binding_supported(topology) {
+            return;
+        }
+        let flags = CpuBindingFlags::THREAD;
+        let initial = topology.cpu_binding(flags).unwrap();
+        let target = single_cpu(&initial);
+
+        // Dropping the guard restores the previous binding
+        {
+            let guard = topology.bind_cpu_scoped(&target, flags).unwrap();
+            assert_eq!(guard.previous_binding(), &initial);
+            assert_eq!(topology.cpu_binding(flags).unwrap(), target);
+        }
+        assert_eq!(topology.cpu_binding(flags).unwrap(), initial);
+
+        // So does an explicit restore()
+        let guard = topology.bind_cpu_scoped(&target, flags).unwrap();
+        assert_eq!(topology.cpu_binding(flags).unwrap(), target);
+        guard.restore().unwrap();
+        assert_eq!(topology.cpu_binding(flags).unwrap(), initial);
+
+        // keep_current() keeps the new binding
+        let guard = topology.bind_cpu_scoped(&target, flags).unwrap();
+        assert_eq!(guard.keep_current(), initial);
+        assert_eq!(topology.cpu_binding(flags).unwrap(), target);
+        topology.bind_cpu(&initial, flags).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn scoped_binding_from_another_thread() {
+        let topology = Topology::test_instance();
+        if !current_thread_binding_supported(topology)
+            || !topology.supports(FeatureSupport::cpu_binding, CpuBindingSupport::get_process)
+            || !topology.supports(FeatureSupport::cpu_binding, CpuBindingSupport::set_process)
+        {
+            return;
+        }
+        let flags = CpuBindingFlags::THREAD;
+        let initial = topology.cpu_binding(flags).unwrap();
+        let target = single_cpu(&initial);
+
+        // A guard that targets a thread by ID can be dropped anywhere
+        // SAFETY: gettid has no safety preconditions
+        let tid = unsafe { libc::gettid() };
+        let guard = topology
+            .bind_process_cpu_scoped(tid, &target, flags)
+            .unwrap();
+        assert_eq!(guard.target(), CpuBoundObject::ProcessOrThread(tid));
+        assert_eq!(guard.previous_binding(), &initial);
+        assert_eq!(topology.cpu_binding(flags).unwrap(), target);
+        std::thread::spawn(move || std::mem::drop(guard))
+            .join()
+            .unwrap();
+        assert_eq!(topology.cpu_binding(flags).unwrap(), initial);
+    }
+
+    #[test]
+    fn scoped_binding_error() {
+        let topology = Topology::test_instance();
+        let initial = topology.cpu_binding(CpuBindingFlags::THREAD).ok();
+        let result = topology.bind_cpu_scoped(
+            topology.cpuset(),
+            CpuBindingFlags::PROCESS | CpuBindingFlags::THREAD,
+        );
+        assert!(matches!(
+            result,
+            Err(HybridError::Rust(CpuBindingError::BadFlags(_)))
+        ));
+        assert_eq!(topology.cpu_binding(CpuBindingFlags::THREAD).ok(), initial);
+    }
+}
@@ -507,6 +507,30 @@ impl CpuSet {
         }
         polymorphized(topology, &nodeset)
     }
+
+    /// Convert a set of PU logical indices into a CPU set
+    ///
+    /// `logical` can be a `&'_ LogicalCpuSet` or a
+    /// `BitmapRef<'_, LogicalCpuSet>`.
+    ///
+    /// For each PU whose logical index is included in the input set, set the
+    /// corresponding OS index in the output cpuset. Logical indices which do
+    /// not correspond to any PU of `topology` are ignored.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    pub fn from_logical(topology: &Topology, logical: impl Deref<Target = LogicalCpuSet>) -> Self {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(topology: &Topology, logical: &LogicalCpuSet) -> CpuSet {
+            topology
+                .objects_with_type(ObjectType::PU)
+                .filter(|pu| logical.is_set(pu.logical_index()))
+                .fold(CpuSet::new(), |mut cpuset, pu| {
+                    cpuset.set(pu.os_index().expect("PUs should have an OS index"));
+                    cpuset
+                })
+        }
+        polymorphized(topology, &logical)
+    }
 }
 
 impl_bitmap_newtype!(
@@ -525,6 +549,58 @@ impl_bitmap_newtype!(
     CpuSet
 );
 
+/// # LogicalCpuSet-specific API
+//
+// --- Implementation details ---
+//
+// This goes before the main impl_bitmap_newtype macro so that it appears before
+// the bitmap API reexport in rustdoc.
+impl LogicalCpuSet {
+    /// Convert a CPU set into a set of PU logical indices
+    ///
+    /// `cpuset` can be a `&'_ CpuSet` or a `BitmapRef<'_, CpuSet>`.
+    ///
+    /// For each PU included in the input `cpuset`, set the corresponding
+    /// logical index in the output set. OS indices which do not correspond to
+    /// any PU of `topology` are ignored.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    pub fn from_cpuset(topology: &Topology, cpuset: impl Deref<Target = CpuSet>) -> Self {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(topology: &Topology, cpuset: &CpuSet) -> LogicalCpuSet {
+            topology
+                .pus_from_cpuset(cpuset)
+                .fold(LogicalCpuSet::new(), |mut logical, pu| {
+                    logical.set(pu.logical_index());
+                    logical
+                })
+        }
+        polymorphized(topology, &cpuset)
+    }
+}
+
+impl_bitmap_newtype!(
+    /// [`Bitmap`] whose bits are set according to PU logical indexes
+    ///
+    /// hwloc identifies PUs in two different ways. The OS index, which is used
+    /// by [`CpuSet`] and all hwloc binding functions, is the CPU number
+    /// assigned by the operating system. The logical index, which is what
+    /// `lstopo` displays by default and what `hwloc-calc -l` operates on, is
+    /// the position of the PU among all PUs of the topology in the
+    /// [topology order](TopologyObject::logical_index()).
+    ///
+    /// A `LogicalCpuSet` is a set of such logical indices. Since the two index
+    /// spaces generally differ, it cannot be used where a [`CpuSet`] is
+    /// expected, and must be converted to one using [`CpuSet::from_logical()`]
+    /// first. [`LogicalCpuSet::from_cpuset()`] performs the reverse
+    /// conversion.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    {
+        LogicalCpuSet => logical_bitmap_newtype, unspecialized
+    }
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -537,9 +613,35 @@ mod tests {
         strategies::topology_related_set,
     };
     use proptest::prelude::*;
+    use static_assertions::assert_not_impl_any;
     use std::collections::HashSet;
 
+    // Logical sets must not be usable where an OS-indexed CPU set is expected
+    assert_not_impl_any!(LogicalCpuSet:
+        crate::bitmap::SpecializedBitmap, From<CpuSet>, Into<CpuSet>
+    );
+
     proptest! {
+        /// Test for [`LogicalCpuSet::from_cpuset()`] and
+        /// [`CpuSet::from_logical()`]
+        #[test]
+        fn logical_cpuset(
+            cpuset in topology_related_set(Topology::cpuset),
+        ) {
+            let topology = Topology::test_instance();
+            let logical = LogicalCpuSet::from_cpuset(topology, &cpuset);
+            prop_assert_eq!(
+                logical.iter_set().map(usize::from).collect::<Vec<_>>(),
+                topology.pus_from_cpuset(&cpuset)
+                        .map(TopologyObject::logical_index)
+                        .collect::<Vec<_>>()
+            );
+            prop_assert_eq!(
+                CpuSet::from_logical(topology, &logical),
+                cpuset & topology.complete_cpuset()
+            );
+        }
+
         /// Test for [`Topology::largest_objects_inside_cpuset()`]
         #[test]
         fn largest_objects_inside_cpuset(set in topology_related_set(Topology::cpuset)) {
@@ -0,0 +1,606 @@
+//! Measuring core-to-core latency
+//!
+//! hwloc rarely knows about distances below the NUMA node level, but the
+//! placement of locks, queues and other shared data structures depends on how
+//! quickly CPU cores can communicate with each other. This module measures
+//! this communication latency by bouncing a cache line between pairs of
+//! pinned threads, and can register the resulting latency matrix as a
+//! [`Distances`] structure of the topology.
+//!
+//! Most of this module's functionality is exposed via [methods of the Topology
+//! struct](../../topology/struct.Topology.html#measuring-core-to-core-latency).
+//! The module itself only hosts type definitions that are related to this
+//! functionality.
+
+#[cfg(doc)]
+use crate::object::distance::Distances;
+use crate::{
+    cpu::{
+        binding::{CpuBindingError, CpuBindingFlags},
+        cpuset::CpuSet,
+    },
+    object::{types::ObjectType, TopologyObject, TopologyObjectID},
+    topology::Topology,
+};
+#[cfg(feature = "hwloc-2_5_0")]
+use crate::{
+    errors::HybridError,
+    object::distance::{AddDistancesError, AddDistancesFlags, DistancesKind},
+    topology::editor::TopologyEditor,
+};
+use std::{
+    hint,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Barrier,
+    },
+    thread,
+    time::Instant,
+};
+use thiserror::Error;
+
+/// # Measuring core-to-core latency
+//
+// --- Implementation details ---
+//
+// hwloc only reports the distances that are advertised by the OS or the
+// firmware, which do not include core-to-core latencies. This measures them
+// instead, by pinning threads with the CPU binding API.
+impl Topology {
+    /// Measure the communication latency between pairs of CPU cores
+    ///
+    /// For each pair of selected objects (by default, every allowed CPU core),
+    /// two threads are pinned to the first allowed PU of each object, and
+    /// then repeatedly hand over a cache line to each other. The one-way
+    /// latency of this exchange is recorded in the output [`LatencyMatrix`].
+    ///
+    /// Pairs are measured one after the other, so the whole process takes a
+    /// time that is quadratic in the number of selected objects. On very
+    /// large machines, use [`LatencyBenchmarkConfig::max_objects()`] to only
+    /// measure a sample of the objects.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hwlocality::{cpu::latency::LatencyBenchmarkConfig, object::types::ObjectType};
+    /// # let topology = hwlocality::Topology::test_instance();
+    /// let config = LatencyBenchmarkConfig::new()
+    ///     .object_type(ObjectType::PU)
+    ///     .max_objects(4)
+    ///     .round_trips(1000);
+    /// # // Binding may not be supported by the host where tests are run
+    /// if let Ok(matrix) = topology.measure_core_latencies(&config) {
+    ///     for from in 0..matrix.num_objects() {
+    ///         for to in 0..matrix.num_objects() {
+    ///             print!("{:>6}", matrix.latency(from, to));
+    ///         }
+    ///         println!();
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - [`CpuBinding`] if a benchmark thread could not be pinned to its CPU
+    /// - [`NotEnoughObjects`] if less than two objects were selected
+    ///
+    /// [`CpuBinding`]: LatencyBenchmarkError::CpuBinding
+    /// [`NotEnoughObjects`]: LatencyBenchmarkError::NotEnoughObjects
+    pub fn measure_core_latencies(
+        &self,
+        config: &LatencyBenchmarkConfig,
+    ) -> Result<LatencyMatrix, LatencyBenchmarkError> {
+        // Select objects and the PU that each of them will be measured from
+        let objects = self.latency_benchmark_objects(config);
+        if objects.len() < 2 {
+            return Err(LatencyBenchmarkError::NotEnoughObjects(objects.len()));
+        }
+        let cpus = objects
+            .iter()
+            .map(|object| {
+                let mut cpu = object.cpuset().expect("Selected objects have a cpuset")
+                    & self.allowed_cpuset();
+                cpu.singlify();
+                cpu
+            })
+            .collect::<Vec<_>>();
+
+        // Measure each pair of objects, assuming symmetrical latencies
+        let num_objects = objects.len();
+        let mut latencies = vec![0; num_objects * num_objects];
+        for from in 0..num_objects {
+            for to in (from + 1)..num_objects {
+                let latency = self.ping_pong(&cpus[from], &cpus[to], config.round_trips)?;
+                latencies[from * num_objects + to] = latency;
+                latencies[to * num_objects + from] = latency;
+            }
+        }
+        Ok(LatencyMatrix {
+            objects: objects
+                .into_iter()
+                .map(TopologyObject::global_persistent_index)
+                .collect(),
+            cpus,
+            latencies,
+        })
+    }
+
+    /// Objects whose latency should be measured according to `config`
+    fn latency_benchmark_objects(&self, config: &LatencyBenchmarkConfig) -> Vec<&TopologyObject> {
+        let object_type = if self.depth_for_type(config.object_type).is_ok() {
+            config.object_type
+        } else {
+            ObjectType::PU
+        };
+        let objects = self
+            .objects_with_type(object_type)
+            .filter(|object| {
+                object
+                    .cpuset()
+                    .is_some_and(|cpuset| cpuset.intersects(&*self.allowed_cpuset()))
+            })
+            .collect::<Vec<_>>();
+        match config.max_objects {
+            Some(max_objects) if max_objects < objects.len() => (0..max_objects)
+                .map(|sample| objects[sample * objects.len() / max_objects])
+                .collect(),
+            _ => objects,
+        }
+    }
+
+    /// Measure the one-way latency of handing over a cache line between a
+    /// thread pinned to `initiator_cpu` and a thread pinned to
+    /// `responder_cpu`, in nanoseconds
+    fn ping_pong(
+        &self,
+        initiator_cpu: &CpuSet,
+        responder_cpu: &CpuSet,
+        round_trips: usize,
+    ) -> Result<u64, LatencyBenchmarkError> {
+        /// Number of untimed round trips that precede the measurement
+        const WARMUP_ROUND_TRIPS: u64 = 100;
+        let round_trips = u64::try_from(round_trips.max(1)).unwrap_or(u64::MAX);
+        let total_round_trips = WARMUP_ROUND_TRIPS.saturating_add(round_trips);
+
+        // Each thread pins itself, then waits for the other thread to do the
+        // same, and only proceeds if both succeeded
+        let line = CacheLine(AtomicU64::new(0));
+        let barrier = Barrier::new(2);
+        let failed = AtomicBool::new(false);
+        let pin = |cpu: &CpuSet| {
+            let result = self.bind_cpu(cpu, CpuBindingFlags::THREAD);
+            if result.is_err() {
+                failed.store(true, Ordering::Relaxed);
+            }
+            barrier.wait();
+            match result {
+                Ok(()) if failed.load(Ordering::Relaxed) => Ok(false),
+                Ok(()) => Ok(true),
+                Err(error) => Err(LatencyBenchmarkError::CpuBinding {
+                    cpuset: cpu.clone(),
+                    error,
+                }),
+            }
+        };
+
+        thread::scope(|scope| {
+            let responder = scope.spawn(|| {
+                if pin(responder_cpu)? {
+                    for round_trip in 0..total_round_trips {
+                        wait_for(&line.0, 2 * round_trip + 1);
+                        line.0.store(2 * round_trip + 2, Ordering::Release);
+                    }
+                }
+                Ok(())
+            });
+            let initiator = scope.spawn(|| {
+                if !pin(initiator_cpu)? {
+                    return Ok(0);
+                }
+                let mut start = Instant::now();
+                for round_trip in 0..total_round_trips {
+                    if round_trip == WARMUP_ROUND_TRIPS {
+                        start = Instant::now();
+                    }
+                    line.0.store(2 * round_trip + 1, Ordering::Release);
+                    wait_for(&line.0, 2 * round_trip + 2);
+                }
+                let latency = start.elapsed().as_nanos() / (2 * u128::from(round_trips));
+                Ok(u64::try_from(latency).unwrap_or(u64::MAX))
+            });
+            let responder_result = responder
+                .join()
+                .unwrap_or_else(|payload| std::panic::resume_unwind(payload));
+            let latency = initiator
+                .join()
+                .unwrap_or_else(|payload| std::panic::resume_unwind(payload))?;
+            responder_result.map(|()| latency)
+        })
+    }
+}
+
+/// # Registering core-to-core latency measurements
+//
+// --- Implementation details ---
+//
+// Measurements are registered as user-provided distances, so that they can
+// be queried and used for object grouping like any other hwloc distances.
+#[cfg(feature = "hwloc-2_5_0")]
+impl TopologyEditor<'_> {
+    /// Register a [`LatencyMatrix`] as a distances structure called `name`
+    ///
+    /// The matrix is registered with the [`FROM_USER`] and [`MEANS_LATENCY`]
+    /// kinds, and can then be looked up using
+    /// [`Topology::distances_with_name()`]. `flags` can be used to group
+    /// objects based on the measured latencies.
+    ///
+    /// # Errors
+    ///
+    /// - [`UnknownObject`] if the matrix mentions an object that is not part
+    ///   of this topology
+    /// - [`AddDistances`] if hwloc failed to add the distances structure
+    ///
+    /// [`AddDistances`]: AddLatencyMatrixError::AddDistances
+    /// [`FROM_USER`]: DistancesKind::FROM_USER
+    /// [`MEANS_LATENCY`]: DistancesKind::MEANS_LATENCY
+    /// [`UnknownObject`]: AddLatencyMatrixError::UnknownObject
+    pub fn add_latency_matrix(
+        &mut self,
+        name: &str,
+        matrix: &LatencyMatrix,
+        flags: AddDistancesFlags,
+    ) -> Result<(), AddLatencyMatrixError> {
+        /// Find the object of `topology` with global persistent index `id`
+        fn find_object(topology: &Topology, id: TopologyObjectID) -> Option<&TopologyObject> {
+            topology
+                .objects()
+                .find(|object| object.global_persistent_index() == id)
+        }
+        let topology = self.topology();
+        if let Some(&unknown) = matrix
+            .objects
+            .iter()
+            .find(|&&id| find_object(topology, id).is_none())
+        {
+            return Err(AddLatencyMatrixError::UnknownObject(unknown));
+        }
+        self.add_distances(
+            Some(name),
+            DistancesKind::FROM_USER | DistancesKind::MEANS_LATENCY,
+            flags,
+            |topology| {
+                let objects = matrix
+                    .objects
+                    .iter()
+                    .map(|&id| find_object(topology, id))
+                    .collect();
+                (objects, matrix.latencies.clone())
+            },
+        )?;
+        Ok(())
+    }
+}
+
+/// Configuration of [`Topology::measure_core_latencies()`]
+///
+/// By default, the latency between every pair of allowed CPU cores is
+/// measured using 10000 round trips.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LatencyBenchmarkConfig {
+    /// Type of objects between which latency is measured
+    object_type: ObjectType,
+
+    /// Maximal number of objects to be measured
+    max_objects: Option<usize>,
+
+    /// Number of timed round trips per pair of objects
+    round_trips: usize,
+}
+//
+impl LatencyBenchmarkConfig {
+    /// Start from the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Type of objects between which latency is measured
+    ///
+    /// This is [`ObjectType::Core`] by default. Use [`ObjectType::PU`] to
+    /// also measure the latency between hardware threads of a core, or a
+    /// larger object type like [`ObjectType::L3Cache`] to only measure one
+    /// PU per object of this type. If the topology has no object of this
+    /// type, PUs are measured instead.
+    pub fn object_type(mut self, object_type: ObjectType) -> Self {
+        self.object_type = object_type;
+        self
+    }
+
+    /// Only measure a sample of at most `max_objects` objects
+    ///
+    /// The sample is evenly spread across the allowed objects of the
+    /// requested type, in topology order. Values below 2 will result in a
+    /// [`NotEnoughObjects`] error.
+    ///
+    /// [`NotEnoughObjects`]: LatencyBenchmarkError::NotEnoughObjects
+    pub fn max_objects(mut self, max_objects: usize) -> Self {
+        self.max_objects = Some(max_objects);
+        self
+    }
+
+    /// Number of timed round trips per pair of objects
+    ///
+    /// More round trips give more accurate results, at the expense of a
+    /// longer measurement. At least one round trip is always performed.
+    pub fn round_trips(mut self, round_trips: usize) -> Self {
+        self.round_trips = round_trips;
+        self
+    }
+}
+//
+impl Default for LatencyBenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            object_type: ObjectType::Core,
+            max_objects: None,
+            round_trips: 10_000,
+        }
+    }
+}
+
+/// Core-to-core latency matrix
+///
+/// This is produced by [`Topology::measure_core_latencies()`]. Like
+/// [`Distances`], it stores the latency between every pair of measured
+/// objects in sender-major order, and can be registered as a distances
+/// structure of the topology with `TopologyEditor::add_latency_matrix()`
+/// (hwloc 2.5+).
+///
+/// Objects are identified by their [global persistent
+/// index](TopologyObject::global_persistent_index()), so that this matrix can
+/// outlive borrows of the topology.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LatencyMatrix {
+    /// Global persistent indices of the measured objects
+    objects: Vec<TopologyObjectID>,
+
+    /// PU that each object was measured from
+    cpus: Vec<CpuSet>,
+
+    /// One-way latencies in nanoseconds, in sender-major order
+    latencies: Vec<u64>,
+}
+//
+impl LatencyMatrix {
+    /// Number of measured objects
+    pub fn num_objects(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Global persistent indices of the measured objects
+    pub fn objects(&self) -> &[TopologyObjectID] {
+        &self.objects
+    }
+
+    /// PU that each object was measured from, in the same order as
+    /// [`objects()`](Self::objects())
+    pub fn cpus(&self) -> &[CpuSet] {
+        &self.cpus
+    }
+
+    /// One-way latencies in nanoseconds, in sender-major order
+    ///
+    /// The latency from object `i` to object `j` is at index
+    /// `i * num_objects() + j`. The latency of each object to itself is 0.
+    pub fn latencies(&self) -> &[u64] {
+        &self.latencies
+    }
+
+    /// One-way latency from object `from` to object `to`, in nanoseconds
+    ///
+    /// # Panics
+    ///
+    /// If `from` or `to` is not below [`num_objects()`](Self::num_objects()).
+    pub fn latency(&self, from: usize, to: usize) -> u64 {
+        let num_objects = self.num_objects();
+        assert!(
+            from < num_objects && to < num_objects,
+            "object index out of range"
+        );
+        self.latencies[from * num_objects + to]
+    }
+}
+
+/// Error returned by [`Topology::measure_core_latencies()`]
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum LatencyBenchmarkError {
+    /// Failed to pin a benchmark thread to its CPU
+    #[error("failed to pin benchmark thread to CPU {cpuset}: {error}")]
+    CpuBinding {
+        /// CPU that the thread should have been pinned to
+        cpuset: CpuSet,
+
+        /// Binding error
+        error: CpuBindingError,
+    },
+
+    /// Less than two objects were selected for measurement
+    #[error("can't measure latencies between {0} objects")]
+    NotEnoughObjects(usize),
+}
+
+/// Error returned by [`TopologyEditor::add_latency_matrix()`]
+#[cfg(feature = "hwloc-2_5_0")]
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum AddLatencyMatrixError {
+    /// The matrix mentions an object that is not part of this topology
+    #[error("object #{0} is not part of this topology")]
+    UnknownObject(TopologyObjectID),
+
+    /// hwloc failed to add the distances structure
+    #[error(transparent)]
+    AddDistances(#[from] HybridError<AddDistancesError>),
+}
+
+/// Cache line that is handed over between ping-pong threads
+///
+/// The alignment also covers adjacent line prefetching on x86 CPUs.
+#[repr(align(128))]
+struct CacheLine(AtomicU64);
+
+/// Spin until `value` is set to `expected`
+fn wait_for(value: &AtomicU64, expected: u64) {
+    while value.load(Ordering::Acquire) != expected {
+        hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(LatencyBenchmarkConfig:
+        Copy, Debug, Default, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(LatencyBenchmarkConfig:
+        Binary, Deref, Display, Drop, Error, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+    assert_impl_all!(LatencyMatrix:
+        Clone, Debug, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(LatencyMatrix:
+        Binary, Copy, Default, Deref, Display, Drop, Error, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialOrd, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(LatencyBenchmarkError:
+        Clone, Error, Eq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    #[cfg(feature = "hwloc-2_5_0")]
+    assert_impl_all!(AddLatencyMatrixError:
+        Clone, Error, Eq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+
+    /// 2 packages of 4 cores with 2 PUs
+    fn topology() -> Topology {
+        Topology::synthetic_instance("Package:2 Core:4 PU:2")
+    }
+
+    #[test]
+    fn object_selection() {
+        let topology = topology();
+        let selected_ids = |config: &LatencyBenchmarkConfig| {
+            topology
+                .latency_benchmark_objects(config)
+                .into_iter()
+                .map(|object| (object.object_type(), object.logical_index()))
+                .collect::<Vec<_>>()
+        };
+
+        let config = LatencyBenchmarkConfig::new();
+        let cores = (0..8)
+            .map(|idx| (ObjectType::Core, idx))
+            .collect::<Vec<_>>();
+        assert_eq!(selected_ids(&config), cores);
+        assert_eq!(selected_ids(&config.max_objects(8)), cores);
+        assert_eq!(
+            selected_ids(&config.max_objects(4)),
+            [0, 2, 4, 6].map(|idx| (ObjectType::Core, idx))
+        );
+        assert_eq!(
+            selected_ids(&config.object_type(ObjectType::Package)),
+            [0, 1].map(|idx| (ObjectType::Package, idx))
+        );
+        assert_eq!(
+            selected_ids(&config.object_type(ObjectType::L3Cache).max_objects(3)),
+            [0, 5, 10].map(|idx| (ObjectType::PU, idx))
+        );
+    }
+
+    #[test]
+    fn measure() {
+        let topology = Topology::test_instance();
+        let config = LatencyBenchmarkConfig::new()
+            .object_type(ObjectType::PU)
+            .max_objects(3)
+            .round_trips(100);
+        let num_pus = topology.allowed_cpuset().weight().unwrap();
+        let matrix = match topology.measure_core_latencies(&config) {
+            Ok(matrix) => matrix,
+            Err(LatencyBenchmarkError::NotEnoughObjects(num_objects)) => {
+                assert!(num_pus < 2);
+                assert_eq!(num_objects, num_pus);
+                return;
+            }
+            // Binding may not be supported by the host where tests are run
+            Err(LatencyBenchmarkError::CpuBinding { .. }) => return,
+        };
+        let num_objects = num_pus.min(3);
+        assert_eq!(matrix.num_objects(), num_objects);
+        assert_eq!(matrix.cpus().len(), num_objects);
+        assert_eq!(matrix.latencies().len(), num_objects.pow(2));
+        for (from, cpu) in matrix.cpus().iter().enumerate() {
+            assert_eq!(cpu.weight(), Some(1));
+            assert!(topology.allowed_cpuset().includes(cpu));
+            for to in 0..num_objects {
+                assert_eq!(matrix.latency(from, to), matrix.latency(to, from));
+                if from == to {
+                    assert_eq!(matrix.latency(from, to), 0);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "hwloc-2_5_0")]
+    #[test]
+    fn add_latency_matrix() {
+        let mut topology = topology();
+        let objects = topology
+            .objects_with_type(ObjectType::Core)
+            .take(2)
+            .map(TopologyObject::global_persistent_index)
+            .collect::<Vec<_>>();
+        let matrix = LatencyMatrix {
+            cpus: vec![CpuSet::from_range(0..=0), CpuSet::from_range(2..=2)],
+            objects,
+            latencies: vec![0, 42, 42, 0],
+        };
+        let unknown = LatencyMatrix {
+            objects: vec![matrix.objects[0], TopologyObjectID::MAX],
+            ..matrix.clone()
+        };
+        topology.edit(|editor| {
+            assert_eq!(
+                editor.add_latency_matrix("Latency", &unknown, AddDistancesFlags::empty()),
+                Err(AddLatencyMatrixError::UnknownObject(TopologyObjectID::MAX))
+            );
+            editor
+                .add_latency_matrix("Latency", &matrix, AddDistancesFlags::empty())
+                .unwrap();
+        });
+        let distances = topology.distances_with_name("Latency").unwrap();
+        assert_eq!(distances.len(), 1);
+        let distances = &distances[0];
+        assert_eq!(
+            distances.kind(),
+            DistancesKind::FROM_USER | DistancesKind::MEANS_LATENCY
+        );
+        assert_eq!(distances.distances(), matrix.latencies());
+    }
+}
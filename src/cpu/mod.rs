@@ -9,6 +9,9 @@ pub mod cache;
 pub mod cpuset;
 #[cfg(feature = "hwloc-2_4_0")]
 pub mod kind;
+pub mod latency;
+pub mod reservation;
+pub mod threads;
 
 #[cfg(doc)]
 use crate::topology::Topology;
@@ -0,0 +1,661 @@
+//! Exclusive CPU reservations
+//!
+//! When many tenants share a large machine, it is common to give each of them
+//! exclusive use of a topology-aligned set of CPUs, as done by the static
+//! policy of the Kubernetes CPU manager. This module provides a
+//! [`CpuReservations`] tracker that hands out disjoint [`CpuSet`]s from a pool
+//! of CPUs, following the structure of the topology.
+
+use crate::{
+    cpu::cpuset::CpuSet,
+    object::{types::ObjectType, TopologyObject},
+    topology::Topology,
+};
+use std::fmt::{self, Display};
+use thiserror::Error;
+
+/// Tracker of exclusive CPU reservations
+///
+/// This hands out disjoint sets of CPUs from a pool, which by default
+/// contains all the allowed CPUs of a [`Topology`]. CPUs remain reserved until
+/// they are explicitly released.
+///
+/// Reservations are aligned with the topology: CPUs are taken from
+/// topology objects that are entirely free whenever possible, in order to
+/// keep the remaining free CPUs in as few topology objects as possible. How
+/// well this works out can be checked using [`CpuReservations::fragmentation()`].
+///
+/// # Examples
+///
+/// ```
+/// # use hwlocality::cpu::reservation::{CpuReservations, ReservationRequest};
+/// # let topology = hwlocality::Topology::test_instance();
+/// let mut reservations = CpuReservations::new(topology);
+/// let cpus = reservations.reserve(ReservationRequest::cores(1))?;
+/// assert!(!cpus.is_empty());
+/// assert!(!reservations.free().intersects(&cpus));
+/// reservations.release(&cpus)?;
+/// # Ok::<(), eyre::Report>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct CpuReservations<'topology> {
+    /// Topology that CPUs belong to
+    topology: &'topology Topology,
+
+    /// CPUs that can be reserved
+    pool: CpuSet,
+
+    /// CPUs of the pool that are not currently reserved
+    free: CpuSet,
+}
+//
+impl<'topology> CpuReservations<'topology> {
+    /// Track reservations of the allowed CPUs of `topology`
+    pub fn new(topology: &'topology Topology) -> Self {
+        Self::with_cpuset(topology, &topology.allowed_cpuset())
+    }
+
+    /// Track reservations of the allowed CPUs of `topology` that belong to
+    /// `cpuset`
+    ///
+    /// This can be used to keep some CPUs out of the pool, e.g. those that are
+    /// reserved for system daemons.
+    pub fn with_cpuset(topology: &'topology Topology, cpuset: &CpuSet) -> Self {
+        let pool = cpuset & topology.allowed_cpuset();
+        Self {
+            topology,
+            free: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Topology that CPUs belong to
+    pub fn topology(&self) -> &'topology Topology {
+        self.topology
+    }
+
+    /// CPUs that can be reserved
+    pub fn pool(&self) -> &CpuSet {
+        &self.pool
+    }
+
+    /// CPUs of the pool that are not currently reserved
+    pub fn free(&self) -> &CpuSet {
+        &self.free
+    }
+
+    /// CPUs of the pool that are currently reserved
+    pub fn reserved(&self) -> CpuSet {
+        &self.pool - &self.free
+    }
+
+    /// Reserve CPUs as directed by `request`
+    ///
+    /// The returned CPUs are removed from the set of free CPUs, and will not
+    /// be handed out again until they are [released](Self::release()).
+    ///
+    /// # Errors
+    ///
+    /// - [`NotEnoughCpus`] if there are not enough free CPUs to satisfy the
+    ///   request
+    /// - [`NotWholeCores`] if whole cores were requested, but no combination
+    ///   of free cores has exactly the requested number of PUs
+    ///
+    /// [`NotEnoughCpus`]: ReservationError::NotEnoughCpus
+    /// [`NotWholeCores`]: ReservationError::NotWholeCores
+    pub fn reserve(&mut self, request: ReservationRequest) -> Result<CpuSet, ReservationError> {
+        // Enumerate the free units that the request can be served with, and
+        // check that there are enough of them
+        let whole_cores = request.whole_cores || request.unit == CpuUnit::Core;
+        let units = self.free_units(whole_cores);
+        let weight = |unit: &CpuSet| match request.unit {
+            CpuUnit::Core => 1,
+            CpuUnit::PU => unit.weight().expect("Units should be finite"),
+        };
+        let available = units.iter().map(weight).sum::<usize>();
+        if available < request.count {
+            return Err(ReservationError::NotEnoughCpus {
+                requested: request.count,
+                available,
+                unit: request.unit,
+            });
+        }
+
+        // Select units according to the requested strategy
+        let selected = match request.strategy {
+            ReservationStrategy::Pack => self.pack(&units, request.count, weight),
+            ReservationStrategy::Spread => self.spread(&units, request.count, weight),
+        };
+        let selected_weight = selected.iter().map(weight).sum::<usize>();
+        if selected_weight != request.count {
+            return Err(ReservationError::NotWholeCores {
+                requested: request.count,
+            });
+        }
+
+        // Reserve the selected CPUs
+        let mut cpuset = CpuSet::new();
+        for unit in selected {
+            cpuset |= unit;
+        }
+        self.free -= &cpuset;
+        Ok(cpuset)
+    }
+
+    /// Release previously reserved CPUs
+    ///
+    /// Reservations do not need to be released all at once, any subset of the
+    /// reserved CPUs can be released.
+    ///
+    /// # Errors
+    ///
+    /// - [`ReleaseError`] if some of these CPUs are not currently reserved.
+    ///   In this case, no CPU is released.
+    pub fn release(&mut self, cpuset: &CpuSet) -> Result<(), ReleaseError> {
+        let not_reserved = cpuset - &self.reserved();
+        if !not_reserved.is_empty() {
+            return Err(ReleaseError(not_reserved));
+        }
+        self.free |= cpuset;
+        Ok(())
+    }
+
+    /// Analyze how fragmented the free CPUs are
+    pub fn fragmentation(&self) -> Fragmentation<'topology> {
+        let free_blocks = self
+            .topology
+            .coarsest_cpuset_partition(&self.free)
+            .expect("Free CPUs should belong to the topology");
+        let block_pus =
+            |block: &TopologyObject| block.cpuset().expect("Blocks have a cpuset") & &self.free;
+        let largest_block_pus = free_blocks
+            .iter()
+            .map(|block| block_pus(block).weight().expect("Blocks should be finite"))
+            .max()
+            .unwrap_or(0);
+        let split_cores = self
+            .cores()
+            .filter(|core| {
+                let core_pool = core.cpuset().expect("Cores have a cpuset") & &self.pool;
+                core_pool.intersects(&self.free) && !self.free.includes(&core_pool)
+            })
+            .count();
+        Fragmentation {
+            free_pus: self.free.weight().expect("Free CPUs should be finite"),
+            largest_block_pus,
+            split_cores,
+            free_blocks,
+        }
+    }
+
+    /// Cores of the topology, or PUs if it has no cores, that overlap with
+    /// the CPU pool
+    fn cores(&self) -> impl Iterator<Item = &'topology TopologyObject> + '_ {
+        let core_type = if self.topology.depth_for_type(ObjectType::Core).is_ok() {
+            ObjectType::Core
+        } else {
+            ObjectType::PU
+        };
+        self.topology
+            .objects_with_type(core_type)
+            .filter(|core| core.cpuset().is_some_and(|set| set.intersects(&self.pool)))
+    }
+
+    /// Free units that can be reserved, either whole cores or individual PUs
+    fn free_units(&self, whole_cores: bool) -> Vec<CpuSet> {
+        if whole_cores {
+            self.cores()
+                .filter_map(|core| {
+                    let core_pool = core.cpuset().expect("Cores have a cpuset") & &self.pool;
+                    self.free.includes(&core_pool).then_some(core_pool)
+                })
+                .collect()
+        } else {
+            self.free.iter_set().map(CpuSet::from).collect()
+        }
+    }
+
+    /// Select `count` worth of `units` from the smallest L3 cache, package or
+    /// machine that has enough free units
+    ///
+    /// Inside of the chosen object, units are first taken from the largest
+    /// entirely free topology objects that fit, then from the smallest
+    /// remaining objects, so that the free CPUs that remain afterwards are
+    /// grouped in as few objects as possible.
+    fn pack(
+        &self,
+        units: &[CpuSet],
+        count: usize,
+        weight: impl Fn(&CpuSet) -> usize + Copy,
+    ) -> Vec<CpuSet> {
+        // Find the smallest container that has enough free units
+        let units_inside = |container: &CpuSet| {
+            units
+                .iter()
+                .filter(|unit| container.includes(*unit))
+                .collect::<Vec<_>>()
+        };
+        let container_levels = [
+            ObjectType::L3Cache,
+            ObjectType::Package,
+            ObjectType::Machine,
+        ];
+        let container_units = container_levels
+            .into_iter()
+            .find_map(|ty| {
+                self.topology
+                    .objects_with_type(ty)
+                    .filter_map(|container| {
+                        let container_set = container.cpuset()?;
+                        let units = units_inside(&container_set);
+                        let capacity = units.iter().map(|unit| weight(unit)).sum::<usize>();
+                        (capacity >= count).then_some((capacity, units))
+                    })
+                    .min_by_key(|(capacity, _units)| *capacity)
+            })
+            .map_or_else(|| units.iter().collect(), |(_capacity, units)| units);
+
+        // Group the container's free units by largest free topology object
+        let mut free_cpus = CpuSet::new();
+        for unit in &container_units {
+            free_cpus |= *unit;
+        }
+        let mut blocks = self
+            .topology
+            .largest_objects_inside_cpuset(free_cpus)
+            .map(|block| {
+                let block_units = units_inside(&block.cpuset().expect("Blocks have a cpuset"));
+                let block_weight = block_units.iter().map(|unit| weight(unit)).sum::<usize>();
+                (block_weight, block_units)
+            })
+            .filter(|(block_weight, _units)| *block_weight > 0)
+            .collect::<Vec<_>>();
+
+        // Take whole blocks, largest first, as long as they fit
+        let mut selected = Vec::new();
+        let mut remaining = count;
+        blocks.sort_by_key(|(block_weight, _units)| std::cmp::Reverse(*block_weight));
+        blocks.retain(|(block_weight, block_units)| {
+            if *block_weight <= remaining {
+                remaining -= block_weight;
+                selected.extend(block_units.iter().map(|unit| (*unit).clone()));
+                false
+            } else {
+                true
+            }
+        });
+
+        // Take the rest from the smallest blocks
+        blocks.sort_by_key(|(block_weight, _units)| *block_weight);
+        for unit in blocks.into_iter().flat_map(|(_weight, units)| units) {
+            if remaining == 0 {
+                break;
+            }
+            let unit_weight = weight(unit);
+            if unit_weight <= remaining {
+                remaining -= unit_weight;
+                selected.push(unit.clone());
+            }
+        }
+        selected
+    }
+
+    /// Select `count` worth of `units`, spread as evenly as possible across
+    /// packages
+    fn spread(
+        &self,
+        units: &[CpuSet],
+        count: usize,
+        weight: impl Fn(&CpuSet) -> usize,
+    ) -> Vec<CpuSet> {
+        // Group free units by package, or put them all in one group if the
+        // topology has no package
+        let mut packages = self
+            .topology
+            .objects_with_type(ObjectType::Package)
+            .filter_map(|package| {
+                let package_set = package.cpuset()?;
+                let package_units = units
+                    .iter()
+                    .filter(|unit| package_set.includes(*unit))
+                    .collect::<Vec<_>>();
+                (!package_units.is_empty()).then_some(package_units.into_iter())
+            })
+            .collect::<Vec<_>>();
+        if packages.is_empty() {
+            packages.push(units.iter().collect::<Vec<_>>().into_iter());
+        }
+
+        // Take one unit at a time from the package with the most free units
+        let mut selected = Vec::new();
+        let mut remaining = count;
+        while remaining > 0 {
+            let Some(package) = packages
+                .iter_mut()
+                .filter(|package| {
+                    package
+                        .as_slice()
+                        .first()
+                        .is_some_and(|unit| weight(unit) <= remaining)
+                })
+                .rev()
+                .max_by_key(ExactSizeIterator::len)
+            else {
+                break;
+            };
+            let unit = package.next().expect("Checked above that a unit remains");
+            remaining -= weight(unit);
+            selected.push(unit.clone());
+        }
+        selected
+    }
+}
+
+/// Request for CPUs, to be served by [`CpuReservations::reserve()`]
+///
+/// By default, requested CPUs are packed into as few topology objects as
+/// possible, and PU requests may be served with PUs of partially reserved
+/// cores. Use the builder methods to change this.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ReservationRequest {
+    /// Number of units
+    count: usize,
+
+    /// Kind of units
+    unit: CpuUnit,
+
+    /// How units are chosen
+    strategy: ReservationStrategy,
+
+    /// Truth that only whole cores should be reserved
+    whole_cores: bool,
+}
+//
+impl ReservationRequest {
+    /// Request `count` whole CPU cores
+    ///
+    /// If the topology has no [`Core`] objects, PUs are used instead.
+    ///
+    /// [`Core`]: ObjectType::Core
+    pub fn cores(count: usize) -> Self {
+        Self {
+            count,
+            unit: CpuUnit::Core,
+            strategy: ReservationStrategy::Pack,
+            whole_cores: true,
+        }
+    }
+
+    /// Request `count` PUs (hardware threads)
+    pub fn pus(count: usize) -> Self {
+        Self {
+            count,
+            unit: CpuUnit::PU,
+            strategy: ReservationStrategy::Pack,
+            whole_cores: false,
+        }
+    }
+
+    /// Choose how CPUs are selected
+    pub fn strategy(mut self, strategy: ReservationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Only reserve PUs as part of entirely free cores
+    ///
+    /// This makes sure that no other tenant shares a core with this one.
+    /// Requests for a number of PUs that cannot be achieved with whole cores
+    /// will then fail. Core requests always reserve whole cores.
+    pub fn whole_cores(mut self, whole_cores: bool) -> Self {
+        self.whole_cores = whole_cores || self.unit == CpuUnit::Core;
+        self
+    }
+}
+
+/// Kind of CPU that is requested
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum CpuUnit {
+    /// CPU core, possibly containing multiple PUs
+    Core,
+
+    /// Processing unit (hardware thread)
+    PU,
+}
+//
+impl Display for CpuUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Core => "cores",
+            Self::PU => "PUs",
+        };
+        f.pad(name)
+    }
+}
+
+/// How CPUs are selected by [`CpuReservations::reserve()`]
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ReservationStrategy {
+    /// Pack CPUs into as few topology objects as possible
+    ///
+    /// CPUs are taken from a single L3 cache if possible, otherwise from a
+    /// single package, and from the whole pool as a last resort. This favors
+    /// communication between the reserved CPUs and keeps large blocks of CPUs
+    /// free for future reservations.
+    #[default]
+    Pack,
+
+    /// Spread CPUs evenly across packages
+    ///
+    /// This maximizes the memory bandwidth and cache capacity that is
+    /// available to the reserved CPUs.
+    Spread,
+}
+
+/// Analysis of the free CPUs of [`CpuReservations`]
+///
+/// This is returned by [`CpuReservations::fragmentation()`].
+#[derive(Clone, Debug)]
+pub struct Fragmentation<'topology> {
+    /// Largest topology objects that exactly cover the free CPUs
+    ///
+    /// The fewer objects there are, the better aligned future reservations
+    /// can be.
+    pub free_blocks: Vec<&'topology TopologyObject>,
+
+    /// Number of free PUs
+    pub free_pus: usize,
+
+    /// Number of free PUs in the largest of the `free_blocks`
+    pub largest_block_pus: usize,
+
+    /// Number of cores that have both free and reserved PUs
+    pub split_cores: usize,
+}
+//
+impl Fragmentation<'_> {
+    /// Fraction of the free PUs that are not part of the largest free block
+    ///
+    /// This is 0.0 when all free PUs belong to a single topology object (or
+    /// when there is no free PU), and gets closer to 1.0 as free PUs are
+    /// scattered across many small objects.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn ratio(&self) -> f64 {
+        if self.free_pus == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_block_pus as f64 / self.free_pus as f64
+        }
+    }
+}
+
+/// Error returned by [`CpuReservations::reserve()`]
+#[derive(Copy, Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub enum ReservationError {
+    /// There are not enough free CPUs
+    #[error("requested {requested} {unit}, but only {available} are free")]
+    NotEnoughCpus {
+        /// Requested number of CPUs
+        requested: usize,
+
+        /// Number of free CPUs that could serve the request
+        available: usize,
+
+        /// Kind of CPUs that was requested
+        unit: CpuUnit,
+    },
+
+    /// The requested number of PUs cannot be reserved as whole cores
+    #[error("{requested} PUs cannot be reserved as whole free cores")]
+    NotWholeCores {
+        /// Requested number of PUs
+        requested: usize,
+    },
+}
+
+/// Error returned by [`CpuReservations::release()`] when trying to release CPUs
+/// that are not reserved
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+#[error("CPUs {0} are not reserved")]
+pub struct ReleaseError(pub CpuSet);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::parse_list;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{Binary, Debug, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex},
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(CpuReservations<'static>:
+        Clone, Debug, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(CpuReservations<'static>:
+        Binary, Copy, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(ReservationRequest:
+        Copy, Debug, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(CpuUnit:
+        Copy, Debug, Display, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(ReservationStrategy:
+        Copy, Debug, Default, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(Fragmentation<'static>:
+        Clone, Debug, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(ReservationError:
+        Copy, Error, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(ReleaseError:
+        Clone, Error, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+
+    /// 2 packages, each with 2 L3 caches of 4 cores with 2 PUs
+    fn topology() -> Topology {
+        Topology::synthetic_instance("Package:2 L3Cache:2 Core:4 PU:2")
+    }
+
+    #[test]
+    fn pack() {
+        let topology = topology();
+        let mut reservations = CpuReservations::new(&topology);
+        assert_eq!(reservations.pool(), &parse_list::<CpuSet>("0-31"));
+        assert_eq!(reservations.fragmentation().free_blocks.len(), 1);
+
+        // Cores are packed into one L3 cache
+        let first = reservations.reserve(ReservationRequest::cores(3)).unwrap();
+        assert_eq!(first.weight(), Some(6));
+        assert!(parse_list::<CpuSet>("0-7").includes(&first));
+
+        // The next request fits in the rest of that L3 cache
+        let second = reservations.reserve(ReservationRequest::pus(2)).unwrap();
+        assert!(parse_list::<CpuSet>("0-7").includes(&second));
+        assert_eq!(reservations.free(), &parse_list::<CpuSet>("8-31"));
+
+        // A request that does not fit in the free L3 caches spans a package
+        let third = reservations.reserve(ReservationRequest::cores(6)).unwrap();
+        assert_eq!(third.weight(), Some(12));
+        assert!(parse_list::<CpuSet>("16-31").includes(&third));
+
+        // Free CPUs are now fragmented
+        let fragmentation = reservations.fragmentation();
+        assert_eq!(fragmentation.free_pus, 12);
+        assert_eq!(fragmentation.largest_block_pus, 8);
+        assert_eq!(fragmentation.split_cores, 0);
+        assert!(fragmentation.ratio() > 0.0);
+
+        // Releasing CPUs makes them available again
+        reservations.release(&first).unwrap();
+        reservations.release(&second).unwrap();
+        reservations.release(&third).unwrap();
+        assert_eq!(reservations.free(), reservations.pool());
+        assert!(reservations.fragmentation().ratio().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn spread() {
+        let topology = topology();
+        let mut reservations = CpuReservations::new(&topology);
+        let cpus = reservations
+            .reserve(ReservationRequest::cores(4).strategy(ReservationStrategy::Spread))
+            .unwrap();
+        assert_eq!(cpus.weight(), Some(8));
+        assert_eq!((&cpus & parse_list::<CpuSet>("0-15")).weight(), Some(4));
+        assert_eq!((&cpus & parse_list::<CpuSet>("16-31")).weight(), Some(4));
+    }
+
+    #[test]
+    fn whole_cores() {
+        let topology = topology();
+        let mut reservations =
+            CpuReservations::with_cpuset(&topology, &parse_list::<CpuSet>("0-7"));
+
+        // Odd PU counts leave split cores behind, unless whole cores are
+        // requested, in which case they cannot be served
+        let odd = reservations.reserve(ReservationRequest::pus(3)).unwrap();
+        assert_eq!(odd.weight(), Some(3));
+        assert_eq!(reservations.fragmentation().split_cores, 1);
+        assert_eq!(
+            reservations.reserve(ReservationRequest::pus(3).whole_cores(true)),
+            Err(ReservationError::NotWholeCores { requested: 3 })
+        );
+        let even = reservations
+            .reserve(ReservationRequest::pus(2).whole_cores(true))
+            .unwrap();
+        assert_eq!(even.weight(), Some(2));
+        assert!(topology
+            .objects_with_type(ObjectType::Core)
+            .any(|core| core.cpuset().unwrap() == even));
+
+        // Requests beyond the pool's capacity fail
+        assert_eq!(
+            reservations.reserve(ReservationRequest::cores(2)),
+            Err(ReservationError::NotEnoughCpus {
+                requested: 2,
+                available: 1,
+                unit: CpuUnit::Core,
+            })
+        );
+
+        // Only reserved CPUs can be released
+        assert_eq!(
+            reservations.release(&parse_list::<CpuSet>("0-8")),
+            Err(ReleaseError(parse_list::<CpuSet>("3,6-8")))
+        );
+        assert_eq!(reservations.reserved(), parse_list::<CpuSet>("0-2,4-5"));
+    }
+}
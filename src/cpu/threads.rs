@@ -0,0 +1,711 @@
+//! Spawning threads pinned to CPUs
+//!
+//! [`Topology::distribute_items()`] tells how a set of work items should be
+//! spread across the CPUs of a topology, but leaves the task of actually
+//! binding threads to the resulting CPU sets to the caller. This module takes
+//! care of that for the common case where one work item is one [`std::thread`].
+//!
+//! Most of this module's functionality is exposed via [methods of the Topology
+//! struct](../../topology/struct.Topology.html#spawning-pinned-threads). The
+//! module itself only hosts type definitions that are related to this
+//! functionality.
+
+#[cfg(doc)]
+use crate::topology::support::CpuBindingSupport;
+use crate::{
+    cpu::{
+        binding::{CpuBindingError, CpuBindingFlags},
+        cpuset::CpuSet,
+    },
+    object::{depth::NormalDepth, TopologyObject},
+    topology::{DistributeError, DistributeFlags, Topology},
+};
+use std::{
+    io,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle, Scope, ScopedJoinHandle, Thread},
+};
+use thiserror::Error;
+
+/// # Spawning pinned threads
+//
+// --- Implementation details ---
+//
+// hwloc can bind existing threads, but it has no notion of thread spawning,
+// so this combines distribute_items() with the current thread binding API
+// from inside of each newly spawned thread.
+impl Topology {
+    /// Prepare to spawn `num_threads` threads pinned to the CPUs below `roots`
+    ///
+    /// CPU sets are assigned to threads using [`distribute_items()`], with the
+    /// specified distribution `flags` and, by default, no limit on the
+    /// distribution depth. The returned [`PinnedThreadsBuilder`] can be used to
+    /// tune this further before spawning threads.
+    ///
+    /// Each spawned thread binds itself to its assigned CPU set before running
+    /// any user code. Threads are only allowed to start running user code
+    /// once all of them have successfully bound themselves, so binding
+    /// failures are reported as spawning errors instead of leaving some
+    /// threads running unpinned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hwlocality::topology::DistributeFlags;
+    /// # let topology = hwlocality::Topology::test_instance();
+    /// let threads = topology
+    ///     .pinned_threads(&[topology.root_object()], 2, DistributeFlags::empty())
+    ///     .singlify(true)
+    ///     .spawn(|worker| worker.cpuset().weight());
+    /// # // Binding may not be supported by the host where tests are run
+    /// # if let Ok(threads) = threads {
+    /// for thread in threads {
+    ///     assert_eq!(thread.join().unwrap(), Some(1));
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`distribute_items()`]: Topology::distribute_items()
+    pub fn pinned_threads<'topology>(
+        &'topology self,
+        roots: &[&'topology TopologyObject],
+        num_threads: usize,
+        flags: DistributeFlags,
+    ) -> PinnedThreadsBuilder<'topology> {
+        PinnedThreadsBuilder {
+            topology: self,
+            roots: roots.to_vec(),
+            num_threads,
+            flags,
+            max_depth: NormalDepth::MAX,
+            singlify: false,
+        }
+    }
+}
+
+/// Mechanism to spawn threads that are pinned to CPUs
+///
+/// This `struct` is created by [`Topology::pinned_threads()`]. It can be used
+/// to configure how CPUs are assigned to threads, then spawn them using either
+/// [`spawn()`](Self::spawn()) or [`spawn_scoped()`](Self::spawn_scoped()).
+#[derive(Clone, Debug)]
+pub struct PinnedThreadsBuilder<'topology> {
+    /// Topology that the roots belong to
+    topology: &'topology Topology,
+
+    /// Roots under which threads are distributed
+    roots: Vec<&'topology TopologyObject>,
+
+    /// Number of threads to be spawned
+    num_threads: usize,
+
+    /// Flags to be passed to [`Topology::distribute_items()`]
+    flags: DistributeFlags,
+
+    /// Maximal distribution depth
+    max_depth: NormalDepth,
+
+    /// Truth that assigned CPU sets should be reduced to a single PU
+    singlify: bool,
+}
+//
+impl<'topology> PinnedThreadsBuilder<'topology> {
+    /// Stop the distribution at a certain topology depth
+    ///
+    /// This is the `max_depth` parameter of [`Topology::distribute_items()`].
+    /// By default, threads are distributed all the way down to PUs.
+    pub fn max_depth(mut self, max_depth: NormalDepth) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Reduce each thread's CPU set to a single PU
+    ///
+    /// When threads are distributed at a depth above the PU level, or when
+    /// there are fewer threads than PUs, each thread is assigned multiple PUs
+    /// and the OS scheduler remains free to migrate it between them. Setting
+    /// this option calls [`CpuSet::singlify()`] on the assigned CPU sets in
+    /// order to prevent these migrations.
+    ///
+    /// This option is disabled by default.
+    pub fn singlify(mut self, singlify: bool) -> Self {
+        self.singlify = singlify;
+        self
+    }
+
+    /// Compute the CPU set and locality that each thread will be bound to
+    ///
+    /// This lets you check which CPUs threads will be bound to before they are
+    /// actually spawned.
+    ///
+    /// # Errors
+    ///
+    /// - [`DistributeError`] if the distribution roots are invalid, see
+    ///   [`Topology::distribute_items()`] for more information.
+    pub fn workers(&self) -> Result<Vec<PinnedWorker<'topology>>, DistributeError> {
+        let cpusets = self.topology.distribute_items(
+            &self.roots,
+            self.num_threads,
+            self.max_depth,
+            self.flags,
+        )?;
+        Ok(cpusets
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut cpuset)| {
+                if self.singlify {
+                    cpuset.singlify();
+                }
+                let locality = self
+                    .topology
+                    .smallest_object_covering_cpuset(&cpuset)
+                    .expect("Distributed cpusets should be non-empty subsets of the topology");
+                PinnedWorker {
+                    index,
+                    cpuset,
+                    locality,
+                }
+            })
+            .collect())
+    }
+
+    /// Spawn the pinned threads
+    ///
+    /// Each thread runs `f` once it has been bound to its assigned CPUs,
+    /// passing it a [`PinnedWorker`] that describes its assignment.
+    ///
+    /// This method is only available when the topology has a `'static`
+    /// lifetime, e.g. because it is stored in a `static` variable. If that is
+    /// not the case, use [`spawn_scoped()`](Self::spawn_scoped()) instead.
+    ///
+    /// # Errors
+    ///
+    /// - [`Distribute`] if CPUs could not be assigned to threads
+    /// - [`Spawn`] if the operating system failed to spawn a thread
+    /// - [`Bind`] if a thread failed to bind itself to its CPUs. This can
+    ///   notably happen if [`CpuBindingSupport::set_current_thread()`] is not
+    ///   supported, or if some of the assigned CPUs are disallowed.
+    /// - [`Exited`] if a thread exited before reporting whether it managed to
+    ///   bind itself to its CPUs, e.g. because the binding call panicked.
+    ///
+    /// Threads are never allowed to run `f` when an error is returned.
+    ///
+    /// [`Bind`]: PinnedThreadsError::Bind
+    /// [`Distribute`]: PinnedThreadsError::Distribute
+    /// [`Exited`]: PinnedThreadsError::Exited
+    /// [`Spawn`]: PinnedThreadsError::Spawn
+    pub fn spawn<F, T>(
+        &self,
+        f: F,
+    ) -> Result<Vec<PinnedThread<'topology, JoinHandle<Option<T>>>>, PinnedThreadsError>
+    where
+        'topology: 'static,
+        F: Fn(&PinnedWorker<'topology>) -> T + Send + Sync + 'static,
+        T: Send + 'static,
+    {
+        let topology = self.topology;
+        let f = Arc::new(f);
+        self.spawn_impl(|builder, worker, report, start| {
+            let f = Arc::clone(&f);
+            builder.spawn(move || run_worker(topology, &worker, report, &start, &*f))
+        })
+    }
+
+    /// Spawn the pinned threads within a [`std::thread::scope()`]
+    ///
+    /// This works like [`spawn()`](Self::spawn()), but threads are spawned as
+    /// part of `scope`, which allows them to borrow non-`'static` data,
+    /// including the topology.
+    ///
+    /// # Errors
+    ///
+    /// See [`spawn()`](Self::spawn()).
+    pub fn spawn_scoped<'scope, 'env, F, T>(
+        &self,
+        scope: &'scope Scope<'scope, 'env>,
+        f: F,
+    ) -> Result<Vec<PinnedThread<'topology, ScopedJoinHandle<'scope, Option<T>>>>, PinnedThreadsError>
+    where
+        'topology: 'scope,
+        F: Fn(&PinnedWorker<'topology>) -> T + Send + Sync + 'scope,
+        T: Send + 'scope,
+    {
+        let topology = self.topology;
+        let f = Arc::new(f);
+        self.spawn_impl(|builder, worker, report, start| {
+            let f = Arc::clone(&f);
+            builder.spawn_scoped(scope, move || {
+                run_worker(topology, &worker, report, &start, &*f)
+            })
+        })
+    }
+
+    /// Spawn threads using `spawn_thread`, then wait for them to be bound
+    ///
+    /// `spawn_thread` is passed a thread builder, the worker's assignment, a
+    /// channel on which the worker must report its binding status, and a
+    /// channel on which it will be told whether it should proceed.
+    fn spawn_impl<Handle>(
+        &self,
+        mut spawn_thread: impl FnMut(
+            thread::Builder,
+            PinnedWorker<'topology>,
+            Sender<BindingReport>,
+            Receiver<bool>,
+        ) -> io::Result<Handle>,
+    ) -> Result<Vec<PinnedThread<'topology, Handle>>, PinnedThreadsError> {
+        let workers = self.workers()?;
+        let (report_tx, report_rx) = mpsc::channel();
+        let mut threads = Vec::with_capacity(workers.len());
+        let mut start_signals = Vec::with_capacity(workers.len());
+        for worker in workers {
+            let (start_tx, start_rx) = mpsc::channel();
+            match spawn_thread(
+                thread::Builder::new(),
+                worker.clone(),
+                report_tx.clone(),
+                start_rx,
+            ) {
+                Ok(handle) => {
+                    threads.push(PinnedThread { worker, handle });
+                    start_signals.push(start_tx);
+                }
+                Err(e) => {
+                    abort(&start_signals);
+                    return Err(PinnedThreadsError::Spawn {
+                        worker: worker.index,
+                        error: e,
+                    });
+                }
+            }
+        }
+        std::mem::drop(report_tx);
+
+        // Wait for all threads to report their binding status, keeping the
+        // first error (in worker order) if any
+        let mut first_error: Option<(usize, CpuBindingError)> = None;
+        let mut reported = vec![false; threads.len()];
+        for _ in 0..threads.len() {
+            // Threads drop their report channel after reporting, so the
+            // channel is only closed early if some threads exited without
+            // reporting, which can e.g. happen if binding panicked
+            let Ok((index, result)) = report_rx.recv() else {
+                abort(&start_signals);
+                let worker = reported
+                    .iter()
+                    .position(|&reported| !reported)
+                    .expect("Channel can only be closed early if a thread did not report");
+                return Err(PinnedThreadsError::Exited { worker });
+            };
+            reported[index] = true;
+            if let Err(error) = result {
+                if first_error
+                    .as_ref()
+                    .map_or(true, |(first, _)| index < *first)
+                {
+                    first_error = Some((index, error));
+                }
+            }
+        }
+        if let Some((index, error)) = first_error {
+            abort(&start_signals);
+            return Err(PinnedThreadsError::Bind {
+                worker: index,
+                cpuset: threads[index].worker.cpuset.clone(),
+                error,
+            });
+        }
+        for start in start_signals {
+            // Threads only stop listening once they have received a signal
+            start
+                .send(true)
+                .expect("Pinned threads should wait for the start signal");
+        }
+        Ok(threads)
+    }
+}
+
+/// Binding status report sent by a pinned thread to its spawner
+type BindingReport = (usize, Result<(), CpuBindingError>);
+
+/// Body of a pinned thread
+///
+/// Returns `None` if the thread was told not to run `f`.
+fn run_worker<'topology, T>(
+    topology: &Topology,
+    worker: &PinnedWorker<'topology>,
+    report: Sender<BindingReport>,
+    start: &Receiver<bool>,
+    f: &(impl Fn(&PinnedWorker<'topology>) -> T + ?Sized),
+) -> Option<T> {
+    let result = topology.bind_cpu(&worker.cpuset, CpuBindingFlags::THREAD);
+    // The spawner stops listening if it failed to spawn another thread. The
+    // report channel is dropped right away so that the spawner can tell
+    // when all remaining threads exited without reporting.
+    let reported = report.send((worker.index, result));
+    std::mem::drop(report);
+    reported.ok()?;
+    // A closed channel means that the spawner gave up on this thread
+    start.recv().unwrap_or(false).then(|| f(worker))
+}
+
+/// Tell already spawned threads not to run user code
+fn abort(start_signals: &[Sender<bool>]) {
+    for start in start_signals {
+        // A thread that has already exited does not need to be told anything
+        start.send(false).ok();
+    }
+}
+
+/// CPU assignment of a pinned thread
+///
+/// This is passed to the function that runs on each pinned thread, and can
+/// also be queried from the spawning thread via [`PinnedThread::worker()`].
+#[derive(Clone, Debug)]
+pub struct PinnedWorker<'topology> {
+    /// Index of this worker in `0..num_threads`
+    index: usize,
+
+    /// CPUs that this worker is bound to
+    cpuset: CpuSet,
+
+    /// Smallest topology object covering `cpuset`
+    locality: &'topology TopologyObject,
+}
+//
+impl<'topology> PinnedWorker<'topology> {
+    /// Index of this worker, from 0 to the requested number of threads
+    ///
+    /// Workers with neighboring indices are assigned to neighboring locations
+    /// in the topology, see [`Topology::distribute_items()`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// CPUs that this worker is bound to
+    pub fn cpuset(&self) -> &CpuSet {
+        &self.cpuset
+    }
+
+    /// Smallest topology object that covers this worker's CPUs
+    ///
+    /// This can be used to find the caches, cores, packages or NUMA nodes that
+    /// this worker is close to, e.g. by walking up its ancestors.
+    pub fn locality(&self) -> &'topology TopologyObject {
+        self.locality
+    }
+}
+
+/// Handle to a thread that was spawned by [`PinnedThreadsBuilder`]
+///
+/// `Handle` is either a [`JoinHandle`] or a [`ScopedJoinHandle`], depending
+/// on whether [`PinnedThreadsBuilder::spawn()`] or
+/// [`PinnedThreadsBuilder::spawn_scoped()`] was used.
+#[derive(Debug)]
+pub struct PinnedThread<'topology, Handle> {
+    /// CPU assignment of the thread
+    worker: PinnedWorker<'topology>,
+
+    /// Underlying thread handle
+    handle: Handle,
+}
+//
+impl<'topology, Handle> PinnedThread<'topology, Handle> {
+    /// CPU assignment of this thread
+    pub fn worker(&self) -> &PinnedWorker<'topology> {
+        &self.worker
+    }
+}
+//
+impl<T> PinnedThread<'_, JoinHandle<Option<T>>> {
+    /// Underlying thread
+    pub fn thread(&self) -> &Thread {
+        self.handle.thread()
+    }
+
+    /// Truth that the thread has finished running
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Wait for the thread to finish and get its result
+    ///
+    /// # Errors
+    ///
+    /// If the thread panicked, the panic payload is returned.
+    pub fn join(self) -> thread::Result<T> {
+        self.handle.join().map(expect_started)
+    }
+}
+//
+impl<T> PinnedThread<'_, ScopedJoinHandle<'_, Option<T>>> {
+    /// Underlying thread
+    pub fn thread(&self) -> &Thread {
+        self.handle.thread()
+    }
+
+    /// Truth that the thread has finished running
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Wait for the thread to finish and get its result
+    ///
+    /// # Errors
+    ///
+    /// If the thread panicked, the panic payload is returned.
+    pub fn join(self) -> thread::Result<T> {
+        self.handle.join().map(expect_started)
+    }
+}
+
+/// Extract the result of a thread that was allowed to run user code
+fn expect_started<T>(result: Option<T>) -> T {
+    result.expect("Threads handed over to the user should have been started")
+}
+
+/// Error while spawning pinned threads
+#[derive(Debug, Error)]
+pub enum PinnedThreadsError {
+    /// Failed to assign CPUs to threads
+    #[error(transparent)]
+    Distribute(#[from] DistributeError),
+
+    /// The operating system failed to spawn a thread
+    #[error("failed to spawn pinned thread #{worker}")]
+    Spawn {
+        /// Index of the worker whose thread could not be spawned
+        worker: usize,
+
+        /// Error reported by the operating system
+        #[source]
+        error: io::Error,
+    },
+
+    /// A thread failed to bind itself to its assigned CPUs
+    ///
+    /// If multiple threads failed, this reports the one with the lowest index.
+    #[error("pinned thread #{worker} failed to bind itself to CPUs {cpuset}")]
+    Bind {
+        /// Index of the worker whose thread could not be bound
+        worker: usize,
+
+        /// CPUs that the thread tried to bind itself to
+        cpuset: CpuSet,
+
+        /// Binding error
+        #[source]
+        error: CpuBindingError,
+    },
+
+    /// A thread exited before reporting whether it managed to bind itself to
+    /// its assigned CPUs
+    ///
+    /// This can happen if the thread panicked while binding itself. If
+    /// multiple threads exited, this reports the one with the lowest index.
+    #[error("pinned thread #{worker} exited before reporting its binding status")]
+    Exited {
+        /// Index of the worker whose thread exited
+        worker: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::types::ObjectType;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::Read,
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+        ptr,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(PinnedThreadsBuilder<'static>:
+        Clone, Debug, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(PinnedThreadsBuilder<'static>:
+        Binary, Copy, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(PinnedWorker<'static>:
+        Clone, Debug, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(PinnedWorker<'static>:
+        Binary, Copy, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(PinnedThread<'static, JoinHandle<Option<()>>>:
+        Debug, Send, Sized, Sync, Unpin
+    );
+    assert_not_impl_any!(PinnedThread<'static, JoinHandle<Option<()>>>:
+        Binary, Clone, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(PinnedThreadsError:
+        Debug, Display, Error, From<DistributeError>, Send, Sized, Sync, Unpin
+    );
+    assert_not_impl_any!(PinnedThreadsError:
+        Binary, Clone, Default, Deref, Drop, Hash, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+
+    /// Check that workers match the output of `distribute_items()`
+    fn check_workers(
+        topology: &Topology,
+        builder: &PinnedThreadsBuilder<'_>,
+        num_threads: usize,
+        singlify: bool,
+    ) {
+        let roots = [topology.root_object()];
+        let expected = topology
+            .distribute_items(
+                &roots,
+                num_threads,
+                NormalDepth::MAX,
+                DistributeFlags::empty(),
+            )
+            .unwrap();
+        let workers = builder.workers().unwrap();
+        assert_eq!(workers.len(), expected.len());
+        for (idx, (worker, mut expected)) in workers.iter().zip(expected).enumerate() {
+            if singlify {
+                expected.singlify();
+            }
+            assert_eq!(worker.index(), idx);
+            assert_eq!(worker.cpuset(), &expected);
+            let locality = worker.locality();
+            assert!(locality.covers_cpuset(worker.cpuset()));
+            assert!(topology
+                .smallest_object_covering_cpuset(worker.cpuset())
+                .is_some_and(|covering| ptr::eq(covering, locality)));
+        }
+    }
+
+    #[test]
+    fn workers() {
+        let topology = Topology::test_instance();
+        let roots = [topology.root_object()];
+        let num_pus = topology.objects_with_type(ObjectType::PU).count();
+        for num_threads in [1, 2, num_pus, 2 * num_pus] {
+            for singlify in [false, true] {
+                let builder = topology
+                    .pinned_threads(&roots, num_threads, DistributeFlags::empty())
+                    .singlify(singlify);
+                check_workers(topology, &builder, num_threads, singlify);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_roots() {
+        let topology = Topology::test_instance();
+        let builder = topology.pinned_threads(&[], 1, DistributeFlags::empty());
+        assert!(matches!(
+            builder.workers(),
+            Err(DistributeError::EmptyRoots)
+        ));
+        assert!(matches!(
+            builder.spawn(|_| ()),
+            Err(PinnedThreadsError::Distribute(DistributeError::EmptyRoots))
+        ));
+    }
+
+    /// Check the outcome of spawning pinned threads
+    fn check_spawned<'topology, Handle>(
+        topology: &Topology,
+        result: Result<Vec<PinnedThread<'topology, Handle>>, PinnedThreadsError>,
+        join: impl Fn(PinnedThread<'topology, Handle>) -> thread::Result<(usize, CpuSet)>,
+    ) {
+        match result {
+            Ok(threads) => {
+                for (idx, thread) in threads.into_iter().enumerate() {
+                    let worker = thread.worker().clone();
+                    assert_eq!(worker.index(), idx);
+                    let (index, binding) = join(thread).unwrap();
+                    assert_eq!(index, idx);
+                    assert!(worker.cpuset().includes(&binding));
+                }
+            }
+            Err(PinnedThreadsError::Bind { worker, cpuset, .. }) => {
+                // Binding may legitimately fail when the test topology contains
+                // disallowed CPUs or the OS does not support thread binding
+                assert!(topology.cpuset().includes(&cpuset), "worker {worker}");
+            }
+            Err(other) => panic!("unexpected spawning error: {other}"),
+        }
+    }
+
+    #[test]
+    fn worker_exits_without_reporting() {
+        let topology = Topology::test_instance();
+        let roots = [topology.root_object()];
+        let builder = topology.pinned_threads(&roots, 2, DistributeFlags::empty());
+        let result = builder.spawn_impl(|builder, worker, report, _start| {
+            builder.spawn(move || {
+                // Worker 1 exits without reporting, like a panicking thread
+                if worker.index() == 0 {
+                    report.send((0, Ok(()))).unwrap();
+                }
+            })
+        });
+        assert!(matches!(
+            result,
+            Err(PinnedThreadsError::Exited { worker: 1 })
+        ));
+    }
+
+    #[test]
+    fn spawn() {
+        let topology = Topology::test_instance();
+        let roots = [topology.root_object()];
+        let result = topology
+            .pinned_threads(&roots, 2, DistributeFlags::empty())
+            .spawn(move |worker| {
+                let binding = topology
+                    .cpu_binding(CpuBindingFlags::THREAD)
+                    .unwrap_or_else(|_| worker.cpuset().clone());
+                (worker.index(), binding)
+            });
+        check_spawned(topology, result, PinnedThread::<JoinHandle<_>>::join);
+    }
+
+    #[test]
+    fn spawn_scoped() {
+        let topology = Topology::test_instance();
+        let roots = [topology.root_object()];
+        thread::scope(|scope| {
+            let result = topology
+                .pinned_threads(&roots, 2, DistributeFlags::REVERSE)
+                .singlify(true)
+                .spawn_scoped(scope, |worker| {
+                    let binding = topology
+                        .cpu_binding(CpuBindingFlags::THREAD)
+                        .unwrap_or_else(|_| worker.cpuset().clone());
+                    (worker.index(), binding)
+                });
+            check_spawned(
+                topology,
+                result,
+                PinnedThread::<ScopedJoinHandle<'_, _>>::join,
+            );
+        });
+    }
+}
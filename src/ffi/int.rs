@@ -22,6 +22,11 @@
 use derive_more::{Binary, Display, LowerExp, LowerHex, Octal, UpperExp, UpperHex};
 #[cfg(any(test, feature = "proptest"))]
 use proptest::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{
+    de::{Error as _, Unexpected},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 #[allow(unused)]
 #[cfg(test)]
 use similar_asserts::assert_eq;
@@ -2582,6 +2587,21 @@ impl Debug for PositiveInt {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for PositiveInt {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let inner = c_uint::deserialize(deserializer)?;
+        if inner <= Self::MAX.0 {
+            Ok(Self(inner))
+        } else {
+            Err(D::Error::invalid_value(
+                Unexpected::Unsigned(inner.into()),
+                &"an integer in range 0..=c_int::MAX",
+            ))
+        }
+    }
+}
+
 impl<B: Borrow<Self>> Div<B> for PositiveInt {
     type Output = Self;
 
@@ -2877,6 +2897,13 @@ where
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for PositiveInt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
 impl Shl<Self> for PositiveInt {
     type Output = Self;
 
@@ -3903,6 +3930,23 @@ mod tests {
         Ok(())
     }
 
+    /// Test serialization and deserialization via serde
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+        assert_tokens(&PositiveInt::ZERO, &[Token::U32(0)]);
+        assert_tokens(&PositiveInt::MAX, &[Token::U32(PositiveInt::MAX.0)]);
+        if let Some(too_big) = PositiveInt::MAX.0.checked_add(1) {
+            assert_de_tokens_error::<PositiveInt>(
+                &[Token::U32(too_big)],
+                &format!(
+                    "invalid value: integer `{too_big}`, expected an integer in range 0..=c_int::MAX"
+                ),
+            );
+        }
+    }
+
     proptest! {
         /// Test str -> PositiveInt conversion via the FromStr trait
         #[test]
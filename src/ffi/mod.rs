@@ -81,6 +81,14 @@ pub(crate) unsafe fn deref_str(p: &*mut c_char) -> Option<&CStr> {
     Some(unsafe { CStr::from_ptr(*p) })
 }
 
+/// Page size of this system
+#[cfg(unix)]
+pub(crate) fn page_size() -> usize {
+    // SAFETY: sysconf has no safety preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(page_size).expect("Page size should be a positive usize")
+}
+
 /// Get text output from an snprintf-like function
 ///
 /// # Safety
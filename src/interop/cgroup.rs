@@ -0,0 +1,565 @@
+//! Linux control group limits
+//!
+//! In containerized environments, the CPUs and NUMA nodes that a process may
+//! use are restricted by its control group (cgroup). hwloc accounts for the
+//! cgroup cpuset when computing [`Topology::allowed_cpuset()`], but not for
+//! the CPU bandwidth quota that container runtimes like Kubernetes use to
+//! enforce CPU limits. Spawning one thread per allowed CPU then leads to
+//! oversubscription and throttling.
+//!
+//! This module reads the cpuset and CPU quota of a process' cgroup, with
+//! support for both the cgroup v1 and cgroup v2 hierarchies, via a
+//! [`CgroupReader`].
+//!
+//! Note that cgroup files use the list format (e.g. `0-3,8`) rather than the
+//! kernel cpumask format of sysfs, so they are parsed using the
+//! [`FromStr`](std::str::FromStr) implementation of [`CpuSet`] and [`NodeSet`]
+//! rather than [`Topology::read_path_as_cpumask()`].
+
+#[cfg(doc)]
+use crate::topology::Topology;
+use crate::{bitmap::ParseBitmapError, cpu::cpuset::CpuSet, memory::nodeset::NodeSet};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use thiserror::Error;
+
+// This file is rustdoc-visible so we must provide a substitute for
+// linux-specific libc entities when people run rustdoc on Windows.
+#[cfg(target_os = "linux")]
+use libc::pid_t;
+#[cfg(all(doc, not(target_os = "linux")))]
+#[allow(non_camel_case_types)]
+struct pid_t;
+
+/// Reader of Linux control group limits
+///
+/// By default, cgroup information is read from the `/proc` and `/sys/fs/cgroup`
+/// directories of the host. Another filesystem root can be specified with
+/// [`with_fs_root()`](Self::with_fs_root()), which is notably useful for
+/// testing.
+///
+/// cgroup hierarchies are assumed to be mounted at their standard location,
+/// i.e. `/sys/fs/cgroup` for the cgroup v2 unified hierarchy and
+/// `/sys/fs/cgroup/<controllers>` for cgroup v1 controllers.
+///
+/// # Examples
+///
+#[cfg_attr(target_os = "linux", doc = "```rust")]
+#[cfg_attr(not(target_os = "linux"), doc = "```rust,ignore")]
+/// # use hwlocality::interop::cgroup::CgroupReader;
+/// let limits = CgroupReader::new().current_process()?;
+/// if let Some(num_cpus) = limits.effective_cpu_count() {
+///     println!("This process should not use more than {num_cpus} CPUs");
+/// }
+/// # Ok::<(), eyre::Report>(())
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CgroupReader {
+    /// Filesystem root under which `/proc` and `/sys` are looked up
+    fs_root: PathBuf,
+}
+//
+impl CgroupReader {
+    /// Read cgroup limits from the host filesystem
+    pub fn new() -> Self {
+        Self::with_fs_root("/")
+    }
+
+    /// Read cgroup limits from a filesystem tree rooted at `fs_root`
+    ///
+    /// This tree should contain `proc/<pid>/cgroup` files and a `sys/fs/cgroup`
+    /// directory laid out like on a Linux system.
+    pub fn with_fs_root(fs_root: impl Into<PathBuf>) -> Self {
+        Self {
+            fs_root: fs_root.into(),
+        }
+    }
+
+    /// Limits that apply to the current process
+    ///
+    /// # Errors
+    ///
+    /// See [`process()`](Self::process()).
+    pub fn current_process(&self) -> Result<CgroupLimits, CgroupError> {
+        self.limits("self")
+    }
+
+    /// Limits that apply to process `pid`
+    ///
+    /// Limits whose cgroup controller is not enabled are reported as `None`.
+    ///
+    /// # Errors
+    ///
+    /// - [`Read`] if the cgroup membership of the process could not be read, or
+    ///   if a cgroup file exists but could not be read.
+    /// - [`Parse`] if a cgroup file has unexpected contents.
+    ///
+    /// [`Parse`]: CgroupError::Parse
+    /// [`Read`]: CgroupError::Read
+    pub fn process(&self, pid: pid_t) -> Result<CgroupLimits, CgroupError> {
+        self.limits(&pid.to_string())
+    }
+
+    /// Implementation of `current_process()` and `process()`
+    fn limits(&self, proc_dir: &str) -> Result<CgroupLimits, CgroupError> {
+        let membership_path = self.fs_root.join("proc").join(proc_dir).join("cgroup");
+        let membership = read_file(&membership_path)?.ok_or_else(|| CgroupError::Read {
+            path: membership_path.clone(),
+            error: io::ErrorKind::NotFound.into(),
+        })?;
+        let mut cpuset_dir = None;
+        let mut cpu_dir = None;
+        let mut unified_dir = None;
+        for line in membership.lines().filter(|line| !line.is_empty()) {
+            let mut fields = line.splitn(3, ':');
+            let (Some(hierarchy), Some(controllers), Some(path)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(CgroupError::Parse {
+                    path: membership_path,
+                    contents: line.to_owned(),
+                });
+            };
+            let relative_path = path.trim_start_matches('/');
+            let mount = self.fs_root.join("sys/fs/cgroup");
+            if hierarchy == "0" && controllers.is_empty() {
+                unified_dir = Some(mount.join(relative_path));
+            } else {
+                let dir = mount.join(controllers).join(relative_path);
+                for controller in controllers.split(',') {
+                    match controller {
+                        "cpuset" => cpuset_dir = Some(dir.clone()),
+                        "cpu" => cpu_dir = Some(dir.clone()),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // Controllers that are not attached to a cgroup v1 hierarchy are
+        // managed by the cgroup v2 unified hierarchy, if any
+        let (cpuset, nodeset) = match (cpuset_dir, &unified_dir) {
+            (Some(dir), _) => (
+                read_first(&dir, &["cpuset.effective_cpus", "cpuset.cpus"])?,
+                read_first(&dir, &["cpuset.effective_mems", "cpuset.mems"])?,
+            ),
+            (None, Some(dir)) => (
+                read_first(dir, &["cpuset.cpus.effective"])?,
+                read_first(dir, &["cpuset.mems.effective"])?,
+            ),
+            (None, None) => (None, None),
+        };
+        let cpu_quota = match (cpu_dir, unified_dir) {
+            (Some(dir), _) => self.hierarchical_quota(&dir, CpuQuota::read_v1)?,
+            (None, Some(dir)) => self.hierarchical_quota(&dir, CpuQuota::read_v2)?,
+            (None, None) => None,
+        };
+        Ok(CgroupLimits {
+            cpuset,
+            nodeset,
+            cpu_quota,
+        })
+    }
+
+    /// Most restrictive CPU quota of cgroup `dir` and its ancestors
+    ///
+    /// Unlike cpusets, whose effective value accounts for ancestor cgroups,
+    /// CPU quotas are set independently at each level of the hierarchy.
+    fn hierarchical_quota(
+        &self,
+        dir: &Path,
+        read_quota: fn(&Path) -> Result<Option<CpuQuota>, CgroupError>,
+    ) -> Result<Option<CpuQuota>, CgroupError> {
+        let mount = self.fs_root.join("sys/fs/cgroup");
+        let mut result: Option<CpuQuota> = None;
+        for dir in dir.ancestors().take_while(|dir| dir.starts_with(&mount)) {
+            if let Some(quota) = read_quota(dir)? {
+                if result.map_or(true, |result| quota.is_stricter_than(result)) {
+                    result = Some(quota);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+//
+impl Default for CgroupReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Limits that a control group imposes on a process
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CgroupLimits {
+    /// CPUs that the process may run on, if the cpuset controller is enabled
+    pub cpuset: Option<CpuSet>,
+
+    /// NUMA nodes that the process may allocate memory from, if the cpuset
+    /// controller is enabled
+    pub nodeset: Option<NodeSet>,
+
+    /// CPU bandwidth quota of the process, if the cpu controller is enabled
+    /// and a quota is set
+    pub cpu_quota: Option<CpuQuota>,
+}
+//
+impl CgroupLimits {
+    /// Number of CPUs that the process can keep busy without oversubscription
+    ///
+    /// This is the smallest of the number of CPUs in [`cpuset`] and the
+    /// number of CPUs that [`cpu_quota`] allows for, rounded up. `None` is
+    /// returned if the cgroup does not limit CPU usage.
+    ///
+    /// [`cpu_quota`]: Self::cpu_quota
+    /// [`cpuset`]: Self::cpuset
+    pub fn effective_cpu_count(&self) -> Option<usize> {
+        let cpuset_count = self.cpuset.as_ref().and_then(CpuSet::weight);
+        let quota_count = self.cpu_quota.map(|quota| quota.cpu_count());
+        match (cpuset_count, quota_count) {
+            (Some(cpuset_count), Some(quota_count)) => Some(cpuset_count.min(quota_count)),
+            (count @ Some(_), None) | (None, count @ Some(_)) => count,
+            (None, None) => None,
+        }
+    }
+}
+
+/// CPU bandwidth quota
+///
+/// Processes in the cgroup may collectively use `quota` worth of CPU time per
+/// `period` of wall-clock time, which amounts to using `quota / period` CPUs.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct CpuQuota {
+    /// CPU time that may be used per period
+    pub quota: Duration,
+
+    /// Accounting period
+    pub period: Duration,
+}
+//
+impl CpuQuota {
+    /// Number of CPUs that this quota amounts to
+    pub fn cpus(&self) -> f64 {
+        self.quota.as_secs_f64() / self.period.as_secs_f64()
+    }
+
+    /// Number of CPUs that this quota amounts to, rounded up
+    ///
+    /// This is never less than 1, since a process that is allowed to run at
+    /// all needs at least one CPU to do so.
+    pub fn cpu_count(&self) -> usize {
+        let quota = self.quota.as_micros();
+        let period = self.period.as_micros().max(1);
+        let count = ((quota + period - 1) / period).max(1);
+        usize::try_from(count).unwrap_or(usize::MAX)
+    }
+
+    /// Truth that this quota allows for less CPU usage than `other`
+    fn is_stricter_than(self, other: Self) -> bool {
+        self.quota.as_micros() * other.period.as_micros()
+            < other.quota.as_micros() * self.period.as_micros()
+    }
+
+    /// Read a cgroup v1 quota from `cpu.cfs_quota_us` and `cpu.cfs_period_us`
+    fn read_v1(dir: &Path) -> Result<Option<Self>, CgroupError> {
+        let quota_path = dir.join("cpu.cfs_quota_us");
+        let Some(quota) = read_file(&quota_path)? else {
+            return Ok(None);
+        };
+        // A negative quota means that CPU usage is unlimited
+        let quota = parse::<i64>(&quota_path, &quota)?;
+        let Ok(quota) = u64::try_from(quota) else {
+            return Ok(None);
+        };
+        let period_path = dir.join("cpu.cfs_period_us");
+        let Some(period) = read_file(&period_path)? else {
+            return Ok(None);
+        };
+        let period = parse::<u64>(&period_path, &period)?;
+        Ok(Some(Self {
+            quota: Duration::from_micros(quota),
+            period: Duration::from_micros(period),
+        }))
+    }
+
+    /// Read a cgroup v2 quota from `cpu.max`
+    fn read_v2(dir: &Path) -> Result<Option<Self>, CgroupError> {
+        let path = dir.join("cpu.max");
+        let Some(contents) = read_file(&path)? else {
+            return Ok(None);
+        };
+        let bad_contents = || CgroupError::Parse {
+            path: path.clone(),
+            contents: contents.clone(),
+        };
+        let mut fields = contents.split_whitespace();
+        let (Some(quota), Some(period), None) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(bad_contents());
+        };
+        if quota == "max" {
+            return Ok(None);
+        }
+        let quota = quota.parse().map_err(|_| bad_contents())?;
+        let period = period.parse().map_err(|_| bad_contents())?;
+        Ok(Some(Self {
+            quota: Duration::from_micros(quota),
+            period: Duration::from_micros(period),
+        }))
+    }
+}
+
+/// Error while reading cgroup limits
+#[derive(Debug, Error)]
+pub enum CgroupError {
+    /// Failed to read a cgroup-related file
+    #[error("failed to read {}", path.display())]
+    Read {
+        /// Path to the file that could not be read
+        path: PathBuf,
+
+        /// Underlying I/O error
+        #[source]
+        error: io::Error,
+    },
+
+    /// A cgroup-related file has unexpected contents
+    #[error("unexpected contents {contents:?} in {}", path.display())]
+    Parse {
+        /// Path to the file
+        path: PathBuf,
+
+        /// Offending contents
+        contents: String,
+    },
+}
+
+/// Read a file, or return `None` if it does not exist
+fn read_file(path: &Path) -> Result<Option<String>, CgroupError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(CgroupError::Read {
+            path: path.to_owned(),
+            error,
+        }),
+    }
+}
+
+/// Parse the trimmed contents of a cgroup file
+fn parse<T: FromStr>(path: &Path, contents: &str) -> Result<T, CgroupError> {
+    contents.trim().parse().map_err(|_| CgroupError::Parse {
+        path: path.to_owned(),
+        contents: contents.to_owned(),
+    })
+}
+
+/// Read and parse the first existing file among `names` within `dir`
+fn read_first<T>(dir: &Path, names: &[&str]) -> Result<Option<T>, CgroupError>
+where
+    T: FromStr<Err = ParseBitmapError>,
+{
+    for name in names {
+        let path = dir.join(name);
+        if let Some(contents) = read_file(&path)? {
+            return parse(&path, &contents).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::Read,
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+    use tempfile::TempDir;
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(CgroupReader:
+        Clone, Debug, Default, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(CgroupReader:
+        Binary, Copy, Deref, Display, Drop, Error, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(CgroupLimits:
+        Clone, Debug, Default, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(CgroupLimits:
+        Binary, Copy, Deref, Display, Drop, Error, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(CpuQuota:
+        Copy, Debug, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(CpuQuota:
+        Binary, Default, Deref, Display, Drop, Error, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(CgroupError:
+        Debug, Display, Error, Send, Sized, Sync, Unpin
+    );
+    assert_not_impl_any!(CgroupError:
+        Binary, Clone, Default, Deref, Drop, Hash, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+
+    /// Create a fake filesystem tree with the specified files
+    fn fake_fs(files: &[(&str, &str)]) -> TempDir {
+        let root = TempDir::new().unwrap();
+        for (path, contents) in files {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        root
+    }
+
+    /// Shorthand to build a CPU quota from microsecond counts
+    fn quota(quota: u64, period: u64) -> CpuQuota {
+        CpuQuota {
+            quota: Duration::from_micros(quota),
+            period: Duration::from_micros(period),
+        }
+    }
+
+    #[test]
+    fn cgroup_v2() {
+        let root = fake_fs(&[
+            ("proc/self/cgroup", "0::/kubepods/pod42\n"),
+            ("proc/42/cgroup", "0::/\n"),
+            ("sys/fs/cgroup/cpuset.cpus.effective", "0-15\n"),
+            ("sys/fs/cgroup/cpuset.mems.effective", "0-1\n"),
+            ("sys/fs/cgroup/kubepods/cpu.max", "400000 100000\n"),
+            (
+                "sys/fs/cgroup/kubepods/pod42/cpuset.cpus.effective",
+                "0-3,8\n",
+            ),
+            ("sys/fs/cgroup/kubepods/pod42/cpuset.mems.effective", "0\n"),
+            ("sys/fs/cgroup/kubepods/pod42/cpu.max", "250000 100000\n"),
+        ]);
+        let reader = CgroupReader::with_fs_root(root.path());
+
+        let limits = reader.current_process().unwrap();
+        assert_eq!(limits.cpuset, Some("0-3,8".parse().unwrap()));
+        assert_eq!(limits.nodeset, Some("0".parse().unwrap()));
+        assert_eq!(limits.cpu_quota, Some(quota(250_000, 100_000)));
+        assert_eq!(limits.effective_cpu_count(), Some(3));
+
+        let limits = reader.process(42).unwrap();
+        assert_eq!(limits.cpuset, Some("0-15".parse().unwrap()));
+        assert_eq!(limits.nodeset, Some("0-1".parse().unwrap()));
+        assert_eq!(limits.cpu_quota, None);
+        assert_eq!(limits.effective_cpu_count(), Some(16));
+    }
+
+    #[test]
+    fn cgroup_v2_hierarchical_quota() {
+        let root = fake_fs(&[
+            ("proc/self/cgroup", "0::/parent/child\n"),
+            ("sys/fs/cgroup/parent/cpu.max", "150000 100000\n"),
+            ("sys/fs/cgroup/parent/child/cpu.max", "max 100000\n"),
+        ]);
+        let limits = CgroupReader::with_fs_root(root.path())
+            .current_process()
+            .unwrap();
+        assert_eq!(limits.cpuset, None);
+        assert_eq!(limits.nodeset, None);
+        assert_eq!(limits.cpu_quota, Some(quota(150_000, 100_000)));
+        assert_eq!(limits.effective_cpu_count(), Some(2));
+    }
+
+    #[test]
+    fn cgroup_v1() {
+        let root = fake_fs(&[
+            (
+                "proc/self/cgroup",
+                "5:cpuset:/docker/abc\n4:cpu,cpuacct:/docker/abc\n1:name=systemd:/\n0::/\n",
+            ),
+            (
+                "sys/fs/cgroup/cpuset/docker/abc/cpuset.effective_cpus",
+                "2-5\n",
+            ),
+            (
+                "sys/fs/cgroup/cpuset/docker/abc/cpuset.effective_mems",
+                "1\n",
+            ),
+            (
+                "sys/fs/cgroup/cpu,cpuacct/docker/abc/cpu.cfs_quota_us",
+                "50000\n",
+            ),
+            (
+                "sys/fs/cgroup/cpu,cpuacct/docker/abc/cpu.cfs_period_us",
+                "100000\n",
+            ),
+            ("sys/fs/cgroup/cpu,cpuacct/cpu.cfs_quota_us", "-1\n"),
+            ("sys/fs/cgroup/cpu,cpuacct/cpu.cfs_period_us", "100000\n"),
+        ]);
+        let limits = CgroupReader::with_fs_root(root.path())
+            .current_process()
+            .unwrap();
+        assert_eq!(limits.cpuset, Some("2-5".parse().unwrap()));
+        assert_eq!(limits.nodeset, Some("1".parse().unwrap()));
+        assert_eq!(limits.cpu_quota, Some(quota(50_000, 100_000)));
+        assert!((limits.cpu_quota.unwrap().cpus() - 0.5).abs() < f64::EPSILON);
+        assert_eq!(limits.effective_cpu_count(), Some(1));
+    }
+
+    #[test]
+    fn errors() {
+        let root = fake_fs(&[
+            ("proc/self/cgroup", "0::/\n"),
+            ("proc/1/cgroup", "garbage\n"),
+            ("proc/2/cgroup", "0::/bad\n"),
+            ("sys/fs/cgroup/bad/cpuset.cpus.effective", "0-3\n"),
+            ("sys/fs/cgroup/bad/cpu.max", "lots 100000\n"),
+            ("proc/3/cgroup", "0::/worse\n"),
+            ("sys/fs/cgroup/worse/cpuset.cpus.effective", "3-0\n"),
+        ]);
+        let reader = CgroupReader::with_fs_root(root.path());
+        assert_eq!(reader.current_process().unwrap(), CgroupLimits::default());
+        assert_eq!(CgroupLimits::default().effective_cpu_count(), None);
+        for pid in 1..=3 {
+            assert!(matches!(
+                reader.process(pid),
+                Err(CgroupError::Parse { .. })
+            ));
+        }
+        assert!(matches!(
+            reader.process(4),
+            Err(CgroupError::Read { error, .. }) if error.kind() == io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn cpu_quota() {
+        assert!((quota(250_000, 100_000).cpus() - 2.5).abs() < f64::EPSILON);
+        assert_eq!(quota(250_000, 100_000).cpu_count(), 3);
+        assert_eq!(quota(200_000, 100_000).cpu_count(), 2);
+        assert_eq!(quota(1_000, 100_000).cpu_count(), 1);
+        assert!(quota(1, 2).is_stricter_than(quota(2, 3)));
+        assert!(!quota(2, 3).is_stricter_than(quota(1, 2)));
+        assert!(!quota(1, 2).is_stricter_than(quota(2, 4)));
+    }
+}
@@ -3,20 +3,35 @@
 #[cfg(doc)]
 use crate::cpu::binding::CpuBindingFlags;
 use crate::{
+    bitmap::BitmapTruncationError,
     cpu::cpuset::CpuSet,
     errors::{self, HybridError, RawHwlocError},
+    ffi::page_size,
+    memory::nodeset::NodeSet,
     path::{self, PathError},
     topology::Topology,
 };
+use errno::Errno;
+use libc::ESRCH;
 #[allow(unused)]
 #[cfg(test)]
 use similar_asserts::assert_eq;
-use std::{ops::Deref, path::Path};
+use std::{
+    collections::BTreeMap,
+    ffi::{c_int, c_ulong, c_void},
+    fs, io, mem,
+    num::NonZeroUsize,
+    ops::Deref,
+    path::Path,
+};
 
 // This file is rustdoc-visible so we must provide a substitute for
 // linux-specific libc entities when people run rustdoc on Windows.
 #[cfg(target_os = "linux")]
-use libc::pid_t;
+use libc::{cpu_set_t, pid_t};
+#[cfg(all(doc, not(target_os = "linux")))]
+#[allow(non_camel_case_types)]
+struct cpu_set_t;
 #[cfg(all(doc, not(target_os = "linux")))]
 #[allow(non_camel_case_types)]
 struct pid_t;
@@ -124,6 +139,92 @@ impl Topology {
         .map(|_| set)
     }
 
+    /// Bind every thread of process `pid` on cpus given in `set`
+    ///
+    /// `set` can be a `&'_ CpuSet` or a `BitmapRef<'_, CpuSet>`.
+    ///
+    /// This enumerates the threads of process `pid` via `/proc/<pid>/task`,
+    /// then binds each of them using [`bind_tid_cpu()`]. Unlike
+    /// [`bind_process_cpu()`], which only reports a global outcome, this tells
+    /// you which threads could be bound and which could not.
+    ///
+    /// Threads that are spawned while the binding is in progress may inherit
+    /// the old binding of their parent thread. To account for this,
+    /// `/proc/<pid>/task` is scanned again after binding all known threads,
+    /// until no new thread shows up or the process exits.
+    ///
+    /// To guarantee termination when the target process keeps spawning
+    /// threads, at most 16 scans are performed. If that limit is reached,
+    /// threads that were created during the last scan or afterwards are
+    /// missing from the result and keep their old binding. Call this function
+    /// again if you need to catch them.
+    ///
+    /// The outcome of binding each thread is reported in a map keyed by TID.
+    /// Threads that exited between the moment where they were listed and the
+    /// moment where they were bound are reported as [`TaskOutcome::Vanished`].
+    /// If the whole process exits after the threads were first listed, the
+    /// outcomes that were collected until then are returned.
+    ///
+    /// # Errors
+    ///
+    /// - [`io::Error`] if the threads of process `pid` could not be listed
+    ///   initially, which notably happens if there is no such process.
+    ///
+    /// [`bind_process_cpu()`]: Topology::bind_process_cpu()
+    /// [`bind_tid_cpu()`]: Topology::bind_tid_cpu()
+    pub fn bind_process_threads_cpu(
+        &self,
+        pid: pid_t,
+        set: impl Deref<Target = CpuSet>,
+    ) -> io::Result<BTreeMap<pid_t, TaskOutcome<()>>> {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(
+            self_: &Topology,
+            pid: pid_t,
+            set: &CpuSet,
+        ) -> io::Result<BTreeMap<pid_t, TaskOutcome<()>>> {
+            bind_scanned_tasks(|| process_tids(pid), |tid| self_.bind_tid_cpu(tid, set))
+        }
+        polymorphized(self, pid, &set)
+    }
+
+    /// Current binding and last CPU location of every thread of process `pid`
+    ///
+    /// This enumerates the threads of process `pid` via `/proc/<pid>/task`,
+    /// then queries each of them using [`tid_cpu_binding()`] and
+    /// [`tid_last_cpu_location()`].
+    ///
+    /// The outcome of querying each thread is reported in a map keyed by TID.
+    /// Threads that exited between the moment where they were listed and the
+    /// moment where they were queried are reported as
+    /// [`TaskOutcome::Vanished`].
+    ///
+    /// # Errors
+    ///
+    /// - [`io::Error`] if the threads of process `pid` could not be listed,
+    ///   which notably happens if there is no such process.
+    ///
+    /// [`tid_cpu_binding()`]: Topology::tid_cpu_binding()
+    /// [`tid_last_cpu_location()`]: Topology::tid_last_cpu_location()
+    pub fn process_thread_bindings(
+        &self,
+        pid: pid_t,
+    ) -> io::Result<BTreeMap<pid_t, TaskOutcome<ThreadCpuBinding>>> {
+        Ok(process_tids(pid)?
+            .into_iter()
+            .map(|tid| {
+                let binding = self.tid_cpu_binding(tid).and_then(|binding| {
+                    let last_cpu_location = self.tid_last_cpu_location(tid)?;
+                    Ok(ThreadCpuBinding {
+                        binding,
+                        last_cpu_location,
+                    })
+                });
+                (tid, TaskOutcome::from(binding))
+            })
+            .collect())
+    }
+
     /// Convert a linux kernel cpumask file path into a hwloc bitmap set.
     ///
     /// Might be used when reading CPU sets from sysfs attributes such as
@@ -191,10 +292,430 @@ impl Topology {
     }
 }
 
+/// Maximal number of times `/proc/<pid>/task` is scanned for new threads by
+/// [`Topology::bind_process_threads_cpu()`]
+///
+/// This limit is mentioned in the documentation of that function, keep it in
+/// sync when changing it.
+const MAX_TASK_SCANS: usize = 16;
+
+/// Bind the threads listed by `scan` using `bind`, scanning again for new
+/// threads until none shows up
+///
+/// Only errors from the first scan are reported. Later scans can only fail
+/// because the process exited or became inaccessible in the meantime, in
+/// which case the outcomes collected so far are returned.
+fn bind_scanned_tasks(
+    mut scan: impl FnMut() -> io::Result<Vec<pid_t>>,
+    mut bind: impl FnMut(pid_t) -> Result<(), RawHwlocError>,
+) -> io::Result<BTreeMap<pid_t, TaskOutcome<()>>> {
+    let mut outcomes = BTreeMap::new();
+    for scan_idx in 0..MAX_TASK_SCANS {
+        let tids = match scan() {
+            Ok(tids) => tids,
+            Err(error) if scan_idx == 0 => return Err(error),
+            Err(_) => break,
+        };
+        let new_tids = tids
+            .into_iter()
+            .filter(|tid| !outcomes.contains_key(tid))
+            .collect::<Vec<_>>();
+        if new_tids.is_empty() {
+            break;
+        }
+        for tid in new_tids {
+            outcomes.insert(tid, TaskOutcome::from(bind(tid)));
+        }
+    }
+    Ok(outcomes)
+}
+
+/// List the threads of process `pid` via `/proc/<pid>/task`
+///
+/// If the process exits while its threads are being listed, the threads that
+/// were listed until then are returned.
+fn process_tids(pid: pid_t) -> io::Result<Vec<pid_t>> {
+    let mut tids = Vec::new();
+    for entry in fs::read_dir(format!("/proc/{pid}/task"))? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) if is_vanished(&error) => break,
+            Err(error) => return Err(error),
+        };
+        // Non-numeric entries are not threads, ignore them
+        if let Some(tid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<pid_t>().ok())
+        {
+            tids.push(tid);
+        }
+    }
+    Ok(tids)
+}
+
+/// Truth that an I/O error indicates that a process or thread has exited
+fn is_vanished(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::NotFound || error.raw_os_error() == Some(ESRCH)
+}
+
+/// Outcome of an operation on one thread of a process
+///
+/// This is returned by [`Topology::bind_process_threads_cpu()`] and
+/// [`Topology::process_thread_bindings()`], which operate on all threads of a
+/// process, for each of these threads.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TaskOutcome<T> {
+    /// The operation succeeded
+    Done(T),
+
+    /// The thread exited before the operation could be carried out
+    Vanished,
+
+    /// The operation failed for another reason
+    Failed(RawHwlocError),
+}
+//
+impl<T> TaskOutcome<T> {
+    /// Extract the result of a successful operation, if any
+    pub fn done(self) -> Option<T> {
+        match self {
+            Self::Done(result) => Some(result),
+            Self::Vanished | Self::Failed(_) => None,
+        }
+    }
+}
+//
+impl<T> From<Result<T, RawHwlocError>> for TaskOutcome<T> {
+    fn from(result: Result<T, RawHwlocError>) -> Self {
+        match result {
+            Ok(result) => Self::Done(result),
+            Err(RawHwlocError {
+                errno: Some(Errno(ESRCH)),
+                ..
+            }) => Self::Vanished,
+            Err(e) => Self::Failed(e),
+        }
+    }
+}
+
+/// CPU binding and location of a thread
+///
+/// This is returned by [`Topology::process_thread_bindings()`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ThreadCpuBinding {
+    /// CPUs that the thread is bound to, see [`Topology::tid_cpu_binding()`]
+    pub binding: CpuSet,
+
+    /// PU that the thread last ran on, see [`Topology::tid_last_cpu_location()`]
+    pub last_cpu_location: CpuSet,
+}
+
+/// Count how many pages of `target` reside on each NUMA node
+///
+/// [`Topology::area_memory_location()`] only tells you the union of the NUMA
+/// nodes where some pages of a memory area reside. This function instead
+/// queries the location of every page (or a sample of pages, as directed by
+/// `sampling`) via the query mode of the Linux `move_pages` system call, and
+/// reports how many pages reside on each NUMA node.
+///
+/// Pages that were never accessed do not reside anywhere yet, and are counted
+/// separately in [`PageHistogram::not_faulted`].
+///
+/// As with any memory location query, the result may be outdated by the time
+/// it is returned, since the operating system may migrate pages at any time.
+///
+/// # Errors
+///
+/// - [`io::Error`] if the `move_pages` system call failed as a whole, e.g.
+///   because the kernel was built without NUMA support.
+///
+/// [`Topology::area_memory_location()`]: crate::topology::Topology::area_memory_location()
+pub fn page_node_histogram<Target: ?Sized>(
+    target: &Target,
+    sampling: PageSampling,
+) -> io::Result<PageHistogram> {
+    let page_size = page_size();
+    let mut histogram = PageHistogram {
+        page_size,
+        ..PageHistogram::default()
+    };
+    let target_len = mem::size_of_val(target);
+    if target_len == 0 {
+        return Ok(histogram);
+    }
+
+    // Enumerate the pages that the target spans
+    let start: *const Target = target;
+    let start = start.cast::<u8>();
+    let start_offset = start as usize % page_size;
+    // Page pointers are only used as addresses, never dereferenced
+    let first_page = start.wrapping_sub(start_offset);
+    histogram.total_pages = (start_offset + target_len + page_size - 1) / page_size;
+    let stride = sampling.stride(histogram.total_pages);
+    let pages = (0..histogram.total_pages)
+        .step_by(stride.get())
+        .map(|page_idx| {
+            first_page
+                .wrapping_add(page_idx * page_size)
+                .cast::<c_void>()
+        });
+
+    // Query page locations in batches, to bound memory usage
+    let mut batch = Vec::with_capacity(PAGE_QUERY_BATCH);
+    let mut status = vec![0; PAGE_QUERY_BATCH];
+    let mut pages = pages.peekable();
+    while pages.peek().is_some() {
+        batch.clear();
+        batch.extend(pages.by_ref().take(PAGE_QUERY_BATCH));
+        query_page_nodes(&batch, &mut status[..batch.len()])?;
+        for &node_or_errno in &status[..batch.len()] {
+            histogram.sampled_pages += 1;
+            match usize::try_from(node_or_errno) {
+                Ok(node) => *histogram.pages_per_node.entry(node).or_default() += 1,
+                Err(_) if node_or_errno == -libc::ENOENT => histogram.not_faulted += 1,
+                Err(_) => histogram.unknown += 1,
+            }
+        }
+    }
+    Ok(histogram)
+}
+
+/// Number of pages whose location is queried by each `move_pages` call
+const PAGE_QUERY_BATCH: usize = 1024;
+
+/// Query the NUMA node of each page in `pages`, storing node OS indices or
+/// negated errno values in `status`
+#[cfg(target_os = "linux")]
+fn query_page_nodes(pages: &[*const c_void], status: &mut [c_int]) -> io::Result<()> {
+    assert_eq!(pages.len(), status.len(), "need one status per page");
+    let current_process: pid_t = 0;
+    let query_flags: c_int = 0;
+    // SAFETY: - pid 0 designates the current process
+    //         - pages and status are valid for pages.len() elements
+    //         - A null nodes pointer requests query mode, where pages are
+    //           not moved and only their location is written to status
+    //         - No flags are needed for query mode
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_move_pages,
+            current_process,
+            pages.len() as c_ulong,
+            pages.as_ptr(),
+            std::ptr::null::<c_int>(),
+            status.as_mut_ptr(),
+            query_flags,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+//
+#[cfg(all(doc, not(target_os = "linux")))]
+fn query_page_nodes(_pages: &[*const c_void], _status: &mut [c_int]) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Which pages of a memory area should be queried by [`page_node_histogram()`]
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PageSampling {
+    /// Query every page
+    #[default]
+    Exhaustive,
+
+    /// Query one page every `n` pages, starting from the first one
+    Stride(NonZeroUsize),
+
+    /// Query at most this many pages, evenly spread across the area
+    ///
+    /// This is a convenient way to bound the cost of querying very large
+    /// memory areas.
+    MaxPages(NonZeroUsize),
+}
+//
+impl PageSampling {
+    /// Distance between two queried pages in an area of `total_pages` pages
+    fn stride(self, total_pages: usize) -> NonZeroUsize {
+        match self {
+            Self::Exhaustive => NonZeroUsize::MIN,
+            Self::Stride(stride) => stride,
+            Self::MaxPages(max_pages) => {
+                let max_pages = max_pages.get();
+                NonZeroUsize::new((total_pages + max_pages - 1) / max_pages)
+                    .unwrap_or(NonZeroUsize::MIN)
+            }
+        }
+    }
+}
+
+/// Distribution of the pages of a memory area across NUMA nodes
+///
+/// This is returned by [`page_node_histogram()`]. When sampling is used, page
+/// counts are relative to the set of queried pages, whose size is
+/// [`PageHistogram::sampled_pages`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PageHistogram {
+    /// Number of queried pages that reside on each NUMA node, keyed by the
+    /// OS index of the NUMA node
+    pub pages_per_node: BTreeMap<usize, usize>,
+
+    /// Number of queried pages that were never accessed, and thus do not
+    /// reside on any NUMA node yet
+    pub not_faulted: usize,
+
+    /// Number of queried pages whose location could not be determined for
+    /// another reason, e.g. because they belong to a special mapping
+    pub unknown: usize,
+
+    /// Number of pages whose location was queried
+    pub sampled_pages: usize,
+
+    /// Number of pages that the memory area spans
+    pub total_pages: usize,
+
+    /// Size of a page in bytes
+    pub page_size: usize,
+}
+//
+impl PageHistogram {
+    /// NUMA nodes where at least one queried page resides
+    pub fn nodeset(&self) -> NodeSet {
+        let mut nodeset = NodeSet::new();
+        for &node in self.pages_per_node.keys() {
+            nodeset.set(node);
+        }
+        nodeset
+    }
+
+    /// Estimated number of pages of the whole memory area that reside on NUMA
+    /// node `node`, extrapolated from the queried pages
+    pub fn estimated_pages_on(&self, node: usize) -> usize {
+        let Some(&pages) = self.pages_per_node.get(&node) else {
+            return 0;
+        };
+        if self.sampled_pages == self.total_pages {
+            return pages;
+        }
+        // Widen to avoid overflow in the multiplication
+        let estimate = pages as u128 * self.total_pages as u128 / self.sampled_pages as u128;
+        usize::try_from(estimate).unwrap_or(usize::MAX)
+    }
+}
+
+/// # Conversions to and from Linux `cpu_set_t`
+///
+/// These conversions let you pass a [`CpuSet`] to Linux APIs that expect a
+/// `cpu_set_t`, like `sched_setaffinity` or `pthread_setaffinity_np`, and
+/// turn the `cpu_set_t` that these APIs return back into a [`CpuSet`].
+///
+/// The fixed-size `cpu_set_t` type can only hold `libc::CPU_SETSIZE` CPUs.
+/// Larger systems need dynamically sized CPU sets, which are allocated using
+/// `CPU_ALLOC()` in C. Such CPU sets can be read and written using the raw
+/// conversions below. Alternatively, you can also use
+/// [`CpuSet::to_ulong_vec()`] to build a dynamically sized CPU set that can be
+/// passed to the Linux APIs as a `masks.as_ptr().cast::<cpu_set_t>()` pointer
+/// of size `masks.len() * std::mem::size_of::<c_ulong>()`.
+//
+// --- Implementation details ---
+//
+// glibc's cpu_set_t is an array of unsigned longs, which is also the layout
+// that is expected by CpuSet::from_ulongs() and CpuSet::to_ulongs(). The libc
+// crate may use a different integer type, but since the underlying memory
+// layout is the same, we can reinterpret it as an array of c_ulong.
+impl CpuSet {
+    /// Convert a fixed-size Linux `cpu_set_t` into a [`CpuSet`]
+    #[doc(alias = "hwloc_cpuset_from_glibc_sched_affinity")]
+    pub fn from_cpu_set_t(set: &cpu_set_t) -> Self {
+        // SAFETY: A cpu_set_t reference is valid for reads of
+        //         size_of::<cpu_set_t>() bytes and suitably aligned
+        unsafe { Self::from_raw_cpu_set_t(set, mem::size_of::<cpu_set_t>()) }
+            .expect("CPU_SETSIZE should be below the maximum bitmap index")
+    }
+
+    /// Convert this [`CpuSet`] into a fixed-size Linux `cpu_set_t`
+    ///
+    /// # Errors
+    ///
+    /// - [`BitmapTruncationError`] if this [`CpuSet`] contains CPUs whose
+    ///   index is `libc::CPU_SETSIZE` or higher, which is always the case for
+    ///   infinite [`CpuSet`]s.
+    #[doc(alias = "hwloc_cpuset_to_glibc_sched_affinity")]
+    pub fn to_cpu_set_t(&self) -> Result<cpu_set_t, BitmapTruncationError> {
+        // SAFETY: cpu_set_t is an array of integers, for which all-zeroes is a
+        //         valid bit pattern (representing an empty CPU set)
+        let mut set: cpu_set_t = unsafe { mem::zeroed() };
+        // SAFETY: A cpu_set_t reference is valid for writes of
+        //         size_of::<cpu_set_t>() bytes and suitably aligned
+        unsafe { self.write_raw_cpu_set_t(&mut set, mem::size_of::<cpu_set_t>()) }?;
+        Ok(set)
+    }
+
+    /// Convert a dynamically sized Linux `cpu_set_t` into a [`CpuSet`]
+    ///
+    /// `size` is the size of the CPU set in bytes, as computed by
+    /// `CPU_ALLOC_SIZE()` in C. Trailing bytes which do not form a complete
+    /// `c_ulong` are ignored.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitmapTruncationError`] if `set` contains CPUs whose index is above
+    ///   the maximum [`CpuSet`] index.
+    ///
+    /// # Safety
+    ///
+    /// `set` must be valid for reads of `size` bytes and aligned like a
+    /// `c_ulong`, which is the case of CPU sets allocated with `CPU_ALLOC()`.
+    pub unsafe fn from_raw_cpu_set_t(
+        set: *const cpu_set_t,
+        size: usize,
+    ) -> Result<Self, BitmapTruncationError> {
+        // SAFETY: Per function precondition
+        let masks = unsafe {
+            std::slice::from_raw_parts(set.cast::<c_ulong>(), size / mem::size_of::<c_ulong>())
+        };
+        Self::from_ulongs(masks)
+    }
+
+    /// Write this [`CpuSet`] into a dynamically sized Linux `cpu_set_t`
+    ///
+    /// `size` is the size of the CPU set in bytes, as computed by
+    /// `CPU_ALLOC_SIZE()` in C. Trailing bytes which do not form a complete
+    /// `c_ulong` are left untouched.
+    ///
+    /// # Errors
+    ///
+    /// - [`BitmapTruncationError`] if this [`CpuSet`] contains CPUs which do
+    ///   not fit in `size` bytes, which is always the case for infinite
+    ///   [`CpuSet`]s. The CPUs that did fit are written to `set` nonetheless.
+    ///
+    /// # Safety
+    ///
+    /// `set` must be valid for writes of `size` bytes and aligned like a
+    /// `c_ulong`, which is the case of CPU sets allocated with `CPU_ALLOC()`.
+    pub unsafe fn write_raw_cpu_set_t(
+        &self,
+        set: *mut cpu_set_t,
+        size: usize,
+    ) -> Result<(), BitmapTruncationError> {
+        // SAFETY: Per function precondition
+        let masks = unsafe {
+            std::slice::from_raw_parts_mut(set.cast::<c_ulong>(), size / mem::size_of::<c_ulong>())
+        };
+        self.to_ulongs(masks)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::object::types::ObjectType;
+    use crate::{
+        cpu::binding::CpuBindingFlags,
+        object::types::ObjectType,
+        topology::support::{CpuBindingSupport, FeatureSupport},
+    };
+    use proptest::prelude::*;
     #[allow(unused)]
     use similar_asserts::assert_eq;
 
@@ -218,4 +739,285 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn process_threads() {
+        let topology = Topology::test_instance();
+
+        // Querying all threads of the test process should cover at least
+        // the main thread, whose TID is the PID, and the current thread
+        let pid = pid_t::try_from(std::process::id()).unwrap();
+        // SAFETY: gettid has no safety preconditions
+        let tid = unsafe { libc::gettid() };
+        let bindings = topology.process_thread_bindings(pid).unwrap();
+        assert!(bindings.contains_key(&pid));
+        let TaskOutcome::Done(current) = bindings[&tid].clone() else {
+            panic!("querying the current thread should succeed");
+        };
+        assert_eq!(current.binding, topology.tid_cpu_binding(tid).unwrap());
+        assert_eq!(current.last_cpu_location.weight(), Some(1));
+
+        // Operating on the threads of a nonexistent process should fail
+        let invalid_pid = -1;
+        topology.process_thread_bindings(invalid_pid).unwrap_err();
+        topology
+            .bind_process_threads_cpu(invalid_pid, topology.cpuset())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn bind_process_threads() {
+        // Rebinding every thread of the test process would interfere with
+        // the tests that run concurrently, so this runs in a child process
+        const CHILD_VAR: &str = "HWLOCALITY_TEST_BIND_PROCESS_THREADS";
+        if std::env::var_os(CHILD_VAR).is_none() {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "--exact",
+                    "interop::linux::tests::bind_process_threads",
+                    "--test-threads=1",
+                ])
+                .env(CHILD_VAR, "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        let topology = Topology::test_instance();
+        if !topology.supports(FeatureSupport::cpu_binding, CpuBindingSupport::get_thread)
+            || !topology.supports(FeatureSupport::cpu_binding, CpuBindingSupport::set_thread)
+        {
+            return;
+        }
+        let Ok(mut target) = topology.cpu_binding(CpuBindingFlags::PROCESS) else {
+            return;
+        };
+        target.singlify();
+
+        // Every thread of the process, including the main thread and the
+        // current thread, should end up bound to the target CPU
+        let pid = pid_t::try_from(std::process::id()).unwrap();
+        // SAFETY: gettid has no safety preconditions
+        let current_tid = unsafe { libc::gettid() };
+        let outcomes = topology.bind_process_threads_cpu(pid, &target).unwrap();
+        assert!(outcomes.contains_key(&pid));
+        assert_eq!(outcomes[&current_tid], TaskOutcome::Done(()));
+        for (tid, outcome) in outcomes {
+            match outcome {
+                TaskOutcome::Done(()) => {
+                    if let Ok(binding) = topology.tid_cpu_binding(tid) {
+                        assert_eq!(binding, target);
+                    }
+                }
+                TaskOutcome::Vanished => {}
+                TaskOutcome::Failed(error) => panic!("failed to bind thread {tid}: {error}"),
+            }
+        }
+        assert_eq!(topology.tid_cpu_binding(current_tid).unwrap(), target);
+    }
+
+    #[test]
+    fn bind_scanned_tasks_rescans() {
+        /// Error reported when listing the threads of an exited process
+        fn exited() -> io::Error {
+            io::Error::from(io::ErrorKind::NotFound)
+        }
+
+        // Failing to list threads initially is an error
+        let result = bind_scanned_tasks(|| Err(exited()), |_tid| unreachable!());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        // Threads spawned during binding are bound by later scans, and the
+        // process exiting between scans does not discard earlier outcomes
+        let mut scans = vec![Ok(vec![1, 2]), Ok(vec![1, 2, 3]), Err(exited())].into_iter();
+        let mut bound = Vec::new();
+        let outcomes = bind_scanned_tasks(
+            || {
+                scans
+                    .next()
+                    .expect("no scan should happen after the process exited")
+            },
+            |tid| {
+                bound.push(tid);
+                if tid == 2 {
+                    Err(RawHwlocError {
+                        api: "hwloc_linux_set_tid_cpubind",
+                        errno: Some(Errno(ESRCH)),
+                    })
+                } else {
+                    Ok(())
+                }
+            },
+        )
+        .unwrap();
+        assert_eq!(bound, [1, 2, 3]);
+        assert_eq!(
+            outcomes.into_iter().collect::<Vec<_>>(),
+            [
+                (1, TaskOutcome::Done(())),
+                (2, TaskOutcome::Vanished),
+                (3, TaskOutcome::Done(())),
+            ]
+        );
+
+        // Scanning stops once no new thread shows up...
+        let mut num_scans = 0;
+        let outcomes = bind_scanned_tasks(
+            || {
+                num_scans += 1;
+                Ok(vec![1])
+            },
+            |_tid| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(num_scans, 2);
+        assert_eq!(outcomes.len(), 1);
+
+        // ...or after a bounded number of scans
+        let mut next_tid = 0;
+        let outcomes = bind_scanned_tasks(
+            || {
+                next_tid += 1;
+                Ok((1..=next_tid).collect())
+            },
+            |_tid| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(outcomes.len(), MAX_TASK_SCANS);
+    }
+
+    #[test]
+    fn task_outcome() {
+        let error = |errno| RawHwlocError {
+            api: "hwloc_linux_set_tid_cpubind",
+            errno,
+        };
+        assert_eq!(
+            TaskOutcome::from(Ok::<_, RawHwlocError>(42)),
+            TaskOutcome::Done(42)
+        );
+        assert_eq!(
+            TaskOutcome::<()>::from(Err(error(Some(Errno(ESRCH))))),
+            TaskOutcome::Vanished
+        );
+        for errno in [None, Some(Errno(libc::EINVAL))] {
+            assert_eq!(
+                TaskOutcome::<()>::from(Err(error(errno))),
+                TaskOutcome::Failed(error(errno))
+            );
+        }
+        assert_eq!(TaskOutcome::Done(42).done(), Some(42));
+        assert_eq!(TaskOutcome::<()>::Vanished.done(), None);
+    }
+
+    #[test]
+    fn page_sampling() {
+        let nonzero = |n| NonZeroUsize::new(n).unwrap();
+        assert_eq!(PageSampling::default(), PageSampling::Exhaustive);
+        assert_eq!(PageSampling::Exhaustive.stride(1000), nonzero(1));
+        assert_eq!(PageSampling::Stride(nonzero(7)).stride(1000), nonzero(7));
+        assert_eq!(
+            PageSampling::MaxPages(nonzero(100)).stride(1000),
+            nonzero(10)
+        );
+        assert_eq!(
+            PageSampling::MaxPages(nonzero(100)).stride(1001),
+            nonzero(11)
+        );
+        assert_eq!(PageSampling::MaxPages(nonzero(100)).stride(10), nonzero(1));
+        assert_eq!(PageSampling::MaxPages(nonzero(100)).stride(0), nonzero(1));
+    }
+
+    #[test]
+    fn page_histogram() {
+        // Large allocations get fresh pages from the OS, which are initially
+        // not faulted in
+        let page_size = page_size();
+        let num_pages = 64;
+        let mut buffer = Vec::<u8>::with_capacity(num_pages * page_size);
+        let Ok(untouched) =
+            page_node_histogram(buffer.spare_capacity_mut(), PageSampling::Exhaustive)
+        else {
+            // move_pages may not be available in the test environment
+            return;
+        };
+        assert_eq!(untouched.page_size, page_size);
+        assert!(untouched.total_pages >= num_pages);
+        assert_eq!(untouched.sampled_pages, untouched.total_pages);
+        let count = |histogram: &PageHistogram| {
+            histogram.pages_per_node.values().sum::<usize>()
+                + histogram.not_faulted
+                + histogram.unknown
+        };
+        assert_eq!(count(&untouched), untouched.sampled_pages);
+
+        // Once pages are touched, they reside on some NUMA node
+        buffer.resize(num_pages * page_size, 42);
+        let touched = page_node_histogram(&buffer[..], PageSampling::Exhaustive).unwrap();
+        assert_eq!(touched.not_faulted, 0);
+        assert_eq!(count(&touched), touched.sampled_pages);
+        let located = touched.pages_per_node.values().sum::<usize>();
+        assert_eq!(located + touched.unknown, touched.total_pages);
+        assert_eq!(
+            touched.nodeset().weight(),
+            Some(touched.pages_per_node.len())
+        );
+        for &node in touched.pages_per_node.keys() {
+            assert_eq!(
+                touched.estimated_pages_on(node),
+                touched.pages_per_node[&node]
+            );
+        }
+
+        // Sampling queries fewer pages
+        let max_pages = NonZeroUsize::new(8).unwrap();
+        let sampled = page_node_histogram(&buffer[..], PageSampling::MaxPages(max_pages)).unwrap();
+        assert_eq!(sampled.total_pages, touched.total_pages);
+        assert!(sampled.sampled_pages <= max_pages.get());
+        assert_eq!(count(&sampled), sampled.sampled_pages);
+
+        // Zero-sized targets span no pages
+        let empty = page_node_histogram(&[0u8; 0], PageSampling::Exhaustive).unwrap();
+        assert_eq!(empty.total_pages, 0);
+        assert_eq!(empty.sampled_pages, 0);
+    }
+
+    proptest! {
+        #[test]
+        fn cpu_set_t(cpuset: CpuSet) {
+            let setsize = usize::try_from(libc::CPU_SETSIZE).unwrap();
+            match cpuset.to_cpu_set_t() {
+                Ok(set) => {
+                    for cpu in 0..setsize {
+                        // SAFETY: cpu is in range 0..CPU_SETSIZE
+                        let is_set = unsafe { libc::CPU_ISSET(cpu, &set) };
+                        prop_assert_eq!(is_set, cpuset.is_set(cpu));
+                    }
+                    prop_assert_eq!(CpuSet::from_cpu_set_t(&set), cpuset);
+                }
+                Err(BitmapTruncationError { capacity, first_truncated }) => {
+                    prop_assert_eq!(capacity, setsize);
+                    prop_assert!(first_truncated >= setsize);
+                    prop_assert!(cpuset.is_set(first_truncated));
+                }
+            }
+        }
+
+        #[test]
+        fn raw_cpu_set_t(cpuset: CpuSet, num_masks in 0usize..8) {
+            let mut masks = vec![c_ulong::MAX; num_masks];
+            let size = num_masks * mem::size_of::<c_ulong>();
+            let set = masks.as_mut_ptr().cast::<cpu_set_t>();
+            // SAFETY: masks is valid for writes of size bytes and c_ulong-aligned
+            let result = unsafe { cpuset.write_raw_cpu_set_t(set, size) };
+            prop_assert_eq!(result, cpuset.to_ulongs(&mut vec![0; num_masks]));
+            // SAFETY: masks is valid for reads of size bytes and c_ulong-aligned
+            let read_back = unsafe { CpuSet::from_raw_cpu_set_t(set, size) };
+            prop_assert_eq!(read_back, CpuSet::from_ulongs(&masks));
+            if result.is_ok() {
+                prop_assert_eq!(CpuSet::from_ulongs(&masks).unwrap(), cpuset);
+            }
+        }
+    }
 }
@@ -6,7 +6,11 @@
 //! translations of hwloc concepts into the vocabulary of other APIs is useful.
 //! This is what the module you're looking at is about.
 
+#[cfg(any(doc, target_os = "linux"))]
+pub mod cgroup;
 #[cfg(any(doc, target_os = "linux"))]
 pub mod linux;
+#[cfg(feature = "rayon")]
+pub mod rayon;
 #[cfg(any(doc, all(feature = "hwloc-2_5_0", target_os = "windows")))]
 pub mod windows;
@@ -0,0 +1,617 @@
+//! Pinned [`rayon`] thread pools
+//!
+//! Pinning the worker threads of a [`ThreadPool`] normally requires writing a
+//! [`start_handler`] that calls into hwlocality. This module provides a
+//! [`PinnedThreadPoolBuilder`] that does this for you, assigning CPUs to
+//! worker threads using [`Topology::distribute_items()`].
+//!
+//! This functionality is only available when the `rayon` cargo feature is
+//! enabled.
+//!
+//! [`start_handler`]: ThreadPoolBuilder::start_handler()
+
+#[cfg(doc)]
+use crate::topology::support::CpuBindingSupport;
+use crate::{
+    cpu::{
+        binding::{CpuBindingError, CpuBindingFlags},
+        cpuset::CpuSet,
+        threads::PinnedWorker,
+    },
+    object::{depth::NormalDepth, types::ObjectType, TopologyObject},
+    topology::{DistributeError, DistributeFlags, Topology},
+};
+use ::rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+#[cfg(any(test, feature = "proptest"))]
+use enum_iterator::Sequence;
+use std::sync::{Arc, Mutex, PoisonError};
+use thiserror::Error;
+
+/// How many worker threads a pinned thread pool should have
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(any(test, feature = "proptest"), derive(Sequence))]
+pub enum WorkerPinning {
+    /// One worker per [`PU`](ObjectType::PU), i.e. per hardware thread
+    OnePerPU,
+
+    /// One worker per [`Core`](ObjectType::Core)
+    ///
+    /// Each worker is bound to all the PUs of its core, unless
+    /// [`PinnedThreadPoolBuilder::singlify()`] is used.
+    #[default]
+    OnePerCore,
+
+    /// One worker per [`NUMANode`](ObjectType::NUMANode)
+    ///
+    /// Each worker is bound to all the CPUs that are local to its NUMA node,
+    /// unless [`PinnedThreadPoolBuilder::singlify()`] is used.
+    OnePerNUMANode,
+}
+//
+impl WorkerPinning {
+    /// Type of objects that workers are assigned to
+    fn object_type(self) -> ObjectType {
+        match self {
+            Self::OnePerPU => ObjectType::PU,
+            Self::OnePerCore => ObjectType::Core,
+            Self::OnePerNUMANode => ObjectType::NUMANode,
+        }
+    }
+}
+//
+crate::impl_arbitrary_for_sequence!(WorkerPinning);
+
+/// Mechanism to build [`ThreadPool`]s whose workers are pinned to CPUs
+///
+/// CPUs are assigned to worker threads using
+/// [`Topology::distribute_items()`], with one worker per object of the type
+/// selected by the [`WorkerPinning`] policy below the distribution roots.
+///
+/// Each worker binds itself to its assigned CPU set as soon as it starts. The
+/// thread pool is only handed over to the caller once all workers are bound,
+/// so binding failures are reported as pool building errors instead of
+/// leaving some workers running unpinned.
+///
+/// # Examples
+///
+/// ```
+/// # use hwlocality::{interop::rayon::{PinnedThreadPoolBuilder, WorkerPinning}, Topology};
+/// # use std::sync::Arc;
+/// let topology = Arc::new(Topology::new()?);
+/// let builder = PinnedThreadPoolBuilder::new(&topology, WorkerPinning::OnePerCore);
+/// # // Binding may not be supported by the host where tests are run
+/// if let Ok(pool) = builder.build() {
+///     let sum: u32 = pool.install(|| {
+///         use rayon::prelude::*;
+///         (1..=100).into_par_iter().sum()
+///     });
+///     assert_eq!(sum, 5050);
+/// }
+/// # Ok::<(), eyre::Report>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct PinnedThreadPoolBuilder<'topology> {
+    /// Topology that workers are distributed over
+    topology: &'topology Arc<Topology>,
+
+    /// Roots under which workers are distributed
+    roots: Vec<&'topology TopologyObject>,
+
+    /// Number of workers and CPU assignment policy
+    pinning: WorkerPinning,
+
+    /// Flags to be passed to [`Topology::distribute_items()`]
+    flags: DistributeFlags,
+
+    /// Truth that assigned CPU sets should be reduced to a single PU
+    singlify: bool,
+}
+//
+impl<'topology> PinnedThreadPoolBuilder<'topology> {
+    /// Start building pinned thread pools for a topology
+    ///
+    /// By default, workers are distributed across the whole topology, in
+    /// the order of [`DistributeFlags::empty()`], without singlifying their
+    /// CPU sets.
+    ///
+    /// Since worker threads need access to the topology in order to bind
+    /// themselves, it must be shared via an [`Arc`].
+    pub fn new(topology: &'topology Arc<Topology>, pinning: WorkerPinning) -> Self {
+        Self {
+            topology,
+            roots: vec![topology.root_object()],
+            pinning,
+            flags: DistributeFlags::empty(),
+            singlify: false,
+        }
+    }
+
+    /// Only distribute workers below some topology objects
+    ///
+    /// This is the `roots` parameter of [`Topology::distribute_items()`]. One
+    /// worker is spawned per object of the selected [`WorkerPinning`] type
+    /// whose CPUs intersect with these roots.
+    pub fn roots(mut self, roots: &[&'topology TopologyObject]) -> Self {
+        self.roots = roots.to_vec();
+        self
+    }
+
+    /// Flags to be passed to [`Topology::distribute_items()`]
+    pub fn flags(mut self, flags: DistributeFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Reduce each worker's CPU set to a single PU
+    ///
+    /// With [`WorkerPinning::OnePerCore`], this can be used to leave the other
+    /// hyperthreads of each core idle. See
+    /// [`PinnedThreadsBuilder::singlify()`] for more information.
+    ///
+    /// [`PinnedThreadsBuilder::singlify()`]: crate::cpu::threads::PinnedThreadsBuilder::singlify()
+    pub fn singlify(mut self, singlify: bool) -> Self {
+        self.singlify = singlify;
+        self
+    }
+
+    /// Compute the CPU set and locality that each worker will be bound to
+    ///
+    /// The `n`-th element of the output is the assignment of the worker
+    /// thread with rayon index `n`.
+    ///
+    /// # Errors
+    ///
+    /// - [`Distribute`] if the distribution roots are invalid. See
+    ///   [`Topology::distribute_items()`] for more information.
+    /// - [`NoObjectInRoots`] if no object of the selected [`WorkerPinning`]
+    ///   type intersects the distribution roots.
+    ///
+    /// [`Distribute`]: PinnedWorkersError::Distribute
+    /// [`NoObjectInRoots`]: PinnedWorkersError::NoObjectInRoots
+    pub fn workers(&self) -> Result<Vec<PinnedWorker<'topology>>, PinnedWorkersError> {
+        if self.roots.is_empty() {
+            return Err(DistributeError::EmptyRoots.into());
+        }
+        let topology: &'topology Topology = self.topology;
+        let roots_cpuset = self.roots_cpuset();
+        let object_type = self.pinning.object_type();
+        let num_workers = topology
+            .objects_with_type(object_type)
+            .filter(|obj| {
+                obj.cpuset()
+                    .is_some_and(|cpuset| cpuset.intersects(&roots_cpuset))
+            })
+            .count();
+        if num_workers == 0 {
+            return Err(PinnedWorkersError::NoObjectInRoots(object_type));
+        }
+        Ok(topology
+            .pinned_threads(&self.roots, num_workers, self.flags)
+            .max_depth(max_depth(topology, object_type))
+            .singlify(self.singlify)
+            .workers()?)
+    }
+
+    /// Build a single thread pool
+    ///
+    /// # Errors
+    ///
+    /// - [`Workers`] if CPUs could not be assigned to workers
+    /// - [`Build`] if rayon failed to build the thread pool
+    /// - [`Bind`] if a worker failed to bind itself to its CPUs. This can
+    ///   notably happen if [`CpuBindingSupport::set_current_thread()`] is not
+    ///   supported, or if some of the assigned CPUs are disallowed.
+    ///
+    /// [`Bind`]: PinnedThreadPoolError::Bind
+    /// [`Build`]: PinnedThreadPoolError::Build
+    /// [`Workers`]: PinnedThreadPoolError::Workers
+    pub fn build(&self) -> Result<ThreadPool, PinnedThreadPoolError> {
+        let cpusets = self
+            .workers()?
+            .into_iter()
+            .map(|worker| worker.cpuset().clone())
+            .collect::<Vec<_>>();
+        let num_workers = cpusets.len();
+        let reports = Arc::new(Mutex::new(vec![None; num_workers]));
+        let pool = {
+            let topology = Arc::clone(self.topology);
+            let reports = Arc::clone(&reports);
+            ThreadPoolBuilder::new()
+                .num_threads(num_workers)
+                .start_handler(move |index| {
+                    let result = topology.bind_cpu(&cpusets[index], CpuBindingFlags::THREAD);
+                    reports.lock().unwrap_or_else(PoisonError::into_inner)[index] =
+                        Some(result.map_err(|error| (cpusets[index].clone(), error)));
+                })
+                .build()?
+        };
+
+        // Rayon workers run the start handler before processing any job, so
+        // once a job has run on every worker, all of them have reported
+        pool.broadcast(|_| ());
+        let mut reports = reports.lock().unwrap_or_else(PoisonError::into_inner);
+        for (worker, report) in reports.iter_mut().enumerate() {
+            let report = report
+                .take()
+                .expect("All workers should have run the start handler");
+            if let Err((cpuset, error)) = report {
+                return Err(PinnedThreadPoolError::Bind {
+                    worker,
+                    cpuset,
+                    error,
+                });
+            }
+        }
+        Ok(pool)
+    }
+
+    /// Build one thread pool per NUMA node
+    ///
+    /// Each pool only contains workers that are bound to CPUs local to its
+    /// NUMA node, following the [`WorkerPinning`] policy. This is useful for
+    /// nested parallelism where each subtask should stay close to its data.
+    ///
+    /// NUMA nodes whose CPUs do not intersect with the distribution roots are
+    /// skipped. If NUMA nodes share CPUs, the resulting pools will overlap.
+    ///
+    /// # Errors
+    ///
+    /// See [`build()`](Self::build()).
+    pub fn build_per_numa_node(
+        &self,
+    ) -> Result<Vec<LocalThreadPool<'topology>>, PinnedThreadPoolError> {
+        self.build_per_object(ObjectType::NUMANode)
+    }
+
+    /// Build one thread pool per [`Package`](ObjectType::Package)
+    ///
+    /// This works like [`build_per_numa_node()`](Self::build_per_numa_node()),
+    /// but pools are scoped to CPU packages (sockets) rather than NUMA nodes.
+    ///
+    /// # Errors
+    ///
+    /// See [`build()`](Self::build()).
+    pub fn build_per_package(
+        &self,
+    ) -> Result<Vec<LocalThreadPool<'topology>>, PinnedThreadPoolError> {
+        self.build_per_object(ObjectType::Package)
+    }
+
+    /// Union of the CPU sets of the distribution roots
+    fn roots_cpuset(&self) -> CpuSet {
+        self.roots
+            .iter()
+            .filter_map(|root| root.cpuset())
+            .fold(CpuSet::new(), |mut acc, cpuset| {
+                acc |= cpuset;
+                acc
+            })
+    }
+
+    /// Build one thread pool per object of a certain type
+    fn build_per_object(
+        &self,
+        object_type: ObjectType,
+    ) -> Result<Vec<LocalThreadPool<'topology>>, PinnedThreadPoolError> {
+        if self.roots.is_empty() {
+            return Err(PinnedWorkersError::from(DistributeError::EmptyRoots).into());
+        }
+        let topology: &'topology Topology = self.topology;
+        let roots_cpuset = self.roots_cpuset();
+        let mut pools = Vec::new();
+        for locality in topology.objects_with_type(object_type) {
+            let Some(local_cpuset) = locality.cpuset() else {
+                continue;
+            };
+            let local_cpuset = local_cpuset & &roots_cpuset;
+            if local_cpuset.is_empty() {
+                continue;
+            }
+            let roots = topology
+                .largest_objects_inside_cpuset(local_cpuset)
+                .collect::<Vec<_>>();
+            let pool = self.clone().roots(&roots).build()?;
+            pools.push(LocalThreadPool { locality, pool });
+        }
+        if pools.is_empty() {
+            return Err(PinnedWorkersError::NoObjectInRoots(object_type).into());
+        }
+        Ok(pools)
+    }
+}
+
+/// Depth at which workers of a certain object type should be distributed
+fn max_depth(topology: &Topology, object_type: ObjectType) -> NormalDepth {
+    if object_type == ObjectType::NUMANode {
+        // NUMA nodes are attached to normal objects, whose depth may vary
+        topology
+            .objects_with_type(ObjectType::NUMANode)
+            .filter_map(|node| {
+                node.ancestors()
+                    .find(|ancestor| ancestor.object_type().is_normal())
+                    .and_then(|parent| NormalDepth::try_from(parent.depth()).ok())
+            })
+            .min()
+            .unwrap_or(NormalDepth::MAX)
+    } else {
+        topology
+            .depth_for_type(object_type)
+            .ok()
+            .and_then(|depth| NormalDepth::try_from(depth).ok())
+            .unwrap_or(NormalDepth::MAX)
+    }
+}
+
+/// Thread pool whose workers are all local to a certain topology object
+///
+/// This `struct` is created by [`PinnedThreadPoolBuilder::build_per_numa_node()`]
+/// and [`PinnedThreadPoolBuilder::build_per_package()`].
+#[derive(Debug)]
+pub struct LocalThreadPool<'topology> {
+    /// Object that the workers are local to
+    locality: &'topology TopologyObject,
+
+    /// Thread pool
+    pool: ThreadPool,
+}
+//
+impl<'topology> LocalThreadPool<'topology> {
+    /// NUMA node or package that the workers of this pool are local to
+    pub fn locality(&self) -> &'topology TopologyObject {
+        self.locality
+    }
+
+    /// Thread pool
+    pub fn pool(&self) -> &ThreadPool {
+        &self.pool
+    }
+
+    /// Extract the thread pool
+    pub fn into_pool(self) -> ThreadPool {
+        self.pool
+    }
+}
+
+/// Error while assigning CPUs to the workers of a pinned thread pool
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub enum PinnedWorkersError {
+    /// The distribution roots are invalid
+    #[error(transparent)]
+    Distribute(#[from] DistributeError),
+
+    /// No object of the requested type intersects the distribution roots
+    ///
+    /// This is the worker pinning type for [`PinnedThreadPoolBuilder::workers()`]
+    /// and [`PinnedThreadPoolBuilder::build()`], and the locality type for
+    /// [`PinnedThreadPoolBuilder::build_per_numa_node()`] and
+    /// [`PinnedThreadPoolBuilder::build_per_package()`].
+    #[error("no {0} object intersects the distribution roots")]
+    NoObjectInRoots(ObjectType),
+}
+
+/// Error while building a pinned thread pool
+#[derive(Debug, Error)]
+pub enum PinnedThreadPoolError {
+    /// Failed to assign CPUs to workers
+    #[error(transparent)]
+    Workers(#[from] PinnedWorkersError),
+
+    /// Rayon failed to build the thread pool
+    #[error("failed to build thread pool")]
+    Build(#[from] ThreadPoolBuildError),
+
+    /// A worker failed to bind itself to its assigned CPUs
+    ///
+    /// If multiple workers failed, this reports the one with the lowest index.
+    #[error("pinned worker #{worker} failed to bind itself to CPUs {cpuset}")]
+    Bind {
+        /// Index of the worker that could not be bound
+        worker: usize,
+
+        /// CPUs that the worker tried to bind itself to
+        cpuset: CpuSet,
+
+        /// Binding error
+        #[source]
+        error: CpuBindingError,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+        ptr,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(WorkerPinning:
+        Copy, Debug, Default, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(WorkerPinning:
+        Binary, Deref, Display, Drop, Error, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+    assert_impl_all!(PinnedThreadPoolBuilder<'static>:
+        Clone, Debug, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(PinnedThreadPoolBuilder<'static>:
+        Binary, Copy, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(LocalThreadPool<'static>:
+        Debug, Send, Sized, Sync, Unpin
+    );
+    assert_not_impl_any!(LocalThreadPool<'static>:
+        Binary, Clone, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(PinnedWorkersError:
+        Clone, Debug, Display, Error, From<DistributeError>, Hash, Sized,
+        Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(PinnedWorkersError:
+        Binary, Copy, Default, Deref, Drop, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+    assert_impl_all!(PinnedThreadPoolError:
+        Debug, Display, Error, From<PinnedWorkersError>,
+        From<ThreadPoolBuildError>, Send, Sized, Sync, Unpin
+    );
+    assert_not_impl_any!(PinnedThreadPoolError:
+        Binary, Clone, Default, Deref, Drop, Hash, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+
+    /// Shared topology used by the tests of this module
+    fn topology() -> &'static Arc<Topology> {
+        static INSTANCE: std::sync::OnceLock<Arc<Topology>> = std::sync::OnceLock::new();
+        INSTANCE.get_or_init(|| Arc::new(Topology::test_instance().clone()))
+    }
+
+    #[test]
+    fn workers() {
+        let topology = topology();
+        for pinning in enum_iterator::all::<WorkerPinning>() {
+            let builder = PinnedThreadPoolBuilder::new(topology, pinning);
+            let workers = builder.workers().unwrap();
+            let objects = topology
+                .objects_with_type(pinning.object_type())
+                .filter(|obj| obj.cpuset().is_some_and(|cpuset| !cpuset.is_empty()));
+            assert_eq!(workers.len(), objects.clone().count());
+            for (idx, worker) in workers.iter().enumerate() {
+                assert_eq!(worker.index(), idx);
+                assert!(topology.cpuset().includes(worker.cpuset()));
+            }
+            if pinning != WorkerPinning::OnePerNUMANode {
+                for (worker, obj) in workers.iter().zip(objects) {
+                    assert_eq!(Some(worker.cpuset()), obj.cpuset().as_deref());
+                    assert_eq!(worker.locality().cpuset(), obj.cpuset());
+                }
+            }
+            for worker in builder.singlify(true).workers().unwrap() {
+                assert_eq!(worker.cpuset().weight(), Some(1));
+            }
+        }
+    }
+
+    #[test]
+    fn empty_roots() {
+        let topology = topology();
+        let builder = PinnedThreadPoolBuilder::new(topology, WorkerPinning::OnePerPU).roots(&[]);
+        let empty_roots = PinnedWorkersError::Distribute(DistributeError::EmptyRoots);
+        assert_eq!(builder.workers().unwrap_err(), empty_roots);
+        assert!(matches!(
+            builder.build(),
+            Err(PinnedThreadPoolError::Workers(error)) if error == empty_roots
+        ));
+        assert!(matches!(
+            builder.build_per_package(),
+            Err(PinnedThreadPoolError::Workers(error)) if error == empty_roots
+        ));
+    }
+
+    #[test]
+    fn no_object_in_roots() {
+        let topology = topology();
+        // Objects without CPUs, like I/O or Misc objects, cannot intersect
+        // with any object of the worker pinning type
+        let Some(root) = topology.objects().find(|obj| obj.cpuset().is_none()) else {
+            return;
+        };
+        let builder =
+            PinnedThreadPoolBuilder::new(topology, WorkerPinning::OnePerPU).roots(&[root]);
+        assert_eq!(
+            builder.workers().unwrap_err(),
+            PinnedWorkersError::NoObjectInRoots(ObjectType::PU)
+        );
+        assert!(matches!(
+            builder.build_per_package(),
+            Err(PinnedThreadPoolError::Workers(
+                PinnedWorkersError::NoObjectInRoots(ObjectType::Package)
+            ))
+        ));
+    }
+
+    /// Check the outcome of building a pinned thread pool
+    fn check_pool(
+        builder: &PinnedThreadPoolBuilder<'_>,
+        result: Result<ThreadPool, PinnedThreadPoolError>,
+    ) {
+        match result {
+            Ok(pool) => {
+                let workers = builder.workers().unwrap();
+                assert_eq!(pool.current_num_threads(), workers.len());
+                let bindings = pool.broadcast(|ctx| {
+                    builder
+                        .topology
+                        .cpu_binding(CpuBindingFlags::THREAD)
+                        .map(|binding| (ctx.index(), binding))
+                });
+                for (index, binding) in bindings.into_iter().flatten() {
+                    assert!(workers[index].cpuset().includes(&binding));
+                }
+            }
+            Err(PinnedThreadPoolError::Bind { cpuset, .. }) => {
+                // Binding may legitimately fail when the test topology contains
+                // disallowed CPUs or the OS does not support thread binding
+                assert!(builder.topology.cpuset().includes(&cpuset));
+            }
+            Err(other) => panic!("unexpected pool building error: {other}"),
+        }
+    }
+
+    #[test]
+    fn build() {
+        let topology = topology();
+        for pinning in enum_iterator::all::<WorkerPinning>() {
+            let builder = PinnedThreadPoolBuilder::new(topology, pinning);
+            check_pool(&builder, builder.build());
+        }
+    }
+
+    #[test]
+    fn build_per_object() {
+        let topology = topology();
+        let builder = PinnedThreadPoolBuilder::new(topology, WorkerPinning::OnePerPU);
+        for (object_type, result) in [
+            (ObjectType::NUMANode, builder.build_per_numa_node()),
+            (ObjectType::Package, builder.build_per_package()),
+        ] {
+            let Ok(pools) = result else {
+                continue;
+            };
+            let localities = topology
+                .objects_with_type(object_type)
+                .filter(|obj| obj.cpuset().is_some_and(|cpuset| !cpuset.is_empty()));
+            assert_eq!(pools.len(), localities.clone().count());
+            for (pool, locality) in pools.into_iter().zip(localities) {
+                assert!(ptr::eq(pool.locality(), locality));
+                let local_cpuset = locality.cpuset().unwrap();
+                let local_builder = builder.clone().roots(
+                    &topology
+                        .largest_objects_inside_cpuset(local_cpuset.clone_target())
+                        .collect::<Vec<_>>(),
+                );
+                check_pool(&local_builder, Ok(pool.into_pool()));
+            }
+        }
+    }
+}
@@ -0,0 +1,641 @@
+//! NUMA-bound memory allocator
+//!
+//! [`Topology::allocate_bound_memory()`] returns untyped [`Bytes`], which is
+//! fine for large buffers but does not help when a whole data structure should
+//! reside on some NUMA node(s). This module provides a [`BoundAllocator`] that
+//! can be plugged into any container that supports custom allocators.
+//!
+//! Large allocations are directly serviced by hwloc, whereas small ones are
+//! carved from bound memory chunks that are shared by all clones of an
+//! allocator, since hwloc allocations have page granularity.
+//!
+//! [`Bytes`]: crate::memory::binding::Bytes
+
+#[cfg(doc)]
+use crate::{bitmap::Bitmap, topology::support::MemoryBindingSupport};
+use crate::{
+    bitmap::{BitmapKind, SpecializedBitmap},
+    cpu::cpuset::CpuSet,
+    memory::{
+        binding::{Bytes, MemoryAllocationError, MemoryBindingFlags, MemoryBindingPolicy},
+        nodeset::NodeSet,
+    },
+    topology::Topology,
+};
+#[cfg(feature = "allocator-api2")]
+use allocator_api2::alloc::{AllocError, Allocator};
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    fmt::{self, Debug},
+    ptr::{self, NonNull},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// Memory allocator that allocates memory on specific NUMA nodes
+///
+/// The memory binding is specified using the same parameters as
+/// [`Topology::allocate_bound_memory()`], and subjected to the same
+/// limitations. In particular, it requires
+/// [`MemoryBindingSupport::allocate_bound()`].
+///
+/// This type implements [`GlobalAlloc`] and, if the `allocator-api2` cargo
+/// feature is enabled, the [`allocator_api2::alloc::Allocator`] trait. The
+/// latter lets you allocate containers from [`allocator_api2`] on specific
+/// NUMA nodes. Since constructing a `BoundAllocator` requires a [`Topology`],
+/// which itself allocates memory, it cannot be used as a
+/// `#[global_allocator]`.
+///
+/// Cloning a `BoundAllocator` is cheap, and all clones share the same pool of
+/// memory for small allocations.
+///
+/// # Examples
+///
+/// ```
+/// # use hwlocality::{
+/// #     memory::{allocator::BoundAllocator, binding::{MemoryBindingFlags, MemoryBindingPolicy}},
+/// #     object::types::ObjectType,
+/// #     Topology,
+/// # };
+/// # use std::sync::Arc;
+/// let topology = Arc::new(Topology::new()?);
+/// let node = topology
+///     .objects_with_type(ObjectType::NUMANode)
+///     .next()
+///     .expect("There should be at least one NUMA node");
+/// let nodeset = node.nodeset().expect("NUMA nodes should have a nodeset");
+/// # // Bound allocation may not be supported by the host where tests are run
+/// if let Ok(allocator) = BoundAllocator::new(
+///     topology.clone(),
+///     &nodeset,
+///     MemoryBindingPolicy::Bind,
+///     MemoryBindingFlags::empty(),
+/// ) {
+///     # #[cfg(feature = "allocator-api2")]
+///     # {
+///     let mut v = allocator_api2::vec::Vec::new_in(allocator);
+///     v.extend_from_slice(&[1, 2, 3]);
+///     assert_eq!(v.iter().sum::<i32>(), 6);
+///     # }
+/// }
+/// # Ok::<(), eyre::Report>(())
+/// ```
+#[derive(Clone)]
+pub struct BoundAllocator {
+    /// State shared by all clones of this allocator
+    state: Arc<AllocatorState>,
+}
+//
+impl BoundAllocator {
+    /// Set up an allocator for memory bound to NUMA nodes specified by `set`
+    ///
+    /// `set`, `policy` and `flags` have the same meaning as in
+    /// [`Topology::allocate_bound_memory()`]. Since worker threads may use the
+    /// allocator, it needs shared ownership of the topology.
+    ///
+    /// A first chunk of memory is allocated for small allocations, which
+    /// ensures that the binding parameters are valid.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::allocate_bound_memory()`].
+    pub fn new<Set: SpecializedBitmap>(
+        topology: Arc<Topology>,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<Self, MemoryAllocationError<Set::Owned>> {
+        let first_chunk = topology
+            .allocate_bound_memory(ARENA_CHUNK_SIZE, set, policy, flags)?
+            .into_raw()
+            .cast::<u8>();
+        let set = match Set::BITMAP_KIND {
+            BitmapKind::CpuSet => BindingSet::CpuSet(CpuSet::from(set.as_ref().clone())),
+            BitmapKind::NodeSet => BindingSet::NodeSet(NodeSet::from(set.as_ref().clone())),
+        };
+        Ok(Self {
+            state: Arc::new(AllocatorState {
+                topology,
+                set,
+                policy,
+                flags,
+                arena: Mutex::new(Arena::new(first_chunk)),
+            }),
+        })
+    }
+
+    /// Topology that this allocator allocates memory from
+    pub fn topology(&self) -> &Topology {
+        &self.state.topology
+    }
+
+    /// CPUs near the NUMA nodes where memory is allocated, if the allocator
+    /// was set up using a [`CpuSet`]
+    pub fn cpuset(&self) -> Option<&CpuSet> {
+        match &self.state.set {
+            BindingSet::CpuSet(set) => Some(set),
+            BindingSet::NodeSet(_) => None,
+        }
+    }
+
+    /// NUMA nodes where memory is allocated, if the allocator was set up using
+    /// a [`NodeSet`]
+    pub fn nodeset(&self) -> Option<&NodeSet> {
+        match &self.state.set {
+            BindingSet::CpuSet(_) => None,
+            BindingSet::NodeSet(set) => Some(set),
+        }
+    }
+
+    /// Memory binding policy of allocations
+    pub fn policy(&self) -> MemoryBindingPolicy {
+        self.state.policy
+    }
+
+    /// Memory binding flags of allocations
+    pub fn flags(&self) -> MemoryBindingFlags {
+        self.state.flags
+    }
+
+    /// Allocate memory for `layout`, returning `None` on failure
+    fn allocate_impl(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let state = &*self.state;
+        match size_class(layout) {
+            Some(class) => state
+                .arena
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .allocate(class, || state.allocate_bound(ARENA_CHUNK_SIZE)),
+            None if layout.align() <= MAX_LARGE_ALIGN => state.allocate_bound(layout.size()),
+            None => None,
+        }
+    }
+
+    /// Liberate memory that was allocated by `allocate_impl()`
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by `allocate_impl()` on a clone of this
+    /// allocator, with the same `layout`, and not have been deallocated yet.
+    unsafe fn deallocate_impl(&self, ptr: NonNull<u8>, layout: Layout) {
+        let state = &*self.state;
+        if let Some(class) = size_class(layout) {
+            // SAFETY: Per function precondition, ptr is a block of this size
+            //         class that was carved from this arena
+            unsafe {
+                state
+                    .arena
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .deallocate(ptr, class);
+            }
+        } else {
+            // SAFETY: Per function precondition, ptr is a large allocation of
+            //         size layout.size()
+            unsafe { state.free_bound(ptr, layout.size()) }
+        }
+    }
+}
+//
+impl Debug for BoundAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundAllocator")
+            .field("set", &self.state.set)
+            .field("policy", &self.state.policy)
+            .field("flags", &self.state.flags)
+            .finish_non_exhaustive()
+    }
+}
+//
+// SAFETY: - Memory blocks are only handed out once until they are deallocated
+//         - Allocations are aligned as requested by the layout, or fail
+//         - Allocations remain valid as long as a clone of the allocator lives
+unsafe impl GlobalAlloc for BoundAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocate_impl(layout)
+            .map_or(ptr::null_mut(), NonNull::as_ptr)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(ptr) = NonNull::new(ptr) {
+            // SAFETY: Per GlobalAlloc contract
+            unsafe { self.deallocate_impl(ptr, layout) }
+        }
+    }
+}
+//
+#[cfg(feature = "allocator-api2")]
+// SAFETY: - Memory blocks are only handed out once until they are deallocated
+//         - Allocations are aligned as requested by the layout, or fail
+//         - Allocations remain valid as long as a clone of the allocator
+//           lives, and clones share the same state
+unsafe impl Allocator for BoundAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let base = self.allocate_impl(layout).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(base, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        // SAFETY: Per Allocator contract
+        unsafe { self.deallocate_impl(ptr, layout) }
+    }
+}
+
+/// Size of the bound memory chunks that small allocations are carved from
+const ARENA_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Smallest size class of the arena, as a power of two
+const MIN_SMALL_SHIFT: u32 = 4;
+
+/// Largest allocation size and alignment that is serviced by the arena
+const MAX_SMALL_SIZE: usize = 2048;
+
+/// Largest alignment that can be achieved by direct hwloc allocations
+///
+/// hwloc allocates page-aligned memory, and pages are at least this large on
+/// all supported operating systems.
+const MAX_LARGE_ALIGN: usize = 4096;
+
+/// Number of size classes in the arena
+const NUM_SIZE_CLASSES: usize = (MAX_SMALL_SIZE.trailing_zeros() - MIN_SMALL_SHIFT + 1) as usize;
+
+/// Arena size class that an allocation belongs to, if it is small enough
+fn size_class(layout: Layout) -> Option<usize> {
+    let block_size = layout
+        .size()
+        .max(layout.align())
+        .max(1 << MIN_SMALL_SHIFT)
+        .checked_next_power_of_two()?;
+    (block_size <= MAX_SMALL_SIZE).then(|| (block_size.trailing_zeros() - MIN_SMALL_SHIFT) as usize)
+}
+
+/// Size of the blocks of an arena size class
+fn block_size(class: usize) -> usize {
+    1 << (class + MIN_SMALL_SHIFT as usize)
+}
+
+/// Set that memory is bound to
+#[derive(Clone, Debug)]
+enum BindingSet {
+    /// Memory is bound to NUMA nodes near these CPUs
+    CpuSet(CpuSet),
+
+    /// Memory is bound to these NUMA nodes
+    NodeSet(NodeSet),
+}
+
+/// State shared by all clones of a [`BoundAllocator`]
+struct AllocatorState {
+    /// Topology that memory is allocated from
+    topology: Arc<Topology>,
+
+    /// Set that memory is bound to
+    set: BindingSet,
+
+    /// Memory binding policy
+    policy: MemoryBindingPolicy,
+
+    /// Memory binding flags
+    flags: MemoryBindingFlags,
+
+    /// Arena that small allocations are carved from
+    arena: Mutex<Arena>,
+}
+//
+impl AllocatorState {
+    /// Allocate `len` bytes of bound memory from hwloc, or return `None`
+    fn allocate_bound(&self, len: usize) -> Option<NonNull<u8>> {
+        let bytes = match &self.set {
+            BindingSet::CpuSet(set) => self
+                .topology
+                .allocate_bound_memory(len, set, self.policy, self.flags)
+                .ok(),
+            BindingSet::NodeSet(set) => self
+                .topology
+                .allocate_bound_memory(len, set, self.policy, self.flags)
+                .ok(),
+        }?;
+        Some(bytes.into_raw().cast::<u8>())
+    }
+
+    /// Liberate bound memory that was allocated by `allocate_bound()`
+    ///
+    /// # Safety
+    ///
+    /// `base` must have been allocated by `allocate_bound()` on this state with
+    /// the same `len`, and not have been liberated yet.
+    unsafe fn free_bound(&self, base: NonNull<u8>, len: usize) {
+        // SAFETY: Per function precondition, this is an hwloc allocation from
+        //         this topology with the right size
+        let bytes = unsafe { Bytes::wrap(&self.topology, base.cast(), len) };
+        std::mem::drop(bytes);
+    }
+}
+//
+impl Drop for AllocatorState {
+    fn drop(&mut self) {
+        let arena = self.arena.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for chunk in std::mem::take(&mut arena.chunks) {
+            // SAFETY: Arena chunks are allocated by allocate_bound() with size
+            //         ARENA_CHUNK_SIZE, and are liberated exactly once here
+            unsafe { self.free_bound(chunk, ARENA_CHUNK_SIZE) }
+        }
+    }
+}
+
+/// Pool of memory from which small allocations are carved
+///
+/// Each allocation is rounded up to a power-of-two size class. Blocks of each
+/// size class are carved from the current chunk, at an address that is a
+/// multiple of their size, and recycled via a per-class free list once they
+/// are deallocated.
+//
+// --- Implementation details ---
+//
+// # Safety
+//
+// - `chunks` contains the base pointers of owned allocations of
+//   ARENA_CHUNK_SIZE bytes, which remain valid as long as the arena lives
+// - `cursor` points inside of (or one past the end of) the last chunk, and
+//   `remaining` is the number of bytes between `cursor` and the chunk's end
+// - Free lists only contain blocks of the matching size class that were
+//   carved from `chunks` and are not currently allocated
+struct Arena {
+    /// Chunks of bound memory owned by the arena
+    chunks: Vec<NonNull<u8>>,
+
+    /// First byte of the current chunk that was not handed out yet
+    cursor: NonNull<u8>,
+
+    /// Number of bytes after `cursor` in the current chunk
+    remaining: usize,
+
+    /// Deallocated blocks of each size class
+    free_lists: [Option<NonNull<FreeBlock>>; NUM_SIZE_CLASSES],
+}
+//
+impl Arena {
+    /// Set up an arena with a first chunk of `ARENA_CHUNK_SIZE` bytes
+    fn new(first_chunk: NonNull<u8>) -> Self {
+        Self {
+            chunks: vec![first_chunk],
+            cursor: first_chunk,
+            remaining: ARENA_CHUNK_SIZE,
+            free_lists: [None; NUM_SIZE_CLASSES],
+        }
+    }
+
+    /// Allocate a block of size class `class`
+    ///
+    /// `new_chunk` is called to allocate a new chunk of `ARENA_CHUNK_SIZE`
+    /// bytes if the current one is exhausted.
+    fn allocate(
+        &mut self,
+        class: usize,
+        new_chunk: impl FnOnce() -> Option<NonNull<u8>>,
+    ) -> Option<NonNull<u8>> {
+        // Recycle a previously deallocated block if possible
+        if let Some(block) = self.free_lists[class] {
+            // SAFETY: Free list blocks are valid and contain a FreeBlock
+            self.free_lists[class] = unsafe { block.as_ptr().read() }.next;
+            return Some(block.cast());
+        }
+
+        // Otherwise, carve a new block from the current chunk, allocating a
+        // new chunk if the current one is exhausted
+        let size = block_size(class);
+        let mut offset = self.cursor.as_ptr().align_offset(size);
+        if offset.saturating_add(size) > self.remaining {
+            let chunk = new_chunk()?;
+            self.chunks.push(chunk);
+            self.cursor = chunk;
+            self.remaining = ARENA_CHUNK_SIZE;
+            offset = chunk.as_ptr().align_offset(size);
+            if offset.saturating_add(size) > self.remaining {
+                return None;
+            }
+        }
+        // SAFETY: offset + size bytes were checked to fit in the current chunk
+        let block = unsafe { self.cursor.as_ptr().add(offset) };
+        // SAFETY: Ditto, so this is at most one past the end of the chunk
+        self.cursor = unsafe { NonNull::new_unchecked(block.add(size)) };
+        self.remaining -= offset + size;
+        NonNull::new(block)
+    }
+
+    /// Recycle a block of size class `class`
+    ///
+    /// # Safety
+    ///
+    /// `block` must have been allocated by `allocate()` on this arena with the
+    /// same size class, and not have been deallocated yet.
+    unsafe fn deallocate(&mut self, block: NonNull<u8>, class: usize) {
+        let block = block.cast::<FreeBlock>();
+        // SAFETY: Blocks are at least as large and aligned as FreeBlock, and
+        //         per function precondition this one is not in use anymore
+        unsafe {
+            block.as_ptr().write(FreeBlock {
+                next: self.free_lists[class],
+            });
+        }
+        self.free_lists[class] = Some(block);
+    }
+}
+//
+// SAFETY: Arena owns the memory that it points to
+unsafe impl Send for Arena {}
+
+/// Header of a deallocated arena block, linking it to the next one
+#[derive(Copy, Clone)]
+struct FreeBlock {
+    /// Next deallocated block of the same size class
+    next: Option<NonNull<Self>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::types::ObjectType;
+    use proptest::prelude::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        collections::HashSet,
+        error::Error,
+        fmt::{Binary, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex},
+        hash::Hash,
+        io::{self, Read},
+        mem::MaybeUninit,
+        ops::{Deref, Drop},
+        sync::OnceLock,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(BoundAllocator:
+        Clone, Debug, GlobalAlloc, Send, Sized, Sync, Unpin
+    );
+    #[cfg(feature = "allocator-api2")]
+    assert_impl_all!(BoundAllocator: Allocator);
+    assert_not_impl_any!(BoundAllocator:
+        Binary, Copy, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+
+    #[test]
+    fn size_classes() {
+        let class = |size, align| size_class(Layout::from_size_align(size, align).unwrap());
+        assert_eq!(class(0, 1), Some(0));
+        assert_eq!(class(1, 1), Some(0));
+        assert_eq!(class(16, 8), Some(0));
+        assert_eq!(class(17, 1), Some(1));
+        assert_eq!(class(1, 64), Some(2));
+        assert_eq!(class(MAX_SMALL_SIZE, 1), Some(NUM_SIZE_CLASSES - 1));
+        assert_eq!(class(MAX_SMALL_SIZE + 1, 1), None);
+        assert_eq!(class(1, MAX_SMALL_SIZE * 2), None);
+        for class in 0..NUM_SIZE_CLASSES {
+            assert_eq!(
+                size_class(Layout::array::<u8>(block_size(class)).unwrap()),
+                Some(class)
+            );
+        }
+    }
+
+    /// Layout of the chunks allocated by arena tests
+    fn chunk_layout() -> Layout {
+        Layout::from_size_align(ARENA_CHUNK_SIZE, MAX_LARGE_ALIGN).unwrap()
+    }
+
+    /// Allocate an arena chunk from the global allocator
+    fn new_chunk() -> Option<NonNull<u8>> {
+        // SAFETY: Chunk layout has nonzero size
+        NonNull::new(unsafe { std::alloc::alloc(chunk_layout()) })
+    }
+
+    proptest! {
+        #[test]
+        fn arena(ops in prop::collection::vec((0..NUM_SIZE_CLASSES, any::<bool>()), 0..1000)) {
+            let mut arena = Arena::new(new_chunk().unwrap());
+            let mut live = Vec::<(NonNull<u8>, usize)>::new();
+            for (class, dealloc) in ops {
+                if dealloc && !live.is_empty() {
+                    let (block, class) = live.swap_remove(0);
+                    // SAFETY: Block was allocated with this class and is live
+                    unsafe { arena.deallocate(block, class) };
+                } else {
+                    let block = arena.allocate(class, new_chunk).unwrap();
+                    let size = block_size(class);
+                    prop_assert_eq!(block.as_ptr().align_offset(size), 0);
+                    live.push((block, class));
+                }
+            }
+
+            // Live blocks should not overlap and reside within chunks
+            let mut bytes = HashSet::new();
+            for (block, class) in &live {
+                let start = block.as_ptr() as usize;
+                let in_chunk = arena.chunks.iter().any(|chunk| {
+                    let chunk_start = chunk.as_ptr() as usize;
+                    (chunk_start..chunk_start + ARENA_CHUNK_SIZE).contains(&start)
+                });
+                prop_assert!(in_chunk);
+                for byte in start..start + block_size(*class) {
+                    prop_assert!(bytes.insert(byte));
+                }
+            }
+
+            for chunk in arena.chunks {
+                // SAFETY: Chunks were allocated with this layout
+                unsafe { std::alloc::dealloc(chunk.as_ptr(), chunk_layout()) };
+            }
+        }
+    }
+
+    /// Allocator bound to the first NUMA node of the test topology, if
+    /// supported by the host
+    fn allocator() -> Option<&'static BoundAllocator> {
+        static INSTANCE: OnceLock<Option<BoundAllocator>> = OnceLock::new();
+        INSTANCE
+            .get_or_init(|| {
+                let topology = Arc::new(Topology::test_instance().clone());
+                let nodeset = topology
+                    .objects_with_type(ObjectType::NUMANode)
+                    .next()?
+                    .nodeset()?
+                    .clone_target();
+                BoundAllocator::new(
+                    topology,
+                    &nodeset,
+                    MemoryBindingPolicy::Bind,
+                    MemoryBindingFlags::empty(),
+                )
+                .ok()
+            })
+            .as_ref()
+    }
+
+    proptest! {
+        #[test]
+        fn global_alloc(
+            sizes in prop::collection::vec(1usize..(4 * MAX_SMALL_SIZE), 1..50),
+            align_shift in 0u32..13,
+        ) {
+            let Some(allocator) = allocator() else {
+                return Ok(());
+            };
+            let allocations = sizes.into_iter().map(|size| {
+                let layout = Layout::from_size_align(size, 1 << align_shift).unwrap();
+                // SAFETY: Layout has nonzero size
+                let ptr = unsafe { allocator.alloc(layout) };
+                (ptr, layout)
+            }).collect::<Vec<_>>();
+            for (idx, &(ptr, layout)) in allocations.iter().enumerate() {
+                if layout.align() > MAX_LARGE_ALIGN {
+                    prop_assert!(ptr.is_null());
+                    continue;
+                }
+                prop_assert!(!ptr.is_null());
+                prop_assert_eq!(ptr.align_offset(layout.align()), 0);
+                // SAFETY: ptr is valid for writes of layout.size() bytes
+                let bytes = unsafe {
+                    std::slice::from_raw_parts_mut(ptr.cast::<MaybeUninit<u8>>(), layout.size())
+                };
+                #[allow(clippy::cast_possible_truncation)]
+                bytes.fill(MaybeUninit::new(idx as u8));
+            }
+            for (idx, (ptr, layout)) in allocations.into_iter().enumerate() {
+                if !ptr.is_null() {
+                    // SAFETY: ptr is valid and initialized for layout.size() bytes
+                    let bytes = unsafe { std::slice::from_raw_parts(ptr, layout.size()) };
+                    #[allow(clippy::cast_possible_truncation)]
+                    let expected = idx as u8;
+                    prop_assert!(bytes.iter().all(|&byte| byte == expected));
+                }
+                // SAFETY: ptr was allocated with this layout
+                unsafe { allocator.dealloc(ptr, layout) };
+            }
+        }
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn allocator_api2() {
+        let Some(allocator) = allocator() else {
+            return;
+        };
+        let mut small = allocator_api2::vec::Vec::new_in(allocator.clone());
+        let mut large = allocator_api2::vec::Vec::new_in(allocator);
+        for i in 0..10_000_u32 {
+            if i < 100 {
+                small.push(i);
+            }
+            large.push(i);
+        }
+        assert!(small.iter().copied().eq(0..100));
+        assert!(large.iter().copied().eq(0..10_000));
+        assert_eq!(allocator.policy(), MemoryBindingPolicy::Bind);
+        assert!(allocator.nodeset().is_some());
+        assert!(allocator.cpuset().is_none());
+    }
+}
@@ -0,0 +1,719 @@
+//! Measuring memory performance
+//!
+//! Memory attributes like [`Bandwidth`] and [`Latency`] are only filled by
+//! hwloc when the platform reports them, e.g. via ACPI HMAT tables, which
+//! most machines don't do. This module measures these figures using
+//! microbenchmarks that run on pinned threads, and registers the results as
+//! user-defined memory attributes, so that queries like
+//! [`MemoryAttribute::best_target()`] become usable on such machines.
+//!
+//! Most of this module's functionality is exposed via [methods of the Topology
+//! struct](../../topology/struct.Topology.html#measuring-memory-performance).
+//! The module itself only hosts type definitions that are related to this
+//! functionality.
+//!
+//! [`Bandwidth`]: MemoryAttribute::bandwidth()
+//! [`Latency`]: MemoryAttribute::latency()
+
+#[cfg(doc)]
+use crate::memory::attribute::MemoryAttribute;
+use crate::{
+    bitmap::BitmapRef,
+    cpu::{
+        binding::{CpuBindingError, CpuBindingFlags},
+        cpuset::CpuSet,
+    },
+    errors::HybridError,
+    memory::{
+        attribute::{
+            MemoryAttributeFlags, MemoryAttributeLocation, RegisterError, ValueInputError,
+        },
+        binding::{MemoryAllocationError, MemoryBindingFlags, MemoryBindingPolicy},
+        containers::BoundVec,
+        nodeset::NodeSet,
+    },
+    object::{types::ObjectType, TopologyObject},
+    topology::{editor::TopologyEditor, Topology},
+};
+use std::{
+    hint::black_box,
+    mem, thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// Name of the memory attribute that holds measured read bandwidths
+///
+/// Like hwloc's [`Bandwidth`](MemoryAttribute::bandwidth()) attribute, values
+/// are expressed in MiB/s, higher is best and an initiator is required.
+pub const MEASURED_READ_BANDWIDTH: &str = "MeasuredReadBandwidth";
+
+/// Name of the memory attribute that holds measured write bandwidths
+///
+/// Like hwloc's [`Bandwidth`](MemoryAttribute::bandwidth()) attribute, values
+/// are expressed in MiB/s, higher is best and an initiator is required.
+pub const MEASURED_WRITE_BANDWIDTH: &str = "MeasuredWriteBandwidth";
+
+/// Name of the memory attribute that holds measured access latencies
+///
+/// Like hwloc's [`Latency`](MemoryAttribute::latency()) attribute, values are
+/// expressed in nanoseconds, lower is best and an initiator is required.
+pub const MEASURED_LATENCY: &str = "MeasuredLatency";
+
+/// # Measuring memory performance
+//
+// --- Implementation details ---
+//
+// hwloc only reports the memory performance that is advertised by the
+// firmware (e.g. ACPI HMAT tables), which many machines do not provide. This
+// measures it instead, using the CPU and memory binding APIs.
+impl Topology {
+    /// Measure the performance of each NUMA node from each group of CPUs
+    ///
+    /// Initiators are the distinct CPU sets of the NUMA nodes, which usually
+    /// means one initiator per package or sub-NUMA cluster. For each initiator
+    /// with allowed CPUs, a thread is pinned to its first allowed PU, and then
+    /// measures in turn the read bandwidth, write bandwidth and
+    /// pointer-chasing latency of a buffer that is allocated on each allowed
+    /// NUMA node.
+    ///
+    /// Measurements are run one after the other, so that they do not
+    /// interfere with each other. The whole process takes about
+    /// `3 * initiators * nodes * config.duration()`, plus the time needed to
+    /// allocate and initialize buffers.
+    ///
+    /// The results can be registered as memory attributes of this topology
+    /// using [`TopologyEditor::register_memory_benchmark()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use hwlocality::memory::benchmark::MemoryBenchmarkConfig;
+    /// # use std::time::Duration;
+    /// # let topology = hwlocality::Topology::test_instance();
+    /// let config = MemoryBenchmarkConfig::new()
+    ///     .bandwidth_buffer_size(1 << 20)
+    ///     .latency_buffer_size(1 << 20)
+    ///     .duration(Duration::from_millis(10));
+    /// # // Binding may not be supported by the host where tests are run
+    /// if let Ok(measurements) = topology.benchmark_memory(&config) {
+    ///     for measurement in measurements {
+    ///         println!(
+    ///             "{} -> NUMA node #{}: {} MiB/s read, {} ns latency",
+    ///             measurement.initiator,
+    ///             measurement.target,
+    ///             measurement.read_bandwidth,
+    ///             measurement.latency
+    ///         );
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - [`CpuBinding`] if a benchmark thread could not be pinned to its
+    ///   initiator's CPUs
+    /// - [`Allocation`] if a benchmark buffer could not be allocated on its
+    ///   target NUMA node
+    ///
+    /// [`Allocation`]: MemoryBenchmarkError::Allocation
+    /// [`CpuBinding`]: MemoryBenchmarkError::CpuBinding
+    pub fn benchmark_memory(
+        &self,
+        config: &MemoryBenchmarkConfig,
+    ) -> Result<Vec<MemoryMeasurement>, MemoryBenchmarkError> {
+        // Find the NUMA nodes to be measured, and their distinct CPU sets
+        let allowed_cpuset = self.allowed_cpuset();
+        let allowed_nodeset = self.allowed_nodeset();
+        let targets = self
+            .objects_with_type(ObjectType::NUMANode)
+            .filter_map(TopologyObject::os_index)
+            .filter(|&os_index| allowed_nodeset.is_set(os_index))
+            .collect::<Vec<_>>();
+        let mut initiators = Vec::<CpuSet>::new();
+        for node in self.objects_with_type(ObjectType::NUMANode) {
+            let Some(cpuset) = node.cpuset() else {
+                continue;
+            };
+            if cpuset.intersects(&*allowed_cpuset) && !initiators.contains(&cpuset) {
+                initiators.push(cpuset.clone_target());
+            }
+        }
+
+        // Run the measurements of each initiator on a dedicated pinned thread
+        let mut measurements = Vec::with_capacity(initiators.len() * targets.len());
+        for initiator in initiators {
+            let results = thread::scope(|scope| {
+                scope
+                    .spawn(|| self.benchmark_initiator(&initiator, &targets, config))
+                    .join()
+                    .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+            })?;
+            measurements.extend(results);
+        }
+        Ok(measurements)
+    }
+
+    /// Pin the current thread to the first PU of `initiator`, then measure
+    /// the performance of each of the `targets` NUMA nodes from there
+    fn benchmark_initiator(
+        &self,
+        initiator: &CpuSet,
+        targets: &[usize],
+        config: &MemoryBenchmarkConfig,
+    ) -> Result<Vec<MemoryMeasurement>, MemoryBenchmarkError> {
+        let mut pinned_cpu = initiator & self.allowed_cpuset();
+        pinned_cpu.singlify();
+        self.bind_cpu(&pinned_cpu, CpuBindingFlags::THREAD)
+            .map_err(|error| MemoryBenchmarkError::CpuBinding {
+                initiator: initiator.clone(),
+                error,
+            })?;
+
+        let mut measurements = Vec::with_capacity(targets.len());
+        for &target in targets {
+            let mut nodeset = NodeSet::new();
+            nodeset.set(target);
+            let allocation_error = |error| MemoryBenchmarkError::Allocation { target, error };
+
+            let mut words = self
+                .benchmark_buffer(
+                    config.bandwidth_buffer_size / mem::size_of::<u64>(),
+                    &nodeset,
+                )
+                .map_err(allocation_error)?;
+            let read_bandwidth = measure_read_bandwidth(&words, config.duration);
+            let write_bandwidth = measure_write_bandwidth(&mut words, config.duration);
+            mem::drop(words);
+
+            let mut chain = self
+                .benchmark_buffer(
+                    config.latency_buffer_size / mem::size_of::<usize>(),
+                    &nodeset,
+                )
+                .map_err(allocation_error)?;
+            let latency = measure_latency(&mut chain, config.duration);
+
+            measurements.push(MemoryMeasurement {
+                initiator: initiator.clone(),
+                target,
+                read_bandwidth,
+                write_bandwidth,
+                latency,
+            });
+        }
+        Ok(measurements)
+    }
+
+    /// Allocate a zeroed buffer of `len` elements (at least one cache line)
+    /// on the NUMA nodes of `nodeset`
+    fn benchmark_buffer<T: Copy + Default>(
+        &self,
+        len: usize,
+        nodeset: &NodeSet,
+    ) -> Result<BoundVec<'_, T>, MemoryAllocationError<NodeSet>> {
+        let len = len.max(CACHE_LINE_SIZE / mem::size_of::<T>());
+        let mut buffer = BoundVec::with_capacity(
+            self,
+            len,
+            nodeset,
+            MemoryBindingPolicy::Bind,
+            MemoryBindingFlags::ASSUME_SINGLE_THREAD,
+        )?;
+        for _ in 0..len {
+            buffer.push(T::default()).map_err(|(_value, error)| error)?;
+        }
+        Ok(buffer)
+    }
+}
+
+/// # Registering memory performance measurements
+//
+// --- Implementation details ---
+//
+// Measurements are registered as custom memory attributes with well-known
+// names, so that they can be queried like hwloc's own memory attributes.
+impl TopologyEditor<'_> {
+    /// Register the results of [`Topology::benchmark_memory()`] as memory
+    /// attributes
+    ///
+    /// This registers the [`MEASURED_READ_BANDWIDTH`],
+    /// [`MEASURED_WRITE_BANDWIDTH`] and [`MEASURED_LATENCY`] attributes, which
+    /// can then be looked up using [`Topology::memory_attribute_named()`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Register`] if these attributes could not be registered, e.g.
+    ///   because measurements were already registered in this topology
+    /// - [`UnknownTarget`] if a measurement targets a NUMA node that is not
+    ///   part of this topology
+    /// - [`UnknownInitiator`] if a measurement's initiator is not the CPU set
+    ///   of an object of this topology
+    /// - [`Values`] if hwloc failed to record a measurement
+    ///
+    /// All of the above conditions are checked before anything is registered,
+    /// except for [`Values`] errors that originate from hwloc itself (e.g. a
+    /// memory allocation failure). In that case, the attributes that were
+    /// registered before the failure are left in place, possibly without
+    /// values, as hwloc provides no way to unregister memory attributes.
+    ///
+    /// [`Register`]: RegisterBenchmarkError::Register
+    /// [`UnknownInitiator`]: RegisterBenchmarkError::UnknownInitiator
+    /// [`UnknownTarget`]: RegisterBenchmarkError::UnknownTarget
+    /// [`Values`]: RegisterBenchmarkError::Values
+    pub fn register_memory_benchmark(
+        &mut self,
+        measurements: &[MemoryMeasurement],
+    ) -> Result<(), RegisterBenchmarkError> {
+        // Each measured quantity is registered as a memory attribute
+        /// Accessor to one of the measured quantities
+        type Quantity = fn(&MemoryMeasurement) -> u64;
+        let attributes: [(&str, MemoryAttributeFlags, Quantity); 3] = [
+            (
+                MEASURED_READ_BANDWIDTH,
+                MemoryAttributeFlags::HIGHER_IS_BEST,
+                |measurement| measurement.read_bandwidth,
+            ),
+            (
+                MEASURED_WRITE_BANDWIDTH,
+                MemoryAttributeFlags::HIGHER_IS_BEST,
+                |measurement| measurement.write_bandwidth,
+            ),
+            (
+                MEASURED_LATENCY,
+                MemoryAttributeFlags::LOWER_IS_BEST,
+                |measurement| measurement.latency,
+            ),
+        ];
+
+        // Check everything that can be checked before registering anything,
+        // since hwloc cannot unregister attributes if a later step fails
+        let topology = self.topology();
+        for (name, _, _) in &attributes {
+            let existing = topology
+                .memory_attribute_named(name)
+                .expect("Benchmark attribute names should not contain NUL");
+            if existing.is_some() {
+                return Err(RegisterError::NameTaken((*name).into()).into());
+            }
+        }
+        for measurement in measurements {
+            if find_target(topology, measurement.target).is_none() {
+                return Err(RegisterBenchmarkError::UnknownTarget(measurement.target));
+            }
+            if find_initiator(topology, &measurement.initiator).is_none() {
+                return Err(RegisterBenchmarkError::UnknownInitiator(
+                    measurement.initiator.clone(),
+                ));
+            }
+        }
+
+        // Register the attributes and their values
+        for (name, flags, value) in attributes {
+            let mut builder =
+                self.register_memory_attribute(name, flags | MemoryAttributeFlags::NEED_INITIATOR)?;
+            builder.set_values(|topology| {
+                let initiators = measurements
+                    .iter()
+                    .map(|measurement| {
+                        let initiator = find_initiator(topology, &measurement.initiator)
+                            .expect("Checked above that all initiators exist");
+                        MemoryAttributeLocation::CpuSet(initiator)
+                    })
+                    .collect();
+                let targets_and_values = measurements
+                    .iter()
+                    .map(|measurement| {
+                        let target = find_target(topology, measurement.target)
+                            .expect("Checked above that all targets exist");
+                        (target, value(measurement))
+                    })
+                    .collect();
+                (Some(initiators), targets_and_values)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the NUMA node with OS index `os_index`
+fn find_target(topology: &Topology, os_index: usize) -> Option<&TopologyObject> {
+    topology
+        .objects_with_type(ObjectType::NUMANode)
+        .find(|node| node.os_index() == Some(os_index))
+}
+
+/// Find a CPU set equal to `initiator` within the objects of `topology`
+///
+/// Memory attribute values must be set using initiators that borrow from the
+/// topology, so the CPU set of an object is used instead of `initiator`.
+fn find_initiator<'topology>(
+    topology: &'topology Topology,
+    initiator: &CpuSet,
+) -> Option<BitmapRef<'topology, CpuSet>> {
+    topology
+        .objects()
+        .filter_map(TopologyObject::cpuset)
+        .find(|cpuset| **cpuset == *initiator)
+}
+
+/// Configuration of [`Topology::benchmark_memory()`]
+///
+/// The default configuration uses 64 MiB buffers, which should be large
+/// enough to defeat the CPU caches of most machines, and runs each
+/// measurement for 100ms.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MemoryBenchmarkConfig {
+    /// Size of the buffers used for bandwidth measurements, in bytes
+    bandwidth_buffer_size: usize,
+
+    /// Size of the buffers used for latency measurements, in bytes
+    latency_buffer_size: usize,
+
+    /// Minimal duration of each measurement
+    duration: Duration,
+}
+//
+impl MemoryBenchmarkConfig {
+    /// Start from the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Size of the buffers used for bandwidth measurements, in bytes
+    pub fn bandwidth_buffer_size(mut self, bytes: usize) -> Self {
+        self.bandwidth_buffer_size = bytes;
+        self
+    }
+
+    /// Size of the buffers used for latency measurements, in bytes
+    ///
+    /// This should be much larger than the CPU caches, otherwise the cache
+    /// latency will be measured instead of the memory latency.
+    pub fn latency_buffer_size(mut self, bytes: usize) -> Self {
+        self.latency_buffer_size = bytes;
+        self
+    }
+
+    /// Minimal duration of each measurement
+    ///
+    /// Each (initiator, target) pair undergoes three measurements. A
+    /// measurement may run for longer than this if a single pass over its
+    /// buffer takes longer.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+}
+//
+impl Default for MemoryBenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            bandwidth_buffer_size: 64 << 20,
+            latency_buffer_size: 64 << 20,
+            duration: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Performance of a NUMA node as seen from a group of CPUs
+///
+/// This is produced by [`Topology::benchmark_memory()`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MemoryMeasurement {
+    /// CPUs from which the measurement was performed
+    ///
+    /// This is the CPU set of a NUMA node. The measurement thread was pinned
+    /// to the first allowed PU of this set.
+    pub initiator: CpuSet,
+
+    /// OS index of the NUMA node that was measured
+    pub target: usize,
+
+    /// Sequential read bandwidth in MiB/s
+    pub read_bandwidth: u64,
+
+    /// Sequential write bandwidth in MiB/s
+    pub write_bandwidth: u64,
+
+    /// Average latency of dependent random accesses in nanoseconds
+    pub latency: u64,
+}
+
+/// Error returned by [`Topology::benchmark_memory()`]
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum MemoryBenchmarkError {
+    /// Failed to pin a benchmark thread to an initiator's CPUs
+    #[error("failed to pin benchmark thread to CPUs {initiator}: {error}")]
+    CpuBinding {
+        /// CPUs that the thread should have been pinned to
+        initiator: CpuSet,
+
+        /// Binding error
+        error: CpuBindingError,
+    },
+
+    /// Failed to allocate a benchmark buffer on a NUMA node
+    #[error("failed to allocate benchmark buffer on NUMA node #{target}: {error}")]
+    Allocation {
+        /// OS index of the target NUMA node
+        target: usize,
+
+        /// Allocation error
+        error: MemoryAllocationError<NodeSet>,
+    },
+}
+
+/// Error returned by [`TopologyEditor::register_memory_benchmark()`]
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum RegisterBenchmarkError {
+    /// Failed to register a memory attribute
+    #[error(transparent)]
+    Register(#[from] RegisterError),
+
+    /// A measurement targets a NUMA node that is not part of the topology
+    #[error("NUMA node #{0} is not part of this topology")]
+    UnknownTarget(usize),
+
+    /// A measurement's initiator is not the CPU set of a topology object
+    #[error("CPU set {0} is not the CPU set of an object of this topology")]
+    UnknownInitiator(CpuSet),
+
+    /// Failed to set the values of a memory attribute
+    #[error(transparent)]
+    Values(#[from] HybridError<ValueInputError>),
+}
+
+/// Assumed CPU cache line size in bytes
+///
+/// Latency measurements make a single access per cache line.
+const CACHE_LINE_SIZE: usize = 64;
+
+/// Measure the bandwidth of summing `words` for at least `duration`
+fn measure_read_bandwidth(words: &[u64], duration: Duration) -> u64 {
+    let start = Instant::now();
+    let mut passes = 0u64;
+    loop {
+        let sum = words.iter().fold(0u64, |acc, &word| acc.wrapping_add(word));
+        black_box(sum);
+        passes += 1;
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            return mib_per_second(passes, mem::size_of_val(words), elapsed);
+        }
+    }
+}
+
+/// Measure the bandwidth of overwriting `words` for at least `duration`
+fn measure_write_bandwidth(words: &mut [u64], duration: Duration) -> u64 {
+    let start = Instant::now();
+    let mut passes = 0u64;
+    loop {
+        words.fill(passes);
+        black_box(&mut *words);
+        passes += 1;
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            return mib_per_second(passes, mem::size_of_val(words), elapsed);
+        }
+    }
+}
+
+/// Measure the average latency of chasing pointers through `chain` for at
+/// least `duration`, in nanoseconds
+///
+/// `chain` is first set up as a random cyclic chain that visits each of its
+/// cache lines once, so that hardware prefetchers cannot predict accesses.
+fn measure_latency(chain: &mut [usize], duration: Duration) -> u64 {
+    // Link cache lines in a random cycle using Sattolo's algorithm
+    let stride = (CACHE_LINE_SIZE / mem::size_of::<usize>()).max(1);
+    let num_lines = chain.len() / stride;
+    let mut next_line = (0..num_lines).collect::<Vec<_>>();
+    let mut rng_state = 0x9E37_79B9_7F4A_7C15_u64;
+    for line in (1..num_lines).rev() {
+        rng_state ^= rng_state << 13;
+        rng_state ^= rng_state >> 7;
+        rng_state ^= rng_state << 17;
+        let modulus = u64::try_from(line).expect("Index should fit in u64");
+        let other = usize::try_from(rng_state % modulus).expect("Result is below an usize");
+        next_line.swap(line, other);
+    }
+    for (line, next) in next_line.into_iter().enumerate() {
+        chain[line * stride] = next * stride;
+    }
+
+    // Chase pointers, checking the clock every few accesses
+    const HOPS_PER_CHECK: u64 = 1024;
+    let start = Instant::now();
+    let mut position = 0;
+    let mut hops = 0u64;
+    loop {
+        for _ in 0..HOPS_PER_CHECK {
+            position = chain[position];
+        }
+        black_box(position);
+        hops += HOPS_PER_CHECK;
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            let latency = elapsed.as_nanos() / u128::from(hops);
+            return u64::try_from(latency).unwrap_or(u64::MAX);
+        }
+    }
+}
+
+/// Convert `passes` over a buffer of `bytes` in `elapsed` time to MiB/s
+fn mib_per_second(passes: u64, bytes: usize, elapsed: Duration) -> u64 {
+    let bytes = u128::from(passes) * u128::try_from(bytes).expect("usize should fit in u128");
+    let nanos = elapsed.as_nanos().max(1);
+    let bandwidth = bytes * 1_000_000_000 / (nanos << 20);
+    u64::try_from(bandwidth).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(MemoryBenchmarkConfig:
+        Copy, Debug, Default, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(MemoryBenchmarkConfig:
+        Binary, Deref, Display, Drop, Error, IntoIterator, LowerExp, LowerHex,
+        Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+    assert_impl_all!(MemoryMeasurement:
+        Clone, Debug, Eq, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(MemoryMeasurement:
+        Binary, Copy, Default, Deref, Display, Drop, Error, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialOrd, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(MemoryBenchmarkError:
+        Clone, Error, Eq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(RegisterBenchmarkError:
+        Clone, Error, Eq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+
+    #[test]
+    fn kernels() {
+        let duration = Duration::from_millis(1);
+        let mut words = vec![1u64; 1024];
+        assert!(measure_read_bandwidth(&words, duration) > 0);
+        assert!(measure_write_bandwidth(&mut words, duration) > 0);
+        assert!(words.iter().all(|&word| word == words[0]));
+
+        // The pointer chain must visit every cache line before looping
+        let mut chain = vec![0usize; 1024];
+        measure_latency(&mut chain, duration);
+        let stride = CACHE_LINE_SIZE / mem::size_of::<usize>();
+        let mut position = 0;
+        let mut visited = 0;
+        loop {
+            position = chain[position];
+            visited += 1;
+            assert_eq!(position % stride, 0);
+            if position == 0 {
+                break;
+            }
+        }
+        assert_eq!(visited, chain.len() / stride);
+    }
+
+    #[test]
+    fn mib_per_second() {
+        assert_eq!(super::mib_per_second(3, 1 << 20, Duration::from_secs(1)), 3);
+        assert_eq!(
+            super::mib_per_second(1, 1 << 30, Duration::from_millis(500)),
+            2048
+        );
+    }
+
+    #[test]
+    fn benchmark_and_register() {
+        let config = MemoryBenchmarkConfig::new()
+            .bandwidth_buffer_size(1 << 16)
+            .latency_buffer_size(1 << 16)
+            .duration(Duration::from_millis(1));
+        let mut topology = Topology::test_instance().clone();
+        // Binding may not be supported by the host where tests are run
+        let Ok(measurements) = topology.benchmark_memory(&config) else {
+            return;
+        };
+        let num_nodes = topology.allowed_nodeset().weight().unwrap();
+        assert!(measurements.len() >= num_nodes);
+        for measurement in &measurements {
+            assert!(topology.allowed_nodeset().is_set(measurement.target));
+            assert!(topology.allowed_cpuset().intersects(&measurement.initiator));
+            assert!(measurement.read_bandwidth > 0);
+            assert!(measurement.write_bandwidth > 0);
+        }
+
+        // Invalid measurements and name conflicts are reported before
+        // anything is registered
+        let mut conflicting = topology.clone();
+        conflicting.edit(|editor| {
+            assert_eq!(
+                editor.register_memory_benchmark(&[MemoryMeasurement {
+                    target: usize::MAX,
+                    ..measurements[0].clone()
+                }]),
+                Err(RegisterBenchmarkError::UnknownTarget(usize::MAX))
+            );
+            editor
+                .register_memory_attribute(MEASURED_LATENCY, MemoryAttributeFlags::LOWER_IS_BEST)
+                .unwrap();
+            assert_eq!(
+                editor.register_memory_benchmark(&measurements),
+                Err(RegisterBenchmarkError::Register(RegisterError::NameTaken(
+                    MEASURED_LATENCY.into()
+                )))
+            );
+        });
+        for name in [MEASURED_READ_BANDWIDTH, MEASURED_WRITE_BANDWIDTH] {
+            assert!(conflicting.memory_attribute_named(name).unwrap().is_none());
+        }
+
+        // Valid measurements can be registered once
+        topology.edit(|editor| {
+            editor.register_memory_benchmark(&measurements).unwrap();
+            assert_eq!(
+                editor.register_memory_benchmark(&measurements),
+                Err(RegisterBenchmarkError::Register(RegisterError::NameTaken(
+                    MEASURED_READ_BANDWIDTH.into()
+                )))
+            );
+        });
+        for name in [
+            MEASURED_READ_BANDWIDTH,
+            MEASURED_WRITE_BANDWIDTH,
+            MEASURED_LATENCY,
+        ] {
+            let attribute = topology.memory_attribute_named(name).unwrap().unwrap();
+            for measurement in &measurements {
+                let initiator = MemoryAttributeLocation::from(&measurement.initiator);
+                let target = topology
+                    .objects_with_type(ObjectType::NUMANode)
+                    .find(|node| node.os_index() == Some(measurement.target))
+                    .unwrap();
+                attribute.value(Some(initiator), target).unwrap();
+            }
+        }
+    }
+}
@@ -30,6 +30,8 @@ use hwlocality_sys::{
 };
 use libc::{ENOMEM, ENOSYS, EXDEV};
 use num_enum::{IntoPrimitive, TryFromPrimitive, TryFromPrimitiveError};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 #[allow(unused)]
 #[cfg(test)]
 use similar_asserts::assert_eq;
@@ -37,6 +39,7 @@ use std::{
     borrow::{Borrow, BorrowMut},
     ffi::{c_int, c_void},
     fmt::{self, Debug, Display},
+    marker::PhantomData,
     mem::MaybeUninit,
     ops::{Deref, DerefMut},
     ptr::{self, NonNull},
@@ -584,6 +587,84 @@ impl Topology {
         }
     }
 
+    /// Temporarily set the default memory binding policy of the current
+    /// process or thread
+    ///
+    /// This queries the current memory binding and policy of the process or
+    /// thread, then binds it like [`Topology::bind_memory()`] would. The
+    /// returned guard restores the previous binding and policy when it is
+    /// dropped, including when the code that holds it returns early or
+    /// panics.
+    ///
+    /// Depending on the flags and operating system, the binding may target the
+    /// current thread and is then restored on the thread where the guard is
+    /// dropped. This is why the guard cannot be sent to another thread.
+    ///
+    /// `flags` are used both to bind and to restore the previous binding, with
+    /// the exception of [`STRICT`], [`MIGRATE`] and [`NO_CPU_BINDING`] which
+    /// are not used when querying the previous binding.
+    ///
+    /// Requires the support flags of both [`Topology::memory_binding()`] and
+    /// [`Topology::bind_memory()`].
+    ///
+    /// This functionality is specific to the Rust bindings.
+    ///
+    /// # Errors
+    ///
+    /// - [`MixedResults`] if the [`PROCESS`] flag is specified and the threads
+    ///   of the process do not share a single memory binding policy, which
+    ///   could therefore not be restored
+    /// - Any other error reported by [`Topology::memory_binding()`] or
+    ///   [`Topology::bind_memory()`]
+    ///
+    /// The binding is left unchanged if an error occurs.
+    ///
+    /// [`MIGRATE`]: MemoryBindingFlags::MIGRATE
+    /// [`MixedResults`]: MemoryBindingError::MixedResults
+    /// [`NO_CPU_BINDING`]: MemoryBindingFlags::NO_CPU_BINDING
+    /// [`PROCESS`]: MemoryBindingFlags::PROCESS
+    /// [`STRICT`]: MemoryBindingFlags::STRICT
+    pub fn bind_memory_scoped<Set: SpecializedBitmap>(
+        &self,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<LocalMemoryBindingGuard<'_, Set::Owned>, MemoryBindingError<Set::Owned>> {
+        Ok(LocalMemoryBindingGuard(
+            MemoryBindingGuard::new(self, MemoryBoundObject::ThisProgram, set, policy, flags)?,
+            PhantomData,
+        ))
+    }
+
+    /// Temporarily set the default memory binding policy of the specified
+    /// process
+    ///
+    /// This is the scoped variant of [`Topology::bind_process_memory()`]. See
+    /// [`Topology::bind_memory_scoped()`] for more information about scoped
+    /// binding.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    ///
+    /// # Errors
+    ///
+    /// - [`MixedResults`] if the threads of the process do not share a single
+    ///   memory binding policy, which could therefore not be restored
+    /// - Any other error reported by [`Topology::process_memory_binding()`] or
+    ///   [`Topology::bind_process_memory()`]
+    ///
+    /// The binding is left unchanged if an error occurs.
+    ///
+    /// [`MixedResults`]: MemoryBindingError::MixedResults
+    pub fn bind_process_memory_scoped<Set: SpecializedBitmap>(
+        &self,
+        pid: ProcessId,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<MemoryBindingGuard<'_, Set::Owned>, MemoryBindingError<Set::Owned>> {
+        MemoryBindingGuard::new(self, MemoryBoundObject::Process(pid), set, policy, flags)
+    }
+
     /// Bind the memory identified by `target` to the NUMA node(s) specified by
     /// `set`
     ///
@@ -1073,6 +1154,7 @@ bitflags! {
     /// Please check the documentation of the [memory binding
     /// method](../../topology/struct.Topology.html#memory-binding) that you are
     /// calling for more information.
+    #[cfg_attr(feature = "serde", derive(Deserialize, Serialize), serde(transparent))]
     #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
     #[doc(alias = "hwloc_membind_flags_t")]
     pub struct MemoryBindingFlags: hwloc_membind_flags_t {
@@ -1224,6 +1306,7 @@ crate::impl_arbitrary_for_bitflags!(MemoryBindingFlags, hwloc_membind_flags_t);
 // NOTE: No default because user must consciously think about the need for PROCESS
 
 /// Object that is being bound to particular NUMA nodes
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum MemoryBoundObject {
     /// A process, identified by its PID
@@ -1253,6 +1336,182 @@ impl From<ProcessId> for MemoryBoundObject {
     }
 }
 
+/// Guard that restores the previous memory binding and policy of a process
+/// when dropped
+///
+/// This `struct` is created by the [`Topology::bind_process_memory_scoped()`]
+/// method. See its documentation for more information.
+///
+/// Since the target process is designated by its PID, the binding can be
+/// restored from any thread, so this guard can be sent to another thread.
+///
+/// Errors that occur while restoring the previous binding on drop are
+/// ignored. Use [`MemoryBindingGuard::restore()`] if you need to handle them.
+#[derive(Debug)]
+#[must_use = "The previous memory binding is restored as soon as the guard is dropped"]
+pub struct MemoryBindingGuard<'topology, OwnedSet: OwnedSpecializedBitmap> {
+    /// Topology that was used to bind the target
+    topology: &'topology Topology,
+
+    /// Object whose memory binding was changed
+    target: MemoryBoundObject,
+
+    /// Memory binding and policy to be restored, or None if they have already
+    /// been restored
+    previous: Option<(OwnedSet, MemoryBindingPolicy)>,
+
+    /// Flags that were used to bind the target
+    flags: MemoryBindingFlags,
+}
+//
+impl<'topology, OwnedSet: OwnedSpecializedBitmap> MemoryBindingGuard<'topology, OwnedSet> {
+    /// Query the current binding of `target`, then bind it to `set`
+    fn new<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        target: MemoryBoundObject,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<Self, MemoryBindingError<OwnedSet>> {
+        let query_flags = flags
+            - (MemoryBindingFlags::STRICT
+                | MemoryBindingFlags::MIGRATE
+                | MemoryBindingFlags::NO_CPU_BINDING);
+        let (previous_set, previous_policy) = match target {
+            MemoryBoundObject::Process(pid) => topology.process_memory_binding(pid, query_flags)?,
+            MemoryBoundObject::ThisProgram => topology.memory_binding(query_flags)?,
+            MemoryBoundObject::Area => unreachable!("Memory areas have no scoped binding"),
+        };
+        let previous_policy = previous_policy.ok_or(MemoryBindingError::MixedResults)?;
+        Self::bind(topology, target, set.borrow(), policy, flags)?;
+        Ok(Self {
+            topology,
+            target,
+            previous: Some((previous_set, previous_policy)),
+            flags,
+        })
+    }
+
+    /// Object whose memory binding will be restored
+    pub fn target(&self) -> MemoryBoundObject {
+        self.target
+    }
+
+    /// Memory binding that will be restored
+    pub fn previous_binding(&self) -> &OwnedSet {
+        &self.previous().0
+    }
+
+    /// Memory binding policy that will be restored
+    pub fn previous_policy(&self) -> MemoryBindingPolicy {
+        self.previous().1
+    }
+
+    /// Restore the previous memory binding and policy now, reporting errors
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by the underlying memory binding method.
+    pub fn restore(mut self) -> Result<(), MemoryBindingError<OwnedSet>> {
+        self.restore_impl()
+    }
+
+    /// Keep the current memory binding, and get the previous binding and
+    /// policy back
+    pub fn keep_current(mut self) -> (OwnedSet, MemoryBindingPolicy) {
+        self.previous
+            .take()
+            .expect("Previous binding is only taken out on restore or drop")
+    }
+
+    /// Previous memory binding and policy
+    fn previous(&self) -> &(OwnedSet, MemoryBindingPolicy) {
+        self.previous
+            .as_ref()
+            .expect("Previous binding is only taken out on restore or drop")
+    }
+
+    /// Set the memory binding of the target
+    fn bind(
+        topology: &Topology,
+        target: MemoryBoundObject,
+        set: &OwnedSet,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<(), MemoryBindingError<OwnedSet>> {
+        match target {
+            MemoryBoundObject::Process(pid) => {
+                topology.bind_process_memory(pid, set, policy, flags)
+            }
+            MemoryBoundObject::ThisProgram => topology.bind_memory(set, policy, flags),
+            MemoryBoundObject::Area => unreachable!("Memory areas have no scoped binding"),
+        }
+    }
+
+    /// Restore the previous memory binding, if it has not been done already
+    fn restore_impl(&mut self) -> Result<(), MemoryBindingError<OwnedSet>> {
+        self.previous.take().map_or(Ok(()), |(set, policy)| {
+            Self::bind(self.topology, self.target, &set, policy, self.flags)
+        })
+    }
+}
+//
+impl<OwnedSet: OwnedSpecializedBitmap> Drop for MemoryBindingGuard<'_, OwnedSet> {
+    fn drop(&mut self) {
+        // Drop cannot report errors and should not panic, as it may run during
+        // unwinding. Users who care about errors should call restore().
+        #[allow(let_underscore_drop)]
+        let _ = self.restore_impl();
+    }
+}
+
+/// Guard that restores the previous memory binding and policy of the current
+/// process or thread when dropped
+///
+/// This `struct` is created by the [`Topology::bind_memory_scoped()`] method.
+/// See its documentation for more information.
+///
+/// Unlike [`MemoryBindingGuard`], this guard cannot be sent to another thread,
+/// because the binding may target the current thread, in which case it must
+/// be restored on the thread where it was changed.
+///
+/// Errors that occur while restoring the previous binding on drop are
+/// ignored. Use [`LocalMemoryBindingGuard::restore()`] if you need to handle
+/// them.
+#[derive(Debug)]
+#[must_use = "The previous memory binding is restored as soon as the guard is dropped"]
+pub struct LocalMemoryBindingGuard<'topology, OwnedSet: OwnedSpecializedBitmap>(
+    MemoryBindingGuard<'topology, OwnedSet>,
+    PhantomData<*const ()>,
+);
+//
+impl<OwnedSet: OwnedSpecializedBitmap> LocalMemoryBindingGuard<'_, OwnedSet> {
+    /// Memory binding that will be restored
+    pub fn previous_binding(&self) -> &OwnedSet {
+        self.0.previous_binding()
+    }
+
+    /// Memory binding policy that will be restored
+    pub fn previous_policy(&self) -> MemoryBindingPolicy {
+        self.0.previous_policy()
+    }
+
+    /// Restore the previous memory binding and policy now, reporting errors
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by [`Topology::bind_memory()`].
+    pub fn restore(self) -> Result<(), MemoryBindingError<OwnedSet>> {
+        self.0.restore()
+    }
+
+    /// Keep the current memory binding, and get the previous binding and
+    /// policy back
+    pub fn keep_current(self) -> (OwnedSet, MemoryBindingPolicy) {
+        self.0.keep_current()
+    }
+}
+
 /// Binding operation
 #[derive(Copy, Clone, Debug, Display, Eq, Hash, PartialEq)]
 pub(crate) enum MemoryBindingOperation {
@@ -1278,6 +1537,7 @@ pub(crate) enum MemoryBindingOperation {
 /// [`Topology::feature_support()`] may be used to query the
 /// actual memory binding support in the currently used operating system.
 #[cfg_attr(any(test, feature = "proptest"), derive(Sequence))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(
     Copy, Clone, Debug, Default, Display, Eq, Hash, IntoPrimitive, PartialEq, TryFromPrimitive,
 )]
@@ -1505,6 +1765,21 @@ impl<'topology> Bytes<'topology> {
             data: NonNull::slice_from_raw_parts(base.cast::<MaybeUninit<u8>>(), size),
         }
     }
+
+    /// Topology that this memory was allocated from
+    pub(crate) fn topology(&self) -> &'topology Topology {
+        self.topology
+    }
+
+    /// Give up ownership of the allocation without liberating it
+    ///
+    /// The allocation can later be liberated by passing its base pointer and
+    /// size back to [`Bytes::wrap()`], then dropping the result.
+    pub(crate) fn into_raw(self) -> NonNull<[MaybeUninit<u8>]> {
+        let data = self.data;
+        std::mem::forget(self);
+        data
+    }
 }
 //
 impl AsRef<[MaybeUninit<u8>]> for Bytes<'_> {
@@ -1583,3 +1858,112 @@ unsafe impl Send for Bytes<'_> {}
 //
 // SAFETY: Exposes no internal mutability
 unsafe impl Sync for Bytes<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topology::support::{FeatureSupport, MemoryBindingSupport};
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{Binary, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex},
+        hash::Hash,
+        io::{self, Read},
+        ops::Drop,
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(MemoryBindingGuard<'static, NodeSet>:
+        Debug, Drop, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(MemoryBindingGuard<'static, NodeSet>:
+        Binary, Clone, Default, Deref, Display, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp,
+        UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(LocalMemoryBindingGuard<'static, NodeSet>:
+        Debug, Sized, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(LocalMemoryBindingGuard<'static, NodeSet>:
+        Binary, Clone, Default, Deref, Display, Drop, Error, Hash,
+        IntoIterator, LowerExp, LowerHex, Octal, PartialEq, Pointer, Read,
+        Send, Sync, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+
+    #[test]
+    fn scoped_binding() {
+        let topology = Topology::test_instance();
+        if !(topology.supports(
+            FeatureSupport::memory_binding,
+            MemoryBindingSupport::get_current_thread,
+        ) && topology.supports(
+            FeatureSupport::memory_binding,
+            MemoryBindingSupport::set_current_thread,
+        ) && topology.supports(
+            FeatureSupport::memory_binding,
+            MemoryBindingSupport::bind_policy,
+        )) {
+            return;
+        }
+        let flags = MemoryBindingFlags::THREAD;
+        let (initial_set, initial_policy) = topology.memory_binding::<NodeSet>(flags).unwrap();
+        let Some(initial_policy) = initial_policy else {
+            return;
+        };
+        let mut target = initial_set.clone();
+        target.singlify();
+        let policy = MemoryBindingPolicy::Bind;
+        let check_binding = |expected_set: &NodeSet, expected_policy| {
+            let (set, policy) = topology.memory_binding::<NodeSet>(flags).unwrap();
+            assert_eq!(&set, expected_set);
+            assert_eq!(policy, Some(expected_policy));
+        };
+
+        // Dropping the guard restores the previous binding
+        {
+            let guard = topology.bind_memory_scoped(&target, policy, flags).unwrap();
+            assert_eq!(guard.previous_binding(), &initial_set);
+            assert_eq!(guard.previous_policy(), initial_policy);
+            check_binding(&target, policy);
+        }
+        check_binding(&initial_set, initial_policy);
+
+        // So does an explicit restore()
+        let guard = topology.bind_memory_scoped(&target, policy, flags).unwrap();
+        check_binding(&target, policy);
+        guard.restore().unwrap();
+        check_binding(&initial_set, initial_policy);
+
+        // keep_current() keeps the new binding
+        let guard = topology.bind_memory_scoped(&target, policy, flags).unwrap();
+        assert_eq!(guard.keep_current(), (initial_set.clone(), initial_policy));
+        check_binding(&target, policy);
+        topology
+            .bind_memory(&initial_set, initial_policy, flags)
+            .unwrap();
+    }
+
+    #[test]
+    fn scoped_binding_error() {
+        let topology = Topology::test_instance();
+        let initial = topology
+            .memory_binding::<NodeSet>(MemoryBindingFlags::THREAD)
+            .ok();
+        let result = topology.bind_memory_scoped(
+            &*topology.nodeset(),
+            MemoryBindingPolicy::Bind,
+            MemoryBindingFlags::PROCESS | MemoryBindingFlags::THREAD,
+        );
+        assert!(matches!(result, Err(MemoryBindingError::BadFlags(_))));
+        assert_eq!(
+            topology
+                .memory_binding::<NodeSet>(MemoryBindingFlags::THREAD)
+                .ok(),
+            initial
+        );
+    }
+}
@@ -0,0 +1,912 @@
+//! Typed containers of bound memory
+//!
+//! [`Bytes`] gives you raw uninitialized bytes, which is the lowest common
+//! denominator of all memory allocations but is not very convenient to use.
+//! This module provides [`BoundBox`] and [`BoundVec`], which behave like their
+//! standard library namesakes but reside on specific NUMA nodes.
+//!
+//! These containers remember which memory binding they were created with, so
+//! that any reallocation ends up on the same NUMA nodes. They also let you
+//! check where their memory actually resides and move it elsewhere.
+
+use crate::{
+    bitmap::{OwnedSpecializedBitmap, SpecializedBitmap},
+    memory::{
+        binding::{
+            Bytes, MemoryAllocationError, MemoryBindingError, MemoryBindingFlags,
+            MemoryBindingPolicy,
+        },
+        nodeset::NodeSet,
+    },
+    topology::Topology,
+};
+#[cfg(doc)]
+use crate::{cpu::cpuset::CpuSet, topology::support::MemoryBindingSupport};
+use std::{
+    borrow::{Borrow, BorrowMut},
+    fmt::{self, Debug},
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+/// Largest alignment that hwloc allocations are guaranteed to have
+///
+/// hwloc allocates page-aligned memory, and pages are at least this large on
+/// all supported operating systems.
+const MAX_ALIGN: usize = 4096;
+
+/// Memory binding that a container's allocations are subjected to
+#[derive(Clone, Debug)]
+struct AllocationBinding<OwnedSet: OwnedSpecializedBitmap> {
+    /// CPUs or NUMA nodes that memory is bound to
+    set: OwnedSet,
+
+    /// Memory binding policy
+    policy: MemoryBindingPolicy,
+
+    /// Memory binding flags
+    flags: MemoryBindingFlags,
+
+    /// Truth that [`Topology::binding_allocate_memory()`] should be used
+    /// instead of [`Topology::allocate_bound_memory()`]
+    rebind_fallback: bool,
+}
+//
+impl<OwnedSet: OwnedSpecializedBitmap> AllocationBinding<OwnedSet> {
+    /// Allocate `len` bytes with this memory binding
+    fn allocate<'topology>(
+        &self,
+        topology: &'topology Topology,
+        len: usize,
+    ) -> Result<Bytes<'topology>, MemoryAllocationError<OwnedSet>> {
+        if self.rebind_fallback {
+            topology.binding_allocate_memory(len, &self.set, self.policy, self.flags)
+        } else {
+            topology.allocate_bound_memory(len, &self.set, self.policy, self.flags)
+        }
+    }
+
+    /// Migrate `bytes` to the NUMA nodes specified by `set` and update this
+    /// memory binding accordingly
+    fn rebind<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        &mut self,
+        bytes: &Bytes<'_>,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+    ) -> Result<(), MemoryBindingError<OwnedSet>> {
+        if !bytes.is_empty() {
+            bytes.topology().bind_memory_area(
+                &**bytes,
+                set,
+                policy,
+                self.flags | MemoryBindingFlags::MIGRATE,
+            )?;
+        }
+        self.set = set.to_owned();
+        self.policy = policy;
+        Ok(())
+    }
+}
+
+/// Check that `T` can be allocated by hwloc
+///
+/// # Panics
+///
+/// If `T` has an alignment that hwloc allocations cannot guarantee
+fn check_alignment<T>() {
+    assert!(
+        mem::align_of::<T>() <= MAX_ALIGN,
+        "types with an alignment above {MAX_ALIGN} are not supported"
+    );
+}
+
+/// Pointer to the first `T` of some bytes
+///
+/// Zero-sized types do not get an allocation, and use a dangling pointer.
+fn base_ptr<T>(bytes: &mut Bytes<'_>) -> *mut T {
+    if mem::size_of::<T>() == 0 {
+        NonNull::dangling().as_ptr()
+    } else {
+        bytes.as_mut_ptr().cast::<T>()
+    }
+}
+
+/// Const version of [`base_ptr()`]
+fn base_ptr_const<T>(bytes: &Bytes<'_>) -> *const T {
+    if mem::size_of::<T>() == 0 {
+        NonNull::dangling().as_ptr()
+    } else {
+        bytes.as_ptr().cast::<T>()
+    }
+}
+
+/// A pointer type that uniquely owns a `T` allocated on specific NUMA nodes
+///
+/// This is the NUMA-aware counterpart of [`Box`]. The memory binding is
+/// specified using the same parameters as [`Topology::allocate_bound_memory()`]
+/// or [`Topology::binding_allocate_memory()`], depending on which constructor
+/// is used.
+///
+/// Memory can be bound by [`CpuSet`] or [`NodeSet`], the latter being the
+/// default since it also works for CPU-less NUMA nodes.
+///
+/// Types with an alignment above 4096 bytes are not supported.
+//
+// --- Implementation details ---
+//
+// # Safety
+//
+// If `T` is not zero-sized, `bytes` is an allocation of `size_of::<T>()` bytes
+// that contains an initialized `T`.
+pub struct BoundBox<'topology, T, OwnedSet: OwnedSpecializedBitmap = NodeSet> {
+    /// Underlying allocation
+    bytes: Bytes<'topology>,
+
+    /// Memory binding of the allocation
+    binding: AllocationBinding<OwnedSet>,
+
+    /// Logically owned value
+    value: PhantomData<T>,
+}
+//
+impl<'topology, T, OwnedSet: OwnedSpecializedBitmap> BoundBox<'topology, T, OwnedSet> {
+    /// Move `value` to memory allocated on NUMA nodes specified by `set`
+    ///
+    /// See [`Topology::allocate_bound_memory()`] for the semantics of `set`,
+    /// `policy` and `flags`.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::allocate_bound_memory()`].
+    ///
+    /// # Panics
+    ///
+    /// If `T` has an alignment above 4096 bytes.
+    pub fn new<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        value: T,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<Self, MemoryAllocationError<OwnedSet>> {
+        Self::new_impl(topology, value, set, policy, flags, false)
+    }
+
+    /// Move `value` to memory allocated on NUMA nodes specified by `set`,
+    /// possibly rebinding the current process or thread if needed
+    ///
+    /// See [`Topology::binding_allocate_memory()`] for the semantics of `set`,
+    /// `policy` and `flags`.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::binding_allocate_memory()`].
+    ///
+    /// # Panics
+    ///
+    /// If `T` has an alignment above 4096 bytes.
+    pub fn binding_new<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        value: T,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<Self, MemoryAllocationError<OwnedSet>> {
+        Self::new_impl(topology, value, set, policy, flags, true)
+    }
+
+    /// Implementation of [`BoundBox::new()`] and [`BoundBox::binding_new()`]
+    fn new_impl<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        value: T,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+        rebind_fallback: bool,
+    ) -> Result<Self, MemoryAllocationError<OwnedSet>> {
+        check_alignment::<T>();
+        let binding = AllocationBinding {
+            set: set.to_owned(),
+            policy,
+            flags,
+            rebind_fallback,
+        };
+        let mut bytes = binding.allocate(topology, mem::size_of::<T>())?;
+        // SAFETY: bytes is a suitably sized and aligned allocation for T
+        unsafe { base_ptr::<T>(&mut bytes).write(value) };
+        Ok(Self {
+            bytes,
+            binding,
+            value: PhantomData,
+        })
+    }
+
+    /// Topology that the memory was allocated from
+    pub fn topology(&self) -> &'topology Topology {
+        self.bytes.topology()
+    }
+
+    /// CPUs or NUMA nodes that the memory is bound to
+    pub fn set(&self) -> &OwnedSet {
+        &self.binding.set
+    }
+
+    /// Memory binding policy
+    pub fn policy(&self) -> MemoryBindingPolicy {
+        self.binding.policy
+    }
+
+    /// Memory binding flags
+    pub fn flags(&self) -> MemoryBindingFlags {
+        self.binding.flags
+    }
+
+    /// Check where the pages of the boxed value are actually located
+    ///
+    /// See [`Topology::area_memory_location()`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::area_memory_location()`]. In particular, zero-sized
+    /// values reside nowhere and will result in a
+    /// [`MemoryBindingError::BadTarget`] error.
+    pub fn area_memory_location<LocationSet: OwnedSpecializedBitmap>(
+        &self,
+        flags: MemoryBindingFlags,
+    ) -> Result<LocationSet, MemoryBindingError<LocationSet>> {
+        self.topology().area_memory_location(&**self, flags)
+    }
+
+    /// Migrate the boxed value to NUMA nodes specified by `set`
+    ///
+    /// This calls [`Topology::bind_memory_area()`] with the flags of this box
+    /// and the [`MIGRATE`] flag, so it requires
+    /// [`MemoryBindingSupport::set_area()`] and
+    /// [`MemoryBindingSupport::migrate_flag()`]. If it succeeds, `set` and
+    /// `policy` will also be used by future allocations.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::bind_memory_area()`].
+    ///
+    /// [`MIGRATE`]: MemoryBindingFlags::MIGRATE
+    pub fn rebind<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        &mut self,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+    ) -> Result<(), MemoryBindingError<OwnedSet>> {
+        self.binding.rebind(&self.bytes, set, policy)
+    }
+
+    /// Extract the boxed value, liberating the underlying allocation
+    pub fn into_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        // SAFETY: - The box contains an initialized T (type invariant)
+        //         - The value and the allocation are moved out of the
+        //           ManuallyDrop exactly once, and the allocation is then
+        //           liberated without dropping the value
+        unsafe {
+            let value = base_ptr::<T>(&mut this.bytes).read();
+            let bytes = ptr::read(&this.bytes);
+            let binding = ptr::read(&this.binding);
+            mem::drop((bytes, binding));
+            value
+        }
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> AsRef<T> for BoundBox<'_, T, OwnedSet> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> AsMut<T> for BoundBox<'_, T, OwnedSet> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> Borrow<T> for BoundBox<'_, T, OwnedSet> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> BorrowMut<T> for BoundBox<'_, T, OwnedSet> {
+    fn borrow_mut(&mut self) -> &mut T {
+        self
+    }
+}
+//
+impl<T: Debug, OwnedSet: OwnedSpecializedBitmap> Debug for BoundBox<'_, T, OwnedSet> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> Deref for BoundBox<'_, T, OwnedSet> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The box contains an initialized T (type invariant)
+        unsafe { &*base_ptr_const::<T>(&self.bytes) }
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> DerefMut for BoundBox<'_, T, OwnedSet> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The box contains an initialized T (type invariant)
+        unsafe { &mut *base_ptr::<T>(&mut self.bytes) }
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> Drop for BoundBox<'_, T, OwnedSet> {
+    fn drop(&mut self) {
+        // SAFETY: The box contains an initialized T (type invariant), which
+        //         will not be used again after Drop
+        unsafe { ptr::drop_in_place(base_ptr::<T>(&mut self.bytes)) }
+    }
+}
+
+/// A contiguous growable array of `T`s allocated on specific NUMA nodes
+///
+/// This is the NUMA-aware counterpart of [`Vec`]. The memory binding is
+/// specified using the same parameters as [`Topology::allocate_bound_memory()`]
+/// or [`Topology::binding_allocate_memory()`], depending on which constructor
+/// is used, and reallocations will use the same parameters.
+///
+/// Since hwloc allocations can fail in more ways than regular allocations,
+/// all operations that may reallocate return a `Result`.
+///
+/// Memory can be bound by [`CpuSet`] or [`NodeSet`], the latter being the
+/// default since it also works for CPU-less NUMA nodes.
+///
+/// Types with an alignment above 4096 bytes are not supported.
+//
+// --- Implementation details ---
+//
+// # Safety
+//
+// - If `T` is not zero-sized, `bytes` is an allocation of a multiple of
+//   `size_of::<T>()` bytes
+// - The first `len` elements of the allocation are initialized
+pub struct BoundVec<'topology, T, OwnedSet: OwnedSpecializedBitmap = NodeSet> {
+    /// Underlying allocation
+    bytes: Bytes<'topology>,
+
+    /// Number of initialized elements
+    len: usize,
+
+    /// Memory binding of the allocation
+    binding: AllocationBinding<OwnedSet>,
+
+    /// Logically owned elements
+    elements: PhantomData<T>,
+}
+//
+impl<'topology, T, OwnedSet: OwnedSpecializedBitmap> BoundVec<'topology, T, OwnedSet> {
+    /// Set up an empty vector whose storage will be allocated on NUMA nodes
+    /// specified by `set`
+    ///
+    /// See [`Topology::allocate_bound_memory()`] for the semantics of `set`,
+    /// `policy` and `flags`. No memory is allocated until elements are
+    /// inserted.
+    ///
+    /// # Errors
+    ///
+    /// [`MemoryBindingError::BadFlags`] if `flags` are not valid for
+    /// [`Topology::allocate_bound_memory()`].
+    ///
+    /// # Panics
+    ///
+    /// If `T` has an alignment above 4096 bytes.
+    pub fn new<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<Self, MemoryAllocationError<OwnedSet>> {
+        Self::with_capacity(topology, 0, set, policy, flags)
+    }
+
+    /// Set up an empty vector with space for at least `capacity` elements,
+    /// allocated on NUMA nodes specified by `set`
+    ///
+    /// See [`Topology::allocate_bound_memory()`] for the semantics of `set`,
+    /// `policy` and `flags`.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::allocate_bound_memory()`].
+    ///
+    /// # Panics
+    ///
+    /// If `T` has an alignment above 4096 bytes.
+    pub fn with_capacity<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        capacity: usize,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<Self, MemoryAllocationError<OwnedSet>> {
+        Self::with_capacity_impl(topology, capacity, set, policy, flags, false)
+    }
+
+    /// Set up an empty vector with space for at least `capacity` elements,
+    /// allocated on NUMA nodes specified by `set`, possibly rebinding the
+    /// current process or thread if needed
+    ///
+    /// See [`Topology::binding_allocate_memory()`] for the semantics of `set`,
+    /// `policy` and `flags`. Every reallocation may rebind the current process
+    /// or thread in the same way.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::binding_allocate_memory()`].
+    ///
+    /// # Panics
+    ///
+    /// If `T` has an alignment above 4096 bytes.
+    pub fn binding_with_capacity<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        capacity: usize,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+    ) -> Result<Self, MemoryAllocationError<OwnedSet>> {
+        Self::with_capacity_impl(topology, capacity, set, policy, flags, true)
+    }
+
+    /// Implementation of [`BoundVec::with_capacity()`] and
+    /// [`BoundVec::binding_with_capacity()`]
+    fn with_capacity_impl<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        topology: &'topology Topology,
+        capacity: usize,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+        flags: MemoryBindingFlags,
+        rebind_fallback: bool,
+    ) -> Result<Self, MemoryAllocationError<OwnedSet>> {
+        check_alignment::<T>();
+        let binding = AllocationBinding {
+            set: set.to_owned(),
+            policy,
+            flags,
+            rebind_fallback,
+        };
+        let bytes = binding.allocate(topology, Self::bytes_for(capacity)?)?;
+        Ok(Self {
+            bytes,
+            len: 0,
+            binding,
+            elements: PhantomData,
+        })
+    }
+
+    /// Number of bytes needed to store `capacity` elements
+    fn bytes_for(capacity: usize) -> Result<usize, MemoryAllocationError<OwnedSet>> {
+        capacity
+            .checked_mul(mem::size_of::<T>())
+            .ok_or(MemoryBindingError::AllocationFailed)
+    }
+
+    /// Topology that the memory was allocated from
+    pub fn topology(&self) -> &'topology Topology {
+        self.bytes.topology()
+    }
+
+    /// CPUs or NUMA nodes that the memory is bound to
+    pub fn set(&self) -> &OwnedSet {
+        &self.binding.set
+    }
+
+    /// Memory binding policy
+    pub fn policy(&self) -> MemoryBindingPolicy {
+        self.binding.policy
+    }
+
+    /// Memory binding flags
+    pub fn flags(&self) -> MemoryBindingFlags {
+        self.binding.flags
+    }
+
+    /// Number of elements in the vector
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Truth that the vector contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements that the vector can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.bytes
+            .len()
+            .checked_div(mem::size_of::<T>())
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Extract a slice containing the entire vector
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: The first len elements are initialized (type invariant)
+        unsafe { std::slice::from_raw_parts(base_ptr_const::<T>(&self.bytes), self.len) }
+    }
+
+    /// Extract a mutable slice of the entire vector
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: The first len elements are initialized (type invariant)
+        unsafe { std::slice::from_raw_parts_mut(base_ptr::<T>(&mut self.bytes), self.len) }
+    }
+
+    /// Make sure that at least `additional` more elements can be inserted
+    ///
+    /// Like [`Vec::reserve()`], this may reserve more space than requested to
+    /// avoid frequent reallocations. The new allocation uses the same memory
+    /// binding parameters as the current one.
+    ///
+    /// # Errors
+    ///
+    /// - [`AllocationFailed`] if the new capacity overflows `usize` or if
+    ///   memory allocation failed
+    /// - Other errors from [`Topology::allocate_bound_memory()`] or
+    ///   [`Topology::binding_allocate_memory()`], if the binding of the system
+    ///   changed since the vector was created
+    ///
+    /// [`AllocationFailed`]: MemoryBindingError::AllocationFailed
+    pub fn reserve(&mut self, additional: usize) -> Result<(), MemoryAllocationError<OwnedSet>> {
+        let capacity = self.capacity();
+        if capacity - self.len >= additional {
+            return Ok(());
+        }
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(MemoryBindingError::AllocationFailed)?;
+        self.reallocate(required.max(capacity.saturating_mul(2)).max(4))
+    }
+
+    /// Reallocate storage to `new_capacity` elements, with
+    /// `new_capacity >= self.len`
+    fn reallocate(&mut self, new_capacity: usize) -> Result<(), MemoryAllocationError<OwnedSet>> {
+        let mut bytes = self
+            .binding
+            .allocate(self.topology(), Self::bytes_for(new_capacity)?)?;
+        // SAFETY: - Both allocations are valid for self.len elements, and the
+        //           source elements are initialized (type invariant)
+        //         - Distinct allocations do not overlap
+        //         - The old allocation is liberated without dropping the
+        //           elements, which now belong to the new allocation
+        unsafe {
+            ptr::copy_nonoverlapping(
+                base_ptr_const::<T>(&self.bytes),
+                base_ptr::<T>(&mut bytes),
+                self.len,
+            );
+        }
+        self.bytes = bytes;
+        Ok(())
+    }
+
+    /// Shrink the capacity of the vector as much as possible
+    ///
+    /// # Errors
+    ///
+    /// See [`BoundVec::reserve()`].
+    pub fn shrink_to_fit(&mut self) -> Result<(), MemoryAllocationError<OwnedSet>> {
+        if self.capacity() > self.len {
+            self.reallocate(self.len)?;
+        }
+        Ok(())
+    }
+
+    /// Append an element at the end of the vector
+    ///
+    /// # Errors
+    ///
+    /// See [`BoundVec::reserve()`]. The element is returned to the caller
+    /// when the vector cannot be grown.
+    pub fn push(&mut self, value: T) -> Result<(), (T, MemoryAllocationError<OwnedSet>)> {
+        if let Err(e) = self.reserve(1) {
+            return Err((value, e));
+        }
+        // SAFETY: Storage was reserved for this element above
+        unsafe { base_ptr::<T>(&mut self.bytes).add(self.len).write(value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove the last element of the vector and return it, if any
+    pub fn pop(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        // SAFETY: This element was initialized and is now logically removed
+        Some(unsafe { base_ptr::<T>(&mut self.bytes).add(self.len).read() })
+    }
+
+    /// Shorten the vector to `len` elements, dropping the rest
+    ///
+    /// This has no effect if the vector is already shorter than that.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let old_len = self.len;
+        self.len = len;
+        // SAFETY: These elements were initialized and are now logically
+        //         removed. len is updated first in case a Drop impl panics.
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                base_ptr::<T>(&mut self.bytes).add(len),
+                old_len - len,
+            ));
+        }
+    }
+
+    /// Remove all elements from the vector
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Append clones of the elements of `other` at the end of the vector
+    ///
+    /// # Errors
+    ///
+    /// See [`BoundVec::reserve()`].
+    pub fn extend_from_slice(&mut self, other: &[T]) -> Result<(), MemoryAllocationError<OwnedSet>>
+    where
+        T: Clone,
+    {
+        self.reserve(other.len())?;
+        for value in other {
+            // SAFETY: Storage was reserved for these elements above
+            unsafe {
+                base_ptr::<T>(&mut self.bytes)
+                    .add(self.len)
+                    .write(value.clone());
+            }
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    /// Check where the pages of the vector's elements are actually located
+    ///
+    /// See [`Topology::area_memory_location()`] for more information.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::area_memory_location()`]. In particular, empty vectors
+    /// and vectors of zero-sized elements reside nowhere and will result in a
+    /// [`MemoryBindingError::BadTarget`] error.
+    pub fn area_memory_location<LocationSet: OwnedSpecializedBitmap>(
+        &self,
+        flags: MemoryBindingFlags,
+    ) -> Result<LocationSet, MemoryBindingError<LocationSet>> {
+        self.topology().area_memory_location(self.as_slice(), flags)
+    }
+
+    /// Migrate the vector's storage to NUMA nodes specified by `set`
+    ///
+    /// This calls [`Topology::bind_memory_area()`] with the flags of this
+    /// vector and the [`MIGRATE`] flag, so it requires
+    /// [`MemoryBindingSupport::set_area()`] and
+    /// [`MemoryBindingSupport::migrate_flag()`]. If it succeeds, `set` and
+    /// `policy` will also be used by future reallocations.
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::bind_memory_area()`].
+    ///
+    /// [`MIGRATE`]: MemoryBindingFlags::MIGRATE
+    pub fn rebind<Set: SpecializedBitmap<Owned = OwnedSet>>(
+        &mut self,
+        set: &Set,
+        policy: MemoryBindingPolicy,
+    ) -> Result<(), MemoryBindingError<OwnedSet>> {
+        self.binding.rebind(&self.bytes, set, policy)
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> AsRef<[T]> for BoundVec<'_, T, OwnedSet> {
+    fn as_ref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> AsMut<[T]> for BoundVec<'_, T, OwnedSet> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> Borrow<[T]> for BoundVec<'_, T, OwnedSet> {
+    fn borrow(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> BorrowMut<[T]> for BoundVec<'_, T, OwnedSet> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+//
+impl<T: Debug, OwnedSet: OwnedSpecializedBitmap> Debug for BoundVec<'_, T, OwnedSet> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_slice(), f)
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> Deref for BoundVec<'_, T, OwnedSet> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> DerefMut for BoundVec<'_, T, OwnedSet> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+//
+impl<T, OwnedSet: OwnedSpecializedBitmap> Drop for BoundVec<'_, T, OwnedSet> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+//
+impl<'vec, T, OwnedSet: OwnedSpecializedBitmap> IntoIterator for &'vec BoundVec<'_, T, OwnedSet> {
+    type Item = &'vec T;
+    type IntoIter = std::slice::Iter<'vec, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+//
+impl<'vec, T, OwnedSet: OwnedSpecializedBitmap> IntoIterator
+    for &'vec mut BoundVec<'_, T, OwnedSet>
+{
+    type Item = &'vec mut T;
+    type IntoIter = std::slice::IterMut<'vec, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cpu::cpuset::CpuSet, object::types::ObjectType};
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{Binary, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex},
+        hash::Hash,
+        io::{self, Read},
+        rc::Rc,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(BoundBox<'static, u32>:
+        AsMut<u32>, AsRef<u32>, Borrow<u32>, BorrowMut<u32>, Debug,
+        Deref<Target = u32>, DerefMut, Drop, Send, Sized, Sync, Unpin
+    );
+    assert_not_impl_any!(BoundBox<'static, u32>:
+        Binary, Clone, Default, Display, Error, Hash, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_not_impl_any!(BoundBox<'static, Rc<u32>>: Send, Sync);
+    assert_impl_all!(BoundVec<'static, u32, CpuSet>:
+        AsMut<[u32]>, AsRef<[u32]>, Borrow<[u32]>, BorrowMut<[u32]>, Debug,
+        Deref<Target = [u32]>, DerefMut, Drop, Send, Sized, Sync, Unpin
+    );
+    assert_not_impl_any!(BoundVec<'static, u32>:
+        Binary, Clone, Default, Display, Error, Hash, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_not_impl_any!(BoundVec<'static, Rc<u32>>: Send, Sync);
+
+    /// Nodeset of the first NUMA node of the test topology
+    fn first_nodeset() -> NodeSet {
+        Topology::test_instance()
+            .objects_with_type(ObjectType::NUMANode)
+            .next()
+            .expect("There should be at least one NUMA node")
+            .nodeset()
+            .expect("NUMA nodes should have a nodeset")
+            .clone_target()
+    }
+
+    #[test]
+    fn bound_box() {
+        let topology = Topology::test_instance();
+        let nodeset = first_nodeset();
+        let policy = MemoryBindingPolicy::Bind;
+        let flags = MemoryBindingFlags::ASSUME_SINGLE_THREAD;
+        let Ok(mut boxed) = BoundBox::new(topology, [42u64; 1000], &nodeset, policy, flags) else {
+            // Bound allocation may not be supported by the host
+            return;
+        };
+        assert!(std::ptr::eq(boxed.topology(), topology));
+        assert_eq!(boxed.set(), &nodeset);
+        assert_eq!(boxed.policy(), policy);
+        assert_eq!(boxed.flags(), flags);
+        assert!(boxed.iter().all(|&x| x == 42));
+        boxed[3] = 24;
+        if let Ok(location) = boxed.area_memory_location::<NodeSet>(MemoryBindingFlags::empty()) {
+            assert!(location.includes(&nodeset) || location.is_empty());
+        }
+        if boxed
+            .rebind(&nodeset, MemoryBindingPolicy::Interleave)
+            .is_ok()
+        {
+            assert_eq!(boxed.policy(), MemoryBindingPolicy::Interleave);
+        }
+        let inner = boxed.into_inner();
+        assert_eq!(inner[3], 24);
+
+        // Drop must run exactly once
+        let rc = Rc::new(());
+        let boxed = BoundBox::new(topology, Rc::clone(&rc), &nodeset, policy, flags).unwrap();
+        assert_eq!(Rc::strong_count(&rc), 2);
+        std::mem::drop(boxed);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn bound_vec() {
+        let topology = Topology::test_instance();
+        let nodeset = first_nodeset();
+        let policy = MemoryBindingPolicy::Bind;
+        let flags = MemoryBindingFlags::ASSUME_SINGLE_THREAD;
+        let Ok(mut vec) = BoundVec::new(topology, &nodeset, policy, flags) else {
+            // Bound allocation may not be supported by the host
+            return;
+        };
+        assert!(vec.is_empty());
+        assert_eq!(vec.capacity(), 0);
+        let rc = Rc::new(());
+        if vec.push(Rc::clone(&rc)).is_err() {
+            return;
+        }
+        for _ in 0..999 {
+            vec.push(Rc::clone(&rc)).unwrap();
+        }
+        assert_eq!(vec.len(), 1000);
+        assert!(vec.capacity() >= 1000);
+        assert_eq!(Rc::strong_count(&rc), 1001);
+        assert!(vec.pop().is_some());
+        assert_eq!(Rc::strong_count(&rc), 1000);
+        vec.truncate(10);
+        assert_eq!(Rc::strong_count(&rc), 11);
+        vec.shrink_to_fit().unwrap();
+        assert_eq!(vec.capacity(), 10);
+        vec.extend_from_slice(&[Rc::clone(&rc)]).unwrap();
+        assert_eq!(vec.len(), 11);
+        assert_eq!(vec.set(), &nodeset);
+        if let Ok(location) = vec.area_memory_location::<NodeSet>(MemoryBindingFlags::empty()) {
+            assert!(location.includes(&nodeset) || location.is_empty());
+        }
+        std::mem::drop(vec);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        // Zero-sized types never need an allocation
+        let mut units = BoundVec::new(topology, &nodeset, policy, flags).unwrap();
+        assert_eq!(units.capacity(), usize::MAX);
+        units.push(()).unwrap();
+        assert_eq!(units.as_slice(), &[()]);
+    }
+}
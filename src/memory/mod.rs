@@ -4,10 +4,16 @@
 //! [`Topology`] struct. The module itself only hosts type definitions that are
 //! related to this functionality.
 
+pub mod allocator;
 #[cfg(feature = "hwloc-2_3_0")]
 pub mod attribute;
+#[cfg(feature = "hwloc-2_3_0")]
+pub mod benchmark;
 pub mod binding;
+pub mod containers;
 pub mod nodeset;
+#[cfg(feature = "hwloc-2_3_0")]
+pub mod placement;
 
 #[cfg(doc)]
 use crate::topology::Topology;
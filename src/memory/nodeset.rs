@@ -3,9 +3,13 @@
 //! These specialized bitmaps represent sets of NUMA nodes, as exposed by the
 //! underlying operating system.
 
+#[cfg(doc)]
+use crate::object::TopologyObject;
 #[cfg(doc)]
 use crate::{bitmap::Bitmap, topology::support::DiscoverySupport};
-use crate::{cpu::cpuset::CpuSet, impl_bitmap_newtype, topology::Topology};
+use crate::{
+    cpu::cpuset::CpuSet, impl_bitmap_newtype, object::types::ObjectType, topology::Topology,
+};
 #[allow(unused)]
 #[cfg(test)]
 use similar_asserts::assert_eq;
@@ -44,6 +48,30 @@ impl NodeSet {
         }
         polymorphized(topology, &cpuset)
     }
+
+    /// Convert a set of NUMA node logical indices into a NUMA node set
+    ///
+    /// `logical` can be a `&'_ LogicalNodeSet` or a
+    /// `BitmapRef<'_, LogicalNodeSet>`.
+    ///
+    /// For each NUMA node whose logical index is included in the input set,
+    /// set the corresponding OS index in the output nodeset. Logical indices
+    /// which do not correspond to any NUMA node of `topology` are ignored.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    pub fn from_logical(topology: &Topology, logical: impl Deref<Target = LogicalNodeSet>) -> Self {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(topology: &Topology, logical: &LogicalNodeSet) -> NodeSet {
+            topology
+                .objects_with_type(ObjectType::NUMANode)
+                .filter(|node| logical.is_set(node.logical_index()))
+                .fold(NodeSet::new(), |mut nodeset, node| {
+                    nodeset.set(node.os_index().expect("NUMA nodes should have an OS index"));
+                    nodeset
+                })
+        }
+        polymorphized(topology, &logical)
+    }
 }
 
 impl_bitmap_newtype!(
@@ -60,13 +88,88 @@ impl_bitmap_newtype!(
     NodeSet
 );
 
+/// # LogicalNodeSet-specific API
+//
+// --- Implementation details ---
+//
+// This goes before the main impl_bitmap_newtype macro so that it appears before
+// the bitmap API reexport in rustdoc.
+impl LogicalNodeSet {
+    /// Convert a NUMA node set into a set of NUMA node logical indices
+    ///
+    /// `nodeset` can be a `&'_ NodeSet` or a `BitmapRef<'_, NodeSet>`.
+    ///
+    /// For each NUMA node included in the input `nodeset`, set the
+    /// corresponding logical index in the output set. OS indices which do not
+    /// correspond to any NUMA node of `topology` are ignored.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    pub fn from_nodeset(topology: &Topology, nodeset: impl Deref<Target = NodeSet>) -> Self {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(topology: &Topology, nodeset: &NodeSet) -> LogicalNodeSet {
+            topology
+                .nodes_from_nodeset(nodeset)
+                .fold(LogicalNodeSet::new(), |mut logical, node| {
+                    logical.set(node.logical_index());
+                    logical
+                })
+        }
+        polymorphized(topology, &nodeset)
+    }
+}
+
+impl_bitmap_newtype!(
+    /// [`Bitmap`] whose bits are set according to NUMA node logical indexes
+    ///
+    /// This is the NUMA node counterpart of
+    /// [`LogicalCpuSet`](crate::cpu::cpuset::LogicalCpuSet): each bit stands
+    /// for the [logical index](TopologyObject::logical_index()) of a NUMA node,
+    /// as displayed by `lstopo`, rather than for its OS index as in
+    /// [`NodeSet`].
+    ///
+    /// A `LogicalNodeSet` cannot be used where a [`NodeSet`] is expected, and
+    /// must be converted to one using [`NodeSet::from_logical()`] first.
+    /// [`LogicalNodeSet::from_nodeset()`] performs the reverse conversion.
+    ///
+    /// This functionality is specific to the Rust bindings.
+    {
+        LogicalNodeSet => logical_bitmap_newtype, unspecialized
+    }
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::strategies::topology_related_set;
     use proptest::prelude::*;
+    use static_assertions::assert_not_impl_any;
+
+    // Logical sets must not be usable where an OS-indexed node set is expected
+    assert_not_impl_any!(LogicalNodeSet:
+        crate::bitmap::SpecializedBitmap, From<NodeSet>, Into<NodeSet>
+    );
 
     proptest! {
+        /// Test for [`LogicalNodeSet::from_nodeset()`] and
+        /// [`NodeSet::from_logical()`]
+        #[test]
+        fn logical_nodeset(
+            nodeset in topology_related_set(Topology::nodeset),
+        ) {
+            let topology = Topology::test_instance();
+            let logical = LogicalNodeSet::from_nodeset(topology, &nodeset);
+            prop_assert_eq!(
+                logical.iter_set().map(usize::from).collect::<Vec<_>>(),
+                topology.nodes_from_nodeset(&nodeset)
+                        .map(crate::object::TopologyObject::logical_index)
+                        .collect::<Vec<_>>()
+            );
+            prop_assert_eq!(
+                NodeSet::from_logical(topology, &logical),
+                nodeset & topology.complete_nodeset()
+            );
+        }
+
         /// Test for [`NodeSet::from_cpuset()`]
         #[test]
         fn nodeset_from_cpuset(
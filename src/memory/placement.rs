@@ -0,0 +1,829 @@
+//! Planning NUMA-aligned placements
+//!
+//! Resource managers often need to answer requests like "give me 8 cores, 64
+//! GiB of RAM and locality to this NIC" with a placement that keeps CPU
+//! cores, memory and devices on the same NUMA nodes, in the spirit of the
+//! Kubernetes topology manager. This module provides a planner that does so.
+//!
+//! Most of this module's functionality is exposed via [methods of the Topology
+//! struct](../../topology/struct.Topology.html#planning-numa-aligned-placements).
+//! The module itself only hosts type definitions that are related to this
+//! functionality.
+
+use crate::{
+    cpu::cpuset::CpuSet,
+    errors::{ForeignObjectError, HybridError, RawHwlocError},
+    memory::{
+        attribute::{LocalNUMANodeFlags, TargetNumaNodes},
+        nodeset::NodeSet,
+    },
+    object::{types::ObjectType, TopologyObject},
+    topology::Topology,
+};
+use std::fmt::{self, Display};
+use thiserror::Error;
+
+/// # Planning NUMA-aligned placements
+//
+// --- Implementation details ---
+//
+// hwloc provides the building blocks of placement decisions (NUMA node
+// locality, memory capacities, object proximity), but leaves it up to
+// resource managers to combine them. This does so in the spirit of the
+// Kubernetes topology manager.
+impl Topology {
+    /// Find placements of CPU cores and memory that satisfy `request`
+    ///
+    /// Candidate placements are built from the NUMA nodes of this topology,
+    /// considered individually and grouped as in the topology tree (e.g. all
+    /// NUMA nodes of a package, then of the whole machine). Each candidate
+    /// that has enough available cores and memory to satisfy the request is
+    /// returned with a [`PlacementScore`], best placements first.
+    ///
+    /// Placements are ranked as follows:
+    ///
+    /// - NUMA-aligned placements (see [`PlacementScore::aligned`]) come first
+    /// - Then placements that are local to more of the requested devices
+    /// - Then placements that span fewer NUMA nodes
+    /// - Then placements that have more cores local to the requested devices
+    /// - Then placements whose NUMA nodes are closer to those of the best
+    ///   placement, as determined by [`Topology::objects_closest_to()`], so
+    ///   that fallback placements stay as close as possible to the preferred
+    ///   one
+    ///
+    /// A NUMA node is considered local to a device if it is reported as such
+    /// by [`Topology::local_numa_nodes()`]. Devices that no NUMA node has the
+    /// exact same locality as (e.g. devices attached to the root of a
+    /// multi-package machine) are considered local to all NUMA nodes with a
+    /// larger or smaller locality.
+    ///
+    /// # Errors
+    ///
+    /// - [`ForeignObject`] if one of the requested devices does not belong to
+    ///   this topology
+    /// - [`Hwloc`] if hwloc failed to list the NUMA nodes local to a device
+    /// - [`Unsatisfiable`] if no candidate placement can satisfy the request,
+    ///   along with the reason why each candidate was rejected
+    ///
+    /// [`ForeignObject`]: PlacementError::ForeignObject
+    /// [`Hwloc`]: PlacementError::Hwloc
+    /// [`Unsatisfiable`]: PlacementError::Unsatisfiable
+    pub fn plan_placement(
+        &self,
+        request: &PlacementRequest<'_>,
+    ) -> Result<Vec<Placement>, PlacementError> {
+        // Find out which NUMA nodes and CPUs are local to each device
+        let devices = request
+            .devices
+            .iter()
+            .map(|&device| self.device_locality(device))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Find the available cores, or PUs if the topology has no cores
+        let available_cpus = request
+            .available_cpus
+            .clone()
+            .unwrap_or_else(|| self.allowed_cpuset().clone_target());
+        let core_type = if self.depth_for_type(ObjectType::Core).is_ok() {
+            ObjectType::Core
+        } else {
+            ObjectType::PU
+        };
+        let cores = self
+            .objects_with_type(core_type)
+            .filter_map(|core| {
+                let cpuset = core.cpuset()?.clone_target();
+                (!cpuset.is_empty() && available_cpus.includes(&cpuset)).then_some(cpuset)
+            })
+            .collect::<Vec<_>>();
+
+        // Evaluate each candidate set of NUMA nodes
+        let mut placements = Vec::new();
+        let mut rejections = Vec::new();
+        for nodeset in self.candidate_nodesets() {
+            match self.evaluate_placement(request, &cores, &devices, nodeset) {
+                Ok(placement) => placements.push(placement),
+                Err(rejection) => rejections.push(rejection),
+            }
+        }
+        if placements.is_empty() {
+            return Err(PlacementError::Unsatisfiable(rejections));
+        }
+
+        // Determine which placements are NUMA-aligned, then rank them
+        let min_numa_nodes = placements
+            .iter()
+            .map(|placement| placement.score.numa_nodes)
+            .min()
+            .expect("Checked above that there is at least one placement");
+        for placement in &mut placements {
+            let score = &mut placement.score;
+            score.aligned =
+                score.numa_nodes == min_numa_nodes && score.local_devices == devices.len();
+        }
+        let rank = |placement: &Placement| {
+            let score = placement.score;
+            (
+                !score.aligned,
+                devices.len() - score.local_devices,
+                score.numa_nodes,
+                request.cores - score.device_local_cores,
+            )
+        };
+        let best = placements
+            .iter()
+            .min_by_key(|placement| rank(placement))
+            .expect("Checked above that there is at least one placement");
+        let nodes_by_proximity = self.numa_nodes_by_proximity(&best.nodeset);
+        placements.sort_by_cached_key(|placement| {
+            let farthest_node = nodes_by_proximity
+                .iter()
+                .rposition(|node| placement.nodeset.intersects(node))
+                .unwrap_or(usize::MAX);
+            (rank(placement), farthest_node)
+        });
+        Ok(placements)
+    }
+
+    /// Nodesets of individual NUMA nodes, sorted by increasing distance from
+    /// the first NUMA node of `nodeset`
+    ///
+    /// Distance is determined by [`Topology::objects_closest_to()`], i.e. by
+    /// how deep the first common ancestor of two NUMA nodes lies in the
+    /// topology tree.
+    fn numa_nodes_by_proximity(&self, nodeset: &NodeSet) -> Vec<NodeSet> {
+        let Some(anchor) = self.objects_with_type(ObjectType::NUMANode).find(|node| {
+            node.nodeset()
+                .is_some_and(|node_set| nodeset.includes(node_set))
+        }) else {
+            return Vec::new();
+        };
+        std::iter::once(anchor)
+            .chain(
+                self.objects_closest_to(anchor)
+                    .into_iter()
+                    .flatten()
+                    .filter(|obj| obj.object_type() == ObjectType::NUMANode),
+            )
+            .filter_map(|node| node.nodeset().map(|node_set| node_set.clone_target()))
+            .collect()
+    }
+
+    /// Check if the NUMA nodes of `nodeset` can satisfy `request`, given the
+    /// `cores` that are available and the locality of requested `devices`
+    ///
+    /// NUMA alignment is not known at this stage and must be set afterwards.
+    fn evaluate_placement(
+        &self,
+        request: &PlacementRequest<'_>,
+        cores: &[CpuSet],
+        devices: &[DeviceLocality],
+        nodeset: NodeSet,
+    ) -> Result<Placement, PlacementRejection> {
+        let nodes = self
+            .objects_with_type(ObjectType::NUMANode)
+            .filter(|node| {
+                node.nodeset()
+                    .is_some_and(|node_set| nodeset.includes(node_set))
+            })
+            .collect::<Vec<_>>();
+
+        // Check memory capacity
+        let memory = nodes.iter().map(|node| node.total_memory()).sum::<u64>();
+        if memory < request.memory {
+            return Err(PlacementRejection {
+                nodeset,
+                reason: RejectionReason::NotEnoughMemory {
+                    available: memory,
+                    requested: request.memory,
+                },
+            });
+        }
+
+        // Check core availability
+        let mut node_cpus = CpuSet::new();
+        for node in &nodes {
+            if let Some(cpuset) = node.cpuset() {
+                node_cpus |= cpuset;
+            }
+        }
+        let mut local_cores = cores
+            .iter()
+            .filter(|core| node_cpus.includes(*core))
+            .collect::<Vec<_>>();
+        if local_cores.len() < request.cores {
+            return Err(PlacementRejection {
+                nodeset,
+                reason: RejectionReason::NotEnoughCores {
+                    available: local_cores.len(),
+                    requested: request.cores,
+                },
+            });
+        }
+
+        // Select cores, preferring those that are local to all devices
+        let is_device_local =
+            |core: &CpuSet| devices.iter().all(|device| device.cpuset.includes(core));
+        local_cores.sort_by_key(|core| !is_device_local(core));
+        let selected = &local_cores[..request.cores];
+        let mut cpuset = CpuSet::new();
+        for core in selected {
+            cpuset |= *core;
+        }
+
+        // Score the resulting placement
+        let score = PlacementScore {
+            numa_nodes: nodes.len(),
+            local_devices: devices
+                .iter()
+                .filter(|device| device.nodeset.intersects(&nodeset))
+                .count(),
+            device_local_cores: selected.iter().filter(|core| is_device_local(core)).count(),
+            aligned: false,
+        };
+        Ok(Placement {
+            cpuset,
+            nodeset,
+            score,
+        })
+    }
+
+    /// Sets of NUMA nodes that are considered as placement candidates
+    ///
+    /// This includes every individual NUMA node, along with the NUMA nodes
+    /// below each of their ancestors, without duplicates.
+    fn candidate_nodesets(&self) -> Vec<NodeSet> {
+        let allowed_nodes = self.allowed_nodeset();
+        let mut candidates = Vec::<NodeSet>::new();
+        for node in self.objects_with_type(ObjectType::NUMANode) {
+            for obj in std::iter::once(node).chain(node.ancestors()) {
+                let Some(nodeset) = obj.nodeset() else {
+                    continue;
+                };
+                let nodeset = nodeset.clone_target() & &*allowed_nodes;
+                if !nodeset.is_empty() && !candidates.contains(&nodeset) {
+                    candidates.push(nodeset);
+                }
+            }
+        }
+        candidates
+    }
+
+    /// NUMA nodes and CPUs that are local to a device
+    fn device_locality(&self, device: &TopologyObject) -> Result<DeviceLocality, PlacementError> {
+        if !self.contains(device) {
+            return Err(ForeignObjectError::from(device).into());
+        }
+        let nodes_with_flags = |flags| {
+            self.local_numa_nodes(TargetNumaNodes::Local {
+                location: device.into(),
+                flags,
+            })
+            .map_err(|e| match e {
+                HybridError::Rust(e) => PlacementError::ForeignObject(e),
+                HybridError::Hwloc(e) => PlacementError::Hwloc(e),
+            })
+        };
+        let mut nodes = nodes_with_flags(LocalNUMANodeFlags::empty())?;
+        if nodes.is_empty() {
+            nodes = nodes_with_flags(
+                LocalNUMANodeFlags::LARGER_LOCALITY | LocalNUMANodeFlags::SMALLER_LOCALITY,
+            )?;
+        }
+        let mut nodeset = NodeSet::new();
+        for node in nodes {
+            if let Some(node_set) = node.nodeset() {
+                nodeset |= node_set;
+            }
+        }
+        let cpuset = std::iter::once(device)
+            .chain(device.ancestors())
+            .find_map(TopologyObject::cpuset)
+            .map_or_else(CpuSet::new, |cpuset| cpuset.clone_target());
+        Ok(DeviceLocality { nodeset, cpuset })
+    }
+}
+
+/// NUMA nodes and CPUs that are local to a requested device
+struct DeviceLocality {
+    /// NUMA nodes that are local to the device
+    nodeset: NodeSet,
+
+    /// CPUs that are local to the device
+    cpuset: CpuSet,
+}
+
+/// Request for CPU cores and memory, to be placed by
+/// [`Topology::plan_placement()`]
+///
+/// By default, a request asks for some CPU cores, without any memory or
+/// device locality requirement, and any allowed core of the topology can be
+/// used. Use the builder methods to specify further requirements.
+#[derive(Clone, Debug)]
+pub struct PlacementRequest<'topology> {
+    /// Number of CPU cores
+    cores: usize,
+
+    /// Amount of memory in bytes
+    memory: u64,
+
+    /// Devices that the placement should be local to
+    devices: Vec<&'topology TopologyObject>,
+
+    /// CPUs that can be used, if not all allowed CPUs of the topology
+    available_cpus: Option<CpuSet>,
+}
+//
+impl<'topology> PlacementRequest<'topology> {
+    /// Request `cores` CPU cores
+    ///
+    /// If the topology has no [`Core`] objects, PUs are used instead.
+    ///
+    /// [`Core`]: ObjectType::Core
+    pub fn new(cores: usize) -> Self {
+        Self {
+            cores,
+            memory: 0,
+            devices: Vec::new(),
+            available_cpus: None,
+        }
+    }
+
+    /// Also request `bytes` bytes of memory
+    ///
+    /// The memory capacity of a set of NUMA nodes is computed using
+    /// [`TopologyObject::total_memory()`]. Memory that is already used by
+    /// other applications is not taken into account.
+    pub fn memory(mut self, bytes: u64) -> Self {
+        self.memory = bytes;
+        self
+    }
+
+    /// Prefer placements that are local to `device`
+    ///
+    /// This is typically used with an I/O object (e.g. a NIC or GPU found via
+    /// the [I/O object search
+    /// API](../../topology/struct.Topology.html#finding-io-objects)), but any
+    /// object of the topology can be used. Multiple devices can be specified.
+    pub fn near(mut self, device: &'topology TopologyObject) -> Self {
+        self.devices.push(device);
+        self
+    }
+
+    /// Only use cores whose CPUs are all part of `cpuset`
+    ///
+    /// This can be used to exclude cores that have already been allocated to
+    /// other jobs. By default, all allowed CPUs of the topology may be used.
+    pub fn available_cpus(mut self, cpuset: CpuSet) -> Self {
+        self.available_cpus = Some(cpuset);
+        self
+    }
+}
+
+/// Candidate placement returned by [`Topology::plan_placement()`]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Placement {
+    /// CPUs of the selected cores
+    pub cpuset: CpuSet,
+
+    /// NUMA nodes that memory should be allocated from
+    pub nodeset: NodeSet,
+
+    /// How well this placement is aligned with the request
+    pub score: PlacementScore,
+}
+
+/// How well a [`Placement`] is aligned with its [`PlacementRequest`]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PlacementScore {
+    /// Number of NUMA nodes that memory is spread across
+    pub numa_nodes: usize,
+
+    /// Number of requested devices that are local to these NUMA nodes
+    pub local_devices: usize,
+
+    /// Number of selected cores that are local to all requested devices
+    pub device_local_cores: usize,
+
+    /// Truth that this placement is NUMA-aligned
+    ///
+    /// A placement is NUMA-aligned if it spans the smallest number of NUMA
+    /// nodes that any placement satisfying the request spans, and all
+    /// requested devices are local to these NUMA nodes.
+    pub aligned: bool,
+}
+
+/// Error returned by [`Topology::plan_placement()`]
+#[derive(Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub enum PlacementError {
+    /// One of the requested devices does not belong to this topology
+    #[error(transparent)]
+    ForeignObject(#[from] ForeignObjectError),
+
+    /// hwloc failed to list the NUMA nodes that are local to a device
+    #[error(transparent)]
+    Hwloc(#[from] RawHwlocError),
+
+    /// No candidate placement can satisfy the request
+    ///
+    /// The reason why each candidate set of NUMA nodes was rejected is
+    /// provided as an explanation.
+    #[error("no set of NUMA nodes can satisfy the request: {}", DisplayRejections(.0))]
+    Unsatisfiable(Vec<PlacementRejection>),
+}
+
+/// Display adaptor for the rejections of [`PlacementError::Unsatisfiable`]
+struct DisplayRejections<'a>(&'a [PlacementRejection]);
+//
+impl Display for DisplayRejections<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, rejection) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{rejection}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Candidate set of NUMA nodes that was rejected by
+/// [`Topology::plan_placement()`]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PlacementRejection {
+    /// Rejected NUMA nodes
+    pub nodeset: NodeSet,
+
+    /// Why these NUMA nodes cannot satisfy the request
+    pub reason: RejectionReason,
+}
+//
+impl Display for PlacementRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NUMA nodes {} have {}", self.nodeset, self.reason)
+    }
+}
+
+/// Reason why a candidate set of NUMA nodes cannot satisfy a request
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum RejectionReason {
+    /// Not enough memory
+    NotEnoughMemory {
+        /// Memory capacity of the NUMA nodes in bytes
+        available: u64,
+
+        /// Requested memory in bytes
+        requested: u64,
+    },
+
+    /// Not enough available CPU cores
+    NotEnoughCores {
+        /// Number of available cores that are local to the NUMA nodes
+        available: usize,
+
+        /// Requested number of cores
+        requested: usize,
+    },
+}
+//
+impl Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotEnoughMemory {
+                available,
+                requested,
+            } => write!(
+                f,
+                "{available} bytes of memory, but {requested} were requested"
+            ),
+            Self::NotEnoughCores {
+                available,
+                requested,
+            } => write!(
+                f,
+                "{available} available cores, but {requested} were requested"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap::parse_list;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{Binary, Debug, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex},
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(PlacementRequest<'static>:
+        Clone, Debug, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(PlacementRequest<'static>:
+        Binary, Copy, Default, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(Placement:
+        Clone, Debug, Eq, Hash, PartialEq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(Placement:
+        Binary, Copy, Default, Deref, Display, Drop, Error, IntoIterator,
+        LowerExp, LowerHex, Octal, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(PlacementScore:
+        Copy, Debug, Eq, Hash, PartialEq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(PlacementScore:
+        Binary, Default, Deref, Display, Drop, Error, IntoIterator, LowerExp,
+        LowerHex, Octal, Pointer, Read, UpperExp, UpperHex, fmt::Write,
+        io::Write
+    );
+    assert_impl_all!(PlacementError:
+        Clone, Error, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(PlacementError:
+        Binary, Copy, Default, Deref, Drop, IntoIterator, LowerExp, LowerHex,
+        Octal, Pointer, Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(PlacementRejection:
+        Clone, Debug, Display, Eq, Hash, PartialEq, Send, Sized, Sync, Unpin,
+        UnwindSafe
+    );
+    assert_impl_all!(RejectionReason:
+        Copy, Debug, Display, Eq, Hash, PartialEq, Send, Sized, Sync, Unpin,
+        UnwindSafe
+    );
+
+    /// Two packages, each with one 16 GiB NUMA node and 4 single-PU cores, and
+    /// a network interface attached to the second package
+    const TWO_PACKAGES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE topology SYSTEM "hwloc2.dtd">
+<topology version="2.0">
+  <object type="Machine" os_index="0" cpuset="0x000000ff" complete_cpuset="0x000000ff" allowed_cpuset="0x000000ff" nodeset="0x00000003" complete_nodeset="0x00000003" allowed_nodeset="0x00000003" gp_index="1">
+    <object type="Package" os_index="0" cpuset="0x0000000f" complete_cpuset="0x0000000f" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="2">
+      <object type="NUMANode" os_index="0" cpuset="0x0000000f" complete_cpuset="0x0000000f" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="3" local_memory="17179869184"/>
+      <object type="Core" os_index="0" cpuset="0x00000001" complete_cpuset="0x00000001" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="4">
+        <object type="PU" os_index="0" cpuset="0x00000001" complete_cpuset="0x00000001" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="5"/>
+      </object>
+      <object type="Core" os_index="1" cpuset="0x00000002" complete_cpuset="0x00000002" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="6">
+        <object type="PU" os_index="1" cpuset="0x00000002" complete_cpuset="0x00000002" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="7"/>
+      </object>
+      <object type="Core" os_index="2" cpuset="0x00000004" complete_cpuset="0x00000004" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="8">
+        <object type="PU" os_index="2" cpuset="0x00000004" complete_cpuset="0x00000004" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="9"/>
+      </object>
+      <object type="Core" os_index="3" cpuset="0x00000008" complete_cpuset="0x00000008" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="10">
+        <object type="PU" os_index="3" cpuset="0x00000008" complete_cpuset="0x00000008" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="11"/>
+      </object>
+    </object>
+    <object type="Package" os_index="1" cpuset="0x000000f0" complete_cpuset="0x000000f0" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="12">
+      <object type="NUMANode" os_index="1" cpuset="0x000000f0" complete_cpuset="0x000000f0" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="13" local_memory="17179869184"/>
+      <object type="Core" os_index="0" cpuset="0x00000010" complete_cpuset="0x00000010" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="14">
+        <object type="PU" os_index="4" cpuset="0x00000010" complete_cpuset="0x00000010" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="15"/>
+      </object>
+      <object type="Core" os_index="1" cpuset="0x00000020" complete_cpuset="0x00000020" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="16">
+        <object type="PU" os_index="5" cpuset="0x00000020" complete_cpuset="0x00000020" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="17"/>
+      </object>
+      <object type="Core" os_index="2" cpuset="0x00000040" complete_cpuset="0x00000040" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="18">
+        <object type="PU" os_index="6" cpuset="0x00000040" complete_cpuset="0x00000040" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="19"/>
+      </object>
+      <object type="Core" os_index="3" cpuset="0x00000080" complete_cpuset="0x00000080" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="20">
+        <object type="PU" os_index="7" cpuset="0x00000080" complete_cpuset="0x00000080" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="21"/>
+      </object>
+      <object type="Bridge" gp_index="22" bridge_type="0-1" depth="0" bridge_pci="0000:[01-01]">
+        <object type="PCIDev" gp_index="23" pci_busid="0000:01:00.0" pci_type="0200 [8086:1521] [8086:0001] 01" pci_link_speed="0.000000">
+          <object type="OSDev" gp_index="24" name="eth0" osdev_type="2"/>
+        </object>
+      </object>
+    </object>
+  </object>
+</topology>
+"#;
+
+    /// Two packages, each with two groups that have one 8 GiB NUMA node and 2
+    /// single-PU cores, and a network interface attached to the first group of
+    /// the second package
+    const FOUR_NODES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE topology SYSTEM "hwloc2.dtd">
+<topology version="2.0">
+  <object type="Machine" os_index="0" cpuset="0x000000ff" complete_cpuset="0x000000ff" allowed_cpuset="0x000000ff" nodeset="0x0000000f" complete_nodeset="0x0000000f" allowed_nodeset="0x0000000f" gp_index="1">
+    <object type="Package" os_index="0" cpuset="0x0000000f" complete_cpuset="0x0000000f" nodeset="0x00000003" complete_nodeset="0x00000003" gp_index="2">
+      <object type="Group" cpuset="0x00000003" complete_cpuset="0x00000003" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="3">
+        <object type="NUMANode" os_index="0" cpuset="0x00000003" complete_cpuset="0x00000003" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="4" local_memory="8589934592"/>
+        <object type="Core" os_index="0" cpuset="0x00000001" complete_cpuset="0x00000001" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="5">
+          <object type="PU" os_index="0" cpuset="0x00000001" complete_cpuset="0x00000001" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="6"/>
+        </object>
+        <object type="Core" os_index="1" cpuset="0x00000002" complete_cpuset="0x00000002" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="7">
+          <object type="PU" os_index="1" cpuset="0x00000002" complete_cpuset="0x00000002" nodeset="0x00000001" complete_nodeset="0x00000001" gp_index="8"/>
+        </object>
+      </object>
+      <object type="Group" cpuset="0x0000000c" complete_cpuset="0x0000000c" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="9">
+        <object type="NUMANode" os_index="1" cpuset="0x0000000c" complete_cpuset="0x0000000c" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="10" local_memory="8589934592"/>
+        <object type="Core" os_index="2" cpuset="0x00000004" complete_cpuset="0x00000004" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="11">
+          <object type="PU" os_index="2" cpuset="0x00000004" complete_cpuset="0x00000004" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="12"/>
+        </object>
+        <object type="Core" os_index="3" cpuset="0x00000008" complete_cpuset="0x00000008" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="13">
+          <object type="PU" os_index="3" cpuset="0x00000008" complete_cpuset="0x00000008" nodeset="0x00000002" complete_nodeset="0x00000002" gp_index="14"/>
+        </object>
+      </object>
+    </object>
+    <object type="Package" os_index="1" cpuset="0x000000f0" complete_cpuset="0x000000f0" nodeset="0x0000000c" complete_nodeset="0x0000000c" gp_index="15">
+      <object type="Group" cpuset="0x00000030" complete_cpuset="0x00000030" nodeset="0x00000004" complete_nodeset="0x00000004" gp_index="16">
+        <object type="NUMANode" os_index="2" cpuset="0x00000030" complete_cpuset="0x00000030" nodeset="0x00000004" complete_nodeset="0x00000004" gp_index="17" local_memory="8589934592"/>
+        <object type="Core" os_index="4" cpuset="0x00000010" complete_cpuset="0x00000010" nodeset="0x00000004" complete_nodeset="0x00000004" gp_index="18">
+          <object type="PU" os_index="4" cpuset="0x00000010" complete_cpuset="0x00000010" nodeset="0x00000004" complete_nodeset="0x00000004" gp_index="19"/>
+        </object>
+        <object type="Core" os_index="5" cpuset="0x00000020" complete_cpuset="0x00000020" nodeset="0x00000004" complete_nodeset="0x00000004" gp_index="20">
+          <object type="PU" os_index="5" cpuset="0x00000020" complete_cpuset="0x00000020" nodeset="0x00000004" complete_nodeset="0x00000004" gp_index="21"/>
+        </object>
+        <object type="Bridge" gp_index="22" bridge_type="0-1" depth="0" bridge_pci="0000:[01-01]">
+          <object type="PCIDev" gp_index="23" pci_busid="0000:01:00.0" pci_type="0200 [8086:1521] [8086:0001] 01" pci_link_speed="0.000000">
+            <object type="OSDev" gp_index="24" name="eth0" osdev_type="2"/>
+          </object>
+        </object>
+      </object>
+      <object type="Group" cpuset="0x000000c0" complete_cpuset="0x000000c0" nodeset="0x00000008" complete_nodeset="0x00000008" gp_index="25">
+        <object type="NUMANode" os_index="3" cpuset="0x000000c0" complete_cpuset="0x000000c0" nodeset="0x00000008" complete_nodeset="0x00000008" gp_index="26" local_memory="8589934592"/>
+        <object type="Core" os_index="6" cpuset="0x00000040" complete_cpuset="0x00000040" nodeset="0x00000008" complete_nodeset="0x00000008" gp_index="27">
+          <object type="PU" os_index="6" cpuset="0x00000040" complete_cpuset="0x00000040" nodeset="0x00000008" complete_nodeset="0x00000008" gp_index="28"/>
+        </object>
+        <object type="Core" os_index="7" cpuset="0x00000080" complete_cpuset="0x00000080" nodeset="0x00000008" complete_nodeset="0x00000008" gp_index="29">
+          <object type="PU" os_index="7" cpuset="0x00000080" complete_cpuset="0x00000080" nodeset="0x00000008" complete_nodeset="0x00000008" gp_index="30"/>
+        </object>
+      </object>
+    </object>
+  </object>
+</topology>
+"#;
+
+    /// Load a test topology, including its I/O objects
+    fn load(xml: &str) -> Topology {
+        Topology::builder()
+            .from_xml(xml)
+            .unwrap()
+            .with_io_type_filter(crate::topology::builder::TypeFilter::KeepAll)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    /// Load the two-package test topology
+    fn topology() -> Topology {
+        load(TWO_PACKAGES_XML)
+    }
+
+    /// 1 GiB in bytes
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn single_node() {
+        let topology = topology();
+        let placements = topology
+            .plan_placement(&PlacementRequest::new(2).memory(8 * GIB))
+            .unwrap();
+
+        // Both NUMA nodes can satisfy the request on their own, and are
+        // equally good. The whole machine comes last.
+        assert_eq!(placements.len(), 3);
+        for placement in &placements[..2] {
+            assert!(placement.score.aligned);
+            assert_eq!(placement.score.numa_nodes, 1);
+            assert_eq!(placement.cpuset.weight(), Some(2));
+        }
+        assert_eq!(placements[0].nodeset, parse_list::<NodeSet>("0"));
+        assert_eq!(placements[0].cpuset, parse_list::<CpuSet>("0-1"));
+        assert_eq!(placements[1].nodeset, parse_list::<NodeSet>("1"));
+        assert_eq!(placements[1].cpuset, parse_list::<CpuSet>("4-5"));
+        assert!(!placements[2].score.aligned);
+        assert_eq!(placements[2].score.numa_nodes, 2);
+    }
+
+    #[test]
+    fn device_locality() {
+        let topology = topology();
+        let nic = topology
+            .os_devices()
+            .find(|dev| dev.name().is_some_and(|name| name.to_bytes() == b"eth0"))
+            .expect("The test topology has an eth0 NIC");
+        let placements = topology
+            .plan_placement(&PlacementRequest::new(2).memory(8 * GIB).near(nic))
+            .unwrap();
+
+        // Only the node of the second package is local to the NIC
+        let best = &placements[0];
+        assert!(best.score.aligned);
+        assert_eq!(best.nodeset, parse_list::<NodeSet>("1"));
+        assert_eq!(best.cpuset, parse_list::<CpuSet>("4-5"));
+        assert_eq!(best.score.local_devices, 1);
+        assert_eq!(best.score.device_local_cores, 2);
+        let first_package = placements
+            .iter()
+            .find(|placement| placement.nodeset == parse_list::<NodeSet>("0"))
+            .unwrap();
+        assert!(!first_package.score.aligned);
+        assert_eq!(first_package.score.local_devices, 0);
+
+        // Objects from other topologies are rejected
+        let other = Topology::test_instance();
+        assert!(matches!(
+            topology.plan_placement(&PlacementRequest::new(1).near(other.root_object())),
+            Err(PlacementError::ForeignObject(_))
+        ));
+    }
+
+    #[test]
+    fn fallback_proximity() {
+        let topology = load(FOUR_NODES_XML);
+        let nic = topology
+            .os_devices()
+            .find(|dev| dev.name().is_some_and(|name| name.to_bytes() == b"eth0"))
+            .expect("The test topology has an eth0 NIC");
+        let placements = topology
+            .plan_placement(&PlacementRequest::new(1).near(nic))
+            .unwrap();
+        let nodesets = placements
+            .iter()
+            .map(|placement| placement.nodeset.to_string())
+            .collect::<Vec<_>>();
+
+        // The NUMA node that is local to the NIC comes first, followed by the
+        // larger placements that include it. Among the single NUMA nodes that
+        // are not local to the NIC, the one from the same package comes first.
+        assert_eq!(
+            nodesets,
+            ["2", "2-3", "0-3", "3", "0", "1", "0-1"].map(String::from)
+        );
+        assert!(placements[0].score.aligned);
+        assert!(placements[1..]
+            .iter()
+            .all(|placement| !placement.score.aligned));
+    }
+
+    #[test]
+    fn spanning_nodes() {
+        let topology = topology();
+
+        // Requests for more memory or cores than a single node has must
+        // span the whole machine, which is then NUMA-aligned
+        for request in [
+            PlacementRequest::new(2).memory(24 * GIB),
+            PlacementRequest::new(6),
+        ] {
+            let placements = topology.plan_placement(&request).unwrap();
+            assert_eq!(placements.len(), 1);
+            assert!(placements[0].score.aligned);
+            assert_eq!(placements[0].nodeset, parse_list::<NodeSet>("0-1"));
+        }
+
+        // Available CPUs are taken into account
+        let placements = topology
+            .plan_placement(&PlacementRequest::new(3).available_cpus(parse_list("0-1,4-7")))
+            .unwrap();
+        assert_eq!(placements[0].nodeset, parse_list::<NodeSet>("1"));
+        assert_eq!(placements[0].cpuset, parse_list::<CpuSet>("4-6"));
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let topology = topology();
+        let error = topology
+            .plan_placement(&PlacementRequest::new(2).memory(64 * GIB))
+            .unwrap_err();
+        let PlacementError::Unsatisfiable(rejections) = &error else {
+            panic!("Expected an unsatisfiable request, got {error}");
+        };
+        assert_eq!(rejections.len(), 3);
+        assert!(rejections.iter().all(|rejection| matches!(
+            rejection.reason,
+            RejectionReason::NotEnoughMemory {
+                requested,
+                ..
+            } if requested == 64 * GIB
+        )));
+        assert!(error.to_string().contains("NUMA nodes 0-1 have"));
+
+        let error = topology
+            .plan_placement(&PlacementRequest::new(9))
+            .unwrap_err();
+        let PlacementError::Unsatisfiable(rejections) = error else {
+            panic!("Expected an unsatisfiable request");
+        };
+        assert!(rejections.contains(&PlacementRejection {
+            nodeset: parse_list("0-1"),
+            reason: RejectionReason::NotEnoughCores {
+                available: 8,
+                requested: 9,
+            },
+        }));
+    }
+}
@@ -0,0 +1,409 @@
+//! Object handles that are not tied to a borrow of the topology
+//!
+//! A [`&TopologyObject`](TopologyObject) borrows from the [`Topology`] it
+//! originates from, which makes it impractical to store in long-lived data
+//! structures like a worker thread's state. This module provides two ways
+//! around this limitation:
+//!
+//! - [`ObjectId`] is a cheap `Copy` identifier that can be stored anywhere,
+//!   and turned back into a [`&TopologyObject`](TopologyObject) later on using
+//!   [`Topology::resolve()`].
+//! - [`ObjectHandle`] keeps the topology alive through an [`Arc`], and can
+//!   therefore be used like a [`&TopologyObject`](TopologyObject) in `'static`
+//!   contexts, e.g. moved into a thread.
+
+use super::{depth::Depth, types::ObjectType, TopologyObject, TopologyObjectID};
+use crate::{errors::ForeignObjectError, topology::Topology};
+#[allow(unused)]
+#[cfg(test)]
+use similar_asserts::assert_eq;
+use std::{
+    fmt::{self, Debug, Display},
+    ops::Deref,
+    ptr::NonNull,
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// # Stable object handles
+//
+// --- Implementation details ---
+//
+// hwloc objects are only valid for as long as the topology that they belong
+// to, and their global persistent index is only unique within that topology.
+// Handles therefore pair the global persistent index with an identifier of
+// the topology, and look objects up again when they are resolved.
+impl Topology {
+    /// Owned identifier of a [`TopologyObject`] from this topology
+    ///
+    /// Unlike a [`&TopologyObject`](TopologyObject), the resulting [`ObjectId`]
+    /// does not borrow from the topology. It can be turned back into a
+    /// [`&TopologyObject`](TopologyObject) later on using
+    /// [`Topology::resolve()`].
+    ///
+    /// # Errors
+    ///
+    /// - [`ForeignObjectError`] if `object` does not belong to this topology.
+    pub fn object_id(&self, object: &TopologyObject) -> Result<ObjectId, ForeignObjectError> {
+        if !self.contains(object) {
+            return Err(object.into());
+        }
+        Ok(ObjectId {
+            topology: self.instance_id(),
+            global_persistent_index: object.global_persistent_index(),
+            object_type: object.object_type(),
+            depth: object.depth(),
+        })
+    }
+
+    /// Find the [`TopologyObject`] designated by an [`ObjectId`]
+    ///
+    /// The object is first looked up at the depth where it was when `id` was
+    /// generated, which takes a time that is proportional to the number of
+    /// objects at that depth. If the object is not found there, e.g. because
+    /// topology modifications removed some levels of the object hierarchy,
+    /// all objects of the same type are scanned. If you need faster lookups,
+    /// consider keeping an [`ObjectHandle`] around instead.
+    ///
+    /// # Errors
+    ///
+    /// - [`ForeignTopology`] if `id` was generated by a different topology.
+    ///   [`ObjectId`]s are tied to a specific topology instance, so this
+    ///   includes clones of the original topology.
+    /// - [`Removed`] if the object does not exist anymore, e.g. because the
+    ///   topology was [restricted] to a subset of its original resources.
+    ///
+    /// [`ForeignTopology`]: ResolveError::ForeignTopology
+    /// [`Removed`]: ResolveError::Removed
+    #[cfg_attr(
+        feature = "hwloc-2_3_0",
+        doc = "[restricted]: crate::topology::editor::TopologyEditor::restrict()"
+    )]
+    #[cfg_attr(
+        not(feature = "hwloc-2_3_0"),
+        doc = "[restricted]: https://docs.rs/hwlocality/latest/hwlocality/topology/editor/struct.TopologyEditor.html#method.restrict"
+    )]
+    pub fn resolve(&self, id: ObjectId) -> Result<&TopologyObject, ResolveError> {
+        if id.topology != self.instance_id() {
+            return Err(ResolveError::ForeignTopology);
+        }
+        let is_target =
+            |obj: &&TopologyObject| obj.global_persistent_index() == id.global_persistent_index;
+        self.objects_at_depth(id.depth)
+            .find(is_target)
+            .or_else(|| self.objects_with_type(id.object_type).find(is_target))
+            .ok_or(ResolveError::Removed(id.global_persistent_index))
+    }
+}
+
+/// Owned identifier of a [`TopologyObject`]
+///
+/// Obtained via [`Topology::object_id()`] and resolved back into a
+/// [`&TopologyObject`](TopologyObject) via [`Topology::resolve()`].
+///
+/// An `ObjectId` remembers which topology instance it was generated by, so
+/// that resolving it against another topology is reported as an error instead
+/// of silently yielding an unrelated object. Every topology that is built,
+/// adopted from shared memory or cloned within a process is a distinct
+/// instance in this sense.
+///
+/// If you need to identify objects across clones of a topology, use the
+/// [global persistent index](Self::global_persistent_index()) directly, for
+/// example via the side tables of the [`map`](super::map) module.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ObjectId {
+    /// Instance ID of the topology that this ID was generated by
+    topology: u64,
+
+    /// Global persistent index of the object
+    global_persistent_index: TopologyObjectID,
+
+    /// Type of the object
+    object_type: ObjectType,
+
+    /// Depth of the object at the time where this ID was generated
+    depth: Depth,
+}
+//
+impl ObjectId {
+    /// [Global persistent index](TopologyObject::global_persistent_index()) of
+    /// the object
+    pub fn global_persistent_index(&self) -> TopologyObjectID {
+        self.global_persistent_index
+    }
+
+    /// [Type](TopologyObject::object_type()) of the object
+    pub fn object_type(&self) -> ObjectType {
+        self.object_type
+    }
+
+    /// [Depth](TopologyObject::depth()) of the object at the time where this
+    /// ID was generated
+    ///
+    /// Topology modifications can remove levels of the object hierarchy, so
+    /// the object may have moved to a different depth since then.
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+}
+
+/// Error returned by [`Topology::resolve()`]
+#[derive(Copy, Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub enum ResolveError {
+    /// Object ID was generated by a different topology
+    #[error("object ID was generated by a different topology")]
+    ForeignTopology,
+
+    /// Object with this global persistent index is not part of the topology
+    /// anymore
+    #[error("object #{0} is not part of the topology anymore")]
+    Removed(TopologyObjectID),
+}
+
+/// [`TopologyObject`] handle that keeps its [`Topology`] alive
+///
+/// This handle holds an [`Arc<Topology>`] and dereferences to the
+/// [`TopologyObject`] it designates, so it can be used like a
+/// [`&TopologyObject`](TopologyObject) without borrowing from the topology.
+/// Cloning it is cheap.
+///
+/// Since the topology is shared, it cannot be modified as long as the handle
+/// exists, which ensures that the object remains valid.
+//
+// --- Implementation details ---
+//
+// # Safety
+//
+// As a type invariant, `object` points to an object of `*topology`. Since
+// `topology` is shared, it cannot be mutated while this handle exists (safe
+// code can only get &mut access to the inner topology via Arc methods that
+// require the reference count to be 1), so the object remains valid.
+#[derive(Clone)]
+pub struct ObjectHandle {
+    /// Topology that the object belongs to
+    topology: Arc<Topology>,
+
+    /// Object that this handle designates
+    object: NonNull<TopologyObject>,
+}
+//
+impl ObjectHandle {
+    /// Create a handle to an object of `topology`, selected by `find`
+    ///
+    /// # Errors
+    ///
+    /// - [`ForeignObjectError`] if `find` returns an object that does not
+    ///   belong to `topology`.
+    pub fn new(
+        topology: Arc<Topology>,
+        find: impl FnOnce(&Topology) -> &TopologyObject,
+    ) -> Result<Self, ForeignObjectError> {
+        let object = find(&topology);
+        if !topology.contains(object) {
+            return Err(object.into());
+        }
+        let object = NonNull::from(object);
+        Ok(Self { topology, object })
+    }
+
+    /// Create a handle to the object of `topology` designated by `id`
+    ///
+    /// # Errors
+    ///
+    /// See [`Topology::resolve()`].
+    pub fn resolve(topology: Arc<Topology>, id: ObjectId) -> Result<Self, ResolveError> {
+        let object = NonNull::from(topology.resolve(id)?);
+        Ok(Self { topology, object })
+    }
+
+    /// Topology that the object belongs to
+    pub fn topology(&self) -> &Arc<Topology> {
+        &self.topology
+    }
+
+    /// Owned identifier of the object
+    pub fn id(&self) -> ObjectId {
+        self.topology
+            .object_id(self)
+            .expect("By construction, the object belongs to the topology")
+    }
+}
+//
+impl Debug for ObjectHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectHandle")
+            .field("topology", &self.topology.as_ptr())
+            .field("object", &format_args!("{}", &**self))
+            .finish()
+    }
+}
+//
+impl Deref for ObjectHandle {
+    type Target = TopologyObject;
+
+    fn deref(&self) -> &TopologyObject {
+        // SAFETY: Per type invariant, object is valid as long as self exists
+        unsafe { self.object.as_ref() }
+    }
+}
+//
+impl Display for ObjectHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&**self, f)
+    }
+}
+//
+// SAFETY: Behaves like an (Arc<Topology>, &TopologyObject) pair, which is Send
+unsafe impl Send for ObjectHandle {}
+//
+// SAFETY: Behaves like an (Arc<Topology>, &TopologyObject) pair, which is Sync
+unsafe impl Sync for ObjectHandle {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "hwloc-2_3_0")]
+    use crate::{bitmap::BitmapIndex, cpu::cpuset::CpuSet, topology::editor::RestrictFlags};
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{Binary, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex},
+        hash::Hash,
+        io::{self, Read},
+        ops::Drop,
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(ObjectId:
+        Copy, Debug, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(ObjectId:
+        Binary, Default, Deref, Display, Drop, Error, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialOrd, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(ResolveError:
+        Copy, Error, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(ResolveError:
+        Binary, Default, Deref, Drop, IntoIterator, LowerExp, LowerHex, Octal,
+        PartialOrd, Pointer, Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(ObjectHandle:
+        Clone, Debug, Deref<Target = TopologyObject>, Display, Send, Sized,
+        Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(ObjectHandle:
+        Binary, Copy, Default, Drop, Error, Hash, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+
+    /// Synthetic topology used by these tests
+    fn topology() -> Topology {
+        Topology::synthetic_instance("Package:2 Core:2 PU:2")
+    }
+
+    #[test]
+    fn resolve() {
+        let topology = topology();
+        for obj in topology.objects() {
+            let id = topology.object_id(obj).unwrap();
+            assert_eq!(id.global_persistent_index(), obj.global_persistent_index());
+            assert_eq!(id.object_type(), obj.object_type());
+            assert_eq!(id.depth(), obj.depth());
+            assert!(std::ptr::eq(topology.resolve(id).unwrap(), obj));
+        }
+
+        let other = topology.clone();
+        let root = other.root_object();
+        assert_eq!(
+            topology.object_id(root),
+            Err(ForeignObjectError::from(root))
+        );
+        let id = topology.object_id(topology.root_object()).unwrap();
+        assert_eq!(
+            other.resolve(id).unwrap_err(),
+            ResolveError::ForeignTopology
+        );
+
+        // IDs from dropped topologies are not mistaken for IDs from newer ones
+        let dropped_id = {
+            let dropped = Topology::synthetic_instance("Package:2 Core:2 PU:2");
+            dropped.object_id(dropped.root_object()).unwrap()
+        };
+        let recreated = Topology::synthetic_instance("Package:2 Core:2 PU:2");
+        assert_eq!(
+            recreated.resolve(dropped_id).unwrap_err(),
+            ResolveError::ForeignTopology
+        );
+    }
+
+    #[test]
+    fn resolve_moved() {
+        // Objects that moved to another depth are found by type
+        let topology = Topology::synthetic_instance("Package:2 Core:2 PU:2");
+        let pu = topology.objects_with_type(ObjectType::PU).last().unwrap();
+        let id = ObjectId {
+            depth: topology.root_object().depth(),
+            ..topology.object_id(pu).unwrap()
+        };
+        assert!(std::ptr::eq(topology.resolve(id).unwrap(), pu));
+    }
+
+    #[cfg(feature = "hwloc-2_3_0")]
+    #[test]
+    fn resolve_after_restrict() {
+        let mut topology = topology();
+        let ids = topology
+            .objects()
+            .map(|obj| topology.object_id(obj).unwrap())
+            .collect::<Vec<_>>();
+        let cpuset = CpuSet::from(BitmapIndex::MIN);
+        topology.edit(|editor| editor.restrict(&cpuset, RestrictFlags::empty()).unwrap());
+        let mut num_removed = 0;
+        for id in ids {
+            match topology.resolve(id) {
+                Ok(obj) => assert_eq!(obj.global_persistent_index(), id.global_persistent_index()),
+                Err(e) => {
+                    assert_eq!(e, ResolveError::Removed(id.global_persistent_index()));
+                    num_removed += 1;
+                }
+            }
+        }
+        assert!(num_removed > 0);
+    }
+
+    #[test]
+    fn handle() {
+        let topology = Arc::new(topology());
+        let pu = ObjectHandle::new(Arc::clone(&topology), |topology| {
+            topology.objects_with_type(ObjectType::PU).last().unwrap()
+        })
+        .unwrap();
+        let id = pu.id();
+        assert_eq!(pu.object_type(), ObjectType::PU);
+        assert_eq!(
+            ObjectHandle::resolve(Arc::clone(&topology), id)
+                .unwrap()
+                .id(),
+            id
+        );
+        let foreign_root = Topology::test_instance().root_object();
+        assert_eq!(
+            ObjectHandle::new(Arc::clone(&topology), |_| foreign_root).unwrap_err(),
+            ForeignObjectError::from(foreign_root)
+        );
+
+        let moved = pu.clone();
+        let logical_index = std::thread::spawn(move || moved.logical_index())
+            .join()
+            .unwrap();
+        assert_eq!(logical_index, pu.logical_index());
+        assert!(Arc::ptr_eq(pu.topology(), &topology));
+    }
+}
@@ -0,0 +1,540 @@
+//! Side tables associating values with topology objects
+//!
+//! It is often convenient to attach some extra data to the [`TopologyObject`]s
+//! of a [`Topology`], for example per-core load statistics or per-package
+//! thread pools. But topology objects cannot be modified through the safe API,
+//! so this data must be stored on the side.
+//!
+//! The containers from this module do so by keying each value with the
+//! [global persistent index](TopologyObject::global_persistent_index()) of the
+//! object it is associated with. This index is unique within a topology, and is
+//! preserved when a topology is cloned or restricted. Therefore, a side table
+//! that was built for one topology can be transferred to a clone or a
+//! restricted copy of that topology using the `remap()` methods.
+//!
+//! Like global persistent indices themselves, these tables should only ever be
+//! used with the topology they were built for and copies thereof. Using them
+//! with an unrelated topology will not cause undefined behavior, but will
+//! associate values with the wrong objects.
+
+use super::{TopologyObject, TopologyObjectID};
+use crate::topology::Topology;
+#[allow(unused)]
+#[cfg(test)]
+use similar_asserts::assert_eq;
+use std::{
+    collections::HashMap,
+    iter::FusedIterator,
+    ops::{Index, IndexMut},
+};
+
+/// Sparse side table associating values with some [`TopologyObject`]s
+///
+/// This is a thin wrapper around a `HashMap` keyed by [global persistent
+/// index](TopologyObject::global_persistent_index()), which provides O(1)
+/// lookup, insertion and removal. It is best suited to situations where only a
+/// few objects of the topology need to have a value attached. If every object
+/// should have a value, [`ObjectVec`] will be more efficient.
+///
+/// See the [module-level documentation](self) for more information about the
+/// conditions under which this container can be used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObjectMap<T>(HashMap<TopologyObjectID, T>);
+//
+impl<T> ObjectMap<T> {
+    /// Create an empty side table
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    /// Create a side table by querying a value for each object of `topology`
+    ///
+    /// Objects for which `make_value` returns `None` will not have a value.
+    pub fn from_fn(
+        topology: &Topology,
+        mut make_value: impl FnMut(&TopologyObject) -> Option<T>,
+    ) -> Self {
+        Self(
+            topology
+                .objects()
+                .filter_map(|obj| Some((obj.global_persistent_index(), make_value(obj)?)))
+                .collect(),
+        )
+    }
+
+    /// Number of objects that have a value
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Truth that no object has a value
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Truth that an object has a value
+    pub fn contains(&self, object: &TopologyObject) -> bool {
+        self.0.contains_key(&object.global_persistent_index())
+    }
+
+    /// Value associated with an object, if any
+    pub fn get(&self, object: &TopologyObject) -> Option<&T> {
+        self.get_by_id(object.global_persistent_index())
+    }
+
+    /// Value associated with the object with a certain global persistent index
+    pub fn get_by_id(&self, id: TopologyObjectID) -> Option<&T> {
+        self.0.get(&id)
+    }
+
+    /// Mutable access to the value associated with an object, if any
+    pub fn get_mut(&mut self, object: &TopologyObject) -> Option<&mut T> {
+        self.0.get_mut(&object.global_persistent_index())
+    }
+
+    /// Associate a value with an object
+    ///
+    /// If the object already had a value, it is returned.
+    pub fn insert(&mut self, object: &TopologyObject, value: T) -> Option<T> {
+        self.0.insert(object.global_persistent_index(), value)
+    }
+
+    /// Remove the value associated with an object, if any
+    pub fn remove(&mut self, object: &TopologyObject) -> Option<T> {
+        self.0.remove(&object.global_persistent_index())
+    }
+
+    /// Remove all values
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    /// Iterate over objects that have a value, in topology order
+    ///
+    /// Topology order is the order of [`Topology::objects()`]: normal objects
+    /// ordered by increasing depth, then virtual objects ordered by type.
+    ///
+    /// This walks over all objects of `topology`, so it is a bit expensive when
+    /// only a few objects have a value. If you do not care about ordering,
+    /// [`ObjectMap::values()`] is cheaper.
+    pub fn iter<'self_>(
+        &'self_ self,
+        topology: &'self_ Topology,
+    ) -> impl FusedIterator<Item = (&'self_ TopologyObject, &'self_ T)> + Clone {
+        topology
+            .objects()
+            .filter_map(|obj| Some((obj, self.get(obj)?)))
+    }
+
+    /// Iterate over values in an unspecified order
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &T> + FusedIterator + Clone {
+        self.0.values()
+    }
+
+    /// Iterate mutably over values in an unspecified order
+    pub fn values_mut(&mut self) -> impl ExactSizeIterator<Item = &mut T> + FusedIterator {
+        self.0.values_mut()
+    }
+
+    /// Transfer this side table to a clone or restricted copy of the topology
+    /// that it was built for
+    ///
+    /// Values associated with objects that do not exist in `topology` anymore,
+    /// for example because they were removed by
+    /// [`TopologyEditor::restrict()`], are dropped.
+    ///
+    #[cfg_attr(
+        feature = "hwloc-2_3_0",
+        doc = "[`TopologyEditor::restrict()`]: crate::topology::editor::TopologyEditor::restrict()"
+    )]
+    #[cfg_attr(
+        not(feature = "hwloc-2_3_0"),
+        doc = "[`TopologyEditor::restrict()`]: https://docs.rs/hwlocality/latest/hwlocality/topology/editor/struct.TopologyEditor.html#method.restrict"
+    )]
+    pub fn remap(mut self, topology: &Topology) -> Self {
+        Self(
+            topology
+                .objects()
+                .filter_map(|obj| {
+                    let id = obj.global_persistent_index();
+                    Some((id, self.0.remove(&id)?))
+                })
+                .collect(),
+        )
+    }
+}
+//
+impl<T> Default for ObjectMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+//
+impl<'topology, T> Extend<(&'topology TopologyObject, T)> for ObjectMap<T> {
+    fn extend<I: IntoIterator<Item = (&'topology TopologyObject, T)>>(&mut self, iter: I) {
+        self.0.extend(
+            iter.into_iter()
+                .map(|(obj, value)| (obj.global_persistent_index(), value)),
+        )
+    }
+}
+//
+impl<'topology, T> FromIterator<(&'topology TopologyObject, T)> for ObjectMap<T> {
+    fn from_iter<I: IntoIterator<Item = (&'topology TopologyObject, T)>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+//
+impl<T> From<ObjectVec<T>> for ObjectMap<T> {
+    fn from(vec: ObjectVec<T>) -> Self {
+        Self(vec.ids.into_iter().zip(vec.values).collect())
+    }
+}
+
+/// Dense side table associating one value with every [`TopologyObject`]
+///
+/// Values are stored contiguously in topology order, i.e. the order of
+/// [`Topology::objects()`]: normal objects ordered by increasing depth, then
+/// virtual objects ordered by type. Lookup by object is O(1), and iteration
+/// in topology order does not require walking the topology.
+///
+/// See the [module-level documentation](self) for more information about the
+/// conditions under which this container can be used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObjectVec<T> {
+    /// Global persistent index of each object, in topology order
+    ids: Vec<TopologyObjectID>,
+
+    /// Position of each object's value within `values`
+    positions: HashMap<TopologyObjectID, usize>,
+
+    /// Value associated with each object, in topology order
+    values: Vec<T>,
+}
+//
+impl<T> ObjectVec<T> {
+    /// Create a side table by computing a value for each object of `topology`
+    pub fn from_fn(topology: &Topology, mut make_value: impl FnMut(&TopologyObject) -> T) -> Self {
+        let num_objects = topology.objects().count();
+        let mut result = Self {
+            ids: Vec::with_capacity(num_objects),
+            positions: HashMap::with_capacity(num_objects),
+            values: Vec::with_capacity(num_objects),
+        };
+        for obj in topology.objects() {
+            result.push(obj.global_persistent_index(), make_value(obj));
+        }
+        result
+    }
+
+    /// Number of objects in the table
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Truth that the table contains no object
+    ///
+    /// This can only happen for tables that were created from an empty
+    /// iterator, since a topology always contains at least one object.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Truth that an object is part of the table
+    pub fn contains(&self, object: &TopologyObject) -> bool {
+        self.positions
+            .contains_key(&object.global_persistent_index())
+    }
+
+    /// Value associated with an object
+    ///
+    /// Returns `None` if `object` does not belong to the topology that this
+    /// table was built for.
+    pub fn get(&self, object: &TopologyObject) -> Option<&T> {
+        self.get_by_id(object.global_persistent_index())
+    }
+
+    /// Value associated with the object with a certain global persistent index
+    pub fn get_by_id(&self, id: TopologyObjectID) -> Option<&T> {
+        let pos = *self.positions.get(&id)?;
+        Some(&self.values[pos])
+    }
+
+    /// Mutable access to the value associated with an object
+    ///
+    /// Returns `None` if `object` does not belong to the topology that this
+    /// table was built for.
+    pub fn get_mut(&mut self, object: &TopologyObject) -> Option<&mut T> {
+        let pos = *self.positions.get(&object.global_persistent_index())?;
+        Some(&mut self.values[pos])
+    }
+
+    /// Global persistent indices of the objects, in topology order
+    pub fn ids(&self) -> &[TopologyObjectID] {
+        &self.ids[..]
+    }
+
+    /// Values, in topology order
+    pub fn values(&self) -> &[T] {
+        &self.values[..]
+    }
+
+    /// Mutable access to values, in topology order
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values[..]
+    }
+
+    /// Iterate over (global persistent index, value) pairs in topology order
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (TopologyObjectID, &T)>
+           + ExactSizeIterator
+           + FusedIterator
+           + Clone {
+        self.ids.iter().copied().zip(&self.values)
+    }
+
+    /// Iterate mutably over (global persistent index, value) pairs in
+    /// topology order
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = (TopologyObjectID, &mut T)> + ExactSizeIterator + FusedIterator
+    {
+        self.ids.iter().copied().zip(&mut self.values)
+    }
+
+    /// Iterate over (object, value) pairs of `topology` in topology order
+    ///
+    /// Objects of `topology` which are not part of this table are skipped.
+    pub fn iter_objects<'self_>(
+        &'self_ self,
+        topology: &'self_ Topology,
+    ) -> impl FusedIterator<Item = (&'self_ TopologyObject, &'self_ T)> + Clone {
+        topology
+            .objects()
+            .filter_map(|obj| Some((obj, self.get(obj)?)))
+    }
+
+    /// Transfer this side table to a clone or restricted copy of the topology
+    /// that it was built for
+    ///
+    /// Values associated with objects that still exist in `topology` are kept,
+    /// values associated with objects that do not exist anymore (for example
+    /// because they were removed by [`TopologyEditor::restrict()`]) are
+    /// dropped, and `make_value` is called to create values for objects of
+    /// `topology` that were not part of this table. After this, the table
+    /// follows the topology order of `topology`.
+    ///
+    #[cfg_attr(
+        feature = "hwloc-2_3_0",
+        doc = "[`TopologyEditor::restrict()`]: crate::topology::editor::TopologyEditor::restrict()"
+    )]
+    #[cfg_attr(
+        not(feature = "hwloc-2_3_0"),
+        doc = "[`TopologyEditor::restrict()`]: https://docs.rs/hwlocality/latest/hwlocality/topology/editor/struct.TopologyEditor.html#method.restrict"
+    )]
+    pub fn remap(
+        self,
+        topology: &Topology,
+        mut make_value: impl FnMut(&TopologyObject) -> T,
+    ) -> Self {
+        let Self {
+            positions, values, ..
+        } = self;
+        let mut old_values = values.into_iter().map(Some).collect::<Vec<_>>();
+        Self::from_fn(topology, |obj| {
+            positions
+                .get(&obj.global_persistent_index())
+                .and_then(|&pos| old_values[pos].take())
+                .unwrap_or_else(|| make_value(obj))
+        })
+    }
+
+    /// Append a value to the table
+    fn push(&mut self, id: TopologyObjectID, value: T) {
+        let old = self.positions.insert(id, self.values.len());
+        assert!(old.is_none(), "object {id} was inserted twice");
+        self.ids.push(id);
+        self.values.push(value);
+    }
+}
+//
+impl<T> Default for ObjectVec<T> {
+    fn default() -> Self {
+        Self {
+            ids: Vec::new(),
+            positions: HashMap::new(),
+            values: Vec::new(),
+        }
+    }
+}
+//
+impl<'topology, T> FromIterator<(&'topology TopologyObject, T)> for ObjectVec<T> {
+    /// Collect (object, value) pairs into a table
+    ///
+    /// The table follows the order of the input iterator, which should
+    /// normally be topology order.
+    ///
+    /// # Panics
+    ///
+    /// If the same object appears multiple times in the input.
+    fn from_iter<I: IntoIterator<Item = (&'topology TopologyObject, T)>>(iter: I) -> Self {
+        let mut result = Self::default();
+        for (obj, value) in iter {
+            result.push(obj.global_persistent_index(), value);
+        }
+        result
+    }
+}
+//
+impl<T> Index<&TopologyObject> for ObjectVec<T> {
+    type Output = T;
+
+    /// # Panics
+    ///
+    /// If `object` does not belong to the topology that this table was built
+    /// for.
+    fn index(&self, object: &TopologyObject) -> &T {
+        self.get(object)
+            .unwrap_or_else(|| panic!("{object} is not part of this ObjectVec"))
+    }
+}
+//
+impl<T> IndexMut<&TopologyObject> for ObjectVec<T> {
+    /// # Panics
+    ///
+    /// If `object` does not belong to the topology that this table was built
+    /// for.
+    fn index_mut(&mut self, object: &TopologyObject) -> &mut T {
+        let pos = *self
+            .positions
+            .get(&object.global_persistent_index())
+            .unwrap_or_else(|| panic!("{object} is not part of this ObjectVec"));
+        &mut self.values[pos]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "hwloc-2_3_0")]
+    use crate::{bitmap::BitmapIndex, cpu::cpuset::CpuSet, topology::editor::RestrictFlags};
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(ObjectMap<u32>:
+        Clone, Debug, Default, Eq, Extend<(&'static TopologyObject, u32)>,
+        From<ObjectVec<u32>>, FromIterator<(&'static TopologyObject, u32)>,
+        PartialEq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(ObjectMap<u32>:
+        Binary, Copy, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(ObjectVec<u32>:
+        Clone, Debug, Default, Eq, FromIterator<(&'static TopologyObject, u32)>,
+        Index<&'static TopologyObject>, IndexMut<&'static TopologyObject>,
+        PartialEq, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(ObjectVec<u32>:
+        Binary, Copy, Deref, Display, Drop, Error, Hash, IntoIterator,
+        LowerExp, LowerHex, Octal, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+
+    /// Synthetic topology used by these tests
+    fn topology() -> Topology {
+        Topology::synthetic_instance("Package:2 Core:2 PU:2")
+    }
+
+    #[test]
+    fn object_map() {
+        let topology = topology();
+        let mut map = ObjectMap::from_fn(&topology, |obj| {
+            (obj.logical_index() % 2 == 0).then_some(obj.global_persistent_index())
+        });
+        for obj in topology.objects() {
+            let expected = (obj.logical_index() % 2 == 0).then_some(obj.global_persistent_index());
+            assert_eq!(map.contains(obj), expected.is_some());
+            assert_eq!(map.get(obj).copied(), expected);
+        }
+        assert!(map.iter(&topology).map(|(_, &id)| id).eq(topology
+            .objects()
+            .filter(|obj| obj.logical_index() % 2 == 0)
+            .map(TopologyObject::global_persistent_index)));
+
+        let root = topology.root_object();
+        assert_eq!(map.remove(root), Some(root.global_persistent_index()));
+        assert!(!map.contains(root));
+        assert_eq!(map.insert(root, 42), None);
+        assert_eq!(map.get(root), Some(&42));
+
+        let map2 = map.clone().remap(&topology.clone());
+        assert_eq!(map2, map);
+    }
+
+    #[test]
+    fn object_vec() {
+        let topology = topology();
+        let mut vec = ObjectVec::from_fn(&topology, TopologyObject::global_persistent_index);
+        assert_eq!(vec.len(), topology.objects().count());
+        assert!(vec.ids().iter().copied().eq(topology
+            .objects()
+            .map(TopologyObject::global_persistent_index)));
+        for obj in topology.objects() {
+            assert_eq!(vec[obj], obj.global_persistent_index());
+        }
+        assert!(vec
+            .iter_objects(&topology)
+            .all(|(obj, &id)| obj.global_persistent_index() == id));
+
+        let root = topology.root_object();
+        vec[root] = 42;
+        assert_eq!(vec.get(root), Some(&42));
+
+        let vec2 = vec.clone().remap(&topology.clone(), |_| unreachable!());
+        assert_eq!(vec2, vec);
+        assert_eq!(ObjectMap::from(vec2).len(), vec.len());
+    }
+
+    #[cfg(feature = "hwloc-2_3_0")]
+    #[test]
+    fn restrict() {
+        let topology = topology();
+        let map = ObjectMap::from_fn(&topology, |obj| Some(obj.global_persistent_index()));
+        let vec = ObjectVec::from_fn(&topology, TopologyObject::global_persistent_index);
+
+        let mut restricted = topology.clone();
+        let cpuset = CpuSet::from(BitmapIndex::MIN);
+        restricted.edit(|editor| editor.restrict(&cpuset, RestrictFlags::empty()).unwrap());
+        assert!(restricted.objects().count() < topology.objects().count());
+
+        let map = map.remap(&restricted);
+        let vec = vec.remap(&restricted, |_| unreachable!());
+        assert_eq!(map.len(), restricted.objects().count());
+        assert_eq!(vec.len(), restricted.objects().count());
+        for obj in restricted.objects() {
+            assert_eq!(map.get(obj), Some(&obj.global_persistent_index()));
+            assert_eq!(vec[obj], obj.global_persistent_index());
+        }
+        assert!(vec.ids().iter().copied().eq(restricted
+            .objects()
+            .map(TopologyObject::global_persistent_index)));
+    }
+}
@@ -15,8 +15,10 @@
 pub mod attributes;
 pub mod depth;
 pub mod distance;
+pub mod handle;
 pub(crate) mod hierarchy;
 pub(crate) mod lists;
+pub mod map;
 pub mod search;
 pub mod types;
 
@@ -116,7 +118,10 @@ use std::{
 // - io_arity is in sync with io_first_child
 // - misc_arity is in sync with misc_first_child
 // - infos_count is in sync with infos
-// - userdata should not be touched as topology duplication aliases it
+// - userdata is aliased by topology duplication, so it may only be written
+//   temporarily, either under &mut Topology (userdata export) or while the
+//   topology is being loaded (userdata import), and must be reset to null
+//   before the topology can be accessed again
 // - gp_index is stable by API contract
 #[allow(clippy::non_send_fields_in_send_ty, missing_copy_implementations)]
 #[doc(alias = "hwloc_obj")]
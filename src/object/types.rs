@@ -35,6 +35,8 @@ use hwlocality_sys::{
 #[cfg(feature = "hwloc-2_1_0")]
 use hwlocality_sys::{HWLOC_OBJ_DIE, HWLOC_OBJ_MEMCACHE};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 #[allow(unused)]
 #[cfg(test)]
 use similar_asserts::assert_eq;
@@ -45,6 +47,7 @@ use std::{
 
 /// Type of one side (upstream or downstream) of an I/O bridge
 #[cfg_attr(any(test, feature = "proptest"), derive(Sequence))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(Copy, Clone, Debug, Display, Eq, Hash, IntoPrimitive, TryFromPrimitive, PartialEq)]
 #[doc(alias = "hwloc_obj_bridge_type_e")]
 #[doc(alias = "hwloc_obj_bridge_type_t")]
@@ -63,6 +66,7 @@ crate::impl_arbitrary_for_sequence!(BridgeType);
 
 /// Cache type
 #[cfg_attr(any(test, feature = "proptest"), derive(Sequence))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(Copy, Clone, Debug, Display, Eq, Hash, IntoPrimitive, TryFromPrimitive, PartialEq)]
 #[doc(alias = "hwloc_obj_cache_type_e")]
 #[doc(alias = "hwloc_obj_cache_type_t")]
@@ -85,6 +89,7 @@ crate::impl_arbitrary_for_sequence!(CacheType);
 
 /// Type of a OS device
 #[cfg_attr(any(test, feature = "proptest"), derive(Sequence))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(Copy, Clone, Debug, Display, Eq, Hash, IntoPrimitive, TryFromPrimitive, PartialEq)]
 #[doc(alias = "hwloc_obj_osdev_type_e")]
 #[doc(alias = "hwloc_obj_osdev_type_t")]
@@ -153,7 +158,14 @@ crate::impl_arbitrary_for_sequence!(OSDeviceType);
 /// It can also help to think of it as comparing the relative depths of each type, so
 /// a `ObjectType::Machine` will be smaller than a `ObjectType::PU` since the machine
 /// contains processing units.
+//
+// --- Implementation details ---
+//
+// Deriving Deserialize is fine even though some methods use unsafe, because
+// deserialization can only produce valid enum variants.
+#[allow(clippy::unsafe_derive_deserialize)]
 #[cfg_attr(any(test, feature = "proptest"), derive(Sequence))]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(Copy, Clone, Debug, Display, Eq, Hash, IntoPrimitive, TryFromPrimitive, PartialEq)]
 #[doc(alias = "hwloc_obj_type_e")]
 #[doc(alias = "hwloc_obj_type_t")]
@@ -547,6 +559,27 @@ mod tests {
         io::Write
     );
 
+    /// Check that object types are serialized by name
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde_test::{assert_tokens, Token};
+        assert_tokens(
+            &ObjectType::PU,
+            &[Token::UnitVariant {
+                name: "ObjectType",
+                variant: "PU",
+            }],
+        );
+        assert_tokens(
+            &CacheType::Unified,
+            &[Token::UnitVariant {
+                name: "CacheType",
+                variant: "Unified",
+            }],
+        );
+    }
+
     proptest! {
         // For object subtypes, the only logic we implement is arbitrary, so just
         // exercise that it doesn't crash and we're good to go
@@ -139,7 +139,9 @@ impl TopologyBuilder {
         // Transfer hwloc_topology ownership to a Topology
         let inner = self.0;
         std::mem::forget(self);
-        Ok(Topology(inner))
+        // SAFETY: The topology was successfully loaded above, and ownership
+        //         was transferred away from the TopologyBuilder
+        Ok(unsafe { Topology::from_non_null(inner) })
     }
 }
 
@@ -959,7 +961,7 @@ impl TopologyBuilder {
     }
 
     /// Contained mutable hwloc topology pointer (for interaction with hwloc)
-    fn as_mut_ptr(&mut self) -> *mut hwloc_topology {
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut hwloc_topology {
         self.0.as_ptr()
     }
 }
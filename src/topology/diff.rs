@@ -0,0 +1,822 @@
+//! Topology differences
+//!
+//! hwloc can compute the difference between two topologies, as a list of
+//! elementary changes that turn the first topology into the second one. This
+//! difference can then be applied to other copies of the first topology, or
+//! exported to XML and loaded back later on.
+//!
+//! Only minor changes are supported, namely modifications of the local memory
+//! size of NUMA nodes, of object names, and of object info attribute values.
+//! Any other change (different object types, CPU sets, children...) is
+//! reported as a "too complex" difference entry, and such differences can
+//! neither be applied nor exported.
+
+#[cfg(feature = "hwloc-2_3_0")]
+use crate::topology::editor::TopologyEditor;
+use crate::{
+    errors::{self, HybridError, NulError, RawHwlocError},
+    ffi::{self, int, string::LibcString},
+    object::depth::Depth,
+    path::{self, PathError},
+    topology::Topology,
+};
+#[cfg(feature = "hwloc-2_3_0")]
+use bitflags::bitflags;
+#[cfg(feature = "hwloc-2_3_0")]
+use hwlocality_sys::{hwloc_topology_diff_apply_flags_e, HWLOC_TOPOLOGY_DIFF_APPLY_REVERSE};
+use hwlocality_sys::{
+    hwloc_topology_diff_obj_attr_u, hwloc_topology_diff_u, HWLOC_TOPOLOGY_DIFF_OBJ_ATTR,
+    HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_INFO, HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_NAME,
+    HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_SIZE, HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX,
+};
+#[allow(unused)]
+#[cfg(test)]
+use similar_asserts::assert_eq;
+use std::{
+    ffi::{c_char, c_int, c_uint, c_void, CStr},
+    fmt::{self, Debug},
+    iter::FusedIterator,
+    path::Path,
+    ptr,
+};
+use thiserror::Error;
+
+/// # Topology differences
+//
+// --- Implementation details ---
+//
+// Upstream docs: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__diff.html
+impl Topology {
+    /// Compute the difference between this topology and `new_topology`
+    ///
+    /// The resulting [`TopologyDiff`] lists the changes that turn this
+    /// topology into `new_topology`. It may later be applied to a copy of
+    /// this topology with [`TopologyEditor::apply_diff()`], or exported to
+    /// XML with [`TopologyDiff::export_xml()`].
+    ///
+    /// Only minor changes to object attributes are recorded in detail. If the
+    /// topologies differ in more complex ways, e.g. some objects have
+    /// different types, CPU sets or children, the difference will contain
+    /// [`TopologyDiffEntry::TooComplex`] entries, which mark the objects below
+    /// which the comparison was not carried out. Such a difference can be
+    /// inspected, but can neither be applied nor exported to XML.
+    ///
+    /// # Errors
+    ///
+    /// Computing a difference is not possible if the topologies were built
+    /// with different [`BuildFlags`], in which case an error will be returned.
+    ///
+    /// [`BuildFlags`]: crate::topology::builder::BuildFlags
+    #[doc(alias = "hwloc_topology_diff_build")]
+    pub fn diff(&self, new_topology: &Self) -> Result<TopologyDiff, RawHwlocError> {
+        let mut diff = ptr::null_mut();
+        // SAFETY: - Topologies are trusted to contain valid ptrs (type invariant)
+        //         - hwloc_topology_diff_build does not modify the topologies
+        //           despite taking *mut pointers
+        //         - diff is an out-parameter, it can have any initial value
+        //         - Per documentation, flags must be zero
+        errors::call_hwloc_int_normal("hwloc_topology_diff_build", || unsafe {
+            hwlocality_sys::hwloc_topology_diff_build(
+                self.as_ptr().cast_mut(),
+                new_topology.as_ptr().cast_mut(),
+                0,
+                &mut diff,
+            )
+        })?;
+        Ok(TopologyDiff(diff))
+    }
+}
+
+/// # Applying topology differences
+//
+// --- Implementation details ---
+//
+// Upstream docs: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__diff.html
+#[cfg(feature = "hwloc-2_3_0")]
+impl TopologyEditor<'_> {
+    /// Apply a topology difference to this topology
+    ///
+    /// The difference is normally applied in the direction in which it was
+    /// computed by [`Topology::diff()`], i.e. this topology should look like
+    /// the first topology that was compared and will be modified to look like
+    /// the second one. The [`DiffApplyFlags::REVERSE`] flag lets you apply the
+    /// difference in the opposite direction.
+    ///
+    /// If the difference cannot be applied entirely, all changes that were
+    /// already applied are reverted before returning, so the topology is left
+    /// unmodified.
+    ///
+    /// # Errors
+    ///
+    /// - [`TooComplex`] if the difference contains
+    ///   [`TopologyDiffEntry::TooComplex`] entries.
+    /// - [`BadEntry`] if one of the difference entries does not match the
+    ///   state of the topology, e.g. because it targets an object that does
+    ///   not exist or has a different value for the modified attribute.
+    ///
+    /// [`BadEntry`]: ApplyDiffError::BadEntry
+    /// [`TooComplex`]: ApplyDiffError::TooComplex
+    #[doc(alias = "hwloc_topology_diff_apply")]
+    pub fn apply_diff(
+        &mut self,
+        diff: &TopologyDiff,
+        flags: DiffApplyFlags,
+    ) -> Result<(), ApplyDiffError> {
+        if diff.is_too_complex() {
+            return Err(ApplyDiffError::TooComplex);
+        }
+        // SAFETY: - TopologyEditor is trusted to contain a valid ptr (type
+        //           invariant)
+        //         - hwloc ops are trusted to keep *mut parameters in a
+        //           valid state unless stated otherwise
+        //         - TopologyDiff is trusted to contain a valid difference list
+        //           (type invariant), which hwloc_topology_diff_apply does not
+        //           modify despite taking a *mut pointer
+        //         - flags only allows values supported by hwloc
+        let result = errors::call_hwloc_int_raw(
+            "hwloc_topology_diff_apply",
+            || unsafe {
+                hwlocality_sys::hwloc_topology_diff_apply(
+                    self.topology_mut_ptr(),
+                    diff.0,
+                    flags.bits(),
+                )
+            },
+            0,
+        );
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                // hwloc reports a failure on the N-th entry as -N
+                let entry = usize::try_from(-i64::from(e.result) - 1)
+                    .expect("Cannot be negative since e.result < 0");
+                Err(ApplyDiffError::BadEntry(entry))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hwloc-2_3_0")]
+bitflags! {
+    /// Flags to be given to [`TopologyEditor::apply_diff()`]
+    #[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+    #[doc(alias = "hwloc_topology_diff_apply_flags_e")]
+    pub struct DiffApplyFlags: hwloc_topology_diff_apply_flags_e {
+        /// Apply the topology difference in reverse direction
+        ///
+        /// This turns the second topology that was given to
+        /// [`Topology::diff()`] back into the first one.
+        #[doc(alias = "HWLOC_TOPOLOGY_DIFF_APPLY_REVERSE")]
+        const REVERSE = HWLOC_TOPOLOGY_DIFF_APPLY_REVERSE;
+    }
+}
+//
+#[cfg(feature = "hwloc-2_3_0")]
+crate::impl_arbitrary_for_bitflags!(DiffApplyFlags, hwloc_topology_diff_apply_flags_e);
+
+/// Error returned by [`TopologyEditor::apply_diff()`]
+#[cfg(feature = "hwloc-2_3_0")]
+#[derive(Copy, Clone, Debug, Error, Eq, Hash, PartialEq)]
+pub enum ApplyDiffError {
+    /// The difference contains [`TopologyDiffEntry::TooComplex`] entries
+    #[error("topology differences that are too complex cannot be applied")]
+    TooComplex,
+
+    /// The difference entry with this index could not be applied
+    ///
+    /// The topology was left unmodified.
+    #[error("failed to apply topology difference entry #{0}")]
+    BadEntry(usize),
+}
+
+/// Difference between two topologies
+///
+/// This is a list of [`TopologyDiffEntry`], which can be iterated over. It is
+/// usually computed using [`Topology::diff()`], but may also be loaded from
+/// XML using [`TopologyDiff::from_xml()`] or
+/// [`TopologyDiff::from_xml_file()`].
+//
+// --- Implementation details
+//
+// # Safety
+//
+// As a type invariant, the inner pointer is assumed to either be null (which
+// stands for an empty difference) or to point to a valid, non-aliased
+// difference list that was allocated by hwloc.
+#[doc(alias = "hwloc_topology_diff_t")]
+pub struct TopologyDiff(*mut hwloc_topology_diff_u);
+//
+impl TopologyDiff {
+    /// Load a topology difference from an XML string
+    ///
+    /// The difference must have been exported using
+    /// [`TopologyDiff::export_xml()`] or [`TopologyDiff::export_xml_file()`].
+    /// Besides the difference itself, the name of the reference topology that
+    /// was specified at export time, if any, is returned.
+    ///
+    /// # Errors
+    ///
+    /// - [`NulError`] if `xml` contains NUL chars.
+    #[doc(alias = "hwloc_topology_diff_load_xmlbuffer")]
+    pub fn from_xml(xml: &str) -> Result<(Self, Option<String>), HybridError<NulError>> {
+        let xml = LibcString::new(xml)?;
+        let mut diff = ptr::null_mut();
+        let mut refname = ptr::null_mut();
+        // SAFETY: - LibcString should yield valid C strings, which we're not
+        //           using beyond their intended lifetime
+        //         - hwloc ops are trusted not to modify *const parameters
+        //         - xml string and length are in sync
+        //         - diff and refname are out-parameters, they can have any
+        //           initial value
+        errors::call_hwloc_int_normal("hwloc_topology_diff_load_xmlbuffer", || unsafe {
+            hwlocality_sys::hwloc_topology_diff_load_xmlbuffer(
+                xml.borrow(),
+                xml.len()
+                    .try_into()
+                    .expect("XML buffer is too big for hwloc"),
+                &mut diff,
+                &mut refname,
+            )
+        })
+        .map_err(HybridError::Hwloc)?;
+        // SAFETY: If hwloc succeeded, diff and refname should be valid
+        Ok(unsafe { Self::wrap_loaded(diff, refname) })
+    }
+
+    /// Load a topology difference from an XML file
+    ///
+    /// This works a lot like [`TopologyDiff::from_xml()`], but takes a file
+    /// name as a parameter instead of an XML string.
+    ///
+    /// # Errors
+    ///
+    /// - [`ContainsNul`] if `path` contains NUL chars.
+    /// - [`NotUnicode`] if `path` contains non-Unicode data
+    ///
+    /// [`ContainsNul`]: PathError::ContainsNul
+    /// [`NotUnicode`]: PathError::NotUnicode
+    #[doc(alias = "hwloc_topology_diff_load_xml")]
+    pub fn from_xml_file(
+        path: impl AsRef<Path>,
+    ) -> Result<(Self, Option<String>), HybridError<PathError>> {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(
+            path: &Path,
+        ) -> Result<(TopologyDiff, Option<String>), HybridError<PathError>> {
+            let path = path::make_hwloc_path(path)?;
+            let mut diff = ptr::null_mut();
+            let mut refname = ptr::null_mut();
+            // SAFETY: - path has been checked to be fit for hwloc consumption
+            //         - hwloc ops are trusted not to modify *const parameters
+            //         - diff and refname are out-parameters, they can have any
+            //           initial value
+            errors::call_hwloc_int_normal("hwloc_topology_diff_load_xml", || unsafe {
+                hwlocality_sys::hwloc_topology_diff_load_xml(path.borrow(), &mut diff, &mut refname)
+            })
+            .map_err(HybridError::Hwloc)?;
+            // SAFETY: If hwloc succeeded, diff and refname should be valid
+            Ok(unsafe { TopologyDiff::wrap_loaded(diff, refname) })
+        }
+        polymorphized(path.as_ref())
+    }
+
+    /// Wrap the outputs of the hwloc XML difference loaders
+    ///
+    /// # Safety
+    ///
+    /// - `diff` must be null or a valid, non-aliased difference list that was
+    ///   allocated by hwloc
+    /// - `refname` must be null or a valid C string that was allocated with
+    ///   `malloc()`, and which will not be used anymore by the caller
+    unsafe fn wrap_loaded(
+        diff: *mut hwloc_topology_diff_u,
+        refname: *mut c_char,
+    ) -> (Self, Option<String>) {
+        // SAFETY: Per input precondition
+        let refname = unsafe { ffi::deref_str(&refname) }.map(|name| {
+            let name = name.to_string_lossy().into_owned();
+            // SAFETY: Per input precondition, refname was allocated by malloc()
+            //         and its contents have been copied
+            unsafe { libc::free(refname.cast::<c_void>()) };
+            name
+        });
+        (Self(diff), refname)
+    }
+
+    /// Truth that this difference is empty, i.e. the topologies are identical
+    pub fn is_empty(&self) -> bool {
+        self.0.is_null()
+    }
+
+    /// Truth that this difference contains [`TopologyDiffEntry::TooComplex`]
+    /// entries
+    ///
+    /// Such a difference can neither be applied nor exported to XML.
+    pub fn is_too_complex(&self) -> bool {
+        self.iter()
+            .any(|entry| matches!(entry, TopologyDiffEntry::TooComplex { .. }))
+    }
+
+    /// Iterate over the entries of this difference
+    pub fn iter(&self) -> TopologyDiffIter<'_> {
+        // SAFETY: If non-null, the pointer is valid per type invariant
+        TopologyDiffIter(unsafe { self.0.as_ref() })
+    }
+
+    /// Export this difference into an XML string
+    ///
+    /// If specified, `refname` is an identifier for the reference topology
+    /// which was used as a base when computing this difference, usually the
+    /// name of the XML file that contains it. It will be returned when the
+    /// difference is loaded back with [`TopologyDiff::from_xml()`].
+    ///
+    /// # Errors
+    ///
+    /// - [`TooComplex`] if the difference contains
+    ///   [`TopologyDiffEntry::TooComplex`] entries.
+    /// - [`RefNameContainsNul`] if `refname` contains NUL chars.
+    ///
+    /// [`RefNameContainsNul`]: DiffExportError::RefNameContainsNul
+    /// [`TooComplex`]: DiffExportError::TooComplex
+    #[doc(alias = "hwloc_topology_diff_export_xmlbuffer")]
+    pub fn export_xml(
+        &self,
+        refname: Option<&str>,
+    ) -> Result<String, HybridError<DiffExportError>> {
+        let refname = self.check_exportable(refname)?;
+        let mut xmlbuffer = ptr::null_mut();
+        let mut buflen = 0;
+        // SAFETY: - TopologyDiff is trusted to contain a valid difference list
+        //           (type invariant), which hwloc does not modify despite
+        //           taking a *mut pointer
+        //         - refname is null or a valid C string, which we're not
+        //           using beyond its intended lifetime
+        //         - xmlbuffer and buflen are out parameters, their initial
+        //           value should not be read by hwloc
+        errors::call_hwloc_int_normal("hwloc_topology_diff_export_xmlbuffer", || unsafe {
+            hwlocality_sys::hwloc_topology_diff_export_xmlbuffer(
+                self.0,
+                refname.as_ref().map_or(ptr::null(), LibcString::borrow),
+                &mut xmlbuffer,
+                &mut buflen,
+            )
+        })
+        .map_err(HybridError::Hwloc)?;
+        assert!(
+            !xmlbuffer.is_null(),
+            "Got null pointer from hwloc_topology_diff_export_xmlbuffer"
+        );
+
+        // Copy the XML into a Rust string, then liberate the hwloc buffer
+        // SAFETY: hwloc is trusted to have generated a proper C string
+        let xml = unsafe { CStr::from_ptr(xmlbuffer) };
+        assert_eq!(
+            xml.to_bytes_with_nul().len(),
+            usize::try_from(buflen).expect("Got negative buffer length from hwloc"),
+            "hwloc query emitted inconsistent results"
+        );
+        let xml = xml
+            .to_str()
+            .expect("Unexpected non-UTF8 XML string from hwloc")
+            .to_owned();
+        // SAFETY: - xmlbuffer was allocated by an hwloc XML export, and its
+        //           contents have been copied
+        //         - hwloc_free_xmlbuffer does not use its topology parameter,
+        //           which only exists for API consistency
+        unsafe { hwlocality_sys::hwloc_free_xmlbuffer(ptr::null(), xmlbuffer) };
+        Ok(xml)
+    }
+
+    /// Export this difference into an XML file at filesystem location `path`
+    ///
+    /// `refname` has the same meaning as in [`TopologyDiff::export_xml()`].
+    ///
+    /// # Errors
+    ///
+    /// - [`TooComplex`] if the difference contains
+    ///   [`TopologyDiffEntry::TooComplex`] entries.
+    /// - [`RefNameContainsNul`] if `refname` contains NUL chars.
+    /// - [`Path`] if `path` is not suitable for hwloc consumption.
+    ///
+    /// [`Path`]: DiffExportError::Path
+    /// [`RefNameContainsNul`]: DiffExportError::RefNameContainsNul
+    /// [`TooComplex`]: DiffExportError::TooComplex
+    #[doc(alias = "hwloc_topology_diff_export_xml")]
+    pub fn export_xml_file(
+        &self,
+        path: impl AsRef<Path>,
+        refname: Option<&str>,
+    ) -> Result<(), HybridError<DiffExportError>> {
+        /// Polymorphized version of this function (avoids generics code bloat)
+        fn polymorphized(
+            self_: &TopologyDiff,
+            path: &Path,
+            refname: Option<&str>,
+        ) -> Result<(), HybridError<DiffExportError>> {
+            let refname = self_.check_exportable(refname)?;
+            let path = path::make_hwloc_path(path).map_err(DiffExportError::from)?;
+            // SAFETY: - TopologyDiff is trusted to contain a valid difference
+            //           list (type invariant), which hwloc does not modify
+            //           despite taking a *mut pointer
+            //         - refname is null or a valid C string, which we're not
+            //           using beyond its intended lifetime
+            //         - path has been checked to be fit for hwloc consumption
+            errors::call_hwloc_int_normal("hwloc_topology_diff_export_xml", || unsafe {
+                hwlocality_sys::hwloc_topology_diff_export_xml(
+                    self_.0,
+                    refname.as_ref().map_or(ptr::null(), LibcString::borrow),
+                    path.borrow(),
+                )
+            })
+            .map_err(HybridError::Hwloc)?;
+            Ok(())
+        }
+        polymorphized(self, path.as_ref(), refname)
+    }
+
+    /// Check that this difference can be exported with the given refname
+    fn check_exportable(
+        &self,
+        refname: Option<&str>,
+    ) -> Result<Option<LibcString>, DiffExportError> {
+        if self.is_too_complex() {
+            return Err(DiffExportError::TooComplex);
+        }
+        refname
+            .map(LibcString::new)
+            .transpose()
+            .map_err(|NulError| DiffExportError::RefNameContainsNul)
+    }
+}
+//
+impl Debug for TopologyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+//
+impl Drop for TopologyDiff {
+    #[doc(alias = "hwloc_topology_diff_destroy")]
+    fn drop(&mut self) {
+        if self.0.is_null() {
+            return;
+        }
+        // SAFETY: - TopologyDiff is trusted to contain a valid difference list
+        //           (type invariant)
+        //         - TopologyDiff will not be usable again after Drop
+        let result = unsafe { hwlocality_sys::hwloc_topology_diff_destroy(self.0) };
+        debug_assert_eq!(result, 0, "Failed to destroy topology difference");
+    }
+}
+//
+impl<'diff> IntoIterator for &'diff TopologyDiff {
+    type Item = TopologyDiffEntry<'diff>;
+    type IntoIter = TopologyDiffIter<'diff>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+//
+// SAFETY: TopologyDiff owns its difference list and exposes no internal
+//         mutability
+unsafe impl Send for TopologyDiff {}
+//
+// SAFETY: TopologyDiff owns its difference list and exposes no internal
+//         mutability
+unsafe impl Sync for TopologyDiff {}
+
+/// Iterator over the entries of a [`TopologyDiff`]
+#[derive(Copy, Clone, Debug)]
+pub struct TopologyDiffIter<'diff>(Option<&'diff hwloc_topology_diff_u>);
+//
+impl<'diff> Iterator for TopologyDiffIter<'diff> {
+    type Item = TopologyDiffEntry<'diff>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.0?;
+        // SAFETY: - All difference list elements start with the generic
+        //           header, so it is always safe to access it
+        //         - If non-null, next points to the next element of a valid
+        //           list, which has the same lifetime as the current one
+        self.0 = unsafe { current.generic.next.as_ref() };
+        // SAFETY: current is a valid difference list element
+        Some(unsafe { TopologyDiffEntry::new(current) })
+    }
+}
+//
+impl FusedIterator for TopologyDiffIter<'_> {}
+
+/// Entry of a [`TopologyDiff`]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[doc(alias = "hwloc_topology_diff_u")]
+pub enum TopologyDiffEntry<'diff> {
+    /// An attribute of an object was modified
+    #[doc(alias = "HWLOC_TOPOLOGY_DIFF_OBJ_ATTR")]
+    #[doc(alias = "hwloc_topology_diff_obj_attr_s")]
+    ObjectAttribute {
+        /// Depth of the modified object
+        object_depth: Depth,
+
+        /// Logical index of the modified object
+        object_index: usize,
+
+        /// Description of the modification
+        change: ObjectAttributeDiff<'diff>,
+    },
+
+    /// The difference is too complex to be represented
+    ///
+    /// The subtree rooted at the designated object has not been compared.
+    #[doc(alias = "HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX")]
+    #[doc(alias = "hwloc_topology_diff_too_complex_s")]
+    TooComplex {
+        /// Depth of the object where the comparison stopped
+        object_depth: Depth,
+
+        /// Logical index of the object where the comparison stopped
+        object_index: usize,
+    },
+}
+//
+impl<'diff> TopologyDiffEntry<'diff> {
+    /// Decode a difference list element
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a valid difference list element, as generated by hwloc.
+    unsafe fn new(raw: &'diff hwloc_topology_diff_u) -> Self {
+        /// Decode the depth and index of the target object
+        fn location(obj_depth: c_int, obj_index: c_uint) -> (Depth, usize) {
+            let depth = Depth::from_raw(obj_depth)
+                .expect("Got unexpected object depth in hwloc topology difference");
+            (depth, int::expect_usize(obj_index))
+        }
+
+        // SAFETY: - All difference list elements start with the generic
+        //           header, so it is always safe to access it
+        //         - Union field accesses are checked against the type, which
+        //           is trusted to be in sync with the union state
+        unsafe {
+            match raw.generic.ty {
+                HWLOC_TOPOLOGY_DIFF_OBJ_ATTR => {
+                    let obj_attr = &raw.obj_attr;
+                    let (object_depth, object_index) =
+                        location(obj_attr.obj_depth, obj_attr.obj_index);
+                    Self::ObjectAttribute {
+                        object_depth,
+                        object_index,
+                        change: ObjectAttributeDiff::new(&obj_attr.diff),
+                    }
+                }
+                HWLOC_TOPOLOGY_DIFF_TOO_COMPLEX => {
+                    let too_complex = &raw.too_complex;
+                    let (object_depth, object_index) =
+                        location(too_complex.obj_depth, too_complex.obj_index);
+                    Self::TooComplex {
+                        object_depth,
+                        object_index,
+                    }
+                }
+                unknown => unreachable!("Got unknown topology difference type {unknown}"),
+            }
+        }
+    }
+}
+
+/// Modification of an object attribute
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[doc(alias = "hwloc_topology_diff_obj_attr_u")]
+pub enum ObjectAttributeDiff<'diff> {
+    /// The local memory size of a NUMA node was modified
+    #[doc(alias = "HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_SIZE")]
+    Size {
+        /// Local memory size in the old topology, in bytes
+        old: u64,
+
+        /// Local memory size in the new topology, in bytes
+        new: u64,
+    },
+
+    /// The name of an object was modified
+    #[doc(alias = "HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_NAME")]
+    Name {
+        /// Object name in the old topology, if any
+        old: Option<&'diff CStr>,
+
+        /// Object name in the new topology, if any
+        new: Option<&'diff CStr>,
+    },
+
+    /// The value of an object info attribute was modified
+    #[doc(alias = "HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_INFO")]
+    Info {
+        /// Name of the info attribute
+        name: &'diff CStr,
+
+        /// Value of the info attribute in the old topology
+        old: Option<&'diff CStr>,
+
+        /// Value of the info attribute in the new topology
+        new: Option<&'diff CStr>,
+    },
+}
+//
+impl<'diff> ObjectAttributeDiff<'diff> {
+    /// Decode an object attribute modification
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a valid object attribute modification, as generated by
+    /// hwloc.
+    unsafe fn new(raw: &'diff hwloc_topology_diff_obj_attr_u) -> Self {
+        // SAFETY: - All object attribute modifications start with the generic
+        //           header, so it is always safe to access it
+        //         - Union field accesses are checked against the type, which
+        //           is trusted to be in sync with the union state
+        //         - Strings are owned by the difference list, which outlives
+        //           'diff and is not modified during that time
+        unsafe {
+            match raw.generic.ty {
+                HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_SIZE => Self::Size {
+                    old: raw.uint64.oldvalue,
+                    new: raw.uint64.newvalue,
+                },
+                HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_NAME => Self::Name {
+                    old: ffi::deref_str(&raw.string.oldvalue),
+                    new: ffi::deref_str(&raw.string.newvalue),
+                },
+                HWLOC_TOPOLOGY_DIFF_OBJ_ATTR_INFO => Self::Info {
+                    name: ffi::deref_str(&raw.string.name)
+                        .expect("Got info attribute difference without a name"),
+                    old: ffi::deref_str(&raw.string.oldvalue),
+                    new: ffi::deref_str(&raw.string.newvalue),
+                },
+                unknown => {
+                    unreachable!("Got unknown object attribute difference type {unknown}")
+                }
+            }
+        }
+    }
+}
+
+/// Error returned by [`TopologyDiff::export_xml()`] and
+/// [`TopologyDiff::export_xml_file()`]
+#[derive(Copy, Clone, Debug, Error, Eq, Hash, PartialEq)]
+pub enum DiffExportError {
+    /// The difference contains [`TopologyDiffEntry::TooComplex`] entries
+    #[error("topology differences that are too complex cannot be exported")]
+    TooComplex,
+
+    /// The reference topology name contains NUL chars
+    #[error("reference topology name can't contain NUL chars")]
+    RefNameContainsNul,
+
+    /// The output file path is not suitable for hwloc consumption
+    #[error(transparent)]
+    Path(#[from] PathError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        ffi::CString,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(TopologyDiff:
+        Debug, Drop, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(TopologyDiff:
+        Binary, Clone, Default, Deref, Display, Error, IntoIterator, LowerExp,
+        LowerHex, Octal, PartialEq, Pointer, Read, UpperExp, UpperHex,
+        fmt::Write, io::Write
+    );
+    assert_impl_all!(TopologyDiffIter<'static>:
+        Copy, Debug, FusedIterator, Sized, Unpin, UnwindSafe
+    );
+    assert_impl_all!(TopologyDiffEntry<'static>:
+        Copy, Debug, Eq, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(ObjectAttributeDiff<'static>:
+        Copy, Debug, Eq, Hash, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_impl_all!(DiffExportError:
+        Copy, Debug, Eq, Error, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    #[cfg(feature = "hwloc-2_3_0")]
+    assert_impl_all!(ApplyDiffError:
+        Copy, Debug, Eq, Error, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+
+    #[test]
+    fn identical() {
+        const DESCRIPTION: &str = "Package:2 Core:2 PU:2";
+        let diff = Topology::synthetic_instance(DESCRIPTION)
+            .diff(&Topology::synthetic_instance(DESCRIPTION))
+            .unwrap();
+        assert!(diff.is_empty());
+        assert!(!diff.is_too_complex());
+        assert_eq!(diff.iter().count(), 0);
+        assert_eq!(format!("{diff:?}"), "[]");
+    }
+
+    #[test]
+    fn too_complex() {
+        let old = Topology::synthetic_instance("Package:2 Core:2 PU:2");
+        let new = Topology::synthetic_instance("Package:2 Core:4 PU:2");
+        let diff = old.diff(&new).unwrap();
+        assert!(!diff.is_empty());
+        assert!(diff.is_too_complex());
+        assert_eq!(
+            diff.export_xml(None).unwrap_err(),
+            HybridError::Rust(DiffExportError::TooComplex)
+        );
+        #[cfg(feature = "hwloc-2_3_0")]
+        {
+            let mut old = old;
+            old.edit(|editor| {
+                assert_eq!(
+                    editor.apply_diff(&diff, DiffApplyFlags::empty()),
+                    Err(ApplyDiffError::TooComplex)
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn info_change() {
+        // hwloc records the synthetic description as an info attribute of the
+        // root object, so equivalent descriptions that are spelled differently
+        // lead to topologies that only differ by this attribute
+        const OLD: &str = "Package:2 Core:2 PU:2";
+        const NEW: &str = "Package:2  Core:2  PU:2";
+        let old = Topology::synthetic_instance(OLD);
+        let new = Topology::synthetic_instance(NEW);
+        let diff = old.diff(&new).unwrap();
+        assert!(!diff.is_too_complex());
+        let description_change = TopologyDiffEntry::ObjectAttribute {
+            object_depth: old.root_object().depth(),
+            object_index: 0,
+            change: ObjectAttributeDiff::Info {
+                name: &CString::new("SyntheticDescription").unwrap(),
+                old: Some(&CString::new(OLD).unwrap()),
+                new: Some(&CString::new(NEW).unwrap()),
+            },
+        };
+        assert!(diff.iter().any(|entry| entry == description_change));
+
+        // Check XML round trip
+        let xml = diff.export_xml(Some("old.xml")).unwrap();
+        let (loaded, refname) = TopologyDiff::from_xml(&xml).unwrap();
+        assert_eq!(refname.as_deref(), Some("old.xml"));
+        assert!(loaded.iter().eq(diff.iter()));
+        assert_eq!(
+            diff.export_xml(Some("old\0.xml")).unwrap_err(),
+            HybridError::Rust(DiffExportError::RefNameContainsNul)
+        );
+
+        // Check application in both directions
+        #[cfg(feature = "hwloc-2_3_0")]
+        {
+            let description = |topology: &Topology| {
+                topology
+                    .root_object()
+                    .info("SyntheticDescription")
+                    .map(|value| value.to_str().unwrap().to_owned())
+            };
+            let mut patched = old;
+            patched.edit(|editor| {
+                editor.apply_diff(&loaded, DiffApplyFlags::empty()).unwrap();
+            });
+            assert_eq!(description(&patched).as_deref(), Some(NEW));
+            patched.edit(|editor| {
+                editor.apply_diff(&loaded, DiffApplyFlags::REVERSE).unwrap();
+            });
+            assert_eq!(description(&patched).as_deref(), Some(OLD));
+
+            // Applying the difference to an incompatible topology should fail
+            // without modifying it
+            patched.edit(|editor| {
+                assert!(matches!(
+                    editor.apply_diff(&loaded, DiffApplyFlags::REVERSE),
+                    Err(ApplyDiffError::BadEntry(_))
+                ));
+            });
+            assert_eq!(description(&patched).as_deref(), Some(OLD));
+        }
+    }
+}
@@ -14,6 +14,7 @@
 //!   probe, but does so at the cost of extra complexity.
 
 pub mod synthetic;
+pub mod userdata;
 pub mod xml;
 
 #[cfg(doc)]
@@ -0,0 +1,858 @@
+//! Exporting object userdata to XML
+//!
+//! hwloc lets applications attach named blobs of data to topology objects when
+//! a topology is exported to XML, and get these blobs back when the XML is
+//! loaded again. This makes it possible for application-specific annotations
+//! of the topology to survive a save and reload cycle.
+//!
+//! Export is carried out by [`Topology::export_xml_with_userdata()`] and
+//! [`Topology::export_xml_file_with_userdata()`], which call a user-provided
+//! closure on every object of the topology. Import is carried out by
+//! [`TopologyBuilder::build_with_userdata()`], which decodes each blob with a
+//! user-provided closure and returns the results in a [`UserDataTable`].
+
+use crate::{
+    errors::{self, HybridError, NulError, RawHwlocError},
+    ffi::{string::LibcString, transparent::AsNewtype},
+    object::{depth::Depth, TopologyObject, TopologyObjectID},
+    path::PathError,
+    topology::{
+        builder::TopologyBuilder,
+        export::xml::{XMLExportFlags, XML},
+        Topology,
+    },
+};
+use hwlocality_sys::{hwloc_obj_t, hwloc_topology, hwloc_topology_t};
+#[allow(unused)]
+#[cfg(test)]
+use similar_asserts::assert_eq;
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::{hash_map, HashMap},
+    ffi::{c_char, c_int, c_void, CStr},
+    fmt::{self, Debug},
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr::{self, NonNull},
+};
+use thiserror::Error;
+
+/// # Exporting object userdata to XML
+//
+// --- Implementation details ---
+//
+// Upstream docs: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__xmlexport.html
+impl Topology {
+    /// Export the topology into an XML memory buffer, along with userdata
+    ///
+    /// This works like [`Topology::export_xml()`], but `export` is called on
+    /// every object of the topology during the export process, and can use
+    /// the provided [`UserDataExporter`] to attach any number of named blobs
+    /// of data to the XML description of this object.
+    ///
+    /// This userdata can later be imported back using
+    /// [`TopologyBuilder::build_with_userdata()`].
+    ///
+    /// This method needs exclusive access to the topology because the export
+    /// callback is stored inside of it while the export is in progress.
+    ///
+    /// # Errors
+    ///
+    /// If `export` returns an error, it will not be called again, and the
+    /// error will be returned once hwloc is done exporting the topology.
+    ///
+    /// # Panics
+    ///
+    /// If `export` panics, it will not be called again, and the panic will
+    /// be propagated once hwloc is done exporting the topology.
+    #[doc(alias = "hwloc_topology_set_userdata_export_callback")]
+    pub fn export_xml_with_userdata(
+        &mut self,
+        flags: XMLExportFlags,
+        export: impl FnMut(&mut UserDataExporter<'_>) -> Result<(), HybridError<UserDataExportError>>,
+    ) -> Result<XML<'_>, HybridError<UserDataExportError>> {
+        self.with_userdata_export(export, |topology| topology.export_xml(flags))?
+            .map_err(HybridError::Hwloc)
+    }
+
+    /// Export the topology into an XML file, along with userdata
+    ///
+    /// This works like [`Topology::export_xml_file()`], but `export` is called
+    /// on every object of the topology during the export process, as in
+    /// [`Topology::export_xml_with_userdata()`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Path`] if `path` is not suitable for hwloc consumption.
+    /// - Any error returned by `export`, in which case it will not be called
+    ///   again.
+    ///
+    /// [`Path`]: UserDataExportError::Path
+    ///
+    /// # Panics
+    ///
+    /// If `export` panics, it will not be called again, and the panic will
+    /// be propagated once hwloc is done exporting the topology.
+    #[doc(alias = "hwloc_topology_set_userdata_export_callback")]
+    pub fn export_xml_file_with_userdata(
+        &mut self,
+        path: Option<impl AsRef<Path>>,
+        flags: XMLExportFlags,
+        export: impl FnMut(&mut UserDataExporter<'_>) -> Result<(), HybridError<UserDataExportError>>,
+    ) -> Result<(), HybridError<UserDataExportError>> {
+        self.with_userdata_export(export, |topology| topology.export_xml_file(path, flags))?
+            .map_err(|e| match e {
+                HybridError::Rust(e) => HybridError::Rust(UserDataExportError::Path(e)),
+                HybridError::Hwloc(e) => HybridError::Hwloc(e),
+            })
+    }
+
+    /// Run `action` while `export` is registered as the userdata export
+    /// callback of this topology
+    fn with_userdata_export<'self_, F, R>(
+        &'self_ mut self,
+        export: F,
+        action: impl FnOnce(&'self_ Self) -> R,
+    ) -> Result<R, HybridError<UserDataExportError>>
+    where
+        F: FnMut(&mut UserDataExporter<'_>) -> Result<(), HybridError<UserDataExportError>>,
+    {
+        // hwloc only calls the export callback on objects with non-null
+        // userdata, which hwlocality does not otherwise use, so mark every
+        // object with a placeholder and register the callback
+        let topology = self.as_mut_ptr();
+        let mut context = ExportContext {
+            export,
+            failure: None,
+        };
+        let result = {
+            let _guard = ExportGuard::new(topology, &mut context);
+            action(self)
+        };
+
+        // Report the outcome of the export callback
+        match context.failure {
+            None => Ok(result),
+            Some(CallbackFailure::Error(e)) => Err(e),
+            Some(CallbackFailure::Panic(payload)) => {
+                drop(result);
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
+/// Handle to the object userdata export process
+///
+/// This is passed to the closure given to
+/// [`Topology::export_xml_with_userdata()`] and
+/// [`Topology::export_xml_file_with_userdata()`] and lets it attach named
+/// blobs of data to the XML description of the [object](Self::object()) that
+/// is being exported.
+///
+/// Each blob may be given an optional name, which must only contain printable
+/// ASCII characters. When the XML is loaded back, the blobs are handed over to
+/// the import closure in the order where they were exported.
+//
+// --- Implementation details ---
+//
+// # Safety
+//
+// As a type invariant, the inner pointers are those that were passed by hwloc
+// to the export callback, which only exists during the callback.
+pub struct UserDataExporter<'callback> {
+    /// Opaque hwloc export state
+    reserved: *mut c_void,
+
+    /// Topology that is being exported
+    topology: hwloc_topology_t,
+
+    /// Object that is being exported
+    object: hwloc_obj_t,
+
+    /// Lifetime of the export callback
+    _callback: PhantomData<&'callback TopologyObject>,
+}
+//
+impl<'callback> UserDataExporter<'callback> {
+    /// Object whose userdata is being exported
+    pub fn object(&self) -> &'callback TopologyObject {
+        // SAFETY: - hwloc is trusted to pass a valid object to the callback
+        //         - The topology is not modified during export
+        unsafe { (&*self.object).as_newtype() }
+    }
+
+    /// Export userdata as text
+    ///
+    /// Only printable ASCII characters, tabs and newlines may be exported
+    /// this way. Binary data and non-ASCII text should be exported using
+    /// [`UserDataExporter::export_base64()`] instead.
+    ///
+    /// # Errors
+    ///
+    /// - [`BadName`] if `name` contains non-printable or non-ASCII characters.
+    /// - [`BadText`] if `text` contains non-printable or non-ASCII characters.
+    ///
+    /// [`BadName`]: UserDataExportError::BadName
+    /// [`BadText`]: UserDataExportError::BadText
+    #[doc(alias = "hwloc_export_obj_userdata")]
+    pub fn export_text(
+        &mut self,
+        name: Option<&str>,
+        text: &str,
+    ) -> Result<(), HybridError<UserDataExportError>> {
+        if !is_exportable(text.as_bytes()) {
+            return Err(UserDataExportError::BadText.into());
+        }
+        self.export(
+            "hwloc_export_obj_userdata",
+            hwlocality_sys::hwloc_export_obj_userdata,
+            name,
+            text.as_bytes(),
+        )
+    }
+
+    /// Export binary userdata, encoded as base64
+    ///
+    /// The data is automatically decoded back when the XML is imported.
+    ///
+    /// # Errors
+    ///
+    /// - [`BadName`] if `name` contains non-printable or non-ASCII characters.
+    ///
+    /// [`BadName`]: UserDataExportError::BadName
+    #[doc(alias = "hwloc_export_obj_userdata_base64")]
+    pub fn export_base64(
+        &mut self,
+        name: Option<&str>,
+        data: &[u8],
+    ) -> Result<(), HybridError<UserDataExportError>> {
+        self.export(
+            "hwloc_export_obj_userdata_base64",
+            hwlocality_sys::hwloc_export_obj_userdata_base64,
+            name,
+            data,
+        )
+    }
+
+    /// Export userdata using the specified hwloc entry point
+    fn export(
+        &mut self,
+        api: &'static str,
+        exporter: unsafe extern "C" fn(
+            *mut c_void,
+            hwloc_topology_t,
+            hwloc_obj_t,
+            *const c_char,
+            *const c_void,
+            usize,
+        ) -> c_int,
+        name: Option<&str>,
+        data: &[u8],
+    ) -> Result<(), HybridError<UserDataExportError>> {
+        let name = name
+            .map(|name| {
+                if !is_exportable(name.as_bytes()) {
+                    return Err(UserDataExportError::BadName);
+                }
+                LibcString::new(name).map_err(|NulError| UserDataExportError::BadName)
+            })
+            .transpose()?;
+        // SAFETY: - reserved, topology and object come from the export
+        //           callback, which is still running (type invariant)
+        //         - name is null or a valid C string, which we're not
+        //           using beyond its intended lifetime
+        //         - data pointer and length are in sync
+        //         - hwloc ops are trusted not to modify *const parameters
+        errors::call_hwloc_int_normal(api, || unsafe {
+            exporter(
+                self.reserved,
+                self.topology,
+                self.object,
+                name.as_ref().map_or(ptr::null(), LibcString::borrow),
+                data.as_ptr().cast::<c_void>(),
+                data.len(),
+            )
+        })
+        .map_err(HybridError::Hwloc)?;
+        Ok(())
+    }
+}
+//
+impl Debug for UserDataExporter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserDataExporter")
+            .field("object", self.object())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Error while exporting object userdata to XML
+#[derive(Copy, Clone, Debug, Error, Eq, Hash, PartialEq)]
+pub enum UserDataExportError {
+    /// Userdata name contains non-printable or non-ASCII characters
+    #[error("userdata names can only contain printable ASCII characters")]
+    BadName,
+
+    /// Text userdata contains non-printable or non-ASCII characters
+    #[error("text userdata can only contain printable ASCII characters, use base64 instead")]
+    BadText,
+
+    /// The XML output path is not suitable for hwloc consumption
+    #[error(transparent)]
+    Path(#[from] PathError),
+}
+
+/// # Importing object userdata from XML
+//
+// --- Implementation details ---
+//
+// Upstream docs: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__xmlexport.html
+impl TopologyBuilder {
+    /// Load the topology, along with any userdata found in its XML description
+    ///
+    /// This works like [`TopologyBuilder::build()`], but if the topology is
+    /// loaded from XML, `import` is called on every userdata blob that was
+    /// exported by [`Topology::export_xml_with_userdata()`], along with its
+    /// name if it had one. The decoded values are collected into a
+    /// [`UserDataTable`], which associates them with the corresponding objects
+    /// of the new topology.
+    ///
+    /// Blobs that were exported as base64 are decoded before being passed to
+    /// `import`. Userdata from objects that hwloc discards while building the
+    /// topology, e.g. due to type filters, is discarded as well.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`TopologyBuilder::build()`].
+    ///
+    /// # Panics
+    ///
+    /// If `import` panics, it will not be called again, and the panic will be
+    /// propagated once hwloc is done loading the topology.
+    #[doc(alias = "hwloc_topology_set_userdata_import_callback")]
+    pub fn build_with_userdata<T, F>(
+        mut self,
+        import: F,
+    ) -> Result<(Topology, UserDataTable<T>), RawHwlocError>
+    where
+        F: FnMut(Option<&CStr>, &[u8]) -> T,
+    {
+        // Register the import callback and load the topology
+        let mut context = ImportContext {
+            import,
+            slots: Vec::new(),
+            panic: None,
+        };
+        // SAFETY: - TopologyBuilder is trusted to contain a valid ptr (type
+        //           invariant)
+        //         - import_trampoline has the signature expected by hwloc, and
+        //           will only be called while the matching context is installed
+        unsafe {
+            hwlocality_sys::hwloc_topology_set_userdata_import_callback(
+                self.as_mut_ptr(),
+                Some(import_trampoline::<T, F>),
+            )
+        }
+        let result = {
+            let _guard = ContextGuard::new(ptr::addr_of_mut!(context).cast::<c_void>());
+            self.build()
+        };
+        if let Some(payload) = context.panic {
+            panic::resume_unwind(payload)
+        }
+        let mut topology = result?;
+
+        // Unregister the import callback and collect the imported userdata
+        let topology_ptr = topology.as_mut_ptr();
+        let mut table = HashMap::new();
+        // SAFETY: - Topology is trusted to contain a valid ptr (type invariant)
+        //         - Object userdata was either left null by hwloc or set to a
+        //           slot index by import_trampoline, and is reset to null so
+        //           that it cannot be observed afterwards
+        unsafe {
+            hwlocality_sys::hwloc_topology_set_userdata_import_callback(topology_ptr, None);
+            for_each_raw_object(topology_ptr, |obj| {
+                let userdata = (*obj).userdata;
+                if userdata.is_null() {
+                    return;
+                }
+                (*obj).userdata = ptr::null_mut();
+                let slot = userdata as usize - 1;
+                let values = std::mem::take(&mut context.slots[slot]);
+                table.insert((*obj).gp_index, values);
+            });
+        }
+        Ok((topology, UserDataTable(table)))
+    }
+}
+
+/// Object userdata that was imported from XML
+///
+/// This side table is produced by [`TopologyBuilder::build_with_userdata()`].
+/// It holds the values that the import closure produced for each object of
+/// the topology, in the order where the corresponding blobs were exported,
+/// and is keyed by [global persistent
+/// index](TopologyObject::global_persistent_index()).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserDataTable<T>(HashMap<TopologyObjectID, Vec<T>>);
+//
+impl<T> UserDataTable<T> {
+    /// Number of objects that have userdata
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Truth that no object has userdata
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Userdata of `object`, in export order
+    ///
+    /// The result is empty if `object` has no userdata.
+    pub fn get(&self, object: &TopologyObject) -> &[T] {
+        self.get_by_id(object.global_persistent_index())
+    }
+
+    /// Userdata of the object with global persistent index `id`, in export
+    /// order
+    ///
+    /// The result is empty if this object has no userdata.
+    pub fn get_by_id(&self, id: TopologyObjectID) -> &[T] {
+        self.0.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Take the userdata of `object` out of the table
+    pub fn remove(&mut self, object: &TopologyObject) -> Vec<T> {
+        self.0
+            .remove(&object.global_persistent_index())
+            .unwrap_or_default()
+    }
+
+    /// Iterate over objects with userdata, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (TopologyObjectID, &[T])> + '_ {
+        self.0.iter().map(|(id, values)| (*id, values.as_slice()))
+    }
+}
+//
+impl<T> Default for UserDataTable<T> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+//
+impl<T> IntoIterator for UserDataTable<T> {
+    type Item = (TopologyObjectID, Vec<T>);
+    type IntoIter = hash_map::IntoIter<TopologyObjectID, Vec<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+thread_local! {
+    /// Context of the userdata callbacks that hwloc may call on this thread
+    ///
+    /// hwloc userdata callbacks do not take a context pointer, but are only
+    /// called synchronously by the thread that is exporting or loading the
+    /// topology, so their context is passed through this thread-local.
+    static CALLBACK_CONTEXT: Cell<*mut c_void> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// RAII guard that installs a userdata callback context on the current thread
+/// and restores the previous one when dropped
+struct ContextGuard(*mut c_void);
+//
+impl ContextGuard {
+    /// Install a new userdata callback context
+    fn new(context: *mut c_void) -> Self {
+        Self(CALLBACK_CONTEXT.with(|cell| cell.replace(context)))
+    }
+}
+//
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CALLBACK_CONTEXT.with(|cell| cell.set(self.0));
+    }
+}
+
+/// Way in which a userdata callback failed
+enum CallbackFailure<E> {
+    /// The user closure returned an error
+    Error(E),
+
+    /// The user closure panicked
+    Panic(Box<dyn Any + Send + 'static>),
+}
+
+/// Context of the userdata export callback
+struct ExportContext<F> {
+    /// User-provided export closure
+    export: F,
+
+    /// First failure of the export closure, if any
+    failure: Option<CallbackFailure<HybridError<UserDataExportError>>>,
+}
+
+/// RAII guard that sets up a topology for userdata export, and restores it
+/// to its normal state when dropped
+struct ExportGuard {
+    /// Topology that is being exported
+    topology: *mut hwloc_topology,
+
+    /// Callback context installation guard
+    _context: ContextGuard,
+}
+//
+impl ExportGuard {
+    /// Set up `topology` for userdata export with `context`
+    fn new<F>(topology: *mut hwloc_topology, context: &mut ExportContext<F>) -> Self
+    where
+        F: FnMut(&mut UserDataExporter<'_>) -> Result<(), HybridError<UserDataExportError>>,
+    {
+        let context = ContextGuard::new(ptr::addr_of_mut!(*context).cast::<c_void>());
+        let placeholder = NonNull::<c_void>::dangling().as_ptr();
+        // SAFETY: - topology comes from an &mut Topology, which is trusted to
+        //           contain a valid ptr (type invariant)
+        //         - hwlocality does not otherwise use object userdata, so it
+        //           can be set to a placeholder that hwloc never dereferences
+        //         - export_trampoline has the signature expected by hwloc, and
+        //           will only be called while the matching context is installed
+        unsafe {
+            for_each_raw_object(topology, |obj| (*obj).userdata = placeholder);
+            hwlocality_sys::hwloc_topology_set_userdata_export_callback(
+                topology,
+                Some(export_trampoline::<F>),
+            );
+        }
+        Self {
+            topology,
+            _context: context,
+        }
+    }
+}
+//
+impl Drop for ExportGuard {
+    fn drop(&mut self) {
+        // SAFETY: - topology is still valid, as the &mut Topology it comes from
+        //           outlives this guard
+        //         - Unregistering the callback before the context guard is
+        //           dropped ensures that it cannot see the wrong context
+        unsafe {
+            hwlocality_sys::hwloc_topology_set_userdata_export_callback(self.topology, None);
+            for_each_raw_object(self.topology, |obj| (*obj).userdata = ptr::null_mut());
+        }
+    }
+}
+
+/// Userdata export callback that forwards to an [`ExportContext<F>`]
+///
+/// # Safety
+///
+/// Must only be called by hwloc while a matching [`ExportContext<F>`] is
+/// installed in [`CALLBACK_CONTEXT`].
+unsafe extern "C" fn export_trampoline<F>(
+    reserved: *mut c_void,
+    topology: hwloc_topology_t,
+    obj: hwloc_obj_t,
+) where
+    F: FnMut(&mut UserDataExporter<'_>) -> Result<(), HybridError<UserDataExportError>>,
+{
+    let context = CALLBACK_CONTEXT.with(Cell::get).cast::<ExportContext<F>>();
+    // SAFETY: Per function precondition
+    let Some(context) = (unsafe { context.as_mut() }) else {
+        return;
+    };
+    if context.failure.is_some() {
+        return;
+    }
+    let mut exporter = UserDataExporter {
+        reserved,
+        topology,
+        object: obj,
+        _callback: PhantomData,
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| (context.export)(&mut exporter)));
+    context.failure = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(CallbackFailure::Error(e)),
+        Err(payload) => Some(CallbackFailure::Panic(payload)),
+    };
+}
+
+/// Context of the userdata import callback
+struct ImportContext<T, F> {
+    /// User-provided import closure
+    import: F,
+
+    /// Imported values, grouped by object
+    ///
+    /// The userdata pointer of objects with imported values is set to the
+    /// index of the associated slot plus one.
+    slots: Vec<Vec<T>>,
+
+    /// Panic from the import closure, if any
+    panic: Option<Box<dyn Any + Send + 'static>>,
+}
+
+/// Userdata import callback that forwards to an [`ImportContext<T, F>`]
+///
+/// # Safety
+///
+/// Must only be called by hwloc while a matching [`ImportContext<T, F>`] is
+/// installed in [`CALLBACK_CONTEXT`].
+unsafe extern "C" fn import_trampoline<T, F>(
+    _topology: hwloc_topology_t,
+    obj: hwloc_obj_t,
+    name: *const c_char,
+    buffer: *const c_void,
+    length: usize,
+) where
+    F: FnMut(Option<&CStr>, &[u8]) -> T,
+{
+    let context = CALLBACK_CONTEXT
+        .with(Cell::get)
+        .cast::<ImportContext<T, F>>();
+    // SAFETY: Per function precondition
+    let Some(context) = (unsafe { context.as_mut() }) else {
+        return;
+    };
+    if context.panic.is_some() {
+        return;
+    }
+    // SAFETY: hwloc is trusted to pass a valid name, if any, and a buffer of
+    //         the specified length
+    let (name, data) = unsafe {
+        let name = (!name.is_null()).then(|| CStr::from_ptr(name));
+        let data = if length == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(buffer.cast::<u8>(), length)
+        };
+        (name, data)
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| (context.import)(name, data))) {
+        Ok(value) => {
+            // SAFETY: hwloc is trusted to pass a valid object, whose userdata
+            //         is either null or was set by a previous call to this
+            //         function during the same topology load
+            unsafe {
+                let userdata = (*obj).userdata;
+                let slot = if userdata.is_null() {
+                    context.slots.push(Vec::new());
+                    (*obj).userdata = context.slots.len() as *mut c_void;
+                    context.slots.len() - 1
+                } else {
+                    userdata as usize - 1
+                };
+                context.slots[slot].push(value);
+            }
+        }
+        Err(payload) => context.panic = Some(payload),
+    }
+}
+
+/// Call `op` on every object of a topology
+///
+/// # Safety
+///
+/// `topology` must be a valid pointer to a loaded topology.
+unsafe fn for_each_raw_object(topology: *const hwloc_topology, mut op: impl FnMut(hwloc_obj_t)) {
+    // SAFETY: Per function precondition
+    let normal_depth = unsafe { hwlocality_sys::hwloc_topology_get_depth(topology) };
+    let virtual_depths = Depth::VIRTUAL_DEPTHS.iter().map(|depth| depth.to_raw());
+    for depth in (0..normal_depth).chain(virtual_depths) {
+        // SAFETY: Per function precondition, and depth is either a normal
+        //         depth of this topology or a virtual depth known to hwloc
+        let num_objects = unsafe { hwlocality_sys::hwloc_get_nbobjs_by_depth(topology, depth) };
+        for idx in 0..num_objects {
+            // SAFETY: Per function precondition, and idx is in range
+            let obj = unsafe { hwlocality_sys::hwloc_get_obj_by_depth(topology, depth, idx) };
+            assert!(
+                !obj.is_null(),
+                "Got null object from hwloc_get_obj_by_depth"
+            );
+            op(obj);
+        }
+    }
+}
+
+/// Truth that hwloc accepts to export these bytes as an XML string attribute
+fn is_exportable(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .all(|&byte| matches!(byte, b' '..=b'~' | b'\t' | b'\n' | b'\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::types::ObjectType;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, Drop},
+        panic::{AssertUnwindSafe, UnwindSafe},
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(UserDataExporter<'static>: Debug, Sized, Unpin, UnwindSafe);
+    assert_not_impl_any!(UserDataExporter<'static>:
+        Binary, Clone, Default, Deref, Display, Drop, IntoIterator,
+        LowerExp, LowerHex, Octal, PartialEq, Pointer, Read, Send, Sync,
+        UpperExp, UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(UserDataTable<u32>:
+        Clone, Debug, Default, Eq, IntoIterator, PartialEq, Send, Sized, Sync,
+        Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(UserDataTable<u32>:
+        Binary, Copy, Deref, Display, Drop, Hash, LowerExp, LowerHex, Octal,
+        Pointer, Read, UpperExp, UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(UserDataExportError:
+        Copy, Debug, Eq, Error, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+
+    /// Name of the text userdata that is attached to cores
+    const TEXT_NAME: &str = "logical_index";
+
+    /// Export some userdata about each core of a topology
+    fn export_cores(
+        exporter: &mut UserDataExporter<'_>,
+    ) -> Result<(), HybridError<UserDataExportError>> {
+        let object = exporter.object();
+        if object.object_type() == ObjectType::Core {
+            exporter.export_text(Some(TEXT_NAME), &object.logical_index().to_string())?;
+            exporter.export_base64(None, &object.global_persistent_index().to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut topology = Topology::synthetic_instance("Package:2 Core:2 PU:2");
+        let xml = topology
+            .export_xml_with_userdata(XMLExportFlags::empty(), export_cores)
+            .unwrap()
+            .as_str()
+            .to_owned();
+
+        // Userdata export should not leak into normal XML export
+        assert!(!topology
+            .export_xml(XMLExportFlags::empty())
+            .unwrap()
+            .as_str()
+            .contains("userdata"));
+
+        // Userdata should be imported back in the right place
+        let (imported, userdata) = Topology::builder()
+            .from_xml(&xml)
+            .unwrap()
+            .build_with_userdata(|name, data| {
+                (
+                    name.map(|name| name.to_str().unwrap().to_owned()),
+                    data.to_vec(),
+                )
+            })
+            .unwrap();
+        assert_eq!(
+            userdata.len(),
+            imported.objects_with_type(ObjectType::Core).count()
+        );
+        for core in imported.objects_with_type(ObjectType::Core) {
+            let expected = [
+                (
+                    Some(TEXT_NAME.to_owned()),
+                    core.logical_index().to_string().into_bytes(),
+                ),
+                (None, core.global_persistent_index().to_le_bytes().to_vec()),
+            ];
+            assert_eq!(userdata.get(core), &expected[..]);
+        }
+        assert!(userdata.get(imported.root_object()).is_empty());
+
+        // Userdata should be ignored by a normal build
+        let normal = Topology::builder().from_xml(&xml).unwrap().build().unwrap();
+        assert!(normal.export_xml(XMLExportFlags::empty()).unwrap().as_str() != xml);
+    }
+
+    #[test]
+    fn bad_userdata() {
+        let mut topology = Topology::synthetic_instance("Package:2 Core:2 PU:2");
+        let mut export = |name: Option<&'static str>, text: &'static str| {
+            topology
+                .export_xml_with_userdata(XMLExportFlags::empty(), |exporter| {
+                    exporter.export_text(name, text)
+                })
+                .map(|xml| xml.as_str().to_owned())
+        };
+        assert_eq!(
+            export(Some("bad\u{1}name"), "text").unwrap_err(),
+            HybridError::Rust(UserDataExportError::BadName)
+        );
+        assert_eq!(
+            export(Some("name"), "bad\0text").unwrap_err(),
+            HybridError::Rust(UserDataExportError::BadText)
+        );
+        assert_eq!(
+            export(None, "ünicode").unwrap_err(),
+            HybridError::Rust(UserDataExportError::BadText)
+        );
+        assert!(export(Some("name"), "good\ttext\n")
+            .unwrap()
+            .contains("userdata"));
+    }
+
+    #[test]
+    fn panicking_callbacks() {
+        let mut topology = Topology::synthetic_instance("Package:2 Core:2 PU:2");
+
+        // Export panics are propagated after a single callback invocation
+        let mut export_calls = 0;
+        let payload = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            topology
+                .export_xml_with_userdata(XMLExportFlags::empty(), |_exporter| {
+                    export_calls += 1;
+                    panic!("export panic")
+                })
+                .map(|xml| xml.as_str().to_owned())
+        }))
+        .unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"export panic"));
+        assert_eq!(export_calls, 1);
+
+        // The topology remains usable afterwards
+        let xml = topology
+            .export_xml_with_userdata(XMLExportFlags::empty(), export_cores)
+            .unwrap()
+            .as_str()
+            .to_owned();
+
+        // Import panics are propagated after a single callback invocation
+        let mut import_calls = 0;
+        let payload = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            Topology::builder()
+                .from_xml(&xml)
+                .unwrap()
+                .build_with_userdata(|_name, _data| {
+                    import_calls += 1;
+                    panic!("import panic")
+                })
+        }))
+        .unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"import panic"));
+        assert_eq!(import_calls, 1);
+    }
+}
@@ -7,9 +7,12 @@
 //! almost any other feature of the library is accessed.
 
 pub mod builder;
+pub mod diff;
 #[cfg(feature = "hwloc-2_3_0")]
 pub mod editor;
 pub mod export;
+#[cfg(unix)]
+pub mod shmem;
 pub mod support;
 
 use self::{
@@ -41,7 +44,10 @@ use std::{
     fmt::{self, Pointer},
     ops::Deref,
     ptr::{self, NonNull},
-    sync::OnceLock,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
 };
 use thiserror::Error;
 
@@ -57,6 +63,7 @@ use thiserror::Error;
 ///
 /// - [Topology building](#topology-building)
 /// - [Full object list](#full-object-list) (specific to Rust bindings)
+/// - [Stable object handles](#stable-object-handles) (specific to Rust bindings)
 /// - [Object levels, depths and types](#object-levels-depths-and-types)
 /// - [CPU cache statistics](#cpu-cache-statistics) (specific to Rust bindings)
 /// - [CPU binding](#cpu-binding)
@@ -66,15 +73,31 @@ use thiserror::Error;
 /// - [Finding objects covering at least a CPU set](#finding-objects-covering-at-least-a-cpu-set)
 /// - [Finding other objects](#finding-other-objects)
 /// - [Distributing work items over a topology](#distributing-work-items-over-a-topology)
+/// - [Spawning pinned threads](#spawning-pinned-threads) (specific to Rust bindings)
+/// - [Measuring core-to-core latency](#measuring-core-to-core-latency) (specific to Rust bindings)
 /// - [CPU and node sets of entire topologies](#cpu-and-node-sets-of-entire-topologies)
 /// - [Finding I/O objects](#finding-io-objects)
 /// - [Exporting Topologies to XML](#exporting-topologies-to-xml)
+/// - [Exporting object userdata to XML](#exporting-object-userdata-to-xml)
 /// - [Exporting Topologies to Synthetic](#exporting-topologies-to-synthetic)
+/// - [Topology differences](#topology-differences)
+#[cfg_attr(
+    unix,
+    doc = "- [Sharing topologies between processes](#sharing-topologies-between-processes)"
+)]
 /// - [Retrieve distances between objects](#retrieve-distances-between-objects)
 #[cfg_attr(
     feature = "hwloc-2_3_0",
     doc = "- [Comparing memory node attributes for finding where to allocate on](#comparing-memory-node-attributes-for-finding-where-to-allocate-on) (hwloc 2.3+)"
 )]
+#[cfg_attr(
+    feature = "hwloc-2_3_0",
+    doc = "- [Planning NUMA-aligned placements](#planning-numa-aligned-placements) (hwloc 2.3+, specific to Rust bindings)"
+)]
+#[cfg_attr(
+    feature = "hwloc-2_3_0",
+    doc = "- [Measuring memory performance](#measuring-memory-performance) (hwloc 2.3+, specific to Rust bindings)"
+)]
 #[cfg_attr(
     feature = "hwloc-2_4_0",
     doc = "- [Kinds of CPU cores](#kinds-of-cpu-cores) (hwloc 2.4+)"
@@ -100,13 +123,18 @@ use thiserror::Error;
 // As a type invariant, the inner pointer is assumed to always point to a valid
 // fully built, non-aliased topology.
 //
+// The second field is an identifier of this Topology instance, which is unique
+// within the current process. It is assigned by Topology::from_non_null() when
+// a topology is built, adopted or cloned, and used to detect object IDs from
+// other topologies in Topology::resolve().
+//
 // Any binding to an hwloc topology function that takes a user-provided
 // &TopologyObject parameter **must** check that this object does belongs to the
 // topology using the Topology::contains() method before passing it to hwloc.
 #[derive(Debug)]
 #[doc(alias = "hwloc_topology")]
 #[doc(alias = "hwloc_topology_t")]
-pub struct Topology(NonNull<hwloc_topology>);
+pub struct Topology(NonNull<hwloc_topology>, u64);
 
 /// # Topology building
 //
@@ -163,6 +191,20 @@ impl Topology {
         })
     }
 
+    /// Synthetic topology built from `description`
+    ///
+    /// Used by unit tests that need a topology whose shape is known in
+    /// advance, or a fresh topology that they can modify. See
+    /// [`TopologyBuilder::from_synthetic()`] for the description syntax.
+    #[cfg(test)]
+    pub(crate) fn synthetic_instance(description: &str) -> Self {
+        Self::builder()
+            .from_synthetic(description)
+            .expect("Synthetic test topology descriptions should be valid")
+            .build()
+            .expect("Failed to build synthetic test topology")
+    }
+
     /// Like test_instance, but separate instance
     ///
     /// Used to test that operations correctly detect foreign topology objects
@@ -885,6 +927,25 @@ impl Topology {
 
 // # General-purpose internal utilities
 impl Topology {
+    /// Wrap a newly created hwloc topology, giving it a new instance ID
+    ///
+    /// # Safety
+    ///
+    /// `topology` must point to a valid fully built topology, which is not
+    /// aliased by any other [`Topology`].
+    pub(crate) unsafe fn from_non_null(topology: NonNull<hwloc_topology>) -> Self {
+        /// Next instance ID to be assigned
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(topology, NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Identifier of this topology instance, unique within this process
+    ///
+    /// Clones of a topology get a different instance ID.
+    pub(crate) fn instance_id(&self) -> u64 {
+        self.1
+    }
+
     /// Contained hwloc topology pointer (for interaction with hwloc)
     pub(crate) fn as_ptr(&self) -> *const hwloc_topology {
         self.0.as_ptr()
@@ -933,7 +994,10 @@ impl Clone for Topology {
         })
         .expect("Duplicating a topology should not fail");
 
-        Self(NonNull::new(clone).expect("Got null pointer from hwloc_topology_dup"))
+        let clone = NonNull::new(clone).expect("Got null pointer from hwloc_topology_dup");
+        // SAFETY: hwloc_topology_dup is trusted to produce a valid, fully
+        //         built and unaliased topology
+        unsafe { Self::from_non_null(clone) }
     }
 }
 
@@ -0,0 +1,387 @@
+//! Sharing topologies between processes
+//!
+//! Loading a topology can take tens of milliseconds on large machines. When
+//! many processes of a host need the same topology, one of them can load it
+//! and write it into a file, which other processes then map into their
+//! address space as a read-only [`SharedTopology`] without any rediscovery.
+//!
+//! Because the topology is stored as a pointer-based data structure, every
+//! process must map the file at the same virtual address. Picking an address
+//! range that is free in all processes is left to the application.
+//!
+//! Most of this module's functionality is exposed via [methods of the Topology
+//! struct](../../topology/struct.Topology.html#sharing-topologies-between-processes).
+//! The module itself only hosts type definitions that are related to this
+//! functionality.
+
+use crate::{
+    errors::{self, RawHwlocError},
+    ffi::page_size,
+    topology::Topology,
+};
+use errno::Errno;
+use libc::{EBUSY, EINVAL};
+use std::{
+    borrow::Borrow,
+    ffi::c_void,
+    ops::Deref,
+    os::fd::{AsFd, AsRawFd},
+    ptr::{self, NonNull},
+};
+use thiserror::Error;
+
+/// # Sharing topologies between processes
+//
+// --- Implementation details ---
+//
+// Upstream docs: https://hwloc.readthedocs.io/en/v2.9/group__hwlocality__shmem.html
+impl Topology {
+    /// Number of bytes needed to share this topology with
+    /// [`Topology::write_to_shmem()`]
+    ///
+    /// This is a multiple of the page size.
+    #[allow(clippy::missing_errors_doc)]
+    #[doc(alias = "hwloc_shmem_topology_get_length")]
+    pub fn shmem_length(&self) -> Result<usize, RawHwlocError> {
+        let mut length = 0;
+        // SAFETY: - Topology is trusted to contain a valid ptr (type invariant)
+        //         - hwloc_shmem_topology_get_length does not modify the
+        //           topology despite taking a *mut pointer
+        //         - length is an out-parameter, it can have any initial value
+        //         - Per documentation, flags must be zero
+        errors::call_hwloc_int_normal("hwloc_shmem_topology_get_length", || unsafe {
+            hwlocality_sys::hwloc_shmem_topology_get_length(
+                self.as_ptr().cast_mut(),
+                &mut length,
+                0,
+            )
+        })?;
+        Ok(length)
+    }
+
+    /// Write this topology into `file`, so that other processes can adopt it
+    ///
+    /// The file is temporarily mapped at virtual address `address` of this
+    /// process, and the topology is duplicated into the `length` bytes that
+    /// follow `file_offset`. `file_offset`, `address` and `length` must be
+    /// multiples of the page size, and `length` must be at least
+    /// [`Topology::shmem_length()`]. The file should be large enough, or
+    /// extensible.
+    ///
+    /// Other processes can then use [`SharedTopology::adopt()`] with the same
+    /// `file_offset`, `address` and `length` to access the topology. The
+    /// address range must therefore be free in these processes too.
+    ///
+    /// # Errors
+    ///
+    /// - [`Unaligned`] if `file_offset`, `address` or `length` is not a
+    ///   multiple of the page size
+    /// - [`LengthTooSmall`] if `length` is less than
+    ///   [`Topology::shmem_length()`]
+    /// - [`AddressUnavailable`] if the address range is already in use in this
+    ///   process
+    /// - [`Hwloc`] if hwloc failed to map the file or write the topology
+    ///
+    /// [`AddressUnavailable`]: ShmemError::AddressUnavailable
+    /// [`Hwloc`]: ShmemError::Hwloc
+    /// [`LengthTooSmall`]: ShmemError::LengthTooSmall
+    /// [`Unaligned`]: ShmemError::Unaligned
+    #[doc(alias = "hwloc_shmem_topology_write")]
+    pub fn write_to_shmem(
+        &self,
+        file: impl AsFd,
+        file_offset: u64,
+        address: usize,
+        length: usize,
+    ) -> Result<(), ShmemError> {
+        check_alignment(file_offset, address, length)?;
+        let required = self.shmem_length()?;
+        if length < required {
+            return Err(ShmemError::LengthTooSmall { required, length });
+        }
+        // SAFETY: - Topology is trusted to contain a valid ptr (type invariant)
+        //         - hwloc_shmem_topology_write does not modify the topology
+        //           despite taking a *mut pointer
+        //         - The file descriptor is valid since it is borrowed
+        //         - hwloc does not map the file over existing mappings, it
+        //           fails with EBUSY if the address range is not free
+        //         - Per documentation, flags must be zero
+        let result = errors::call_hwloc_int_normal("hwloc_shmem_topology_write", || unsafe {
+            hwlocality_sys::hwloc_shmem_topology_write(
+                self.as_ptr().cast_mut(),
+                file.as_fd().as_raw_fd(),
+                file_offset,
+                address as *mut c_void,
+                length,
+                0,
+            )
+        });
+        match result {
+            Ok(_) => Ok(()),
+            Err(RawHwlocError {
+                errno: Some(Errno(EBUSY)),
+                ..
+            }) => Err(ShmemError::AddressUnavailable),
+            Err(other) => Err(ShmemError::Hwloc(other)),
+        }
+    }
+}
+
+/// Read-only topology that is shared with other processes
+///
+/// This is obtained by [adopting](Self::adopt()) a topology that another
+/// process wrote into a file with [`Topology::write_to_shmem()`]. It derefs to
+/// [`Topology`], so all topology queries are available, but since it only
+/// provides shared access to the underlying topology, it cannot be edited.
+/// Use [`Topology::clone()`] to get a private copy that can be edited.
+#[derive(Debug)]
+pub struct SharedTopology(Topology);
+//
+impl SharedTopology {
+    /// Adopt a topology that was written into `file`
+    ///
+    /// `file_offset`, `address` and `length` must be the values that were
+    /// passed to [`Topology::write_to_shmem()`] when writing the topology. The
+    /// file is mapped at virtual address `address` of this process until the
+    /// `SharedTopology` is dropped.
+    ///
+    /// # Errors
+    ///
+    /// - [`Unaligned`] if `file_offset`, `address` or `length` is not a
+    ///   multiple of the page size
+    /// - [`AddressUnavailable`] if the address range is already in use in this
+    ///   process
+    /// - [`Mismatch`] if the parameters do not match those that the topology
+    ///   was written with, or the topology was written by an incompatible build
+    ///   of hwloc
+    /// - [`Hwloc`] if hwloc failed to map the file for another reason
+    ///
+    /// # Safety
+    ///
+    /// The specified part of `file` must contain a topology that was written
+    /// by [`Topology::write_to_shmem()`], and it must not be modified for as
+    /// long as the resulting `SharedTopology` exists, as the topology is
+    /// accessed in place without any validation beyond a header check.
+    ///
+    /// [`AddressUnavailable`]: ShmemError::AddressUnavailable
+    /// [`Hwloc`]: ShmemError::Hwloc
+    /// [`Mismatch`]: ShmemError::Mismatch
+    /// [`Unaligned`]: ShmemError::Unaligned
+    #[doc(alias = "hwloc_shmem_topology_adopt")]
+    pub unsafe fn adopt(
+        file: impl AsFd,
+        file_offset: u64,
+        address: usize,
+        length: usize,
+    ) -> Result<Self, ShmemError> {
+        check_alignment(file_offset, address, length)?;
+        let mut topology = ptr::null_mut();
+        // SAFETY: - topology is an out-parameter, it can have any initial value
+        //         - The file descriptor is valid since it is borrowed
+        //         - hwloc does not map the file over existing mappings, it
+        //           fails with EBUSY if the address range is not free
+        //         - File contents are trusted per function precondition
+        //         - Per documentation, flags must be zero
+        let result = errors::call_hwloc_int_normal("hwloc_shmem_topology_adopt", || unsafe {
+            hwlocality_sys::hwloc_shmem_topology_adopt(
+                &mut topology,
+                file.as_fd().as_raw_fd(),
+                file_offset,
+                address as *mut c_void,
+                length,
+                0,
+            )
+        });
+        match result {
+            Ok(_) => {
+                let topology = NonNull::new(topology)
+                    .expect("Got null pointer from hwloc_shmem_topology_adopt");
+                // SAFETY: hwloc_shmem_topology_adopt is trusted to produce a
+                //         valid, fully built topology on success
+                Ok(Self(unsafe { Topology::from_non_null(topology) }))
+            }
+            Err(RawHwlocError {
+                errno: Some(Errno(EBUSY)),
+                ..
+            }) => Err(ShmemError::AddressUnavailable),
+            Err(RawHwlocError {
+                errno: Some(Errno(EINVAL)),
+                ..
+            }) => Err(ShmemError::Mismatch),
+            Err(other) => Err(ShmemError::Hwloc(other)),
+        }
+    }
+}
+//
+impl AsRef<Topology> for SharedTopology {
+    fn as_ref(&self) -> &Topology {
+        &self.0
+    }
+}
+//
+impl Borrow<Topology> for SharedTopology {
+    fn borrow(&self) -> &Topology {
+        &self.0
+    }
+}
+//
+impl Deref for SharedTopology {
+    type Target = Topology;
+
+    fn deref(&self) -> &Topology {
+        &self.0
+    }
+}
+
+/// Error while sharing a topology between processes
+#[derive(Copy, Clone, Debug, Eq, Error, Hash, PartialEq)]
+pub enum ShmemError {
+    /// File offset, mapping address or length is not page-aligned
+    #[error(
+        "file offset, mapping address and length must be multiples of the page size ({0} bytes)"
+    )]
+    Unaligned(usize),
+
+    /// Mapping length is too small to hold the topology
+    #[error("topology needs {required} bytes of shared memory, but only {length} were provided")]
+    LengthTooSmall {
+        /// Length that is required to hold the topology
+        required: usize,
+
+        /// Length that was provided
+        length: usize,
+    },
+
+    /// Requested virtual address range is already in use in this process
+    #[error("requested virtual address range is not available in this process")]
+    AddressUnavailable,
+
+    /// Adopted topology does not match the requested mapping, or was written
+    /// by an incompatible build of hwloc
+    #[error("shared topology was written with a different mapping or an incompatible hwloc build")]
+    Mismatch,
+
+    /// hwloc failed for another reason
+    #[error(transparent)]
+    Hwloc(#[from] RawHwlocError),
+}
+
+/// Check that shared memory mapping parameters are page-aligned
+fn check_alignment(file_offset: u64, address: usize, length: usize) -> Result<(), ShmemError> {
+    let page_size = page_size();
+    let page_size_u64 = u64::try_from(page_size).expect("Page size should fit in u64");
+    if file_offset % page_size_u64 != 0 || address % page_size != 0 || length % page_size != 0 {
+        return Err(ShmemError::Unaligned(page_size));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::types::ObjectType;
+    #[allow(unused)]
+    use similar_asserts::assert_eq;
+    use static_assertions::{assert_impl_all, assert_not_impl_any};
+    use std::{
+        error::Error,
+        fmt::{
+            self, Binary, Debug, Display, LowerExp, LowerHex, Octal, Pointer, UpperExp, UpperHex,
+        },
+        hash::Hash,
+        io::{self, Read},
+        ops::{Deref, DerefMut, Drop},
+        panic::UnwindSafe,
+    };
+
+    // Check that public types in this module keep implementing all expected
+    // traits, in the interest of detecting future semver-breaking changes
+    assert_impl_all!(SharedTopology:
+        AsRef<Topology>, Borrow<Topology>, Debug, Deref<Target = Topology>,
+        Send, Sized, Sync, Unpin, UnwindSafe
+    );
+    assert_not_impl_any!(SharedTopology:
+        AsMut<Topology>, Binary, Clone, Default, DerefMut, Display, Drop, Error,
+        IntoIterator, LowerExp, LowerHex, Octal, PartialEq, Pointer, Read,
+        UpperExp, UpperHex, fmt::Write, io::Write
+    );
+    assert_impl_all!(ShmemError:
+        Copy, Debug, Eq, Error, Hash, Send, Sized, Sync, Unpin, UnwindSafe
+    );
+
+    /// Find a free page-aligned virtual address range of `length` bytes
+    fn free_address_range(length: usize) -> usize {
+        // SAFETY: Anonymous mappings at an address chosen by the OS do not
+        //         affect existing memory
+        let address = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                length,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(address, libc::MAP_FAILED);
+        // SAFETY: This is the mapping that was just created
+        assert_eq!(unsafe { libc::munmap(address, length) }, 0);
+        address as usize
+    }
+
+    #[test]
+    fn alignment() {
+        let page_size = page_size();
+        assert!(page_size.is_power_of_two());
+        assert_eq!(check_alignment(0, page_size, 2 * page_size), Ok(()));
+        for (file_offset, address, length) in [(1, 0, page_size), (0, 1, page_size), (0, 0, 1)] {
+            assert_eq!(
+                check_alignment(file_offset, address, length),
+                Err(ShmemError::Unaligned(page_size))
+            );
+        }
+    }
+
+    #[test]
+    fn write_and_adopt() {
+        let topology = Topology::test_instance();
+        let length = topology.shmem_length().unwrap();
+        assert_eq!(length % page_size(), 0);
+        let file = tempfile::tempfile().unwrap();
+        let address = free_address_range(length);
+
+        assert_eq!(
+            topology.write_to_shmem(&file, 0, address, length - page_size()),
+            Err(ShmemError::LengthTooSmall {
+                required: length,
+                length: length - page_size()
+            })
+        );
+        topology.write_to_shmem(&file, 0, address, length).unwrap();
+
+        // SAFETY: The file contains a topology that was just written
+        let shared = unsafe { SharedTopology::adopt(&file, 0, address, length) }.unwrap();
+        assert_eq!(shared.objects().count(), topology.objects().count());
+        assert_eq!(shared.cpuset(), topology.cpuset());
+        assert_eq!(shared.nodeset(), topology.nodeset());
+        for ty in [ObjectType::Machine, ObjectType::NUMANode, ObjectType::PU] {
+            assert_eq!(
+                shared.objects_with_type(ty).count(),
+                topology.objects_with_type(ty).count()
+            );
+        }
+        let private = shared.clone();
+        assert_eq!(private.objects().count(), topology.objects().count());
+
+        // The address range is now in use, so it can't be used again
+        assert_eq!(
+            // SAFETY: The file contains a topology that was just written
+            unsafe { SharedTopology::adopt(&file, 0, address, length) }.unwrap_err(),
+            ShmemError::AddressUnavailable
+        );
+        assert_eq!(
+            topology.write_to_shmem(&file, 0, address, length),
+            Err(ShmemError::AddressUnavailable)
+        );
+    }
+}